@@ -0,0 +1,136 @@
+//! Read throughput of the driver and conversion layers.
+//!
+//! The `dummy` group measures the overhead of the typed and the generic device API. With
+//! `SEIFY_BENCH_HARDWARE=1`, the `hardware` group compares the native RTL-SDR and HackRF drivers
+//! to the Soapy route for the same device, if it is connected. Hardware reads are bound by the
+//! sample rate, so the CPU load of the process during the measurement is printed as well.
+//!
+//! ```sh
+//! SEIFY_BENCH_HARDWARE=1 cargo bench --features dummy,soapy,rtlsdr,hackrfone
+//! ```
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use num_complex::Complex32;
+use std::time::Duration;
+use std::time::Instant;
+
+use seify::Args;
+use seify::Device;
+use seify::Direction;
+use seify::RxStreamer;
+use seify::SampleRate;
+
+const BLOCK_SIZES: [usize; 3] = [256, 4096, 65536];
+
+/// Driver name and the arguments of the native and the Soapy route.
+const HARDWARE: [(&str, &str, &str); 2] = [
+    (
+        "rtlsdr",
+        "driver=rtlsdr",
+        "driver=soapy, soapy_driver=rtlsdr",
+    ),
+    (
+        "hackrf",
+        "driver=hackrf",
+        "driver=soapy, soapy_driver=hackrf",
+    ),
+];
+const SAMPLE_RATE: f64 = 2.4e6;
+/// Samples per iteration of the hardware benchmarks.
+const CHUNK: usize = 1 << 18;
+
+fn dummy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dummy");
+    for block in BLOCK_SIZES {
+        let mut buf = vec![Complex32::new(0.0, 0.0); block];
+        group.throughput(Throughput::Elements(block as u64));
+
+        let typed = Device::from_impl(seify::impls::Dummy::open(Args::new()).unwrap());
+        let mut rx = typed.rx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        group.bench_with_input(BenchmarkId::new("typed", block), &block, |b, _| {
+            b.iter(|| rx.read(&mut [&mut buf], 0).unwrap())
+        });
+
+        let generic = Device::from_args("driver=dummy").unwrap();
+        let mut rx = generic.rx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        group.bench_with_input(BenchmarkId::new("generic", block), &block, |b, _| {
+            b.iter(|| rx.read(&mut [&mut buf], 0).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn hardware(c: &mut Criterion) {
+    if std::env::var_os("SEIFY_BENCH_HARDWARE").is_none() {
+        return;
+    }
+    let mut group = c.benchmark_group("hardware");
+    group
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(10))
+        .throughput(Throughput::Elements(CHUNK as u64));
+    for (name, native, soapy) in HARDWARE {
+        for (route, args) in [("native", native), ("soapy", soapy)] {
+            let dev = match Device::from_args(args) {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("skipping {name}/{route}: {e}");
+                    continue;
+                }
+            };
+            dev.set_rate(Direction::Rx, 0, SampleRate::sps(SAMPLE_RATE))
+                .unwrap();
+            let mut rx = dev.rx_streamer(&[0]).unwrap();
+            let mut buf = vec![Complex32::new(0.0, 0.0); 16384];
+            rx.activate().unwrap();
+
+            let mut cpu = Duration::ZERO;
+            let mut wall = Duration::ZERO;
+            group.bench_function(BenchmarkId::new(name, route), |b| {
+                b.iter_custom(|iters| {
+                    let cpu_start = cpu_time();
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        let mut n = 0;
+                        while n < CHUNK {
+                            let len = buf.len().min(CHUNK - n);
+                            n += rx.read(&mut [&mut buf[..len]], 1_000_000).unwrap();
+                        }
+                    }
+                    let elapsed = start.elapsed();
+                    if let (Some(a), Some(b)) = (cpu_start, cpu_time()) {
+                        cpu += b.saturating_sub(a);
+                        wall += elapsed;
+                    }
+                    elapsed
+                })
+            });
+            if !wall.is_zero() {
+                let load = 100.0 * cpu.as_secs_f64() / wall.as_secs_f64();
+                eprintln!("{name}/{route}: {load:.1}% CPU");
+            }
+            rx.deactivate().unwrap();
+        }
+    }
+    group.finish();
+}
+
+/// CPU time of the process, including the reader threads of the drivers.
+///
+/// Only available on Linux, where it is read from `/proc/self/stat` in clock ticks of 10 ms.
+fn cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // the fields after the command name start with the state, i.e., field 3
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis((utime + stime) * 10))
+}
+
+criterion_group!(benches, dummy, hardware);
+criterion_main!(benches);
@@ -1,7 +1,10 @@
 use clap::Parser;
 use seify::enumerate_with_args;
+use seify::CapabilityReport;
+use seify::ChannelReport;
 use seify::Device;
-use seify::Direction::Rx;
+use seify::Favorite;
+use seify::Favorites;
 
 #[derive(Parser, Debug)]
 #[clap(version)]
@@ -9,32 +12,103 @@ struct Args {
     /// Device Filters
     #[clap(short, long, default_value = "")]
     args: String,
+    /// Only probe the bookmarked device with this name
+    #[clap(short, long, conflicts_with = "args")]
+    name: Option<String>,
+    /// Bookmark the first device found under this name
+    #[clap(long)]
+    save: Option<String>,
+    /// Print the capability reports as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+fn print_channel(direction: &str, c: &ChannelReport) {
+    println!();
+    println!("{direction} channel {}", c.channel);
+    println!("-----------------------------------------");
+    println!("full duplex:       {:?}", c.full_duplex);
+    println!("antennas:          {:?}", c.antennas);
+    println!("agc:               {:?}", c.agc);
+    println!("gain range:        {:?}", c.gain_range);
+    for e in &c.gain_elements {
+        println!("  gain {:12} {:?}", e.name, e.range);
+    }
+    println!("frequency range:   {:?}", c.frequency_range);
+    for e in &c.frequency_components {
+        println!("  freq {:12} {:?}", e.name, e.range);
+    }
+    println!("sample rate range: {:?}", c.sample_rate_range);
+    println!("bandwidth range:   {:?}", c.bandwidth_range);
+}
+
+fn print_report(report: &CapabilityReport) {
+    println!();
+    println!("Device ({:?} - {:?}), ", report.driver, report.id);
+    println!("=========================================");
+    println!("driver:       {:?}", report.driver);
+    println!("id:           {:?}", report.id);
+    println!("info:         {:?}", report.info);
+    println!("extensions:   {:?}", report.extensions);
+    for (name, value) in &report.sensors {
+        println!("{:13} {value}", format!("{name}:"));
+    }
+    for c in &report.rx {
+        print_channel("RX", c);
+    }
+    for c in &report.tx {
+        print_channel("TX", c);
+    }
 }
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     let cli = Args::parse();
 
-    let devs = enumerate_with_args(cli.args)?;
-    println!("Devices");
-    println!("=========================================");
-    println!("devs: {devs:?}");
+    let devs = match &cli.name {
+        Some(name) => {
+            let favorites = Favorites::load()?;
+            let favorite = favorites.get(name).ok_or(seify::Error::NotFound)?;
+            vec![favorite.args.clone()]
+        }
+        None => enumerate_with_args(cli.args)?,
+    };
+    if !cli.json {
+        println!("Devices");
+        println!("=========================================");
+        println!("devs: {devs:?}");
+    }
 
-    for d in devs {
-        let dev = Device::from_args(d)?;
+    if let Some(name) = &cli.save {
+        let args = devs.first().ok_or(seify::Error::NotFound)?;
+        let mut favorites = Favorites::load()?;
+        favorites.insert(
+            name,
+            Favorite {
+                args: args.clone(),
+                ..Default::default()
+            },
+        );
+        favorites.save()?;
+        println!(
+            "saved {args:?} as {name:?} in {:?}",
+            Favorites::default_path()?
+        );
+    }
 
-        println!();
-        println!("Device ({:?} - {:?}), ", dev.driver(), dev.id()?);
-        println!("=========================================");
+    let mut reports = Vec::new();
+    for d in devs {
+        let dev = match &cli.name {
+            Some(name) => Device::from_name(name)?,
+            None => Device::from_args(d)?,
+        };
+        reports.push(dev.probe_capabilities()?);
+    }
 
-        println!("driver:       {:?}", dev.driver());
-        println!("id:           {:?}", dev.id()?);
-        println!("info:         {:?}", dev.info()?);
-        println!("sample rate:  {:?}", dev.sample_rate(Rx, 0)?);
-        println!("frequency:    {:?}", dev.frequency(Rx, 0)?);
-        println!("gain:         {:?}", dev.gain(Rx, 0)?);
-        println!("gain range:   {:?}", dev.gain_range(Rx, 0)?);
-        println!("sample rate range: {:?}", dev.get_sample_rate_range(Rx, 0)?);
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        reports.iter().for_each(print_report);
     }
 
     Ok(())
@@ -8,6 +8,7 @@ use vmcircbuffer::sync;
 
 use seify::Device;
 use seify::Direction::Rx;
+use seify::Error as SeifyError;
 use seify::RxStreamer;
 
 #[derive(Parser, Debug)]
@@ -34,24 +35,32 @@ pub fn main() -> Result<(), Box<dyn Error>> {
     let mut w = sync::Circular::with_capacity::<Complex32>(8192)?;
     let mut r = w.add_reader();
 
+    let mut rx = dev.rx_streamer(&[0])?;
+    // allows the Ctrl-C handler to interrupt a blocking read
+    let canceller = rx.canceller().ok();
+
     // producer thread
     let terminate = Arc::new(AtomicBool::new(false));
     let rx_thread = std::thread::spawn({
         let terminate = terminate.clone();
         move || -> Result<(), Box<dyn Error + Send + Sync>> {
-            let mut rx = dev.rx_streamer(&[0])?;
             let mtu = rx.mtu()?;
             rx.activate()?;
 
-            loop {
+            let res = loop {
                 if terminate.load(Ordering::Relaxed) {
                     break Ok(());
                 }
                 let w_buff = w.slice();
                 let n = std::cmp::min(w_buff.len(), mtu);
-                let n = rx.read(&mut [&mut w_buff[0..n]], 200000)?;
-                w.produce(n);
-            }
+                match rx.read(&mut [&mut w_buff[0..n]], 200000) {
+                    Ok(n) => w.produce(n),
+                    Err(SeifyError::Cancelled) => break Ok(()),
+                    Err(e) => break Err(e.into()),
+                }
+            };
+            rx.deactivate()?;
+            res
         }
     });
 
@@ -60,6 +69,9 @@ pub fn main() -> Result<(), Box<dyn Error>> {
         move || {
             println!("terminating...");
             terminate.store(true, Ordering::Relaxed);
+            if let Some(c) = &canceller {
+                c.cancel();
+            }
         }
     })
     .expect("Error setting Ctrl-C handler");
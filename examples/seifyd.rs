@@ -0,0 +1,92 @@
+//! Owns a device and arbitrates its control between client processes.
+//!
+//! Clients claim the device with `POST /claim` and pass the returned token to changing requests.
+//! Other clients observe the device read-only, until the claim is released or expires.
+//!
+//! Built with the `websocket` feature, `--stream` shares the RX stream of a channel: the daemon
+//! reads the samples once and every client receives them over WebSocket (see
+//! `IqWebSocketServer`), while the claim owner tunes the channel. TX is not brokered.
+//!
+//! With `--token`, clients have to authenticate with `Authorization: Bearer <token>`. Clients
+//! with a `--read-token` can only observe the device. Build with the `tls` feature and pass
+//! `--cert` and `--key` to serve over HTTPS.
+use clap::Parser;
+use std::time::Duration;
+
+use seify::Access;
+use seify::ControlServer;
+use seify::Device;
+use seify::Permission;
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Args {
+    /// Device Filters
+    #[clap(short, long, default_value = "")]
+    args: String,
+    /// Address of the control server
+    #[clap(short, long, default_value = "127.0.0.1:1234")]
+    listen: String,
+    /// Address of the WebSocket server for the shared RX stream
+    #[cfg(feature = "websocket")]
+    #[clap(long)]
+    stream: Option<String>,
+    /// RX channel of the shared stream
+    #[cfg(feature = "websocket")]
+    #[clap(long, default_value_t = 0)]
+    channel: usize,
+    /// Seconds a claim is valid without renewal
+    #[clap(long, default_value_t = 30.0)]
+    lease: f64,
+    /// Access token, permitting control of the device
+    #[clap(long)]
+    token: Vec<String>,
+    /// Access token, permitting read-only access
+    #[clap(long)]
+    read_token: Vec<String>,
+    /// PEM certificate chain for TLS
+    #[cfg(feature = "tls")]
+    #[clap(long, requires = "key")]
+    cert: Option<String>,
+    /// PEM private key for TLS
+    #[cfg(feature = "tls")]
+    #[clap(long, requires = "cert")]
+    key: Option<String>,
+}
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    let cli = Args::parse();
+
+    let mut access = Access::new();
+    for token in cli.token {
+        access = access.token(token, Permission::Control);
+    }
+    for token in cli.read_token {
+        access = access.token(token, Permission::ReadOnly);
+    }
+    #[cfg(feature = "tls")]
+    if let (Some(cert), Some(key)) = (&cli.cert, &cli.key) {
+        access = access.tls_pem(cert, key)?;
+    }
+
+    let dev = Device::from_args(cli.args)?;
+    println!("serving {:?} {} on {}", dev.driver(), dev.id()?, cli.listen);
+    #[cfg(feature = "websocket")]
+    if let Some(addr) = &cli.stream {
+        let rx = dev.rx_streamer(&[cli.channel])?;
+        let mtu = seify::RxStreamer::mtu(&rx)?;
+        let fanout = seify::FanOut::start(rx, mtu)?;
+        seify::IqWebSocketServer::new(fanout)
+            .access(access.clone())
+            .serve(addr)?;
+        println!("streaming RX channel {} on {addr}", cli.channel);
+    }
+    ControlServer::new(dev)
+        .lease(Duration::from_secs_f64(cli.lease))
+        .access(access)
+        .serve(&cli.listen)?
+        .join()
+        .map_err(|_| "control server failed")?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use clap::Parser;
+use clap::ValueEnum;
+use num_complex::Complex32;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use seify::Device;
+use seify::Direction::Tx;
+use seify::Frequency;
+use seify::SampleRate;
+use seify::SignalGenerator;
+use seify::TxStreamer;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Mode {
+    Cw,
+    Fm,
+    Am,
+}
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Args {
+    /// Device Filters
+    #[clap(short, long, default_value = "")]
+    args: String,
+    /// Center Frequency
+    #[clap(short, long, default_value_t = 435e6)]
+    frequency: f64,
+    /// Sample Rate
+    #[clap(short, long, default_value_t = 2e6)]
+    sample_rate: f64,
+    /// Gain
+    #[clap(short, long, default_value_t = 0.0)]
+    gain: f64,
+    /// Baseband offset of the carrier in Hz
+    #[clap(short, long, default_value_t = 100e3)]
+    offset: f64,
+    /// Signal
+    #[clap(short, long, value_enum, default_value_t = Mode::Cw)]
+    mode: Mode,
+}
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    let cli = Args::parse();
+
+    let dev = Device::from_args(cli.args)?;
+    dev.tune(Tx, 0, Frequency::hz(cli.frequency))?;
+    dev.set_rate(Tx, 0, SampleRate::sps(cli.sample_rate))?;
+    dev.set_gain(Tx, 0, cli.gain)?;
+
+    let fs = cli.sample_rate;
+    let mut signal = match cli.mode {
+        Mode::Cw => SignalGenerator::cw(fs, cli.offset)?,
+        Mode::Fm => SignalGenerator::fm(fs, cli.offset, 1e3, 5e3)?,
+        Mode::Am => SignalGenerator::am(fs, cli.offset, 1e3, 0.8)?,
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || r.store(false, Ordering::SeqCst))?;
+
+    let mut tx = dev.tx_streamer(&[0])?;
+    let mut buf = vec![Complex32::new(0.0, 0.0); tx.mtu()?];
+    tx.activate()?;
+    println!(
+        "transmitting {:?} at {} Hz, Ctrl-C to stop",
+        cli.mode,
+        cli.frequency + cli.offset
+    );
+    while running.load(Ordering::SeqCst) {
+        signal.fill(&mut buf);
+        tx.write_all(&[&buf], None, false, 1_000_000)?;
+    }
+    tx.deactivate()?;
+
+    Ok(())
+}
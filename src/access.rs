@@ -0,0 +1,298 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+#[cfg(feature = "tls")]
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Error;
+
+/// Time for a client to complete the TLS handshake, send its request, and receive the response,
+/// or to complete the WebSocket handshake.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections that a server handles concurrently; further clients are dropped.
+const MAX_CONNECTIONS: usize = 32;
+
+/// Permission of a client of the network servers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Permission {
+    /// Observe the settings and stream samples.
+    ReadOnly,
+    /// Also change the settings and claim the device.
+    Control,
+}
+
+/// Authentication and transport security of the network servers, i.e., the
+/// [`ControlServer`](crate::ControlServer) and the
+/// [`IqWebSocketServer`](crate::IqWebSocketServer).
+///
+/// Without tokens, the servers are open and every client has [`Permission::Control`]. Once a
+/// token is added, clients authenticate with an `Authorization: Bearer <token>` header or, since
+/// browsers cannot set headers for WebSockets, an `access_token=<token>` query parameter.
+/// Requests without a valid token are rejected with `401 Unauthorized`, changes with a read-only
+/// token with `403 Forbidden`.
+///
+/// Tokens are sent in plain text, unless the server uses TLS (cargo feature `tls`).
+///
+/// ```
+/// use seify::Access;
+/// use seify::Permission;
+///
+/// let access = Access::new()
+///     .token("operator-secret", Permission::Control)
+///     .token("viewer-secret", Permission::ReadOnly);
+/// ```
+#[derive(Clone, Default)]
+pub struct Access {
+    tokens: Vec<(String, Permission)>,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Access {
+    /// Create an open configuration, without tokens and TLS.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept a shared token, granting `permission`.
+    pub fn token(mut self, token: impl Into<String>, permission: Permission) -> Self {
+        self.tokens.push((token.into(), permission));
+        self
+    }
+
+    /// Serve over TLS with a certificate chain and a private key from PEM files.
+    #[cfg(feature = "tls")]
+    pub fn tls_pem(self, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self, Error> {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::CertificateDer;
+        use rustls::pki_types::PrivateKeyDer;
+
+        let certs = CertificateDer::pem_file_iter(cert)
+            .and_then(|c| c.collect::<Result<Vec<_>, _>>())
+            .map_err(tls_err)?;
+        let key = PrivateKeyDer::from_pem_file(key).map_err(tls_err)?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(tls_err)?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(tls_err)?;
+        Ok(self.tls(Arc::new(config)))
+    }
+
+    /// Serve over TLS with a rustls configuration, e.g., to authenticate clients with
+    /// certificates.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, config: Arc<rustls::ServerConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// Permission of a client presenting `token`.
+    pub(crate) fn authenticate(&self, token: Option<&str>) -> Result<Permission, Error> {
+        if self.tokens.is_empty() {
+            return Ok(Permission::Control);
+        }
+        let token = token.ok_or(Error::Unauthorized)?;
+        self.tokens
+            .iter()
+            .filter(|(t, _)| same(t, token))
+            .map(|(_, p)| *p)
+            .max()
+            .ok_or(Error::Unauthorized)
+    }
+
+    /// Wrap an accepted connection, starting TLS, if configured.
+    ///
+    /// The TLS handshake runs with the first read or write, so it is bounded by the deadline of
+    /// the stream.
+    pub(crate) fn accept(&self, stream: TcpStream) -> Result<Stream, Error> {
+        let socket = Socket {
+            tcp: stream,
+            deadline: None,
+        };
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            let conn = rustls::ServerConnection::new(config.clone()).map_err(tls_err)?;
+            return Ok(Stream::Tls(Box::new(rustls::StreamOwned::new(
+                conn, socket,
+            ))));
+        }
+        Ok(Stream::Plain(socket))
+    }
+}
+
+/// Token of a request, from the `Authorization` header or the `access_token` query parameter.
+pub(crate) fn token<'a>(authorization: Option<&'a str>, query: &'a str) -> Option<&'a str> {
+    authorization
+        .and_then(|h| h.trim().strip_prefix("Bearer "))
+        .map(str::trim)
+        .or_else(|| {
+            query
+                .split('&')
+                .find_map(|kv| kv.strip_prefix("access_token="))
+        })
+}
+
+/// Compare tokens in constant time, so that the timing does not reveal matching prefixes.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |d, (x, y)| d | (x ^ y)) == 0
+}
+
+#[cfg(feature = "tls")]
+fn tls_err(e: impl std::fmt::Display) -> Error {
+    Error::Misc(format!("tls: {e}"))
+}
+
+/// One of the [`MAX_CONNECTIONS`] of a server, released on drop.
+pub(crate) struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Take a slot of the connections counted by `active`, if one is free.
+    pub(crate) fn take(active: &Arc<AtomicUsize>) -> Option<Self> {
+        let slot = Slot(active.clone());
+        (active.fetch_add(1, Ordering::AcqRel) < MAX_CONNECTIONS).then_some(slot)
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// TCP connection, whose reads and writes fail once a deadline expired.
+pub(crate) struct Socket {
+    tcp: TcpStream,
+    deadline: Option<Instant>,
+}
+
+impl Socket {
+    /// Time left until the deadline, set as timeout of the next socket operation.
+    fn left(&self) -> std::io::Result<Option<std::time::Duration>> {
+        match self.deadline {
+            None => Ok(None),
+            Some(d) => match d.checked_duration_since(Instant::now()) {
+                Some(left) if !left.is_zero() => Ok(Some(left)),
+                _ => Err(std::io::ErrorKind::TimedOut.into()),
+            },
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.tcp.set_read_timeout(self.left()?)?;
+        self.tcp.read(buf)
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tcp.set_write_timeout(self.left()?)?;
+        self.tcp.write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.tcp.flush()
+    }
+}
+
+/// Connection of a client, plain or TLS.
+pub(crate) enum Stream {
+    Plain(Socket),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ServerConnection, Socket>>),
+}
+
+impl Stream {
+    /// Fail reads and writes, including the TLS handshake, after `deadline`.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        match self {
+            Stream::Plain(s) => s.deadline = deadline,
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.sock.deadline = deadline,
+        }
+    }
+
+    /// Flush the response and close the connection.
+    #[cfg_attr(
+        not(any(feature = "control-http", feature = "prometheus")),
+        allow(dead_code)
+    )]
+    pub fn finish(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "tls")]
+        if let Stream::Tls(s) = self {
+            s.conn.send_close_notify();
+        }
+        Ok(self.flush()?)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(s) => s.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(s) => s.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Plain(s) => s.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authenticate() {
+        assert_eq!(
+            Access::new().authenticate(None).unwrap(),
+            Permission::Control
+        );
+        let access = Access::new()
+            .token("rw", Permission::Control)
+            .token("ro", Permission::ReadOnly);
+        assert_eq!(
+            access.authenticate(Some("rw")).unwrap(),
+            Permission::Control
+        );
+        assert_eq!(
+            access.authenticate(Some("ro")).unwrap(),
+            Permission::ReadOnly
+        );
+        assert!(matches!(
+            access.authenticate(Some("r")),
+            Err(Error::Unauthorized)
+        ));
+        assert!(matches!(
+            access.authenticate(None),
+            Err(Error::Unauthorized)
+        ));
+
+        assert_eq!(token(Some("Bearer abc"), ""), Some("abc"));
+        assert_eq!(token(None, "size=8&access_token=abc"), Some("abc"));
+        assert_eq!(token(Some("Basic abc"), ""), None);
+    }
+}
@@ -0,0 +1,145 @@
+use futures::channel::oneshot;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Error;
+
+type Job<D> = Box<dyn FnOnce(&Device<D>) + Send>;
+
+/// Serializes all control calls of a device through a command queue.
+///
+/// A dedicated thread owns the device and executes submitted commands one after another. Since
+/// only this thread calls into the driver, control calls from multiple threads cannot interleave
+/// or deadlock on driver-internal locks. Commands are submitted synchronously with
+/// [`call`](Self::call) or asynchronously with [`submit`](Self::submit).
+///
+/// Clones share the queue. The thread ends, once all clones are dropped.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::Device;
+/// use seify::DeviceActor;
+/// use seify::Direction::Rx;
+/// use seify::Frequency;
+///
+/// let actor = DeviceActor::spawn(Device::from_args("driver=dummy").unwrap());
+/// actor.call(|dev| dev.tune(Rx, 0, Frequency::mhz(100.0))).unwrap();
+/// let pending = actor.submit(|dev| dev.center_frequency(Rx, 0));
+/// assert_eq!(pending.wait().unwrap(), Frequency::mhz(100.0));
+/// # }
+/// ```
+pub struct DeviceActor<D: DeviceTrait + Clone + 'static> {
+    queue: mpsc::Sender<Job<D>>,
+}
+
+/// Result of a command submitted to a [`DeviceActor`] or of a
+/// [`TimedCommands`](crate::TimedCommands) batch.
+///
+/// It is a [`Future`] or can be waited for synchronously with [`wait`](Self::wait).
+pub struct PendingCommand<O> {
+    rx: oneshot::Receiver<Result<O, Error>>,
+}
+
+impl<D: DeviceTrait + Clone + 'static> Clone for DeviceActor<D> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<D: DeviceTrait + Clone + 'static> DeviceActor<D> {
+    /// Move the device to a new command thread.
+    pub fn spawn(dev: Device<D>) -> Self {
+        let (queue, jobs) = mpsc::channel::<Job<D>>();
+        std::thread::spawn(move || {
+            for job in jobs {
+                job(&dev);
+            }
+        });
+        Self { queue }
+    }
+
+    /// Queue a command without waiting for it.
+    ///
+    /// Returns [`Error::Inactive`] when awaited, if the command thread panicked.
+    pub fn submit<O, F>(&self, f: F) -> PendingCommand<O>
+    where
+        O: Send + 'static,
+        F: FnOnce(&Device<D>) -> Result<O, Error> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.queue.send(Box::new(move |dev| {
+            let _ = tx.send(f(dev));
+        }));
+        PendingCommand { rx }
+    }
+
+    /// Queue a command and wait for its result.
+    pub fn call<O, F>(&self, f: F) -> Result<O, Error>
+    where
+        O: Send + 'static,
+        F: FnOnce(&Device<D>) -> Result<O, Error> + Send + 'static,
+    {
+        self.submit(f).wait()
+    }
+}
+
+impl<O> PendingCommand<O> {
+    pub(crate) fn new(rx: oneshot::Receiver<Result<O, Error>>) -> Self {
+        Self { rx }
+    }
+    /// Block until the command was executed.
+    pub fn wait(self) -> Result<O, Error> {
+        futures::executor::block_on(self)
+    }
+}
+
+impl<O> Future for PendingCommand<O> {
+    type Output = Result<O, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx)
+            .poll(cx)
+            .map(|r| r.unwrap_or(Err(Error::Inactive)))
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::Args;
+    use crate::Direction::Rx;
+
+    #[test]
+    fn serializes_commands() {
+        let dev = Device::from_impl(crate::impls::Dummy::open(Args::new()).unwrap());
+        let actor = DeviceActor::spawn(dev);
+        let pending: Vec<_> = (0..10)
+            .map(|i| {
+                let actor = actor.clone();
+                std::thread::spawn(move || actor.call(move |dev| dev.set_gain(Rx, 0, i as f64)))
+            })
+            .collect();
+        for p in pending {
+            p.join().unwrap().unwrap();
+        }
+        let gain = actor.submit(|dev| dev.gain(Rx, 0));
+        assert!(futures::executor::block_on(gain).unwrap().is_some());
+        assert_eq!(actor.call(|dev| Ok(dev.events().len())).unwrap(), 10);
+        assert!(matches!(
+            actor.call(|_| -> Result<(), Error> { panic!("command failed") }),
+            Err(Error::Inactive)
+        ));
+        assert!(matches!(
+            actor.call(|dev| dev.gain(Rx, 0)),
+            Err(Error::Inactive)
+        ));
+    }
+}
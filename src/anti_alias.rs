@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::Args;
+use crate::Direction;
+use crate::Error;
+
+/// Fraction of the sample rate used as analog bandwidth with `auto_bandwidth=true`.
+const DEFAULT_FRACTION: f64 = 0.8;
+
+/// Analog bandwidth of a [`GenericDevice`](crate::GenericDevice) following its sample rate, set
+/// with the `auto_bandwidth` device argument.
+#[derive(Debug, Default)]
+pub(crate) struct AntiAlias {
+    /// Maximum bandwidth as fraction of the sample rate, `None` if disabled.
+    fraction: Option<f64>,
+    /// Bandwidths set explicitly per channel, which are not exceeded.
+    requested: Mutex<HashMap<(Direction, usize), f64>>,
+}
+
+impl AntiAlias {
+    /// Parse the `auto_bandwidth` argument, either a boolean or the fraction of the sample rate.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        let fraction = match args.get::<String>("auto_bandwidth") {
+            Err(Error::NotFound) => None,
+            Err(e) => return Err(e),
+            Ok(v) => match v.parse::<bool>() {
+                Ok(true) => Some(DEFAULT_FRACTION),
+                Ok(false) => None,
+                Err(_) => match v.parse::<f64>() {
+                    Ok(f) if f > 0.0 && f <= 1.0 => Some(f),
+                    _ => return Err(Error::ValueError),
+                },
+            },
+        };
+        Ok(Self {
+            fraction,
+            requested: Default::default(),
+        })
+    }
+
+    /// True, if the bandwidth follows the sample rate.
+    pub fn enabled(&self) -> bool {
+        self.fraction.is_some()
+    }
+
+    /// Remember a bandwidth set explicitly for a channel.
+    pub fn request(&self, direction: Direction, channel: usize, bw: f64) {
+        self.requested
+            .lock()
+            .unwrap()
+            .insert((direction, channel), bw);
+    }
+
+    /// Bandwidth of a channel at a sample rate, i.e., the requested bandwidth, limited to the
+    /// fraction of the rate. `None`, if tracking is disabled.
+    pub fn bandwidth(&self, direction: Direction, channel: usize, rate: f64) -> Option<f64> {
+        let limit = self.fraction? * rate;
+        match self.requested.lock().unwrap().get(&(direction, channel)) {
+            Some(bw) => Some(bw.min(limit)),
+            None => Some(limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SampleRate;
+
+    #[test]
+    fn bandwidth() {
+        let rx = Direction::Rx;
+        let aa = AntiAlias::from_args(&Args::from("auto_bandwidth=true").unwrap()).unwrap();
+        assert_eq!(aa.bandwidth(rx, 0, 2e6), Some(1.6e6));
+        aa.request(rx, 0, 1e6);
+        assert_eq!(aa.bandwidth(rx, 0, 2e6), Some(1e6));
+        assert_eq!(aa.bandwidth(rx, 0, 1e6), Some(0.8e6));
+
+        let aa = AntiAlias::from_args(&Args::from("auto_bandwidth=0.5").unwrap()).unwrap();
+        assert_eq!(aa.bandwidth(rx, 0, 2e6), Some(1e6));
+        assert_eq!(
+            AntiAlias::from_args(&Args::new())
+                .unwrap()
+                .bandwidth(rx, 0, 2e6),
+            None
+        );
+        assert!(AntiAlias::from_args(&Args::from("auto_bandwidth=2").unwrap()).is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn tracking_device() {
+        let dev = crate::Device::from_args("driver=dummy, auto_bandwidth=true").unwrap();
+        let rx = Direction::Rx;
+        dev.set_rate(rx, 0, SampleRate::sps(2e6)).unwrap();
+        assert_eq!(dev.bandwidth(rx, 0).unwrap(), 1.6e6);
+        dev.set_bandwidth(rx, 0, 1e6).unwrap();
+        dev.set_rate(rx, 0, SampleRate::sps(1e6)).unwrap();
+        assert_eq!(dev.bandwidth(rx, 0).unwrap(), 0.8e6);
+        dev.set_rate(rx, 0, SampleRate::sps(4e6)).unwrap();
+        assert_eq!(dev.bandwidth(rx, 0).unwrap(), 1e6);
+    }
+}
@@ -0,0 +1,1004 @@
+// without compiled-in drivers, the enums are empty and the arguments of the methods are unused
+#![allow(unused_variables)]
+use num_complex::Complex32;
+use std::any::Any;
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::BufferLayout;
+use crate::CoherencyGroup;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Driver;
+use crate::Error;
+use crate::EventLog;
+use crate::Extensions;
+use crate::GainElementInfo;
+use crate::Location;
+use crate::PowerState;
+use crate::Range;
+use crate::ReferenceDoc;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::SelfTestReport;
+use crate::SpectrumStreamer;
+use crate::Spur;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+use crate::TimeDiscipline;
+use crate::TimedCommand;
+use crate::TxStreamer;
+
+/// Match on all variants of an enum over the compiled-in drivers, binding their value.
+macro_rules! dispatch {
+    ($value:expr, ref mut $d:ident => $body:expr) => {
+        dispatch!(@ $value, (ref mut $d), $body)
+    };
+    ($value:expr, ref $d:ident => $body:expr) => {
+        dispatch!(@ $value, (ref $d), $body)
+    };
+    (@ $value:expr, ($($p:tt)+), $body:expr) => {
+        match $value {
+            #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+            Self::Aaronia($($p)+) => $body,
+            #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+            Self::AaroniaHttp($($p)+) => $body,
+            #[cfg(feature = "dummy")]
+            Self::Dummy($($p)+) => $body,
+            #[cfg(feature = "file")]
+            Self::File($($p)+) => $body,
+            #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+            Self::HackRf($($p)+) => $body,
+            #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+            Self::Hpsdr($($p)+) => $body,
+            #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+            Self::Ka9q($($p)+) => $body,
+            #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+            Self::RtlSdr($($p)+) => $body,
+            #[cfg(all(feature = "signalhound", any(target_os = "linux", target_os = "windows")))]
+            Self::SignalHound($($p)+) => $body,
+            #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+            Self::Soapy($($p)+) => $body,
+        }
+    };
+}
+
+/// Device over the compiled-in drivers, using static dispatch.
+///
+/// Unlike [`GenericDevice`](crate::GenericDevice), it does not put the driver in an `Arc` and its
+/// streamers, [`AnyRxStreamer`] and [`AnyTxStreamer`], are not boxed, so streaming does not
+/// allocate or go through a vtable.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::AnyDevice;
+/// use seify::Device;
+/// use seify::Driver;
+///
+/// let dev = Device::from_impl(AnyDevice::from_args("driver=dummy").unwrap());
+/// assert_eq!(dev.driver(), Driver::Dummy);
+/// # }
+/// ```
+// the network drivers keep their state inline; a device is created once, so the size does not
+// matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum AnyDevice {
+    #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+    Aaronia(crate::impls::Aaronia),
+    #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+    AaroniaHttp(crate::impls::AaroniaHttp),
+    #[cfg(feature = "dummy")]
+    Dummy(crate::impls::Dummy),
+    #[cfg(feature = "file")]
+    File(crate::impls::File),
+    #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+    HackRf(crate::impls::HackRfOne),
+    #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+    Hpsdr(crate::impls::Hpsdr),
+    #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+    Ka9q(crate::impls::Ka9q),
+    #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+    RtlSdr(crate::impls::RtlSdr),
+    #[cfg(all(
+        feature = "signalhound",
+        any(target_os = "linux", target_os = "windows")
+    ))]
+    SignalHound(crate::impls::SignalHound),
+    #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+    Soapy(crate::impls::Soapy),
+}
+
+/// RX streamer of an [`AnyDevice`].
+pub enum AnyRxStreamer {
+    #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+    Aaronia(crate::impls::aaronia::RxStreamer),
+    #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+    AaroniaHttp(crate::impls::aaronia_http::RxStreamer),
+    #[cfg(feature = "dummy")]
+    Dummy(crate::impls::dummy::RxStreamer),
+    #[cfg(feature = "file")]
+    File(crate::impls::file::RxStreamer),
+    #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+    HackRf(crate::impls::hackrfone::RxStreamer),
+    #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+    Hpsdr(crate::impls::hpsdr::RxStreamer),
+    #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+    Ka9q(crate::impls::ka9q::RxStreamer),
+    #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+    RtlSdr(crate::impls::rtlsdr::RxStreamer),
+    #[cfg(all(
+        feature = "signalhound",
+        any(target_os = "linux", target_os = "windows")
+    ))]
+    SignalHound(crate::impls::signalhound::RxStreamer),
+    #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+    Soapy(crate::impls::soapy::RxStreamer),
+}
+
+/// TX streamer of an [`AnyDevice`].
+pub enum AnyTxStreamer {
+    #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+    Aaronia(crate::impls::aaronia::TxStreamer),
+    #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+    AaroniaHttp(crate::impls::aaronia_http::TxStreamer),
+    #[cfg(feature = "dummy")]
+    Dummy(crate::impls::dummy::TxStreamer),
+    #[cfg(feature = "file")]
+    File(crate::impls::file::TxStreamer),
+    #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+    HackRf(crate::impls::hackrfone::TxStreamer),
+    #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+    Hpsdr(crate::impls::hpsdr::TxStreamer),
+    #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+    Ka9q(crate::impls::ka9q::TxDummy),
+    #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+    RtlSdr(crate::impls::rtlsdr::TxDummy),
+    #[cfg(all(
+        feature = "signalhound",
+        any(target_os = "linux", target_os = "windows")
+    ))]
+    SignalHound(crate::impls::signalhound::TxDummy),
+    #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+    Soapy(crate::impls::soapy::TxStreamer),
+}
+
+impl AnyDevice {
+    /// Open the device described by `args`, trying the compiled-in drivers in the same order as
+    /// [`Device::from_args`](crate::Device::from_args).
+    pub fn from_args<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args = args.try_into().or(Err(Error::ValueError))?;
+        let driver = match args.get::<String>("driver") {
+            Ok(d) => Some(d.parse::<Driver>()?),
+            Err(Error::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(d) = driver {
+            d.ensure_available()?;
+        }
+        #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::Aaronia)) {
+                match crate::impls::Aaronia::open(&args) {
+                    Ok(d) => return Ok(Self::Aaronia(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::AaroniaHttp)) {
+                match crate::impls::AaroniaHttp::open(&args) {
+                    Ok(d) => return Ok(Self::AaroniaHttp(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::RtlSdr)) {
+                match crate::impls::RtlSdr::open(&args) {
+                    Ok(d) => return Ok(Self::RtlSdr(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(
+            feature = "signalhound",
+            any(target_os = "linux", target_os = "windows")
+        ))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::SignalHound)) {
+                match crate::impls::SignalHound::open(&args) {
+                    Ok(d) => return Ok(Self::SignalHound(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::Soapy)) {
+                match crate::impls::Soapy::open(&args) {
+                    Ok(d) => return Ok(Self::Soapy(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::HackRf)) {
+                match crate::impls::HackRfOne::open(&args) {
+                    Ok(d) => return Ok(Self::HackRf(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::Hpsdr)) {
+                match crate::impls::Hpsdr::open(&args) {
+                    Ok(d) => return Ok(Self::Hpsdr(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::Ka9q)) {
+                match crate::impls::Ka9q::open(&args) {
+                    Ok(d) => return Ok(Self::Ka9q(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(feature = "dummy")]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::Dummy)) {
+                match crate::impls::Dummy::open(&args) {
+                    Ok(d) => return Ok(Self::Dummy(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        #[cfg(feature = "file")]
+        {
+            if driver.is_none() || matches!(driver, Some(Driver::File)) {
+                match crate::impls::File::open(&args) {
+                    Ok(d) => return Ok(Self::File(d)),
+                    Err(Error::NotFound) if driver.is_none() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        let _ = driver;
+        Err(Error::NotFound)
+    }
+}
+
+#[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+impl From<crate::impls::Aaronia> for AnyDevice {
+    fn from(value: crate::impls::Aaronia) -> Self {
+        Self::Aaronia(value)
+    }
+}
+
+#[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+impl From<crate::impls::aaronia::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::aaronia::RxStreamer) -> Self {
+        Self::Aaronia(value)
+    }
+}
+
+#[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+impl From<crate::impls::aaronia::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::aaronia::TxStreamer) -> Self {
+        Self::Aaronia(value)
+    }
+}
+
+#[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+impl From<crate::impls::AaroniaHttp> for AnyDevice {
+    fn from(value: crate::impls::AaroniaHttp) -> Self {
+        Self::AaroniaHttp(value)
+    }
+}
+
+#[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+impl From<crate::impls::aaronia_http::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::aaronia_http::RxStreamer) -> Self {
+        Self::AaroniaHttp(value)
+    }
+}
+
+#[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+impl From<crate::impls::aaronia_http::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::aaronia_http::TxStreamer) -> Self {
+        Self::AaroniaHttp(value)
+    }
+}
+
+#[cfg(feature = "dummy")]
+impl From<crate::impls::Dummy> for AnyDevice {
+    fn from(value: crate::impls::Dummy) -> Self {
+        Self::Dummy(value)
+    }
+}
+
+#[cfg(feature = "dummy")]
+impl From<crate::impls::dummy::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::dummy::RxStreamer) -> Self {
+        Self::Dummy(value)
+    }
+}
+
+#[cfg(feature = "dummy")]
+impl From<crate::impls::dummy::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::dummy::TxStreamer) -> Self {
+        Self::Dummy(value)
+    }
+}
+
+#[cfg(feature = "file")]
+impl From<crate::impls::File> for AnyDevice {
+    fn from(value: crate::impls::File) -> Self {
+        Self::File(value)
+    }
+}
+
+#[cfg(feature = "file")]
+impl From<crate::impls::file::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::file::RxStreamer) -> Self {
+        Self::File(value)
+    }
+}
+
+#[cfg(feature = "file")]
+impl From<crate::impls::file::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::file::TxStreamer) -> Self {
+        Self::File(value)
+    }
+}
+
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+impl From<crate::impls::HackRfOne> for AnyDevice {
+    fn from(value: crate::impls::HackRfOne) -> Self {
+        Self::HackRf(value)
+    }
+}
+
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+impl From<crate::impls::hackrfone::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::hackrfone::RxStreamer) -> Self {
+        Self::HackRf(value)
+    }
+}
+
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+impl From<crate::impls::hackrfone::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::hackrfone::TxStreamer) -> Self {
+        Self::HackRf(value)
+    }
+}
+
+#[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::Hpsdr> for AnyDevice {
+    fn from(value: crate::impls::Hpsdr) -> Self {
+        Self::Hpsdr(value)
+    }
+}
+
+#[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::hpsdr::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::hpsdr::RxStreamer) -> Self {
+        Self::Hpsdr(value)
+    }
+}
+
+#[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::hpsdr::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::hpsdr::TxStreamer) -> Self {
+        Self::Hpsdr(value)
+    }
+}
+
+#[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+impl From<crate::impls::Ka9q> for AnyDevice {
+    fn from(value: crate::impls::Ka9q) -> Self {
+        Self::Ka9q(value)
+    }
+}
+
+#[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+impl From<crate::impls::ka9q::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::ka9q::RxStreamer) -> Self {
+        Self::Ka9q(value)
+    }
+}
+
+#[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+impl From<crate::impls::ka9q::TxDummy> for AnyTxStreamer {
+    fn from(value: crate::impls::ka9q::TxDummy) -> Self {
+        Self::Ka9q(value)
+    }
+}
+
+#[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::RtlSdr> for AnyDevice {
+    fn from(value: crate::impls::RtlSdr) -> Self {
+        Self::RtlSdr(value)
+    }
+}
+
+#[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::rtlsdr::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::rtlsdr::RxStreamer) -> Self {
+        Self::RtlSdr(value)
+    }
+}
+
+#[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+impl From<crate::impls::rtlsdr::TxDummy> for AnyTxStreamer {
+    fn from(value: crate::impls::rtlsdr::TxDummy) -> Self {
+        Self::RtlSdr(value)
+    }
+}
+
+#[cfg(all(
+    feature = "signalhound",
+    any(target_os = "linux", target_os = "windows")
+))]
+impl From<crate::impls::SignalHound> for AnyDevice {
+    fn from(value: crate::impls::SignalHound) -> Self {
+        Self::SignalHound(value)
+    }
+}
+
+#[cfg(all(
+    feature = "signalhound",
+    any(target_os = "linux", target_os = "windows")
+))]
+impl From<crate::impls::signalhound::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::signalhound::RxStreamer) -> Self {
+        Self::SignalHound(value)
+    }
+}
+
+#[cfg(all(
+    feature = "signalhound",
+    any(target_os = "linux", target_os = "windows")
+))]
+impl From<crate::impls::signalhound::TxDummy> for AnyTxStreamer {
+    fn from(value: crate::impls::signalhound::TxDummy) -> Self {
+        Self::SignalHound(value)
+    }
+}
+
+#[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+impl From<crate::impls::Soapy> for AnyDevice {
+    fn from(value: crate::impls::Soapy) -> Self {
+        Self::Soapy(value)
+    }
+}
+
+#[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+impl From<crate::impls::soapy::RxStreamer> for AnyRxStreamer {
+    fn from(value: crate::impls::soapy::RxStreamer) -> Self {
+        Self::Soapy(value)
+    }
+}
+
+#[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+impl From<crate::impls::soapy::TxStreamer> for AnyTxStreamer {
+    fn from(value: crate::impls::soapy::TxStreamer) -> Self {
+        Self::Soapy(value)
+    }
+}
+
+impl DeviceTrait for AnyDevice {
+    type RxStreamer = AnyRxStreamer;
+    type TxStreamer = AnyTxStreamer;
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn driver(&self) -> Driver {
+        dispatch!(*self, ref d => d.driver())
+    }
+    fn id(&self) -> Result<String, Error> {
+        dispatch!(*self, ref d => d.id())
+    }
+    fn info(&self) -> Result<Args, Error> {
+        dispatch!(*self, ref d => d.info())
+    }
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        dispatch!(*self, ref d => d.reference_docs())
+    }
+    fn extensions(&self) -> Extensions {
+        dispatch!(*self, ref d => d.extensions())
+    }
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        dispatch!(*self, ref d => d.num_channels(direction))
+    }
+    fn full_duplex(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.full_duplex(direction, channel))
+    }
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        dispatch!(*self, ref d => d.coherency_groups())
+    }
+
+    fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
+        dispatch!(*self, ref d => Ok(AnyRxStreamer::from(d.rx_streamer(channels, args)?)))
+    }
+    fn tx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::TxStreamer, Error> {
+        dispatch!(*self, ref d => Ok(AnyTxStreamer::from(d.tx_streamer(channels, args)?)))
+    }
+
+    fn spectrum_streamer(
+        &self,
+        channel: usize,
+        args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        dispatch!(*self, ref d => d.spectrum_streamer(channel, args))
+    }
+
+    fn driver_event_log(&self) -> Option<EventLog> {
+        dispatch!(*self, ref d => d.driver_event_log())
+    }
+
+    fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        dispatch!(*self, ref d => d.antennas(direction, channel))
+    }
+
+    fn antenna(&self, direction: Direction, channel: usize) -> Result<String, Error> {
+        dispatch!(*self, ref d => d.antenna(direction, channel))
+    }
+
+    fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_antenna(direction, channel, name))
+    }
+
+    fn gain_elements(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        dispatch!(*self, ref d => d.gain_elements(direction, channel))
+    }
+
+    fn supports_agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.supports_agc(direction, channel))
+    }
+
+    fn enable_agc(&self, direction: Direction, channel: usize, agc: bool) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.enable_agc(direction, channel, agc))
+    }
+
+    fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.agc(direction, channel))
+    }
+
+    fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.supports_agc_element(direction, channel, name))
+    }
+
+    fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.enable_agc_element(direction, channel, name, agc))
+    }
+
+    fn agc_element(&self, direction: Direction, channel: usize, name: &str) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.agc_element(direction, channel, name))
+    }
+
+    fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_gain(direction, channel, gain))
+    }
+
+    fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
+        dispatch!(*self, ref d => d.gain(direction, channel))
+    }
+
+    fn gain_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.gain_range(direction, channel))
+    }
+
+    fn set_gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        gain: f64,
+    ) -> Result<f64, Error> {
+        dispatch!(*self, ref d => d.set_gain_element(direction, channel, name, gain))
+    }
+
+    fn gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Option<f64>, Error> {
+        dispatch!(*self, ref d => d.gain_element(direction, channel, name))
+    }
+
+    fn gain_element_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.gain_element_range(direction, channel, name))
+    }
+
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        dispatch!(*self, ref d => d.gain_element_info(direction, channel, name))
+    }
+
+    fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.frequency_range(direction, channel))
+    }
+
+    fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        dispatch!(*self, ref d => d.frequency(direction, channel))
+    }
+
+    fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        args: Args,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_frequency(direction, channel, frequency, args))
+    }
+
+    fn frequency_components(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Vec<String>, Error> {
+        dispatch!(*self, ref d => d.frequency_components(direction, channel))
+    }
+
+    fn component_frequency_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.component_frequency_range(direction, channel, name))
+    }
+
+    fn component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        dispatch!(*self, ref d => d.component_frequency(direction, channel, name))
+    }
+
+    fn set_component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        frequency: f64,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref d => d
+            .set_component_frequency(direction, channel, name, frequency))
+    }
+
+    fn spurs(&self, direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        dispatch!(*self, ref d => d.spurs(direction, channel))
+    }
+
+    fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        dispatch!(*self, ref d => d.sample_rate(direction, channel))
+    }
+
+    fn set_sample_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_sample_rate(direction, channel, rate))
+    }
+
+    fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.get_sample_rate_range(direction, channel))
+    }
+
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        dispatch!(*self, ref d => d.bandwidth(direction, channel))
+    }
+
+    fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_bandwidth(direction, channel, bw))
+    }
+
+    fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        dispatch!(*self, ref d => d.get_bandwidth_range(direction, channel))
+    }
+
+    fn has_dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.has_dc_offset_mode(direction, channel))
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        direction: Direction,
+        channel: usize,
+        automatic: bool,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_dc_offset_mode(direction, channel, automatic))
+    }
+
+    fn dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.dc_offset_mode(direction, channel))
+    }
+
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        dispatch!(*self, ref d => d.list_loopback_modes())
+    }
+
+    fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_loopback_mode(mode))
+    }
+
+    fn loopback_mode(&self) -> Result<String, Error> {
+        dispatch!(*self, ref d => d.loopback_mode())
+    }
+
+    fn self_test(&self) -> Result<SelfTestReport, Error> {
+        dispatch!(*self, ref d => d.self_test())
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_power_state(state))
+    }
+
+    fn power_state(&self) -> Result<PowerState, Error> {
+        dispatch!(*self, ref d => d.power_state())
+    }
+
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        dispatch!(*self, ref d => d.list_time_sources())
+    }
+
+    fn time_source(&self) -> Result<String, Error> {
+        dispatch!(*self, ref d => d.time_source())
+    }
+
+    fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_time_source(name))
+    }
+
+    fn gps_locked(&self) -> Result<bool, Error> {
+        dispatch!(*self, ref d => d.gps_locked())
+    }
+
+    fn gps_location(&self) -> Result<Location, Error> {
+        dispatch!(*self, ref d => d.gps_location())
+    }
+
+    fn hardware_time(&self) -> Result<i64, Error> {
+        dispatch!(*self, ref d => d.hardware_time())
+    }
+
+    fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_hardware_time(time_ns))
+    }
+
+    fn set_hardware_time_next_pps(&self, time_ns: i64) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.set_hardware_time_next_pps(time_ns))
+    }
+
+    fn time_discipline(&self) -> Result<TimeDiscipline, Error> {
+        dispatch!(*self, ref d => d.time_discipline())
+    }
+
+    fn execute_at(&self, time_ns: i64, commands: &[TimedCommand]) -> Result<(), Error> {
+        dispatch!(*self, ref d => d.execute_at(time_ns, commands))
+    }
+}
+
+impl RxStreamer for AnyRxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        dispatch!(*self, ref s => s.mtu())
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.activate_at(time_ns))
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.deactivate_at(time_ns))
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.read(buffers, timeout_us))
+    }
+    fn try_read(&mut self, buffers: &mut [&mut [Complex32]]) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.try_read(buffers))
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        dispatch!(*self, ref mut s => s.poll_ready(cx))
+    }
+    fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffers: &mut [&mut [Complex32]],
+    ) -> Poll<Result<usize, Error>> {
+        dispatch!(*self, ref mut s => s.poll_read(cx, buffers))
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        dispatch!(*self, ref s => s.sample_position())
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        dispatch!(*self, ref mut s => s.read_with_metadata(buffers, timeout_us))
+    }
+    fn native_layout(&self) -> BufferLayout {
+        dispatch!(*self, ref s => s.native_layout())
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.read_interleaved(buffer, channels, timeout_us))
+    }
+    fn native_format(&self) -> StreamFormat {
+        dispatch!(*self, ref s => s.native_format())
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.read_format(format, buffers, timeout_us))
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        dispatch!(*self, ref s => s.buffer_config())
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.set_output_scale(scale))
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        dispatch!(*self, ref s => s.canceller())
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        dispatch!(*self, ref s => s.stream_events())
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        dispatch!(*self, ref s => s.activation_error_ns())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.flush())
+    }
+}
+
+impl TxStreamer for AnyTxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        dispatch!(*self, ref s => s.mtu())
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.activate_at(time_ns))
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.deactivate_at(time_ns))
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.write(buffers, at_ns, end_burst, timeout_us))
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.write_all(buffers, at_ns, end_burst, timeout_us))
+    }
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.write_segments(segments, at_ns, end_burst, timeout_us))
+    }
+    fn native_format(&self) -> StreamFormat {
+        dispatch!(*self, ref s => s.native_format())
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        dispatch!(*self, ref mut s => s.write_format(format, buffers, at_ns, end_burst, timeout_us))
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.set_input_scale(scale))
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        dispatch!(*self, ref s => s.canceller())
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        dispatch!(*self, ref s => s.stream_events())
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        dispatch!(*self, ref mut s => s.flush(timeout_us))
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        dispatch!(*self, ref s => s.queued_samples())
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        dispatch!(*self, ref s => s.activation_error_ns())
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::Device;
+
+    #[test]
+    fn dummy_stream() {
+        let dev = Device::from_impl(AnyDevice::from_args("driver=dummy").unwrap());
+        assert!(matches!(
+            dev.impl_ref::<AnyDevice>(),
+            Ok(AnyDevice::Dummy(_))
+        ));
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        assert!(matches!(rx, AnyRxStreamer::Dummy(_)));
+        let mut buf = vec![Complex32::new(0.0, 0.0); 128];
+        rx.activate().unwrap();
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        rx.deactivate().unwrap();
+    }
+}
@@ -0,0 +1,652 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::BTreeMap;
+
+use crate::Args;
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Driver;
+use crate::Error;
+use crate::Range;
+use crate::Sensors;
+
+/// [`DeviceTrait`](crate::DeviceTrait) methods implemented by a driver, see [`driver_matrix`].
+///
+/// The table is declared by hand and has to be updated with the drivers. A method is listed as
+/// unsupported, if it always returns [`Error::NotSupported`](crate::Error::NotSupported). A
+/// supported method may still return it for some directions or channels or, for Soapy,
+/// depending on the hardware.
+#[derive(Debug)]
+pub struct DriverCapabilities {
+    /// Driver.
+    pub driver: Driver,
+    /// True, if the driver is enabled in this build.
+    pub compiled: bool,
+    /// Methods that always return `Err(Error::NotSupported)`.
+    pub unsupported: &'static [&'static str],
+}
+
+impl DriverCapabilities {
+    /// Methods implemented by the driver.
+    pub fn supported(&self) -> impl Iterator<Item = &'static str> + '_ {
+        METHODS
+            .iter()
+            .copied()
+            .filter(|m| !self.unsupported.contains(m))
+    }
+
+    /// Check, if the driver implements a method.
+    pub fn supports(&self, method: &str) -> bool {
+        METHODS.contains(&method) && !self.unsupported.contains(&method)
+    }
+}
+
+/// [`DeviceTrait`](crate::DeviceTrait) methods that return a `Result`, in declaration order.
+const METHODS: &[&str] = &[
+    "id",
+    "info",
+    "reference_docs",
+    "num_channels",
+    "full_duplex",
+    "coherency_groups",
+    "rx_streamer",
+    "tx_streamer",
+    "spectrum_streamer",
+    "antennas",
+    "antenna",
+    "set_antenna",
+    "supports_agc",
+    "enable_agc",
+    "agc",
+    "supports_agc_element",
+    "enable_agc_element",
+    "agc_element",
+    "gain_elements",
+    "set_gain",
+    "gain",
+    "gain_range",
+    "set_gain_element",
+    "gain_element",
+    "gain_element_range",
+    "gain_element_info",
+    "frequency_range",
+    "frequency",
+    "set_frequency",
+    "frequency_components",
+    "component_frequency_range",
+    "component_frequency",
+    "set_component_frequency",
+    "spurs",
+    "sample_rate",
+    "set_sample_rate",
+    "get_sample_rate_range",
+    "bandwidth",
+    "set_bandwidth",
+    "get_bandwidth_range",
+    "has_dc_offset_mode",
+    "set_dc_offset_mode",
+    "dc_offset_mode",
+    "list_loopback_modes",
+    "set_loopback_mode",
+    "loopback_mode",
+    "self_test",
+    "set_power_state",
+    "power_state",
+    "list_time_sources",
+    "time_source",
+    "set_time_source",
+    "gps_locked",
+    "gps_location",
+    "hardware_time",
+    "set_hardware_time",
+    "set_hardware_time_next_pps",
+    "time_discipline",
+    "execute_at",
+];
+
+static DRIVER_MATRIX: &[DriverCapabilities] = &[
+    DriverCapabilities {
+        driver: Driver::Aaronia,
+        compiled: cfg!(all(
+            feature = "aaronia",
+            any(target_os = "linux", target_os = "windows")
+        )),
+        unsupported: &[
+            "coherency_groups",
+            "tx_streamer",
+            "spectrum_streamer",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "spurs",
+            "bandwidth",
+            "set_bandwidth",
+            "get_bandwidth_range",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::AaroniaHttp,
+        compiled: cfg!(all(feature = "aaronia_http", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "coherency_groups",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "set_gain_element",
+            "gain_element",
+            "gain_element_range",
+            "frequency_range",
+            "component_frequency_range",
+            "spurs",
+            "bandwidth",
+            "set_bandwidth",
+            "get_bandwidth_range",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::Dummy,
+        compiled: cfg!(feature = "dummy"),
+        unsupported: &[
+            "spectrum_streamer",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "gps_locked",
+            "gps_location",
+            "set_hardware_time_next_pps",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::File,
+        compiled: cfg!(feature = "file"),
+        unsupported: &[
+            "coherency_groups",
+            "spectrum_streamer",
+            "enable_agc",
+            "agc",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "set_gain",
+            "gain",
+            "gain_range",
+            "set_gain_element",
+            "gain_element",
+            "gain_element_range",
+            "spurs",
+            "set_bandwidth",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::HackRf,
+        compiled: cfg!(all(feature = "hackrfone", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "spectrum_streamer",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::Hpsdr,
+        compiled: cfg!(all(feature = "hpsdr", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "coherency_groups",
+            "spectrum_streamer",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "spurs",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::Ka9q,
+        compiled: cfg!(all(feature = "ka9q", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "coherency_groups",
+            "tx_streamer",
+            "spectrum_streamer",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "spurs",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::RtlSdr,
+        compiled: cfg!(all(feature = "rtlsdr", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "tx_streamer",
+            "spectrum_streamer",
+            "bandwidth",
+            "get_bandwidth_range",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::SignalHound,
+        compiled: cfg!(all(
+            feature = "signalhound",
+            any(target_os = "linux", target_os = "windows")
+        )),
+        unsupported: &[
+            "tx_streamer",
+            "enable_agc",
+            "agc",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "spurs",
+            "has_dc_offset_mode",
+            "set_dc_offset_mode",
+            "dc_offset_mode",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "list_time_sources",
+            "time_source",
+            "set_time_source",
+            "gps_locked",
+            "gps_location",
+            "hardware_time",
+            "set_hardware_time",
+            "set_hardware_time_next_pps",
+            "time_discipline",
+            "execute_at",
+        ],
+    },
+    DriverCapabilities {
+        driver: Driver::Soapy,
+        compiled: cfg!(all(feature = "soapy", not(target_arch = "wasm32"))),
+        unsupported: &[
+            "coherency_groups",
+            "spectrum_streamer",
+            "supports_agc_element",
+            "enable_agc_element",
+            "agc_element",
+            "spurs",
+            "list_loopback_modes",
+            "set_loopback_mode",
+            "loopback_mode",
+            "set_power_state",
+            "power_state",
+            "execute_at",
+        ],
+    },
+];
+
+/// Capabilities of all drivers, including the ones that are not compiled.
+pub fn driver_matrix() -> &'static [DriverCapabilities] {
+    DRIVER_MATRIX
+}
+
+/// Capabilities of an opened device, see [`Device::probe_capabilities`].
+///
+/// Unlike the [`driver_matrix`], the report is queried from the hardware. Values the device does
+/// not report are `None` or empty. The report can be serialized, e.g., to JSON, for tools that
+/// compare devices.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    /// Driver of the device.
+    pub driver: Driver,
+    /// Identifier of the device.
+    pub id: String,
+    /// Driver-specific information.
+    pub info: Args,
+    /// RX channels.
+    pub rx: Vec<ChannelReport>,
+    /// TX channels.
+    pub tx: Vec<ChannelReport>,
+    /// Implemented [extensions](crate::Extension), e.g., `sensors/1`.
+    pub extensions: Vec<String>,
+    /// Sensor readings, e.g., `gps_locked`, by name.
+    pub sensors: BTreeMap<String, String>,
+}
+
+/// Capabilities of a channel of a [`CapabilityReport`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelReport {
+    /// Channel index.
+    pub channel: usize,
+    /// True, if the channel can stream while the other direction streams.
+    pub full_duplex: Option<bool>,
+    /// Available antennas.
+    pub antennas: Vec<String>,
+    /// True, if the channel supports automatic gain control.
+    pub agc: Option<bool>,
+    /// Overall gain range.
+    pub gain_range: Option<Range>,
+    /// Gain elements with their ranges.
+    pub gain_elements: Vec<NamedRange>,
+    /// Overall frequency range.
+    pub frequency_range: Option<Range>,
+    /// Tunable frequency components with their ranges.
+    pub frequency_components: Vec<NamedRange>,
+    /// Sample rate range.
+    pub sample_rate_range: Option<Range>,
+    /// Bandwidth range.
+    pub bandwidth_range: Option<Range>,
+}
+
+/// Range of a named element, e.g., a gain element.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NamedRange {
+    /// Name of the element.
+    pub name: String,
+    /// Range, if reported.
+    pub range: Option<Range>,
+}
+
+impl CapabilityReport {
+    /// Query the capabilities of a device.
+    ///
+    /// Fails only, if the identity or the channels of the device cannot be read. Other values are
+    /// left out.
+    pub fn collect<D: DeviceTrait + Clone + Any>(dev: &Device<D>) -> Result<Self, Error> {
+        let channels = |direction| -> Result<Vec<ChannelReport>, Error> {
+            Ok((0..dev.num_channels(direction)?)
+                .map(|channel| ChannelReport::collect(dev, direction, channel))
+                .collect())
+        };
+
+        let mut sensors = BTreeMap::new();
+        if let Ok(locked) = dev.gps_locked() {
+            sensors.insert("gps_locked".to_string(), locked.to_string());
+        }
+        if let Ok(source) = dev.time_source() {
+            sensors.insert("time_source".to_string(), source);
+        }
+        if let Ok(time) = dev.hardware_time() {
+            sensors.insert("hardware_time_ns".to_string(), time.to_string());
+        }
+        if let Ok(state) = dev.power_state() {
+            sensors.insert("power_state".to_string(), format!("{state:?}"));
+        }
+        if let Some(s) = dev.extension::<dyn Sensors>() {
+            for name in s.list_sensors().unwrap_or_default() {
+                if let Ok(value) = s.read_sensor(&name) {
+                    sensors.insert(name, value);
+                }
+            }
+        }
+
+        Ok(Self {
+            driver: dev.driver(),
+            id: dev.id()?,
+            info: dev.info()?,
+            rx: channels(Direction::Rx)?,
+            tx: channels(Direction::Tx)?,
+            extensions: dev.extensions().names(),
+            sensors,
+        })
+    }
+}
+
+impl ChannelReport {
+    fn collect<D: DeviceTrait + Clone + Any>(
+        dev: &Device<D>,
+        direction: Direction,
+        channel: usize,
+    ) -> Self {
+        let named = |names: Result<Vec<String>, Error>,
+                     range: &dyn Fn(&str) -> Result<Range, Error>| {
+            names
+                .unwrap_or_default()
+                .into_iter()
+                .map(|name| NamedRange {
+                    range: range(&name).ok(),
+                    name,
+                })
+                .collect()
+        };
+        Self {
+            channel,
+            full_duplex: dev.full_duplex(direction, channel).ok(),
+            antennas: dev.antennas(direction, channel).unwrap_or_default(),
+            agc: dev.supports_agc(direction, channel).ok(),
+            gain_range: dev.gain_range(direction, channel).ok(),
+            gain_elements: named(dev.gain_elements(direction, channel), &|name| {
+                dev.gain_element_range(direction, channel, name)
+            }),
+            frequency_range: dev.frequency_range(direction, channel).ok(),
+            frequency_components: named(dev.frequency_components(direction, channel), &|name| {
+                dev.component_frequency_range(direction, channel, name)
+            }),
+            sample_rate_range: dev.get_sample_rate_range(direction, channel).ok(),
+            bandwidth_range: dev.get_bandwidth_range(direction, channel).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix() {
+        let matrix = driver_matrix();
+        assert_eq!(matrix.len(), 10);
+        let rtl = matrix.iter().find(|c| c.driver == Driver::RtlSdr).unwrap();
+        assert_eq!(rtl.compiled, cfg!(feature = "rtlsdr"));
+        assert!(rtl.supports("set_frequency"));
+        assert!(!rtl.supports("gps_locked"));
+        assert!(rtl.unsupported.contains(&"bandwidth"));
+        for c in matrix {
+            assert!(c.unsupported.iter().all(|m| METHODS.contains(m)));
+            assert_eq!(c.supported().count() + c.unsupported.len(), METHODS.len());
+        }
+    }
+
+    /// Check the declared table of a driver against the device: every method listed as
+    /// unsupported has to fail with `Error::NotSupported`.
+    #[cfg(any(feature = "dummy", feature = "file"))]
+    fn check<D: DeviceTrait>(dev: &D, d: Direction) {
+        let caps = driver_matrix()
+            .iter()
+            .find(|c| c.driver == dev.driver())
+            .unwrap();
+        assert!(caps.compiled);
+        let c = 0;
+        for m in caps.unsupported {
+            let r = match *m {
+                "coherency_groups" => dev.coherency_groups().map(drop),
+                "tx_streamer" => dev.tx_streamer(&[c], Args::new()).map(drop),
+                "spectrum_streamer" => dev.spectrum_streamer(c, Args::new()).map(drop),
+                "enable_agc" => dev.enable_agc(d, c, true),
+                "agc" => dev.agc(d, c).map(drop),
+                "supports_agc_element" => dev.supports_agc_element(d, c, "").map(drop),
+                "enable_agc_element" => dev.enable_agc_element(d, c, "", true),
+                "agc_element" => dev.agc_element(d, c, "").map(drop),
+                "set_gain" => dev.set_gain(d, c, 0.0),
+                "gain" => dev.gain(d, c).map(drop),
+                "gain_range" => dev.gain_range(d, c).map(drop),
+                "set_gain_element" => dev.set_gain_element(d, c, "", 0.0).map(drop),
+                "gain_element" => dev.gain_element(d, c, "").map(drop),
+                "gain_element_range" => dev.gain_element_range(d, c, "").map(drop),
+                "gain_element_info" => dev.gain_element_info(d, c, "").map(drop),
+                "frequency_range" => dev.frequency_range(d, c).map(drop),
+                "component_frequency_range" => dev.component_frequency_range(d, c, "").map(drop),
+                "spurs" => dev.spurs(d, c).map(drop),
+                "bandwidth" => dev.bandwidth(d, c).map(drop),
+                "set_bandwidth" => dev.set_bandwidth(d, c, 1e6),
+                "get_bandwidth_range" => dev.get_bandwidth_range(d, c).map(drop),
+                "has_dc_offset_mode" => dev.has_dc_offset_mode(d, c).map(drop),
+                "set_dc_offset_mode" => dev.set_dc_offset_mode(d, c, true),
+                "dc_offset_mode" => dev.dc_offset_mode(d, c).map(drop),
+                "list_loopback_modes" => dev.list_loopback_modes().map(drop),
+                "set_loopback_mode" => dev.set_loopback_mode("none"),
+                "loopback_mode" => dev.loopback_mode().map(drop),
+                "set_power_state" => dev.set_power_state(crate::PowerState::On),
+                "power_state" => dev.power_state().map(drop),
+                "list_time_sources" => dev.list_time_sources().map(drop),
+                "time_source" => dev.time_source().map(drop),
+                "set_time_source" => dev.set_time_source("internal"),
+                "gps_locked" => dev.gps_locked().map(drop),
+                "gps_location" => dev.gps_location().map(drop),
+                "hardware_time" => dev.hardware_time().map(drop),
+                "set_hardware_time" => dev.set_hardware_time(0),
+                "set_hardware_time_next_pps" => dev.set_hardware_time_next_pps(0),
+                "time_discipline" => dev.time_discipline().map(drop),
+                "execute_at" => dev.execute_at(0, &[]),
+                m => panic!("no check for {m}"),
+            };
+            assert!(matches!(r, Err(Error::NotSupported)), "{m}: {r:?}");
+        }
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn dummy_matches_matrix() {
+        check(&crate::impls::Dummy::open("").unwrap(), Direction::Rx);
+    }
+
+    #[cfg(feature = "file")]
+    #[test]
+    fn file_matches_matrix() {
+        let path = std::env::temp_dir().join(format!("{}_seify_matrix.cs8", std::process::id()));
+        let args = format!("tx_path={},sample_rate=8000", path.display());
+        check(
+            &crate::impls::File::open(args.as_str()).unwrap(),
+            Direction::Tx,
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn report() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let report = dev.probe_capabilities().unwrap();
+        assert_eq!(report.driver, Driver::Dummy);
+        assert_eq!(report.rx.len(), 1);
+        assert_eq!(report.rx[0].channel, 0);
+        assert!(report.rx[0].frequency_range.is_some());
+        assert_eq!(report.sensors["temperature"], "25.0");
+
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: CapabilityReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Error;
+use crate::Frequency;
+use crate::Range;
+use crate::RxStreamer;
+use crate::SampleRate;
+use crate::TxStreamer;
+
+/// Channels of a device that are driven by the same sample clock, see
+/// [`DeviceTrait::coherency_groups`].
+///
+/// Channels of a group sample at the same time, so the phase between them is stable as long as
+/// they stay tuned. Beamforming and direction finding need this. Whether it also survives a
+/// retune depends on whether the channels share the LO.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::Device;
+/// use seify::Direction::*;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let groups = dev.coherency_groups().unwrap();
+/// assert!(groups.iter().any(|g| g.contains(Rx, 0) && g.contains(Tx, 0)));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoherencyGroup {
+    /// Direction and index of the channels.
+    pub channels: Vec<(Direction, usize)>,
+    /// True, if the channels also share the LO, so their phase difference is kept across
+    /// retunes. Otherwise, it changes with every retune and has to be calibrated again.
+    pub shared_lo: bool,
+}
+
+impl CoherencyGroup {
+    /// Create a group.
+    pub fn new(channels: Vec<(Direction, usize)>, shared_lo: bool) -> Self {
+        Self {
+            channels,
+            shared_lo,
+        }
+    }
+    /// Is the channel part of the group?
+    pub fn contains(&self, direction: Direction, channel: usize) -> bool {
+        self.channels.contains(&(direction, channel))
+    }
+}
+
+/// Handle to one channel of a [`Device`], created with [`Device::rx_channel`] or
+/// [`Device::tx_channel`].
+///
+/// The handle forwards to the methods of the device, passing its direction and channel, and uses
+/// the typed [`Frequency`] and [`SampleRate`].
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::Device;
+/// use seify::Frequency;
+/// use seify::SampleRate;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let rx = dev.rx_channel(0);
+/// rx.set_frequency(Frequency::mhz(100.0)).unwrap();
+/// rx.set_sample_rate(SampleRate::msps(2.4)).unwrap();
+/// assert_eq!(rx.frequency().unwrap(), Frequency::mhz(100.0));
+/// # }
+/// ```
+pub struct Channel<'a, D: DeviceTrait + Clone + 'static> {
+    dev: &'a Device<D>,
+    direction: Direction,
+    channel: usize,
+}
+
+impl<
+        'a,
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    > Channel<'a, D>
+{
+    pub(crate) fn new(dev: &'a Device<D>, direction: Direction, channel: usize) -> Self {
+        Self {
+            dev,
+            direction,
+            channel,
+        }
+    }
+
+    /// Direction of the channel.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+    /// Index of the channel.
+    pub fn index(&self) -> usize {
+        self.channel
+    }
+    /// Device of the channel.
+    pub fn device(&self) -> &'a Device<D> {
+        self.dev
+    }
+
+    /// Get the center frequency.
+    pub fn frequency(&self) -> Result<Frequency, Error> {
+        self.dev.center_frequency(self.direction, self.channel)
+    }
+    /// Set the center frequency, returning the frequency the driver reports after tuning, see
+    /// [`Device::set_frequency`](crate::Device::set_frequency).
+    pub fn set_frequency(&self, frequency: Frequency) -> Result<Frequency, Error> {
+        self.dev.tune(self.direction, self.channel, frequency)
+    }
+    /// Get the range of the center frequency in Hz.
+    pub fn frequency_range(&self) -> Result<Range, Error> {
+        self.dev.frequency_range(self.direction, self.channel)
+    }
+
+    /// Get the sample rate.
+    pub fn sample_rate(&self) -> Result<SampleRate, Error> {
+        self.dev.rate(self.direction, self.channel)
+    }
+    /// Set the sample rate, returning the rate the device applied.
+    pub fn set_sample_rate(&self, rate: SampleRate) -> Result<SampleRate, Error> {
+        self.dev.set_rate(self.direction, self.channel, rate)
+    }
+    /// Get the range of the sample rate in samples per second.
+    pub fn sample_rate_range(&self) -> Result<Range, Error> {
+        self.dev.get_sample_rate_range(self.direction, self.channel)
+    }
+
+    /// Get the bandwidth of the hardware filter.
+    pub fn bandwidth(&self) -> Result<Frequency, Error> {
+        self.dev
+            .bandwidth(self.direction, self.channel)
+            .map(Frequency::hz)
+    }
+    /// Set the bandwidth of the hardware filter.
+    pub fn set_bandwidth(&self, bandwidth: Frequency) -> Result<(), Error> {
+        self.dev
+            .set_bandwidth(self.direction, self.channel, bandwidth.as_hz())
+    }
+
+    /// Get the overall gain in dB, `None` if AGC is active.
+    pub fn gain(&self) -> Result<Option<f64>, Error> {
+        self.dev.gain(self.direction, self.channel)
+    }
+    /// Set the overall gain in dB, returning the gain the device applied.
+    pub fn set_gain(&self, gain: f64) -> Result<f64, Error> {
+        self.dev.set_gain(self.direction, self.channel, gain)
+    }
+    /// Get the range of the overall gain in dB.
+    pub fn gain_range(&self) -> Result<Range, Error> {
+        self.dev.gain_range(self.direction, self.channel)
+    }
+    /// List the gain elements.
+    pub fn gain_elements(&self) -> Result<Vec<String>, Error> {
+        self.dev.gain_elements(self.direction, self.channel)
+    }
+    /// Set the gain of an element in dB, returning the applied gain.
+    pub fn set_gain_element(&self, name: &str, gain: f64) -> Result<f64, Error> {
+        self.dev
+            .set_gain_element(self.direction, self.channel, name, gain)
+    }
+
+    /// Is automatic gain control enabled?
+    pub fn agc(&self) -> Result<bool, Error> {
+        self.dev.agc(self.direction, self.channel)
+    }
+    /// Enable or disable automatic gain control.
+    pub fn enable_agc(&self, agc: bool) -> Result<(), Error> {
+        self.dev.enable_agc(self.direction, self.channel, agc)
+    }
+
+    /// List the antennas.
+    pub fn antennas(&self) -> Result<Vec<String>, Error> {
+        self.dev.antennas(self.direction, self.channel)
+    }
+    /// Get the selected antenna.
+    pub fn antenna(&self) -> Result<String, Error> {
+        self.dev.antenna(self.direction, self.channel)
+    }
+    /// Select an antenna.
+    pub fn set_antenna(&self, name: &str) -> Result<(), Error> {
+        self.dev.set_antenna(self.direction, self.channel, name)
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwards_to_device() {
+        let dev = Device::from_impl(crate::impls::Dummy::open(crate::Args::new()).unwrap());
+        let tx = dev.tx_channel(0);
+        assert_eq!(tx.direction(), Direction::Tx);
+        tx.set_frequency(Frequency::mhz(433.92)).unwrap();
+        tx.set_gain(10.0).unwrap();
+        assert_eq!(
+            dev.center_frequency(Direction::Tx, 0).unwrap().as_hz(),
+            433.92e6
+        );
+        assert_eq!(tx.gain().unwrap(), Some(10.0));
+        assert_eq!(tx.antennas().unwrap(), ["A"]);
+    }
+}
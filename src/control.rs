@@ -0,0 +1,449 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::ToSocketAddrs;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::http::Request;
+use crate::http::Response;
+use crate::Access;
+use crate::Args;
+use crate::Device;
+use crate::Direction;
+use crate::Driver;
+use crate::Error;
+use crate::Frequency;
+use crate::GenericDevice;
+use crate::Permission;
+use crate::SampleRate;
+
+/// JSON HTTP server for the control plane of a device.
+///
+/// Dashboards and scripts can inspect and adjust a running application through the server:
+///
+/// | Request | Response |
+/// |---|---|
+/// | `GET /enumerate` | [`Args`] of the available devices |
+/// | `GET /device` | driver, id, info, and number of channels of the device |
+/// | `GET /rx/<channel>`, `GET /tx/<channel>` | settings of the channel |
+/// | `PUT /rx/<channel>`, `PUT /tx/<channel>` | apply settings and return the new settings |
+///
+/// Settings are `frequency`, `sample_rate`, `bandwidth`, `gain`, `agc`, and `antenna`. A `PUT`
+/// only changes the settings in the body, e.g., `{"frequency": 100e6, "agc": true}`. Errors are
+/// returned as `{"error": "..."}`.
+///
+/// Several processes sharing the device arbitrate through claims. A client claims the device
+/// exclusively with `POST /claim`, which returns a token, and passes the token as `?token=...`
+/// query parameter to changing requests and `POST /release`. While the device is claimed, other
+/// clients can only observe it with `GET` requests and see the claim with `GET /claim`. Claims
+/// expire after the [lease](Self::lease), unless they are renewed by claiming again with the
+/// token. Without a claim, any client can change the settings.
+///
+/// The server only covers the control plane. To share the samples, a daemon can serve the RX
+/// stream of the device with a [`FanOut`](crate::FanOut) and an `IqWebSocketServer` (cargo
+/// feature `websocket`), as the `seifyd` example does.
+///
+/// On shared networks, the server should require [access tokens](Self::access), where clients
+/// with a [read-only](Permission::ReadOnly) token can only send `GET` requests.
+pub struct ControlServer {
+    device: Device<GenericDevice>,
+    claim: Mutex<Option<Claim>>,
+    lease: Duration,
+    access: Access,
+}
+
+/// Exclusive claim of the device by a client.
+struct Claim {
+    token: String,
+    expires: Instant,
+}
+
+/// Settings of a channel, returned by `GET /rx/<channel>`.
+#[derive(Debug, Default, Serialize)]
+struct ChannelState {
+    frequency: Option<f64>,
+    sample_rate: Option<f64>,
+    bandwidth: Option<f64>,
+    gain: Option<f64>,
+    agc: Option<bool>,
+    antenna: Option<String>,
+    antennas: Vec<String>,
+}
+
+/// Settings to change with `PUT /rx/<channel>`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ChannelSettings {
+    frequency: Option<f64>,
+    sample_rate: Option<f64>,
+    bandwidth: Option<f64>,
+    gain: Option<f64>,
+    agc: Option<bool>,
+    antenna: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeviceState {
+    driver: Driver,
+    id: Option<String>,
+    info: Option<Args>,
+    rx_channels: usize,
+    tx_channels: usize,
+}
+
+impl ControlServer {
+    /// Create a server for the device.
+    pub fn new(device: Device<GenericDevice>) -> Self {
+        Self {
+            device,
+            claim: Mutex::new(None),
+            lease: Duration::from_secs(30),
+            access: Access::new(),
+        }
+    }
+
+    /// Set the access tokens and TLS configuration. Defaults to an open server.
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Set how long a claim is valid without renewal. Defaults to 30 s.
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Serve the API over HTTP, or HTTPS if configured, from a background thread.
+    pub fn serve<A: ToSocketAddrs>(self, addr: A) -> Result<JoinHandle<()>, Error> {
+        let access = self.access.clone();
+        crate::http::serve(addr, access, move |request, permission| {
+            match self.handle(request, permission) {
+                Ok(body) => Response::new("200 OK", "application/json", body),
+                Err(e) => error_response(e),
+            }
+        })
+    }
+
+    fn handle(&self, request: Request, permission: Permission) -> Result<String, Error> {
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        if request.method != "GET" && permission < Permission::Control {
+            return Err(Error::PermissionDenied {
+                remedy: "the access token only permits read-only access".to_string(),
+            });
+        }
+        let token = query
+            .split('&')
+            .find_map(|kv| kv.strip_prefix("token="))
+            .unwrap_or("");
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let method = request.method.as_str();
+        match (method, segments.as_slice()) {
+            ("GET", ["claim"]) => {
+                let claimed = self.claim().is_some();
+                Ok(serde_json::json!({ "claimed": claimed }).to_string())
+            }
+            ("POST", ["claim"]) => {
+                // concurrent claims are decided under the same lock
+                let mut claim = self.claim();
+                check_owner(&claim, token)?;
+                let token = claim.as_ref().map_or_else(
+                    || format!("{:016x}", RandomState::new().hash_one(Instant::now())),
+                    |c| c.token.clone(),
+                );
+                *claim = Some(Claim {
+                    token: token.clone(),
+                    expires: Instant::now() + self.lease,
+                });
+                let lease = self.lease.as_secs_f64();
+                Ok(serde_json::json!({ "token": token, "lease": lease }).to_string())
+            }
+            ("POST", ["release"]) => {
+                let mut claim = self.claim();
+                check_owner(&claim, token)?;
+                *claim = None;
+                Ok("{}".to_string())
+            }
+            ("GET", ["enumerate"]) => Ok(serde_json::to_string(&crate::enumerate()?)?),
+            ("GET", ["device"]) => {
+                let dev = &self.device;
+                let state = DeviceState {
+                    driver: dev.driver(),
+                    id: dev.id().ok(),
+                    info: dev.info().ok(),
+                    rx_channels: dev.num_channels(Direction::Rx)?,
+                    tx_channels: dev.num_channels(Direction::Tx)?,
+                };
+                Ok(serde_json::to_string(&state)?)
+            }
+            ("GET" | "PUT" | "POST", [dir, channel]) => {
+                let direction = match *dir {
+                    "rx" => Direction::Rx,
+                    "tx" => Direction::Tx,
+                    _ => return Err(Error::NotFound),
+                };
+                let channel: usize = channel.parse().or(Err(Error::NotFound))?;
+                if channel >= self.device.num_channels(direction)? {
+                    return Err(Error::NotFound);
+                }
+                if method != "GET" {
+                    // the claim cannot change while the settings are applied
+                    let claim = self.claim();
+                    check_owner(&claim, token)?;
+                    let settings: ChannelSettings = serde_json::from_slice(&request.body)?;
+                    self.apply(direction, channel, settings)?;
+                }
+                Ok(serde_json::to_string(&self.state(direction, channel))?)
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    /// Lock the current claim, dropping it, if it expired.
+    fn claim(&self) -> MutexGuard<'_, Option<Claim>> {
+        let mut claim = self.claim.lock().unwrap();
+        if claim.as_ref().is_some_and(|c| c.expires < Instant::now()) {
+            *claim = None;
+        }
+        claim
+    }
+
+    fn state(&self, direction: Direction, channel: usize) -> ChannelState {
+        let dev = &self.device;
+        ChannelState {
+            frequency: dev
+                .center_frequency(direction, channel)
+                .map(Frequency::as_hz)
+                .ok(),
+            sample_rate: dev.rate(direction, channel).map(SampleRate::as_sps).ok(),
+            bandwidth: dev.bandwidth(direction, channel).ok(),
+            gain: dev.gain(direction, channel).ok().flatten(),
+            agc: dev.agc(direction, channel).ok(),
+            antenna: dev.antenna(direction, channel).ok(),
+            antennas: dev.antennas(direction, channel).unwrap_or_default(),
+        }
+    }
+
+    /// Apply settings, changing the sample rate first, since it can affect the other settings.
+    fn apply(&self, direction: Direction, channel: usize, s: ChannelSettings) -> Result<(), Error> {
+        let dev = &self.device;
+        if let Some(rate) = s.sample_rate {
+            dev.set_rate(direction, channel, SampleRate::sps(rate))?;
+        }
+        if let Some(bw) = s.bandwidth {
+            dev.set_bandwidth(direction, channel, bw)?;
+        }
+        if let Some(antenna) = s.antenna {
+            dev.set_antenna(direction, channel, &antenna)?;
+        }
+        if let Some(agc) = s.agc {
+            dev.enable_agc(direction, channel, agc)?;
+        }
+        if let Some(gain) = s.gain {
+            dev.set_gain(direction, channel, gain)?;
+        }
+        if let Some(frequency) = s.frequency {
+            dev.tune(direction, channel, Frequency::hz(frequency))?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that the device is not claimed by a different client.
+fn check_owner(claim: &Option<Claim>, token: &str) -> Result<(), Error> {
+    match claim {
+        Some(c) if c.token != token => Err(Error::PermissionDenied {
+            remedy: "the device is claimed by another client".to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn error_response(e: Error) -> Response {
+    let status = match e {
+        Error::ValueError | Error::OutOfRange(..) | Error::Json(_) => "400 Bad Request",
+        Error::Unauthorized => "401 Unauthorized",
+        Error::PermissionDenied { .. } => "403 Forbidden",
+        Error::NotFound => "404 Not Found",
+        Error::NotSupported => "501 Not Implemented",
+        _ => "500 Internal Server Error",
+    };
+    let body = serde_json::json!({ "error": e.to_string() }).to_string();
+    Response::new(status, "application/json", body)
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+
+    fn request(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> String {
+        let mut s = TcpStream::connect(addr).unwrap();
+        write!(
+            s,
+            "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        s.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn get_and_set() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        ControlServer::new(dev.clone()).serve(addr).unwrap();
+
+        let r = request(addr, "PUT", "/rx/0", r#"{"frequency": 123e6, "gain": 10}"#);
+        assert!(r.starts_with("HTTP/1.1 200 OK"), "{r}");
+        assert!(r.contains("\"frequency\":123000000.0"));
+        assert_eq!(
+            dev.center_frequency(Direction::Rx, 0).unwrap().as_hz(),
+            123e6
+        );
+
+        let r = request(addr, "GET", "/device", "");
+        assert!(r.contains("\"driver\":\"Dummy\""));
+        let r = request(addr, "PUT", "/rx/0", r#"{"freq": 1}"#);
+        assert!(r.starts_with("HTTP/1.1 400"));
+        let r = request(addr, "GET", "/rx/7", "");
+        assert!(r.starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn access_tokens() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let access = Access::new()
+            .token("rw", Permission::Control)
+            .token("ro", Permission::ReadOnly);
+        ControlServer::new(dev).access(access).serve(addr).unwrap();
+
+        assert!(request(addr, "GET", "/device", "").starts_with("HTTP/1.1 401"));
+        assert!(request(addr, "GET", "/device?access_token=x", "").starts_with("HTTP/1.1 401"));
+        let r = request(addr, "GET", "/rx/0?access_token=ro", "");
+        assert!(r.starts_with("HTTP/1.1 200"), "{r}");
+        let r = request(addr, "PUT", "/rx/0?access_token=ro", r#"{"gain": 5}"#);
+        assert!(r.starts_with("HTTP/1.1 403"));
+        assert!(request(addr, "POST", "/claim?access_token=ro", "").starts_with("HTTP/1.1 403"));
+
+        let mut s = TcpStream::connect(addr).unwrap();
+        let body = r#"{"gain": 5}"#;
+        write!(
+            s,
+            "PUT /rx/0 HTTP/1.1\r\nAuthorization: Bearer rw\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut r = String::new();
+        s.read_to_string(&mut r).unwrap();
+        assert!(r.starts_with("HTTP/1.1 200"), "{r}");
+    }
+
+    #[test]
+    fn slow_clients() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let access = Access::new().token("rw", Permission::Control);
+        ControlServer::new(dev).access(access).serve(addr).unwrap();
+
+        // a client that stalls does not block the others
+        let mut stalled = TcpStream::connect(addr).unwrap();
+        write!(stalled, "PUT /rx/0 HTTP/1.1\r\n").unwrap();
+        let r = request(addr, "GET", "/device?access_token=rw", "");
+        assert!(r.starts_with("HTTP/1.1 200"), "{r}");
+
+        // clients are rejected before they send the body
+        let mut s = TcpStream::connect(addr).unwrap();
+        write!(s, "PUT /rx/0 HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n").unwrap();
+        let mut r = String::new();
+        s.read_to_string(&mut r).unwrap();
+        assert!(r.starts_with("HTTP/1.1 401"), "{r}");
+
+        // the stalled request is dropped after the request timeout
+        stalled
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        let mut r = String::new();
+        let _ = stalled.read_to_string(&mut r);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn claim_and_release() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        ControlServer::new(dev.clone())
+            .lease(Duration::from_millis(300))
+            .serve(addr)
+            .unwrap();
+
+        let r = request(addr, "POST", "/claim", "");
+        let body: serde_json::Value =
+            serde_json::from_str(r.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        let token = body["token"].as_str().unwrap().to_string();
+        assert!(request(addr, "GET", "/claim", "").contains("\"claimed\":true"));
+        // observers can read, but not change or claim
+        assert!(request(addr, "GET", "/rx/0", "").starts_with("HTTP/1.1 200"));
+        let r = request(addr, "PUT", "/rx/0", r#"{"gain": 5}"#);
+        assert!(r.starts_with("HTTP/1.1 403"), "{r}");
+        assert!(request(addr, "POST", "/claim", "").starts_with("HTTP/1.1 403"));
+        let path = format!("/rx/0?token={token}");
+        assert!(request(addr, "PUT", &path, r#"{"gain": 5}"#).starts_with("HTTP/1.1 200"));
+        // renewing keeps the token
+        let r = request(addr, "POST", &format!("/claim?token={token}"), "");
+        assert!(r.contains(&token));
+        let r = request(addr, "POST", &format!("/release?token={token}"), "");
+        assert!(r.starts_with("HTTP/1.1 200"));
+        assert!(request(addr, "GET", "/claim", "").contains("\"claimed\":false"));
+
+        // expired claims are dropped
+        request(addr, "POST", "/claim", "");
+        std::thread::sleep(Duration::from_millis(400));
+        assert!(request(addr, "PUT", "/rx/0", r#"{"gain": 1}"#).starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn concurrent_claims() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        ControlServer::new(dev).serve(addr).unwrap();
+
+        let start = std::sync::Arc::new(std::sync::Barrier::new(8));
+        let clients: Vec<_> = (0..8)
+            .map(|_| {
+                let start = start.clone();
+                std::thread::spawn(move || {
+                    start.wait();
+                    request(addr, "POST", "/claim", "")
+                })
+            })
+            .collect();
+        let granted = clients
+            .into_iter()
+            .map(|c| c.join().unwrap())
+            .filter(|r| r.starts_with("HTTP/1.1 200"))
+            .count();
+        assert_eq!(granted, 1);
+    }
+}
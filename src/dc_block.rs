@@ -0,0 +1,185 @@
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+
+use num_complex::Complex32;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+
+/// RX streamer adapter that removes the DC spike of zero-IF receivers.
+///
+/// Each channel runs through a first-order IIR filter that tracks the DC offset,
+/// `dc += alpha * (x - dc)`, and subtracts it from the samples. Smaller values of `alpha` notch
+/// a narrower band around DC but settle slower.
+///
+/// Streamers of a [`GenericDevice`](crate::GenericDevice) are wrapped automatically, if the
+/// `dc_block` streamer argument is `auto` (using [`DEFAULT_ALPHA`](Self::DEFAULT_ALPHA)) or a
+/// value for `alpha`. It is off by default, keeping the raw samples of the driver.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::Args;
+/// use seify::Device;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let args = Args::from("dc_block=auto").unwrap();
+/// let rx = dev.rx_streamer_with_args(&[0], args).unwrap();
+/// # }
+/// ```
+pub struct DcBlocker<S: RxStreamer> {
+    inner: S,
+    alpha: f32,
+    dc: Vec<Complex32>,
+}
+
+impl<S: RxStreamer> DcBlocker<S> {
+    /// Coefficient used for `dc_block=auto`.
+    pub const DEFAULT_ALPHA: f32 = 1e-4;
+
+    /// Filter the samples of a streamer, `alpha` has to be in `(0, 1]`.
+    pub fn new(inner: S, alpha: f32) -> Result<Self, Error> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(Error::ValueError);
+        }
+        Ok(Self {
+            inner,
+            alpha,
+            dc: Vec::new(),
+        })
+    }
+    /// Get a reference to the wrapped streamer.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    /// Remove the filter and get the wrapped streamer.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn filter(&mut self, buffers: &mut [&mut [Complex32]], n: usize) {
+        self.dc.resize(buffers.len(), Complex32::new(0.0, 0.0));
+        for (b, dc) in buffers.iter_mut().zip(self.dc.iter_mut()) {
+            for s in b[..n].iter_mut() {
+                *dc += (*s - *dc) * self.alpha;
+                *s -= *dc;
+            }
+        }
+    }
+}
+
+/// Parse the `dc_block` streamer argument: `off` (default), `auto`, or the filter coefficient.
+pub(crate) fn alpha_from_args(args: &Args) -> Result<Option<f32>, Error> {
+    match args.get::<String>("dc_block").as_deref() {
+        Err(Error::NotFound) | Ok("off") => Ok(None),
+        Ok("auto") => Ok(Some(DcBlocker::<Box<dyn RxStreamer>>::DEFAULT_ALPHA)),
+        Ok(alpha) => Ok(Some(alpha.parse().or(Err(Error::ValueError))?)),
+        Err(_) => Err(Error::ValueError),
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for DcBlocker<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.dc.clear();
+        self.inner.activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        let n = self.inner.read(buffers, timeout_us)?;
+        self.filter(buffers, n);
+        Ok(n)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let (n, meta) = self.inner.read_with_metadata(buffers, timeout_us)?;
+        self.filter(buffers, n);
+        Ok((n, meta))
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Constant offset with a small tone on top.
+    struct Offset(usize);
+
+    impl RxStreamer for Offset {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(1024)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn read(
+            &mut self,
+            buffers: &mut [&mut [Complex32]],
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            for s in buffers[0].iter_mut() {
+                let tone = Complex32::from_polar(0.1, self.0 as f32 * 0.5);
+                *s = Complex32::new(0.3, -0.2) + tone;
+                self.0 += 1;
+            }
+            Ok(buffers[0].len())
+        }
+    }
+
+    #[test]
+    fn removes_offset() {
+        let mut rx = DcBlocker::new(Offset(0), 1e-2).unwrap();
+        let mut buf = vec![Complex32::new(0.0, 0.0); 4096];
+        rx.read(&mut [&mut buf], 0).unwrap();
+        let mean: Complex32 = buf[2048..].iter().sum::<Complex32>() / 2048.0;
+        assert!(mean.norm() < 1e-2, "{mean}");
+        // the tone passes
+        assert!((buf[4095].norm() - 0.1).abs() < 1e-2);
+
+        assert!(DcBlocker::new(Offset(0), 0.0).is_err());
+        let args = Args::from("dc_block=auto").unwrap();
+        assert_eq!(alpha_from_args(&args).unwrap(), Some(1e-4));
+        assert_eq!(alpha_from_args(&Args::new()).unwrap(), None);
+        assert!(alpha_from_args(&Args::from("dc_block=on").unwrap()).is_err());
+    }
+}
@@ -2,21 +2,70 @@
 #![allow(unused_variables)]
 use std::any::Any;
 use std::sync::Arc;
-
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::anti_alias::AntiAlias;
+use crate::restart;
+use crate::restart::Change;
+use crate::slew::SlewLimits;
+use crate::spurs;
+use crate::validate;
 use crate::Args;
+use crate::CapabilityReport;
+use crate::Channel;
+use crate::CoherencyGroup;
+use crate::DeviceState;
 use crate::Direction;
 use crate::Driver;
 use crate::Error;
+use crate::Event;
+use crate::EventKind;
+use crate::EventLog;
+use crate::Extension;
+use crate::Extensions;
+use crate::Frequency;
+use crate::GainElementInfo;
+use crate::Latency;
+use crate::Location;
+use crate::PowerState;
 use crate::Range;
+use crate::ReferenceDoc;
 use crate::RxStreamer;
+use crate::Sample;
+use crate::SampleRate;
+use crate::SampleRateClock;
+use crate::SelfTestReport;
+use crate::SpectrumStreamer;
+use crate::Spur;
+use crate::TimeDiscipline;
+use crate::TimedCommand;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::TimedCommands;
 use crate::TxStreamer;
+use crate::TypedRxStreamer;
+use crate::TypedTxStreamer;
 
 /// Central trait, implemented by hardware drivers.
+///
+/// Devices are `Send` and, to be used as [`GenericDevice`], also `Sync`, since they are shared
+/// between threads that configure the hardware and threads that stream. Drivers whose handles
+/// are not thread safe can wrap them in a [`SharedHandle`](crate::SharedHandle) instead of
+/// implementing `Send` and `Sync` unsafely.
+///
+/// Streamers keep the device open. They own the driver state they need, e.g., behind an `Arc`,
+/// and never borrow from the device, so a stream keeps running after the device and all its
+/// clones were dropped. Dropping the device must neither stop nor block its streamers. The
+/// hardware is released once the device and all its streamers are dropped.
+///
+/// Drivers must not panic. Methods for functionality that a driver or the hardware does not
+/// provide return `Err(Error::NotSupported)`; invalid directions, channels, or element names
+/// return `Err(Error::ValueError)`.
 pub trait DeviceTrait: Any + Send {
-    /// Associated RX streamer
-    type RxStreamer: RxStreamer;
-    /// Associated TX streamer
-    type TxStreamer: TxStreamer;
+    /// Associated RX streamer, owning what it needs from the device
+    type RxStreamer: RxStreamer + 'static;
+    /// Associated TX streamer, owning what it needs from the device
+    type TxStreamer: TxStreamer + 'static;
 
     /// Cast to Any for downcasting.
     fn as_any(&self) -> &dyn Any;
@@ -29,17 +78,49 @@ pub trait DeviceTrait: Any + Send {
     fn id(&self) -> Result<String, Error>;
     /// Device info that can be displayed to the user.
     fn info(&self) -> Result<Args, Error>;
+    /// Links to documentation of the hardware or the library of the driver.
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(Vec::new())
+    }
+    /// Optional functionality implemented by the driver, see [`Extension`].
+    fn extensions(&self) -> Extensions {
+        Extensions::new()
+    }
     /// Number of supported Channels.
     fn num_channels(&self, direction: Direction) -> Result<usize, Error>;
     /// Full Duplex support.
     fn full_duplex(&self, direction: Direction, channel: usize) -> Result<bool, Error>;
 
+    /// Groups of channels that are driven by the same clock, see [`CoherencyGroup`].
+    ///
+    /// The phase between channels of different groups is not meaningful. Returns
+    /// `Err(Error::NotSupported)`, if the driver does not know how the channels are clocked.
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        Err(Error::NotSupported)
+    }
+
     //================================ STREAMER ============================================
     /// Create an RX streamer.
     fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error>;
     /// Create a TX streamer.
     fn tx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::TxStreamer, Error>;
 
+    /// Create a native spectrum streamer for an RX channel, e.g., of a spectrum analyzer.
+    fn spectrum_streamer(
+        &self,
+        _channel: usize,
+        _args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Event log, to which the streamers of the driver report events, like overflows.
+    ///
+    /// A [`Device`] created from the driver uses it as its [event log](Device::event_log).
+    fn driver_event_log(&self) -> Option<EventLog> {
+        None
+    }
+
     //================================ ANTENNA ============================================
     /// List of available antenna ports.
     fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error>;
@@ -58,6 +139,40 @@ pub trait DeviceTrait: Any + Send {
     /// Returns true, if automatic gain control is enabled
     fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error>;
 
+    /// Does the gain element support automatic gain control?
+    fn supports_agc_element(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+    ) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Enable or disable automatic gain control of a single gain element.
+    ///
+    /// Elements can be AGC stages that are not listed in [`gain_elements`](Self::gain_elements),
+    /// e.g., a digital AGC in the ADC.
+    fn enable_agc_element(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+        _agc: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Returns true, if automatic gain control is enabled for a gain element.
+    fn agc_element(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+    ) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
     //================================ GAIN ============================================
     /// List of available gain elements.
     ///
@@ -81,13 +196,16 @@ pub trait DeviceTrait: Any + Send {
     /// ## Arguments
     /// * `name`: the name of an amplification element from `Device::list_gains`
     /// * `gain`: the new amplification value in dB
+    ///
+    /// Returns the gain the element applied, read back through
+    /// [`gain_element`](Self::gain_element), or the requested gain, if the driver cannot read it.
     fn set_gain_element(
         &self,
         direction: Direction,
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error>;
+    ) -> Result<f64, Error>;
 
     /// Get the value of an individual amplification element in a chain in dB.
     fn gain_element(
@@ -105,9 +223,38 @@ pub trait DeviceTrait: Any + Send {
         name: &str,
     ) -> Result<Range, Error>;
 
+    /// Describe what the value of a gain element means, e.g., to label it in a user interface.
+    ///
+    /// The default implementation describes elements listed by `gain_elements` as plain gains in dB.
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        if self
+            .gain_elements(direction, channel)?
+            .iter()
+            .any(|e| e == name)
+        {
+            Ok(GainElementInfo::new(
+                name,
+                name,
+                "dB",
+                format!("Gain of the {name} element in dB."),
+            ))
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
     //================================ FREQUENCY ============================================
 
     /// Get the ranges of overall frequency values.
+    ///
+    /// Devices with multiple converter paths report one interval per path, where adjacent paths
+    /// share their boundary. A [`GenericDevice`] keeps the sampled band within one of them, see
+    /// [`Device::set_frequency_with_args`].
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error>;
 
     /// Get the overall center frequency of the chain.
@@ -186,6 +333,13 @@ pub trait DeviceTrait: Any + Send {
         frequency: f64,
     ) -> Result<(), Error>;
 
+    /// Known spurs (birdies) of the chain, e.g., clock harmonics and the DC spike.
+    ///
+    /// Used to plan the LO, when tuning with `avoid_spurs=1`, see [`Device::set_frequency_with_args`].
+    fn spurs(&self, _direction: Direction, _channel: usize) -> Result<Vec<Spur>, Error> {
+        Err(Error::NotSupported)
+    }
+
     //================================ SAMPLE RATE ============================================
 
     /// Get the baseband sample rate of the chain in samples per second.
@@ -230,8 +384,132 @@ pub trait DeviceTrait: Any + Send {
 
     /// Returns true if automatic DC offset mode is enabled
     fn dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error>;
+
+    //================================ LOOPBACK ============================================
+    /// List of available loopback and test signal modes.
+    ///
+    /// Loopback modes route the TX chain or an internal test signal to the RX chain, which allows
+    /// self-test routines without external equipment. The mode `none` disables the loopback.
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Set the loopback or test signal mode.
+    fn set_loopback_mode(&self, _mode: &str) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Currently used loopback or test signal mode.
+    fn loopback_mode(&self) -> Result<String, Error> {
+        Err(Error::NotSupported)
+    }
+
+    //================================ SELF TEST ============================================
+    /// Run a health check of the device.
+    ///
+    /// The default implementation reads back control values, streams a few samples, and runs an
+    /// internal loopback test, if available. Drivers can override this with hardware-specific
+    /// checks. Failed checks are reported in the [`SelfTestReport`], while the returned error is
+    /// reserved for cases where no test could be run at all.
+    fn self_test(&self) -> Result<SelfTestReport, Error> {
+        Ok(crate::self_test::run(self))
+    }
+
+    //================================ POWER ============================================
+    /// Put the device in a [power state](PowerState).
+    ///
+    /// Streams have to be deactivated before leaving [`PowerState::On`].
+    fn set_power_state(&self, _state: PowerState) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Current [power state](PowerState).
+    fn power_state(&self) -> Result<PowerState, Error> {
+        Err(Error::NotSupported)
+    }
+
+    //================================ TIME ============================================
+    /// List of available time sources, e.g., `internal`, `external` (PPS input), or `gpsdo`.
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Currently used time source.
+    fn time_source(&self) -> Result<String, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Set the time source.
+    fn set_time_source(&self, _name: &str) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Returns true, if the GPSDO of the device is locked.
+    fn gps_locked(&self) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Position reported by the GPS receiver of the device.
+    ///
+    /// Fails with [`Error::NotFound`], if the receiver has no fix.
+    fn gps_location(&self) -> Result<Location, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Current hardware time in nanoseconds.
+    fn hardware_time(&self) -> Result<i64, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Set the hardware time in nanoseconds immediately.
+    fn set_hardware_time(&self, _time_ns: i64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Set the hardware time in nanoseconds on the next PPS edge.
+    ///
+    /// To align devices to UNIX time, call this right after a PPS edge with the time of the next
+    /// full second.
+    fn set_hardware_time_next_pps(&self, _time_ns: i64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Status of the time discipline, i.e., time source, GPS lock, and offset to the host clock.
+    ///
+    /// The default implementation combines the other time-related methods and requires
+    /// [`hardware_time`](Self::hardware_time).
+    fn time_discipline(&self) -> Result<TimeDiscipline, Error> {
+        crate::time::status(self)
+    }
+
+    /// Queue commands in the hardware command queue, to take effect at `time_ns` of the hardware
+    /// time.
+    ///
+    /// Only drivers with a command queue implement this. For others, [`Device::at`] executes the
+    /// commands on a scheduler thread instead.
+    fn execute_at(&self, _time_ns: i64, _commands: &[TimedCommand]) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
 }
 
+/// Marker for drivers that can receive.
+///
+/// See [`TxInterface`].
+pub trait RxInterface: DeviceTrait {}
+
+/// Marker for drivers that can transmit.
+///
+/// The markers do not split the [`DeviceTrait`]: every driver still implements it, including
+/// streamer types for both directions, whose methods fail with [`Error::NotSupported`] if the
+/// hardware lacks the direction. Only drivers with a working streamer for a direction implement
+/// its marker. Applications that transmit can require this trait to reject receive-only
+/// drivers, like the RTL-SDR, at compile time, e.g., with a `D: TxInterface` bound on a
+/// [`Device<D>`].
+///
+/// [`GenericDevice`] implements both markers, since the capabilities of the wrapped driver are
+/// only known at runtime.
+pub trait TxInterface: DeviceTrait {}
+
 /// Wrapps a driver, implementing the [DeviceTrait].
 ///
 /// Implements a more ergonomic version of the [`DeviceTrait`], e.g., using `Into<Args>`, which
@@ -239,6 +517,9 @@ pub trait DeviceTrait: Any + Send {
 #[derive(Clone)]
 pub struct Device<T: DeviceTrait + Clone + Any> {
     dev: T,
+    events: EventLog,
+    latency: Arc<Mutex<Option<Latency>>>,
+    location: Arc<Mutex<Option<Location>>>,
 }
 
 impl Device<GenericDevice> {
@@ -255,124 +536,124 @@ impl Device<GenericDevice> {
     /// Creates a [`GenericDevice`] opening the first device with a given `driver`, specified in
     /// the `args` or the first device discovered through [`enumerate`](crate::enumerate) that
     /// matches the args.
+    ///
+    /// Fails with [`Error::FeatureNotEnabled`], if the `driver` is not compiled in.
+    ///
+    /// The [`location`](Self::location) of the device is set from the `lat`, `lon`, `alt`, and
+    /// `azimuth` arguments or, with the `gpsd` feature, queried from the gpsd daemon at the
+    /// address given as `gpsd` argument.
+    ///
+    /// To protect downstream amplifiers from abrupt power steps, e.g., during automated sweeps,
+    /// the changes of the device can be slew-rate limited:
+    /// - `max_gain_step_db_per_call`: each [`set_gain`](Self::set_gain) or
+    ///   [`set_gain_element`](Self::set_gain_element) call moves the gain at most this far
+    ///   towards the requested value, so that larger changes take several calls.
+    /// - `max_retune_step_hz`: [`set_frequency`](Self::set_frequency) ramps to the requested
+    ///   frequency in steps of at most this size.
+    /// - `retune_dwell_us`: time to wait at each intermediate frequency of a ramp.
+    ///
+    /// With `auto_bandwidth=true`, the analog bandwidth follows the sample rate, limiting
+    /// [`set_bandwidth`](Self::set_bandwidth) to 0.8 times the rate to reject aliases. A number
+    /// instead of `true` sets another fraction of the rate.
     pub fn from_args<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
         let args = args.try_into().map_err(|_| Error::ValueError)?;
+        let location = match (Location::from_args(&args)?, args.get::<String>("gpsd")) {
+            (None, Ok(addr)) => Some(Location::from_gpsd(&addr, Duration::from_secs(2))?),
+            (location, _) => location,
+        };
+        let dev = Self::open(args)?;
+        dev.set_location(location);
+        Ok(dev)
+    }
+
+    fn open(args: Args) -> Result<Self, Error> {
         let driver = match args.get::<Driver>("driver") {
             Ok(d) => Some(d),
             Err(Error::NotFound) => None,
             Err(e) => return Err(e),
         };
-        #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::Aaronia)) {
-                match crate::impls::Aaronia::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
-            }
-        }
-        #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::AaroniaHttp)) {
-                match crate::impls::AaroniaHttp::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
-            }
-        }
-        #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::RtlSdr)) {
-                match crate::impls::RtlSdr::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
-            }
+        if let Some(d) = driver {
+            d.ensure_available()?;
         }
-        #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::Soapy)) {
-                match crate::impls::Soapy::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
+        // reject invalid arguments of the wrapper before opening hardware
+        SlewLimits::from_args(&args)?;
+        AntiAlias::from_args(&args)?;
+        let drivers: &[(Driver, OpenFn)] = &[
+            #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
+            (Driver::Aaronia, |a| {
+                generic(crate::impls::Aaronia::open(a)?, a)
+            }),
+            #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
+            (Driver::AaroniaHttp, |a| {
+                generic(crate::impls::AaroniaHttp::open(a)?, a)
+            }),
+            #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
+            (Driver::RtlSdr, |a| {
+                generic(crate::impls::RtlSdr::open(a)?, a)
+            }),
+            #[cfg(all(
+                feature = "signalhound",
+                any(target_os = "linux", target_os = "windows")
+            ))]
+            (Driver::SignalHound, |a| {
+                generic(crate::impls::SignalHound::open(a)?, a)
+            }),
+            #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
+            (Driver::Soapy, |a| generic(crate::impls::Soapy::open(a)?, a)),
+            #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+            (Driver::HackRf, |a| {
+                generic(crate::impls::HackRfOne::open(a)?, a)
+            }),
+            #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+            (Driver::Hpsdr, |a| generic(crate::impls::Hpsdr::open(a)?, a)),
+            #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+            (Driver::Ka9q, |a| generic(crate::impls::Ka9q::open(a)?, a)),
+            #[cfg(feature = "dummy")]
+            (Driver::Dummy, |a| generic(crate::impls::Dummy::open(a)?, a)),
+            #[cfg(feature = "file")]
+            (Driver::File, |a| generic(crate::impls::File::open(a)?, a)),
+        ];
+        for (candidate, open) in drivers {
+            if driver.is_some_and(|d| d != *candidate) {
+                continue;
             }
-        }
-        #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::HackRf)) {
-                match crate::impls::HackRfOne::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
+            match open(&args) {
+                Ok(d) => return Ok(Device::from_impl(d)),
+                Err(Error::NotFound) if driver.is_none() => {}
+                Err(e) => return Err(e),
             }
         }
-        #[cfg(feature = "dummy")]
-        {
-            if driver.is_none() || matches!(driver, Some(Driver::Dummy)) {
-                match crate::impls::Dummy::open(&args) {
-                    Ok(d) => {
-                        return Ok(Device {
-                            dev: Arc::new(DeviceWrapper { dev: d }),
-                        })
-                    }
-                    Err(Error::NotFound) => {
-                        if driver.is_some() {
-                            return Err(Error::NotFound);
-                        }
-                    }
-                    Err(e) => return Err(e),
-                }
-            }
-        }
-
         Err(Error::NotFound)
     }
+
+    /// Opens a device bookmarked in the [`Favorites`](crate::Favorites) store at the default
+    /// location and applies its [`Profile`](crate::Profile).
+    #[cfg(all(feature = "favorites", not(target_arch = "wasm32")))]
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        let favorites = crate::Favorites::load()?;
+        let favorite = favorites.get(name).ok_or(Error::NotFound)?;
+        let dev = Self::from_args(favorite.args.clone())?;
+        favorite.profile.apply(&dev)?;
+        Ok(dev)
+    }
+}
+
+/// Open a driver as [`GenericDevice`], see [`Device::from_args`].
+type OpenFn = fn(&Args) -> Result<GenericDevice, Error>;
+
+/// Wrap a driver into a [`GenericDevice`] with the slew-rate limits and the anti-aliasing
+/// configured by the device arguments.
+fn generic<D>(dev: D, args: &Args) -> Result<GenericDevice, Error>
+where
+    D: DeviceTrait + Sync,
+    D::RxStreamer: 'static,
+    D::TxStreamer: 'static,
+{
+    Ok(Arc::new(
+        DeviceWrapper::new(dev)
+            .slew(SlewLimits::from_args(args)?)
+            .anti_alias(AntiAlias::from_args(args)?),
+    ))
 }
 
 /// Type for a generic/wrapped hardware driver, implementing the [`DeviceTrait`].
@@ -384,10 +665,61 @@ impl Device<GenericDevice> {
 pub type GenericDevice =
     Arc<dyn DeviceTrait<RxStreamer = Box<dyn RxStreamer>, TxStreamer = Box<dyn TxStreamer>> + Sync>;
 
+/// Construct a [`GenericDevice`] from a device implementation.
+///
+/// This allows libraries to accept a `Device<GenericDevice>`, while users construct typed
+/// devices, e.g., `Device::from_impl(GenericDevice::from_impl(rtl))`. See also
+/// [`Device::into_generic`].
+pub trait FromImpl {
+    /// Wrap the device implementation `dev`.
+    fn from_impl<D>(dev: D) -> Self
+    where
+        D: DeviceTrait + Sync,
+        D::RxStreamer: 'static,
+        D::TxStreamer: 'static;
+}
+
+impl FromImpl for GenericDevice {
+    fn from_impl<D>(dev: D) -> Self
+    where
+        D: DeviceTrait + Sync,
+        D::RxStreamer: 'static,
+        D::TxStreamer: 'static,
+    {
+        // avoid wrapping generic devices a second time
+        let any: &dyn Any = &dev;
+        if let Some(d) = any.downcast_ref::<GenericDevice>() {
+            return d.clone();
+        }
+        Arc::new(DeviceWrapper::new(dev))
+    }
+}
+
 impl<T: DeviceTrait + Clone + Any> Device<T> {
     /// Create a device from the device implementation.
     pub fn from_impl(dev: T) -> Self {
-        Self { dev }
+        Self {
+            events: dev.driver_event_log().unwrap_or_default(),
+            dev,
+            latency: Arc::new(Mutex::new(None)),
+            location: Arc::new(Mutex::new(None)),
+        }
+    }
+    /// Convert into a [`GenericDevice`], erasing the type of the device implementation.
+    ///
+    /// The typed implementation can still be accessed through [`impl_ref`](Self::impl_ref).
+    pub fn into_generic(self) -> Device<GenericDevice>
+    where
+        T: Sync,
+        T::RxStreamer: 'static,
+        T::TxStreamer: 'static,
+    {
+        Device {
+            dev: GenericDevice::from_impl(self.dev),
+            events: self.events,
+            latency: self.latency,
+            location: self.location,
+        }
     }
     /// Try to downcast to a given device implementation `D`, either directly (from `Device<D>`)
     /// or indirectly (from a `Device<GenericDevice>` that wraps a `D`).
@@ -399,13 +731,7 @@ impl<T: DeviceTrait + Clone + Any> Device<T> {
         let d = self
             .dev
             .as_any()
-            .downcast_ref::<Arc<
-                (dyn DeviceTrait<
-                    RxStreamer = Box<(dyn RxStreamer + 'static)>,
-                    TxStreamer = Box<(dyn TxStreamer + 'static)>,
-                > + Sync
-                     + 'static),
-            >>()
+            .downcast_ref::<GenericDevice>()
             .ok_or(Error::ValueError)?;
 
         let d = (**d)
@@ -416,33 +742,227 @@ impl<T: DeviceTrait + Clone + Any> Device<T> {
     }
     /// Try to downcast mutably to a given device implementation `D`, either directly
     /// (from `Device<D>`) or indirectly (from a `Device<GenericDevice>` that wraps a `D`).
+    ///
+    /// A [`GenericDevice`] can only be accessed mutably, if it is not shared with other clones.
     pub fn impl_mut<D: DeviceTrait + Any>(&mut self) -> Result<&mut D, Error> {
         // work around borrow checker limitation
-        if let Some(d) = self.dev.as_any().downcast_ref::<D>() {
+        if self.dev.as_any().downcast_ref::<D>().is_some() {
             Ok(self.dev.as_any_mut().downcast_mut::<D>().unwrap())
         } else {
             let d = self
                 .dev
                 .as_any_mut()
-                .downcast_mut::<Box<
-                    (dyn DeviceTrait<
-                        RxStreamer = Box<(dyn RxStreamer + 'static)>,
-                        TxStreamer = Box<(dyn TxStreamer + 'static)>,
-                    > + 'static),
-                >>()
+                .downcast_mut::<GenericDevice>()
                 .ok_or(Error::ValueError)?;
 
-            let d = (**d)
+            let d = Arc::get_mut(d)
+                .ok_or(Error::ValueError)?
                 .as_any_mut()
                 .downcast_mut::<DeviceWrapper<D>>()
                 .ok_or(Error::ValueError)?;
             Ok(&mut d.dev)
         }
     }
+    /// Recent events of the device, oldest first.
+    ///
+    /// Settings changed through the `Device` are logged automatically. Clones of the device share
+    /// the log.
+    pub fn events(&self) -> Vec<Event> {
+        self.events.events()
+    }
+    /// Remove all events from the log.
+    pub fn clear_events(&self) {
+        self.events.clear()
+    }
+    /// Get a handle to the event log, e.g., to add overflows or reconnects from streaming code.
+    pub fn event_log(&self) -> EventLog {
+        self.events.clone()
+    }
+}
+
+/// Event message of a setting, mentioning the requested value, if the device coerced it.
+fn applied_message(
+    direction: Direction,
+    channel: usize,
+    setting: &str,
+    applied: f64,
+    requested: f64,
+    unit: &str,
+) -> String {
+    if applied == requested {
+        format!("{direction:?} {channel}: {setting} {applied} {unit}")
+    } else {
+        format!(
+            "{direction:?} {channel}: {setting} {applied} {unit} (requested {requested} {unit})"
+        )
+    }
 }
 
 struct DeviceWrapper<D: DeviceTrait> {
     dev: D,
+    /// LO offsets from tuning with `avoid_spurs` or across converter paths, compensated in the
+    /// streamers.
+    shifts: spurs::Shifts,
+    /// Settings changes, restarting streamers created with the `restart` argument.
+    changes: restart::Changes,
+    /// Slew-rate limits of gain and frequency changes.
+    slew: SlewLimits,
+    /// Analog bandwidth following the sample rate.
+    anti_alias: AntiAlias,
+}
+
+impl<D: DeviceTrait> DeviceWrapper<D> {
+    fn new(dev: D) -> Self {
+        Self {
+            dev,
+            shifts: Default::default(),
+            changes: Default::default(),
+            slew: Default::default(),
+            anti_alias: Default::default(),
+        }
+    }
+
+    fn slew(mut self, slew: SlewLimits) -> Self {
+        self.slew = slew;
+        self
+    }
+
+    fn anti_alias(mut self, anti_alias: AntiAlias) -> Self {
+        self.anti_alias = anti_alias;
+        self
+    }
+
+    /// Set the analog bandwidth of a channel that follows its sample rate, returning `false`, if
+    /// disabled or not supported by the driver.
+    fn track_bandwidth(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        if !self.anti_alias.enabled() {
+            return Ok(false);
+        }
+        let rate = self.dev.sample_rate(direction, channel)?;
+        let Some(target) = self
+            .anti_alias
+            .bandwidth(direction, channel, rate)
+            .filter(|_| rate > 0.0)
+        else {
+            return Ok(false);
+        };
+        let range = match self.dev.get_bandwidth_range(direction, channel) {
+            Ok(range) => range,
+            Err(Error::NotSupported) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        // prefer the widest filter that still rejects the aliases
+        let bw = range
+            .at_max(target)
+            .or_else(|| range.closest(target))
+            .ok_or(Error::OutOfRange(range, target))?;
+        log::debug!("{direction:?} {channel}: bandwidth {bw} Hz at sample rate {rate} Hz");
+        self.dev.set_bandwidth(direction, channel, bw)?;
+        Ok(true)
+    }
+
+    /// Record a new sample rate of a channel, adapt the bandwidth, and move its LO offset back
+    /// into the bandwidth.
+    fn sample_rate_changed(&self, direction: Direction, channel: usize) -> Result<(), Error> {
+        restart::record(&self.changes, direction, channel, Change::SampleRate);
+        self.track_bandwidth(direction, channel)?;
+        let shift = self
+            .shifts
+            .lock()
+            .unwrap()
+            .get(&(direction, channel))
+            .copied();
+        match shift {
+            Some(shift) => {
+                let frequency = self.dev.frequency(direction, channel)? - shift.offset;
+                self.tune_avoiding_spurs(
+                    direction,
+                    channel,
+                    frequency,
+                    shift.passband,
+                    shift.avoid_spurs,
+                    Args::new(),
+                )?;
+            }
+            None => {
+                if let Ok(frequency) = self.dev.frequency(direction, channel) {
+                    self.tune_into_path(direction, channel, frequency, Args::new())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tune with the LO moved away from the spurs of the chain, if `avoid_spurs` is set, and into
+    /// a single converter path, if the band crosses into another one.
+    fn tune_avoiding_spurs(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        passband: Option<f64>,
+        avoid_spurs: bool,
+        args: Args,
+    ) -> Result<(), Error> {
+        let rate = self.dev.sample_rate(direction, channel)?;
+        let range = self.dev.frequency_range(direction, channel)?;
+        let spurs = if avoid_spurs {
+            self.dev.spurs(direction, channel)?
+        } else {
+            Vec::new()
+        };
+        let plan = |range: &Range| {
+            spurs::plan_lo_offset(
+                frequency,
+                rate,
+                passband.unwrap_or(rate / 4.0),
+                &spurs,
+                range,
+            )
+        };
+        // spurs can still be avoided across a path boundary
+        let offset = match spurs::path_range(frequency, rate, &range) {
+            Some(path) if avoid_spurs => plan(&path).or_else(|_| plan(&range))?,
+            Some(path) => plan(&path)?,
+            None => plan(&range)?,
+        };
+        self.dev
+            .set_frequency(direction, channel, frequency + offset, args)?;
+        self.shifts.lock().unwrap().insert(
+            (direction, channel),
+            spurs::Shift {
+                offset,
+                rate,
+                passband,
+                avoid_spurs,
+            },
+        );
+        Ok(())
+    }
+
+    /// Move the LO into a single converter path, if the band at `frequency` crosses into another
+    /// one. Returns `false`, if the band stays in its path or no LO fits into one.
+    fn tune_into_path(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        args: Args,
+    ) -> Result<bool, Error> {
+        let (Ok(rate), Ok(range)) = (
+            self.dev.sample_rate(direction, channel),
+            self.dev.frequency_range(direction, channel),
+        ) else {
+            return Ok(false);
+        };
+        if spurs::path_range(frequency, rate, &range).is_none() {
+            return Ok(false);
+        }
+        match self.tune_avoiding_spurs(direction, channel, frequency, None, false, args) {
+            Err(Error::OutOfRange(..)) => Ok(false),
+            r => r.map(|_| true),
+        }
+    }
 }
 
 impl<
@@ -470,6 +990,12 @@ impl<
     fn info(&self) -> Result<Args, Error> {
         self.dev.info()
     }
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        self.dev.reference_docs()
+    }
+    fn extensions(&self) -> Extensions {
+        self.dev.extensions()
+    }
     fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
         self.dev.num_channels(direction)
     }
@@ -477,11 +1003,63 @@ impl<
         self.dev.full_duplex(direction, channel)
     }
 
-    fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
-        Ok(Box::new(self.dev.rx_streamer(channels, args)?))
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        self.dev.coherency_groups()
+    }
+
+    fn rx_streamer(&self, channels: &[usize], mut args: Args) -> Result<Self::RxStreamer, Error> {
+        validate::check_channels(channels, self.dev.num_channels(Direction::Rx)?)?;
+        validate::check_enabled(&self.dev, Direction::Rx, channels)?;
+        let dc_block = crate::dc_block::alpha_from_args(&args)?;
+        args.remove("dc_block");
+        let policy = restart::Policy::from_args(&args)?;
+        args.remove("restart");
+        let rx = self.dev.rx_streamer(channels, args)?;
+        let rx: Box<dyn RxStreamer> = match policy {
+            restart::Policy::Off => Box::new(rx),
+            _ => Box::new(restart::RestartingRx::new(
+                rx,
+                self.changes.clone(),
+                channels,
+                policy,
+            )),
+        };
+        let rx = spurs::ShiftedRx::new(rx, self.shifts.clone(), channels);
+        let rx: Box<dyn RxStreamer> = match dc_block {
+            Some(alpha) => Box::new(crate::DcBlocker::new(rx, alpha)?),
+            None => Box::new(rx),
+        };
+        Ok(Box::new(validate::CheckedRx::new(rx, channels.len())))
+    }
+    fn tx_streamer(&self, channels: &[usize], mut args: Args) -> Result<Self::TxStreamer, Error> {
+        validate::check_channels(channels, self.dev.num_channels(Direction::Tx)?)?;
+        validate::check_enabled(&self.dev, Direction::Tx, channels)?;
+        let policy = restart::Policy::from_args(&args)?;
+        args.remove("restart");
+        let tx = self.dev.tx_streamer(channels, args)?;
+        let tx: Box<dyn TxStreamer> = match policy {
+            restart::Policy::Off => Box::new(tx),
+            _ => Box::new(restart::RestartingTx::new(
+                tx,
+                self.changes.clone(),
+                channels,
+                policy,
+            )),
+        };
+        let tx = spurs::ShiftedTx::new(tx, self.shifts.clone(), channels);
+        Ok(Box::new(validate::CheckedTx::new(tx, channels.len())))
     }
-    fn tx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::TxStreamer, Error> {
-        Ok(Box::new(self.dev.tx_streamer(channels, args)?))
+
+    fn spectrum_streamer(
+        &self,
+        channel: usize,
+        args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        self.dev.spectrum_streamer(channel, args)
+    }
+
+    fn driver_event_log(&self) -> Option<EventLog> {
+        self.dev.driver_event_log()
     }
 
     fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
@@ -493,7 +1071,9 @@ impl<
     }
 
     fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
-        self.dev.set_antenna(direction, channel, name)
+        self.dev.set_antenna(direction, channel, name)?;
+        restart::record(&self.changes, direction, channel, Change::Tuning);
+        Ok(())
     }
 
     fn gain_elements(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
@@ -512,8 +1092,33 @@ impl<
         self.dev.agc(direction, channel)
     }
 
+    fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        self.dev.supports_agc_element(direction, channel, name)
+    }
+
+    fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        self.dev.enable_agc_element(direction, channel, name, agc)
+    }
+
+    fn agc_element(&self, direction: Direction, channel: usize, name: &str) -> Result<bool, Error> {
+        self.dev.agc_element(direction, channel, name)
+    }
+
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
-        self.dev.set_gain(direction, channel, gain)
+        let current = self.dev.gain(direction, channel).ok().flatten();
+        self.dev
+            .set_gain(direction, channel, self.slew.gain(current, gain))
     }
 
     fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
@@ -530,8 +1135,15 @@ impl<
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
-        self.dev.set_gain_element(direction, channel, name, gain)
+    ) -> Result<f64, Error> {
+        let current = self
+            .dev
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten();
+        // the applied gain is the clamped step, not the requested target
+        let clamped = self.slew.gain(current, gain);
+        self.dev.set_gain_element(direction, channel, name, clamped)
     }
 
     fn gain_element(
@@ -552,12 +1164,27 @@ impl<
         self.dev.gain_element_range(direction, channel, name)
     }
 
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        self.dev.gain_element_info(direction, channel, name)
+    }
+
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         self.dev.frequency_range(direction, channel)
     }
 
     fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
-        self.dev.frequency(direction, channel)
+        let offset = self
+            .shifts
+            .lock()
+            .unwrap()
+            .get(&(direction, channel))
+            .map_or(0.0, |s| s.offset);
+        Ok(self.dev.frequency(direction, channel)? - offset)
     }
 
     fn set_frequency(
@@ -565,9 +1192,26 @@ impl<
         direction: Direction,
         channel: usize,
         frequency: f64,
-        args: Args,
+        mut args: Args,
     ) -> Result<(), Error> {
-        self.dev.set_frequency(direction, channel, frequency, args)
+        let passband = spurs::take_args(&mut args)?;
+        for f in self
+            .slew
+            .ramp(self.frequency(direction, channel).ok(), frequency)
+        {
+            self.dev
+                .set_frequency(direction, channel, f, args.clone())?;
+            self.slew.dwell();
+        }
+        if let Some(passband) = passband {
+            self.tune_avoiding_spurs(direction, channel, frequency, passband, true, args)?;
+        } else if !self.tune_into_path(direction, channel, frequency, args.clone())? {
+            self.shifts.lock().unwrap().remove(&(direction, channel));
+            self.dev
+                .set_frequency(direction, channel, frequency, args)?;
+        }
+        restart::record(&self.changes, direction, channel, Change::Tuning);
+        Ok(())
     }
 
     fn frequency_components(
@@ -603,8 +1247,15 @@ impl<
         name: &str,
         frequency: f64,
     ) -> Result<(), Error> {
+        self.shifts.lock().unwrap().remove(&(direction, channel));
         self.dev
-            .set_component_frequency(direction, channel, name, frequency)
+            .set_component_frequency(direction, channel, name, frequency)?;
+        restart::record(&self.changes, direction, channel, Change::Tuning);
+        Ok(())
+    }
+
+    fn spurs(&self, direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        self.dev.spurs(direction, channel)
     }
 
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
@@ -617,7 +1268,37 @@ impl<
         channel: usize,
         rate: f64,
     ) -> Result<(), Error> {
-        self.dev.set_sample_rate(direction, channel, rate)
+        let shared = self
+            .dev
+            .extensions()
+            .get::<dyn SampleRateClock>()
+            .is_some_and(|c| c.independent_sample_rates().ok() == Some(false));
+        if !shared {
+            self.dev.set_sample_rate(direction, channel, rate)?;
+            return self.sample_rate_changed(direction, channel);
+        }
+        // one clock for both directions: the rate has to suit all channels before changing any
+        let other = match direction {
+            Direction::Rx => Direction::Tx,
+            Direction::Tx => Direction::Rx,
+        };
+        let followers = self.dev.num_channels(other)?;
+        for ch in 0..followers {
+            let range = self.dev.get_sample_rate_range(other, ch)?;
+            if !range.contains(rate) {
+                return Err(Error::OutOfRange(range, rate));
+            }
+        }
+        self.dev.set_sample_rate(direction, channel, rate)?;
+        self.sample_rate_changed(direction, channel)?;
+        for ch in 0..followers {
+            if self.dev.sample_rate(other, ch)? != rate {
+                log::info!("{other:?} {ch}: sample rate follows to {rate} Hz");
+                self.dev.set_sample_rate(other, ch, rate)?;
+                self.sample_rate_changed(other, ch)?;
+            }
+        }
+        Ok(())
     }
 
     fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
@@ -629,7 +1310,12 @@ impl<
     }
 
     fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
-        self.dev.set_bandwidth(direction, channel, bw)
+        self.anti_alias.request(direction, channel, bw);
+        if !self.track_bandwidth(direction, channel)? {
+            self.dev.set_bandwidth(direction, channel, bw)?;
+        }
+        restart::record(&self.changes, direction, channel, Change::Tuning);
+        Ok(())
     }
 
     fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
@@ -652,6 +1338,70 @@ impl<
     fn dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
         self.dev.dc_offset_mode(direction, channel)
     }
+
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        self.dev.list_loopback_modes()
+    }
+
+    fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        self.dev.set_loopback_mode(mode)
+    }
+
+    fn loopback_mode(&self) -> Result<String, Error> {
+        self.dev.loopback_mode()
+    }
+
+    fn self_test(&self) -> Result<SelfTestReport, Error> {
+        self.dev.self_test()
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        self.dev.set_power_state(state)
+    }
+
+    fn power_state(&self) -> Result<PowerState, Error> {
+        self.dev.power_state()
+    }
+
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        self.dev.list_time_sources()
+    }
+
+    fn time_source(&self) -> Result<String, Error> {
+        self.dev.time_source()
+    }
+
+    fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        self.dev.set_time_source(name)
+    }
+
+    fn gps_locked(&self) -> Result<bool, Error> {
+        self.dev.gps_locked()
+    }
+
+    fn gps_location(&self) -> Result<Location, Error> {
+        self.dev.gps_location()
+    }
+
+    fn hardware_time(&self) -> Result<i64, Error> {
+        self.dev.hardware_time()
+    }
+
+    fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        self.dev.set_hardware_time(time_ns)
+    }
+
+    fn set_hardware_time_next_pps(&self, time_ns: i64) -> Result<(), Error> {
+        self.dev.set_hardware_time_next_pps(time_ns)
+    }
+
+    fn time_discipline(&self) -> Result<TimeDiscipline, Error> {
+        self.dev.time_discipline()
+    }
+
+    fn execute_at(&self, time_ns: i64, commands: &[TimedCommand]) -> Result<(), Error> {
+        self.dev.execute_at(time_ns, commands)
+    }
 }
 
 #[doc(hidden)]
@@ -676,6 +1426,12 @@ impl DeviceTrait for GenericDevice {
     fn info(&self) -> Result<Args, Error> {
         self.as_ref().info()
     }
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        self.as_ref().reference_docs()
+    }
+    fn extensions(&self) -> Extensions {
+        self.as_ref().extensions()
+    }
     fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
         self.as_ref().num_channels(direction)
     }
@@ -683,6 +1439,10 @@ impl DeviceTrait for GenericDevice {
         self.as_ref().full_duplex(direction, channel)
     }
 
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        self.as_ref().coherency_groups()
+    }
+
     fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
         Ok(Box::new(self.as_ref().rx_streamer(channels, args)?))
     }
@@ -691,6 +1451,18 @@ impl DeviceTrait for GenericDevice {
         Ok(Box::new(self.as_ref().tx_streamer(channels, args)?))
     }
 
+    fn spectrum_streamer(
+        &self,
+        channel: usize,
+        args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        self.as_ref().spectrum_streamer(channel, args)
+    }
+
+    fn driver_event_log(&self) -> Option<EventLog> {
+        self.as_ref().driver_event_log()
+    }
+
     fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
         self.as_ref().antennas(direction, channel)
     }
@@ -719,6 +1491,30 @@ impl DeviceTrait for GenericDevice {
         self.as_ref().agc(direction, channel)
     }
 
+    fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        self.as_ref().supports_agc_element(direction, channel, name)
+    }
+
+    fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        self.as_ref()
+            .enable_agc_element(direction, channel, name, agc)
+    }
+
+    fn agc_element(&self, direction: Direction, channel: usize, name: &str) -> Result<bool, Error> {
+        self.as_ref().agc_element(direction, channel, name)
+    }
+
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
         self.as_ref().set_gain(direction, channel, gain)
     }
@@ -737,7 +1533,7 @@ impl DeviceTrait for GenericDevice {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
+    ) -> Result<f64, Error> {
         self.as_ref()
             .set_gain_element(direction, channel, name, gain)
     }
@@ -760,6 +1556,15 @@ impl DeviceTrait for GenericDevice {
         self.as_ref().gain_element_range(direction, channel, name)
     }
 
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        self.as_ref().gain_element_info(direction, channel, name)
+    }
+
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         self.as_ref().frequency_range(direction, channel)
     }
@@ -817,6 +1622,10 @@ impl DeviceTrait for GenericDevice {
             .set_component_frequency(direction, channel, name, frequency)
     }
 
+    fn spurs(&self, direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        self.as_ref().spurs(direction, channel)
+    }
+
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
         self.as_ref().sample_rate(direction, channel)
     }
@@ -863,8 +1672,75 @@ impl DeviceTrait for GenericDevice {
     fn dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
         self.as_ref().dc_offset_mode(direction, channel)
     }
+
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        self.as_ref().list_loopback_modes()
+    }
+
+    fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        self.as_ref().set_loopback_mode(mode)
+    }
+
+    fn loopback_mode(&self) -> Result<String, Error> {
+        self.as_ref().loopback_mode()
+    }
+
+    fn self_test(&self) -> Result<SelfTestReport, Error> {
+        self.as_ref().self_test()
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        self.as_ref().set_power_state(state)
+    }
+
+    fn power_state(&self) -> Result<PowerState, Error> {
+        self.as_ref().power_state()
+    }
+
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        self.as_ref().list_time_sources()
+    }
+
+    fn time_source(&self) -> Result<String, Error> {
+        self.as_ref().time_source()
+    }
+
+    fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        self.as_ref().set_time_source(name)
+    }
+
+    fn gps_locked(&self) -> Result<bool, Error> {
+        self.as_ref().gps_locked()
+    }
+
+    fn gps_location(&self) -> Result<Location, Error> {
+        self.as_ref().gps_location()
+    }
+
+    fn hardware_time(&self) -> Result<i64, Error> {
+        self.as_ref().hardware_time()
+    }
+
+    fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        self.as_ref().set_hardware_time(time_ns)
+    }
+
+    fn set_hardware_time_next_pps(&self, time_ns: i64) -> Result<(), Error> {
+        self.as_ref().set_hardware_time_next_pps(time_ns)
+    }
+
+    fn time_discipline(&self) -> Result<TimeDiscipline, Error> {
+        self.as_ref().time_discipline()
+    }
+
+    fn execute_at(&self, time_ns: i64, commands: &[TimedCommand]) -> Result<(), Error> {
+        self.as_ref().execute_at(time_ns, commands)
+    }
 }
 
+impl RxInterface for GenericDevice {}
+impl TxInterface for GenericDevice {}
+
 impl<
         R: RxStreamer + 'static,
         T: TxStreamer + 'static,
@@ -883,6 +1759,22 @@ impl<
     pub fn info(&self) -> Result<Args, Error> {
         self.dev.info()
     }
+    /// Links to documentation of the hardware or the library of the driver.
+    pub fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        self.dev.reference_docs()
+    }
+    /// Optional functionality implemented by the driver, see [`Extension`].
+    pub fn extensions(&self) -> Extensions {
+        self.dev.extensions()
+    }
+    /// Check, if the driver implements the [extension](Extension) `E`, e.g., `dyn Sensors`.
+    pub fn supports<E: Extension + ?Sized>(&self) -> bool {
+        self.dev.extensions().contains::<E>()
+    }
+    /// Get the [extension](Extension) `E` of the driver.
+    pub fn extension<E: Extension + Send + Sync + ?Sized>(&self) -> Option<Arc<E>> {
+        self.dev.extensions().get::<E>()
+    }
     /// Number of supported Channels.
     pub fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
         self.dev.num_channels(direction)
@@ -892,22 +1784,139 @@ impl<
         self.dev.full_duplex(direction, channel)
     }
 
+    /// Groups of channels that are driven by the same clock, see [`CoherencyGroup`].
+    ///
+    /// The phase between channels of different groups is not meaningful. Returns
+    /// `Err(Error::NotSupported)`, if the driver does not know how the channels are clocked.
+    pub fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        self.dev.coherency_groups()
+    }
+
+    /// Handle to an RX channel, to configure it without passing direction and channel.
+    pub fn rx_channel(&self, channel: usize) -> Channel<'_, D> {
+        Channel::new(self, Direction::Rx, channel)
+    }
+    /// Handle to a TX channel, to configure it without passing direction and channel.
+    pub fn tx_channel(&self, channel: usize) -> Channel<'_, D> {
+        Channel::new(self, Direction::Tx, channel)
+    }
+
     //================================ STREAMER ============================================
     /// Create an RX streamer.
+    ///
+    /// The streamer owns what it needs from the driver, so it can be moved to another thread and
+    /// keeps working after the device is dropped.
     pub fn rx_streamer(&self, channels: &[usize]) -> Result<R, Error> {
-        self.dev.rx_streamer(channels, Args::new())
+        self.rx_streamer_with_args(channels, Args::new())
     }
     /// Create an RX streamer, using `args`.
+    ///
+    /// For a [`GenericDevice`], `dc_block=auto|off|<alpha>` removes the DC spike of the
+    /// receiver, see [`DcBlocker`](crate::DcBlocker).
+    ///
+    /// Some drivers only apply settings when the stream starts. For a [`GenericDevice`],
+    /// `restart=rate` restarts the active stream with its next read, after the sample rate of
+    /// one of its channels changed; `restart=all` also after frequency, bandwidth, and antenna
+    /// changes. The default `off` leaves the stream running.
     pub fn rx_streamer_with_args(&self, channels: &[usize], args: Args) -> Result<R, Error> {
-        self.dev.rx_streamer(channels, args)
+        self.dev.rx_streamer(channels, self.stream_args(args))
+    }
+    /// Create an RX streamer for samples of type `S`, e.g., `Complex<u8>`.
+    ///
+    /// Samples in the [native format](RxStreamer::native_format) of the driver are read without
+    /// conversion, see [`TypedRxStreamer`](crate::TypedRxStreamer).
+    pub fn rx_streamer_typed<S: Sample>(
+        &self,
+        channels: &[usize],
+    ) -> Result<TypedRxStreamer<R, S>, Error> {
+        Ok(TypedRxStreamer::new(self.rx_streamer(channels)?))
     }
     /// Create a TX Streamer.
     pub fn tx_streamer(&self, channels: &[usize]) -> Result<T, Error> {
-        self.dev.tx_streamer(channels, Args::new())
+        self.tx_streamer_with_args(channels, Args::new())
     }
+
     /// Create a TX Streamer, using `args`.
+    ///
+    /// For a [`GenericDevice`], `restart=rate|all|off` restarts the active stream with its next
+    /// write after settings changes, see [`rx_streamer_with_args`](Self::rx_streamer_with_args).
     pub fn tx_streamer_with_args(&self, channels: &[usize], args: Args) -> Result<T, Error> {
-        self.dev.tx_streamer(channels, args)
+        self.dev.tx_streamer(channels, self.stream_args(args))
+    }
+    /// Create a TX streamer for samples of type `S`, see
+    /// [`rx_streamer_typed`](Self::rx_streamer_typed).
+    pub fn tx_streamer_typed<S: Sample>(
+        &self,
+        channels: &[usize],
+    ) -> Result<TypedTxStreamer<T, S>, Error> {
+        Ok(TypedTxStreamer::new(self.tx_streamer(channels)?))
+    }
+    /// Create a spectrum streamer for an RX channel.
+    ///
+    /// Uses the native spectrum stream of the device, if it has one. Otherwise, with the
+    /// `spectrum` feature, computes spectra of `size` bins (default 1024) from the IQ samples of
+    /// an [RX streamer](Self::rx_streamer_with_args), see [`FftSpectrum`](crate::FftSpectrum).
+    /// The other `args` are passed to the streamer.
+    pub fn spectrum_streamer(
+        &self,
+        channel: usize,
+        args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        match self.dev.spectrum_streamer(channel, args.clone()) {
+            Err(Error::NotSupported) => self.fft_spectrum_streamer(channel, args),
+            r => r,
+        }
+    }
+
+    #[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+    fn fft_spectrum_streamer(
+        &self,
+        channel: usize,
+        mut args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        let size = match args.get::<usize>("size") {
+            Ok(size) => size,
+            Err(Error::NotFound) => 1024,
+            Err(e) => return Err(e),
+        };
+        args.remove("size");
+        let frequency = self.center_frequency(Direction::Rx, channel)?.as_hz();
+        let sample_rate = self.rate(Direction::Rx, channel)?.as_sps();
+        let rx = self.rx_streamer_with_args(&[channel], args)?;
+        Ok(Box::new(crate::FftSpectrum::new(
+            rx,
+            size,
+            frequency,
+            sample_rate,
+        )?))
+    }
+
+    #[cfg(not(all(feature = "spectrum", not(target_arch = "wasm32"))))]
+    fn fft_spectrum_streamer(
+        &self,
+        _channel: usize,
+        _args: Args,
+    ) -> Result<Box<dyn SpectrumStreamer>, Error> {
+        Err(Error::FeatureNotEnabled("spectrum"))
+    }
+    /// Set the [latency hint](Latency) for streamers created afterwards.
+    ///
+    /// It is passed to the driver as `latency` argument, unless the streamer arguments already
+    /// contain one.
+    pub fn set_latency_hint(&self, latency: Latency) {
+        *self.latency.lock().unwrap() = Some(latency);
+    }
+    /// The [latency hint](Latency) for new streamers, if one was set.
+    pub fn latency_hint(&self) -> Option<Latency> {
+        *self.latency.lock().unwrap()
+    }
+    fn stream_args(&self, mut args: Args) -> Args {
+        if let Some(latency) = self.latency_hint() {
+            if args.get::<String>("latency").is_err() {
+                args.set("latency", latency.to_string());
+            }
+        }
+        args
     }
 
     //================================ ANTENNA ============================================
@@ -936,13 +1945,57 @@ impl<
     }
     /// Enable or disable automatic gain control.
     pub fn enable_agc(&self, direction: Direction, channel: usize, agc: bool) -> Result<(), Error> {
-        self.dev.enable_agc(direction, channel, agc)
+        self.dev.enable_agc(direction, channel, agc)?;
+        self.events.push(
+            EventKind::Agc,
+            format!("{direction:?} {channel}: AGC {agc}"),
+        );
+        Ok(())
     }
     /// Returns true, if automatic gain control is enabled
     pub fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
         self.dev.agc(direction, channel)
     }
 
+    /// Does the gain element support automatic gain control?
+    pub fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        self.dev.supports_agc_element(direction, channel, name)
+    }
+
+    /// Enable or disable automatic gain control of a single gain element.
+    ///
+    /// Elements can be AGC stages that are not listed in [`gain_elements`](Self::gain_elements),
+    /// e.g., a digital AGC in the ADC.
+    pub fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        self.dev.enable_agc_element(direction, channel, name, agc)?;
+        self.events.push(
+            EventKind::Agc,
+            format!("{direction:?} {channel}: AGC of {name} {agc}"),
+        );
+        Ok(())
+    }
+
+    /// Returns true, if automatic gain control is enabled for a gain element.
+    pub fn agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        self.dev.agc_element(direction, channel, name)
+    }
+
     //================================ GAIN ============================================
     /// List of available gain elements.
     ///
@@ -960,8 +2013,20 @@ impl<
     /// The gain will be distributed automatically across available elements.
     ///
     /// `gain`: the new amplification value in dB
-    pub fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
-        self.dev.set_gain(direction, channel, gain)
+    ///
+    /// Returns the gain the device applied, e.g., quantized to the steps of the gain elements or
+    /// limited with `max_gain_step_db_per_call`, see [`from_args`](Self::from_args).
+    pub fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<f64, Error> {
+        self.dev.set_gain(direction, channel, gain)?;
+        let applied = match self.dev.gain(direction, channel) {
+            Ok(Some(applied)) => applied,
+            _ => gain,
+        };
+        self.events.push(
+            EventKind::Gain,
+            applied_message(direction, channel, "gain", applied, gain, "dB"),
+        );
+        Ok(applied)
     }
 
     /// Get the overall value of the gain elements in a chain in dB.
@@ -979,14 +2044,29 @@ impl<
     /// ## Arguments
     /// * `name`: the name of an amplification element from `Device::list_gains`
     /// * `gain`: the new amplification value in dB
+    ///
+    /// Returns the gain the element applied, e.g., quantized to its steps or limited with
+    /// `max_gain_step_db_per_call`, see [`from_args`](Self::from_args).
     pub fn set_gain_element(
         &self,
         direction: Direction,
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
-        self.dev.set_gain_element(direction, channel, name, gain)
+    ) -> Result<f64, Error> {
+        let applied = self.dev.set_gain_element(direction, channel, name, gain)?;
+        self.events.push(
+            EventKind::Gain,
+            applied_message(
+                direction,
+                channel,
+                &format!("gain of {name}"),
+                applied,
+                gain,
+                "dB",
+            ),
+        );
+        Ok(applied)
     }
 
     /// Get the value of an individual amplification element in a chain in dB.
@@ -1009,6 +2089,18 @@ impl<
         self.dev.gain_element_range(direction, channel, name)
     }
 
+    /// Describe what the value of a gain element means, e.g., to label it in a user interface.
+    ///
+    /// The default implementation describes elements listed by `gain_elements` as plain gains in dB.
+    pub fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        self.dev.gain_element_info(direction, channel, name)
+    }
+
     //================================ FREQUENCY ============================================
 
     /// Get the ranges of overall frequency values.
@@ -1016,14 +2108,35 @@ impl<
         self.dev.frequency_range(direction, channel)
     }
 
+    /// Get the overall center frequency of the chain in Hz.
+    #[deprecated(note = "use tune/center_frequency/set_rate/rate")]
+    pub fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.center_frequency(direction, channel)
+            .map(Frequency::as_hz)
+    }
+
+    /// Set the center frequency of the chain in Hz, see [`tune`](Self::tune).
+    #[deprecated(note = "use tune/center_frequency/set_rate/rate")]
+    pub fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+    ) -> Result<f64, Error> {
+        self.tune(direction, channel, Frequency::hz(frequency))
+            .map(Frequency::as_hz)
+    }
+
     /// Get the overall center frequency of the chain.
     ///
     ///   - For RX, this specifies the down-conversion frequency.
     ///   - For TX, this specifies the up-conversion frequency.
-    ///
-    /// Returns the center frequency in Hz.
-    pub fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
-        self.dev.frequency(direction, channel)
+    pub fn center_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Frequency, Error> {
+        self.dev.frequency(direction, channel).map(Frequency::hz)
     }
 
     /// Set the center frequency of the chain.
@@ -1031,18 +2144,22 @@ impl<
     ///   - For RX, this specifies the down-conversion frequency.
     ///   - For TX, this specifies the up-conversion frequency.
     ///
-    /// The default implementation of `set_frequency` will tune the "RF"
-    /// component as close as possible to the requested center frequency in Hz.
-    /// Tuning inaccuracies will be compensated for with the "BB" component.
+    /// The default implementation of the drivers will tune the "RF" component as close as
+    /// possible to the requested center frequency. Tuning inaccuracies will be compensated for
+    /// with the "BB" component.
     ///
-    pub fn set_frequency(
+    /// Returns the frequency the driver reports after tuning, see
+    /// [`center_frequency`](Self::center_frequency). Drivers report the value they sent to the
+    /// hardware, e.g., the request rounded to whole Hz for RTL-SDRs and HackRFs, not the output
+    /// of the synthesizers, which deviates further by the resolution of their PLLs.
+    pub fn tune(
         &self,
         direction: Direction,
         channel: usize,
-        frequency: f64,
-    ) -> Result<(), Error> {
-        self.dev
-            .set_frequency(direction, channel, frequency, Args::new())
+        frequency: Frequency,
+    ) -> Result<Frequency, Error> {
+        self.set_frequency_with_args(direction, channel, frequency.as_hz(), Args::new())
+            .map(Frequency::hz)
     }
 
     /// Like [`set_frequency`](Self::set_frequency) but using `args` to augment the tuning algorithm.
@@ -1058,15 +2175,35 @@ impl<
     ///     so that the tuning algorithm will avoid altering the component.
     ///   - Vendor specific implementations can also use the same args to augment
     ///     tuning in other ways such as specifying fractional vs integer N tuning.
+    ///   - For a [`GenericDevice`], use `avoid_spurs=1` to move the LO, such that the
+    ///     [spurs](Self::spurs) of the chain are outside the passband, see
+    ///     [`plan_lo_offset`](crate::plan_lo_offset). The passband defaults to a quarter of the
+    ///     sample rate and can be set with `spur_bw` in Hz. The streamers compensate the
+    ///     offset digitally and [`frequency`](Self::frequency) reports the requested frequency.
+    ///   - A [`GenericDevice`] also moves the LO, if the sampled band would cross the boundary
+    ///     between two converter paths of the [frequency range](Self::frequency_range), e.g., the
+    ///     filters of a HackRF. The LO is kept in the path with the smallest offset, if the
+    ///     passband fits, and the [frequency components](Self::frequency_components) report
+    ///     the tuning of the selected path.
     ///
+    /// Returns the frequency in Hz the driver reports after tuning, like [`tune`](Self::tune).
     pub fn set_frequency_with_args(
         &self,
         direction: Direction,
         channel: usize,
         frequency: f64,
         args: Args,
-    ) -> Result<(), Error> {
-        self.dev.set_frequency(direction, channel, frequency, args)
+    ) -> Result<f64, Error> {
+        self.dev
+            .set_frequency(direction, channel, frequency, args)?;
+        // the value the driver sent to the hardware; drivers that cannot read it back are
+        // assumed to take the request unchanged
+        let tuned = self.dev.frequency(direction, channel).unwrap_or(frequency);
+        self.events.push(
+            EventKind::Retune,
+            applied_message(direction, channel, "frequency", tuned, frequency, "Hz"),
+        );
+        Ok(tuned)
     }
 
     /// List available tunable elements in the chain.
@@ -1112,24 +2249,72 @@ impl<
         frequency: f64,
     ) -> Result<(), Error> {
         self.dev
-            .set_component_frequency(direction, channel, name, frequency)
+            .set_component_frequency(direction, channel, name, frequency)?;
+        self.events.push(
+            EventKind::Retune,
+            format!("{direction:?} {channel}: frequency of {name} {frequency} Hz"),
+        );
+        Ok(())
+    }
+
+    /// Known spurs (birdies) of the chain, e.g., clock harmonics and the DC spike.
+    ///
+    /// Used to plan the LO, when tuning with `avoid_spurs=1`, see [`Device::set_frequency_with_args`].
+    pub fn spurs(&self, direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        self.dev.spurs(direction, channel)
     }
 
     //================================ SAMPLE RATE ============================================
 
     /// Get the baseband sample rate of the chain in samples per second.
+    #[deprecated(note = "use tune/center_frequency/set_rate/rate")]
     pub fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
-        self.dev.sample_rate(direction, channel)
+        self.rate(direction, channel).map(SampleRate::as_sps)
     }
 
-    /// Set the baseband sample rate of the chain in samples per second.
+    /// Set the baseband sample rate of the chain in samples per second, see
+    /// [`set_rate`](Self::set_rate).
+    #[deprecated(note = "use tune/center_frequency/set_rate/rate")]
     pub fn set_sample_rate(
         &self,
         direction: Direction,
         channel: usize,
         rate: f64,
-    ) -> Result<(), Error> {
-        self.dev.set_sample_rate(direction, channel, rate)
+    ) -> Result<f64, Error> {
+        self.set_rate(direction, channel, SampleRate::sps(rate))
+            .map(SampleRate::as_sps)
+    }
+
+    /// Get the baseband sample rate of the chain.
+    pub fn rate(&self, direction: Direction, channel: usize) -> Result<SampleRate, Error> {
+        self.dev
+            .sample_rate(direction, channel)
+            .map(SampleRate::sps)
+    }
+
+    /// Set the baseband sample rate of the chain.
+    ///
+    /// On devices without [independent sample rates](crate::SampleRateClock), a
+    /// generic device sets the rate of all channels of the other direction as well. A rate
+    /// that does not suit all of them is rejected with `Err(Error::OutOfRange)` before any
+    /// channel changes.
+    ///
+    /// Returns the rate the device applied, which can differ from the requested one, if the
+    /// driver coerces it to the rates of its clocks.
+    pub fn set_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: SampleRate,
+    ) -> Result<SampleRate, Error> {
+        let rate = rate.as_sps();
+        self.dev.set_sample_rate(direction, channel, rate)?;
+        let applied = self.dev.sample_rate(direction, channel).unwrap_or(rate);
+        self.events.push(
+            EventKind::SampleRate,
+            applied_message(direction, channel, "sample rate", applied, rate, "Hz"),
+        );
+        Ok(SampleRate::sps(applied))
     }
 
     /// Get the range of possible baseband sample rates.
@@ -1172,4 +2357,149 @@ impl<
     ) -> Result<Range, Error> {
         self.dev.get_bandwidth_range(direction, channel)
     }
+
+    //================================ LOOPBACK ============================================
+    /// List of available loopback and test signal modes.
+    ///
+    /// Loopback modes route the TX chain or an internal test signal to the RX chain, which allows
+    /// self-test routines without external equipment. The mode `none` disables the loopback.
+    pub fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        self.dev.list_loopback_modes()
+    }
+
+    /// Set the loopback or test signal mode.
+    pub fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        self.dev.set_loopback_mode(mode)
+    }
+
+    /// Currently used loopback or test signal mode.
+    pub fn loopback_mode(&self) -> Result<String, Error> {
+        self.dev.loopback_mode()
+    }
+
+    //================================ SELF TEST ============================================
+    /// Run a health check of the device.
+    ///
+    /// The default implementation reads back control values, streams a few samples, and runs an
+    /// internal loopback test, if available. Drivers can override this with hardware-specific
+    /// checks. Failed checks are reported in the [`SelfTestReport`], while the returned error is
+    /// reserved for cases where no test could be run at all.
+    pub fn self_test(&self) -> Result<SelfTestReport, Error> {
+        self.dev.self_test()
+    }
+
+    /// Query the [capabilities](CapabilityReport) of the device, e.g., for a probe tool.
+    pub fn probe_capabilities(&self) -> Result<CapabilityReport, Error> {
+        CapabilityReport::collect(self)
+    }
+
+    /// Read the settings of the device into a [`DeviceState`], e.g., to restore or compare them.
+    pub fn snapshot(&self) -> Result<DeviceState, Error> {
+        DeviceState::collect(self)
+    }
+
+    //================================ POWER ============================================
+    /// Put the device in a [power state](PowerState).
+    ///
+    /// Streams have to be deactivated before leaving [`PowerState::On`].
+    pub fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        self.dev.set_power_state(state)?;
+        self.events
+            .push(EventKind::Other, format!("power state {state:?}"));
+        Ok(())
+    }
+
+    /// Current [power state](PowerState).
+    pub fn power_state(&self) -> Result<PowerState, Error> {
+        self.dev.power_state()
+    }
+
+    //================================ TIME ============================================
+    /// List of available time sources, e.g., `internal`, `external` (PPS input), or `gpsdo`.
+    pub fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        self.dev.list_time_sources()
+    }
+
+    /// Currently used time source.
+    pub fn time_source(&self) -> Result<String, Error> {
+        self.dev.time_source()
+    }
+
+    /// Set the time source.
+    pub fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        self.dev.set_time_source(name)
+    }
+
+    /// Returns true, if the GPSDO of the device is locked.
+    pub fn gps_locked(&self) -> Result<bool, Error> {
+        self.dev.gps_locked()
+    }
+
+    /// Position reported by the GPS receiver of the device.
+    ///
+    /// Fails with [`Error::NotFound`], if the receiver has no fix.
+    pub fn gps_location(&self) -> Result<Location, Error> {
+        self.dev.gps_location()
+    }
+
+    /// Location of the device, attached to recordings.
+    ///
+    /// A location set with [`set_location`](Self::set_location) or passed in the device
+    /// arguments takes precedence over the [GPS receiver](Self::gps_location) of the device.
+    /// Returns `None`, if neither is available.
+    pub fn location(&self) -> Result<Option<Location>, Error> {
+        if let Some(loc) = *self.location.lock().unwrap() {
+            return Ok(Some(loc));
+        }
+        match self.dev.gps_location() {
+            Ok(loc) => Ok(Some(loc)),
+            Err(Error::NotSupported | Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    /// Set the location of the device, or `None` to fall back to its GPS receiver.
+    pub fn set_location(&self, location: Option<Location>) {
+        *self.location.lock().unwrap() = location;
+    }
+
+    /// Current hardware time in nanoseconds.
+    pub fn hardware_time(&self) -> Result<i64, Error> {
+        self.dev.hardware_time()
+    }
+
+    /// Set the hardware time in nanoseconds immediately.
+    pub fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        self.dev.set_hardware_time(time_ns)
+    }
+
+    /// Set the hardware time in nanoseconds on the next PPS edge.
+    ///
+    /// To align devices to UNIX time, call this right after a PPS edge with the time of the next
+    /// full second.
+    pub fn set_hardware_time_next_pps(&self, time_ns: i64) -> Result<(), Error> {
+        self.dev.set_hardware_time_next_pps(time_ns)
+    }
+
+    /// Status of the time discipline, i.e., time source, GPS lock, and offset to the host clock.
+    ///
+    /// The default implementation combines the other time-related methods and requires
+    /// [`hardware_time`](Self::hardware_time).
+    pub fn time_discipline(&self) -> Result<TimeDiscipline, Error> {
+        self.dev.time_discipline()
+    }
+
+    /// Queue commands in the hardware command queue, to take effect at `time_ns` of the hardware
+    /// time.
+    ///
+    /// Only drivers with a command queue implement this. For others, [`Device::at`] executes the
+    /// commands on a scheduler thread instead.
+    pub fn execute_at(&self, time_ns: i64, commands: &[TimedCommand]) -> Result<(), Error> {
+        self.dev.execute_at(time_ns, commands)
+    }
+
+    /// Batch commands that take effect at `time_ns`, see [`TimedCommands`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn at(&self, time_ns: i64) -> TimedCommands<D> {
+        TimedCommands::new(self.clone(), time_ns)
+    }
 }
@@ -0,0 +1,74 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Meaning of a gain element, returned by [`DeviceTrait::gain_element_info`](crate::DeviceTrait::gain_element_info).
+///
+/// Drivers do not agree on what "gain" means. Aaronia devices, for example, map it to a reference
+/// level. This describes the mapping, so that user interfaces can label the value correctly.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GainElementInfo {
+    /// Name of the element, as listed by `gain_elements`.
+    pub name: String,
+    /// Label for user interfaces, e.g., `LNA` or `Reference level`.
+    pub label: String,
+    /// Unit of the value passed to and returned by the gain methods, usually `dB`.
+    pub unit: String,
+    /// How the value maps to the hardware setting.
+    pub description: String,
+}
+
+impl GainElementInfo {
+    /// Create the description of a gain element.
+    pub fn new(
+        name: impl Into<String>,
+        label: impl Into<String>,
+        unit: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            label: label.into(),
+            unit: unit.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Link to documentation of the hardware or the vendor library that a driver builds upon.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceDoc {
+    /// Title of the document.
+    pub title: String,
+    /// URL of the document.
+    pub url: String,
+}
+
+impl ReferenceDoc {
+    /// Create a link to a document.
+    pub fn new(title: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            url: url.into(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use crate::Device;
+    use crate::Direction::Rx;
+    use crate::Error;
+
+    #[test]
+    fn default_gain_element_info() {
+        let dev = Device::from_impl(crate::impls::Dummy::open(crate::Args::new()).unwrap());
+        let info = dev.gain_element_info(Rx, 0, "RF").unwrap();
+        assert_eq!(info.name, "RF");
+        assert_eq!(info.unit, "dB");
+        assert!(matches!(
+            dev.gain_element_info(Rx, 0, "LNA"),
+            Err(Error::ValueError)
+        ));
+        assert!(dev.reference_docs().unwrap().is_empty());
+    }
+}
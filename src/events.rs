@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Number of events kept by a [`Device`](crate::Device).
+pub const EVENT_LOG_CAPACITY: usize = 256;
+
+/// Kind of an [`Event`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EventKind {
+    /// The frequency of a channel was changed.
+    Retune,
+    /// The sample rate of a channel was changed.
+    SampleRate,
+    /// A gain was changed.
+    Gain,
+    /// Automatic gain control was enabled or disabled.
+    Agc,
+    /// Samples were dropped.
+    Overflow,
+    /// The transmitter ran out of samples.
+    Underflow,
+    /// A timed command or burst arrived too late to be executed at its time.
+    LateCommand,
+    /// The connection to the device was re-established.
+    Reconnect,
+    /// Any other event.
+    Other,
+}
+
+/// Notable event of a device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Host time since the UNIX epoch in nanoseconds, not available on wasm32.
+    pub time_ns: Option<i64>,
+    /// Kind of the event.
+    pub kind: EventKind,
+    /// Description of the event.
+    pub message: String,
+}
+
+/// Bounded log of recent device events.
+///
+/// Clones share the log, so streaming code can add events, like overflows, to the log of the
+/// device. Once the log is full, the oldest events are dropped, but they still count towards the
+/// [totals](Self::total).
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    inner: Arc<Mutex<Inner>>,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    events: VecDeque<Event>,
+    totals: HashMap<EventKind, u64>,
+}
+
+impl EventLog {
+    /// Create a log that keeps the last `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                events: VecDeque::with_capacity(capacity),
+                totals: HashMap::new(),
+            })),
+            capacity,
+        }
+    }
+    /// Add an event.
+    pub fn push(&self, kind: EventKind, message: impl Into<String>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let time_ns = crate::time::host_time_ns().ok();
+        #[cfg(target_arch = "wasm32")]
+        let time_ns = None;
+        let event = Event {
+            time_ns,
+            kind,
+            message: message.into(),
+        };
+        log::debug!("{:?}: {}", event.kind, event.message);
+        let mut inner = self.inner.lock().unwrap();
+        *inner.totals.entry(event.kind).or_default() += 1;
+        if inner.events.len() == self.capacity {
+            inner.events.pop_front();
+        }
+        if self.capacity > 0 {
+            inner.events.push_back(event);
+        }
+    }
+    /// Get the events, oldest first.
+    pub fn events(&self) -> Vec<Event> {
+        self.inner.lock().unwrap().events.iter().cloned().collect()
+    }
+    /// Number of events of the given kind since the log was created.
+    pub fn total(&self, kind: EventKind) -> u64 {
+        self.inner
+            .lock()
+            .unwrap()
+            .totals
+            .get(&kind)
+            .copied()
+            .unwrap_or(0)
+    }
+    /// Remove all events. The totals are not reset.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().events.clear();
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded() {
+        let log = EventLog::new(2);
+        let shared = log.clone();
+        log.push(EventKind::Retune, "a");
+        log.push(EventKind::Gain, "b");
+        shared.push(EventKind::Overflow, "c");
+        let events = log.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::Gain);
+        assert_eq!(events[1].message, "c");
+        log.clear();
+        assert!(shared.events().is_empty());
+        assert_eq!(shared.total(EventKind::Retune), 1);
+        assert_eq!(shared.total(EventKind::Reconnect), 0);
+    }
+}
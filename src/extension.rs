@@ -0,0 +1,186 @@
+use std::any::Any;
+use std::any::TypeId;
+use std::sync::Arc;
+
+use crate::Direction;
+use crate::Error;
+
+/// Optional functionality of a driver, beyond the [`DeviceTrait`](crate::DeviceTrait).
+///
+/// New features are added as extension traits instead of growing the `DeviceTrait`, so that
+/// out-of-tree drivers keep compiling with new releases. An extension trait is implemented by the
+/// driver and registered in its [`extensions`](crate::DeviceTrait::extensions). Users check for
+/// it with [`Device::supports`](crate::Device::supports) and get it with
+/// [`Device::extension`](crate::Device::extension).
+///
+/// Extension traits are not changed incompatibly after a release. A breaking change adds a new
+/// trait with a new `VERSION`, e.g., `SensorsV2`, which drivers register next to the old one.
+///
+/// The trait is implemented for the trait object of the extension:
+///
+/// ```
+/// use seify::Extension;
+///
+/// pub trait Clock: Send + Sync {
+///     fn reference_hz(&self) -> f64;
+/// }
+///
+/// impl Extension for dyn Clock {
+///     const NAME: &'static str = "clock";
+///     const VERSION: u32 = 1;
+/// }
+/// ```
+pub trait Extension: 'static {
+    /// Name of the extension.
+    const NAME: &'static str;
+    /// Version of the extension, incremented with a new trait for breaking changes.
+    const VERSION: u32;
+}
+
+/// Extensions implemented by a driver, see [`Extension`].
+#[derive(Default)]
+pub struct Extensions {
+    entries: Vec<(TypeId, &'static str, u32, Box<dyn Any + Send + Sync>)>,
+}
+
+impl Extensions {
+    /// Create an empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register the implementation of an extension, replacing a previous one.
+    pub fn insert<E: Extension + Send + Sync + ?Sized>(&mut self, ext: Arc<E>) {
+        self.entries.retain(|e| e.0 != TypeId::of::<E>());
+        self.entries
+            .push((TypeId::of::<E>(), E::NAME, E::VERSION, Box::new(ext)));
+    }
+    /// Check, if the extension is implemented.
+    pub fn contains<E: Extension + ?Sized>(&self) -> bool {
+        self.entries.iter().any(|e| e.0 == TypeId::of::<E>())
+    }
+    /// Get the implementation of an extension.
+    pub fn get<E: Extension + Send + Sync + ?Sized>(&self) -> Option<Arc<E>> {
+        self.entries
+            .iter()
+            .find(|e| e.0 == TypeId::of::<E>())
+            .and_then(|e| e.3.downcast_ref::<Arc<E>>())
+            .cloned()
+    }
+    /// Names and versions of the implemented extensions, e.g., `sensors/1`.
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|e| format!("{}/{}", e.1, e.2))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Sensors of a device, e.g., temperatures or lock indicators.
+///
+/// Extension `sensors`, version 1.
+pub trait Sensors: Send + Sync {
+    /// Names of the sensors.
+    fn list_sensors(&self) -> Result<Vec<String>, Error>;
+    /// Reading of a sensor.
+    ///
+    /// Fails with [`Error::NotFound`] for unknown sensors.
+    fn read_sensor(&self, name: &str) -> Result<String, Error>;
+}
+
+impl Extension for dyn Sensors {
+    const NAME: &'static str = "sensors";
+    const VERSION: u32 = 1;
+}
+
+/// Enabling and disabling channels for streaming.
+///
+/// The channels of MIMO devices share the bandwidth of the interface, so disabling unused
+/// channels allows higher sample rates for the others. A
+/// [`GenericDevice`](crate::GenericDevice) refuses to create streamers for disabled channels
+/// with `Err(Error::Inactive)`.
+///
+/// Extension `channel_enable`, version 1.
+pub trait ChannelEnable: Send + Sync {
+    /// Whether a channel is enabled for streaming.
+    fn channel_enabled(&self, direction: Direction, channel: usize) -> Result<bool, Error>;
+    /// Enable or disable a channel for streaming.
+    fn set_channel_enabled(
+        &self,
+        direction: Direction,
+        channel: usize,
+        enabled: bool,
+    ) -> Result<(), Error>;
+}
+
+impl Extension for dyn ChannelEnable {
+    const NAME: &'static str = "channel_enable";
+    const VERSION: u32 = 1;
+}
+
+/// Sample rate clock of RX and TX.
+///
+/// Drivers of devices that derive the rates of both directions from one clock register the
+/// extension. A [`GenericDevice`](crate::GenericDevice) then changes the rate of the other
+/// direction along with it, see [`Device::set_sample_rate`](crate::Device::set_sample_rate).
+/// Without the extension, RX and TX have independent rates.
+///
+/// Extension `sample_rate_clock`, version 1.
+pub trait SampleRateClock: Send + Sync {
+    /// Whether RX and TX currently run at independent sample rates.
+    fn independent_sample_rates(&self) -> Result<bool, Error>;
+}
+
+impl Extension for dyn SampleRateClock {
+    const NAME: &'static str = "sample_rate_clock";
+    const VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn sensors() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        assert!(dev.supports::<dyn Sensors>());
+        assert_eq!(
+            dev.extensions().names(),
+            ["sensors/1", "channel_enable/1", "sample_rate_clock/1"]
+        );
+        let sensors = dev.extension::<dyn Sensors>().unwrap();
+        for name in sensors.list_sensors().unwrap() {
+            assert!(sensors.read_sensor(&name).is_ok());
+        }
+        assert!(matches!(sensors.read_sensor("x"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn versions() {
+        trait Old: Send + Sync {}
+        trait New: Send + Sync {}
+        impl Extension for dyn Old {
+            const NAME: &'static str = "test";
+            const VERSION: u32 = 1;
+        }
+        impl Extension for dyn New {
+            const NAME: &'static str = "test";
+            const VERSION: u32 = 2;
+        }
+        struct Driver;
+        impl Old for Driver {}
+
+        let mut ext = Extensions::new();
+        ext.insert::<dyn Old>(Arc::new(Driver));
+        assert!(ext.contains::<dyn Old>());
+        assert!(!ext.contains::<dyn New>());
+        assert!(ext.get::<dyn New>().is_none());
+        assert!(ext.get::<dyn Old>().is_some());
+    }
+}
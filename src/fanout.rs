@@ -0,0 +1,184 @@
+use num_complex::Complex32;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::TrySendError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::Error;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+
+/// Timeout of the reads of the fan-out thread, which bounds the time to stop it.
+const READ_TIMEOUT_US: i64 = 100_000;
+
+/// Distributes the samples of an RX stream to multiple consumers.
+///
+/// A background thread reads blocks of samples and passes them to all
+/// [subscriptions](Self::subscribe). Each subscription has a bounded queue. If a consumer does not
+/// keep up, blocks are dropped for this consumer only and counted in
+/// [`Subscription::dropped`], so slow consumers never stall the stream or other consumers.
+///
+/// Clones share the fan-out. The thread runs until [`stop`](Self::stop) is called or the stream
+/// fails.
+#[derive(Clone)]
+pub struct FanOut {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    subscribers: Mutex<Vec<Subscriber>>,
+    stop: AtomicBool,
+    canceller: Option<StreamCanceller>,
+    thread: Mutex<Option<JoinHandle<Result<(), Error>>>>,
+}
+
+struct Subscriber {
+    tx: SyncSender<Arc<[Complex32]>>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// Consumer of a [`FanOut`].
+pub struct Subscription {
+    rx: Receiver<Arc<[Complex32]>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl FanOut {
+    /// Activate the stream and start distributing blocks of up to `block` samples.
+    pub fn start<S: RxStreamer + 'static>(mut rx: S, block: usize) -> Result<Self, Error> {
+        if block == 0 {
+            return Err(Error::ValueError);
+        }
+        rx.activate()?;
+        let inner = Arc::new(Inner {
+            subscribers: Mutex::new(Vec::new()),
+            stop: AtomicBool::new(false),
+            canceller: rx.canceller().ok(),
+            thread: Mutex::new(None),
+        });
+        let i = inner.clone();
+        let thread = std::thread::spawn(move || {
+            let r = i.run(&mut rx, block);
+            // disconnect the subscriptions
+            i.subscribers.lock().unwrap().clear();
+            if let Err(e) = &r {
+                log::warn!("fan-out stream failed: {e}");
+            }
+            r.and(rx.deactivate())
+        });
+        *inner.thread.lock().unwrap() = Some(thread);
+        Ok(Self { inner })
+    }
+
+    /// Subscribe to the stream, queueing up to `depth` blocks.
+    pub fn subscribe(&self, depth: usize) -> Subscription {
+        let (tx, rx) = std::sync::mpsc::sync_channel(depth.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.inner.subscribers.lock().unwrap().push(Subscriber {
+            tx,
+            dropped: dropped.clone(),
+        });
+        Subscription { rx, dropped }
+    }
+
+    /// Stop and deactivate the stream.
+    ///
+    /// Returns the error that ended the stream, if it failed.
+    pub fn stop(&self) -> Result<(), Error> {
+        self.inner.stop.store(true, Ordering::Relaxed);
+        if let Some(c) = &self.inner.canceller {
+            c.cancel();
+        }
+        match self.inner.thread.lock().unwrap().take() {
+            Some(t) => t
+                .join()
+                .unwrap_or_else(|_| Err(Error::Misc("fan-out thread panicked".to_string()))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Inner {
+    fn run<S: RxStreamer>(&self, rx: &mut S, block: usize) -> Result<(), Error> {
+        let mut buf = vec![Complex32::new(0.0, 0.0); block];
+        while !self.stop.load(Ordering::Relaxed) {
+            match rx.read(&mut [&mut buf], READ_TIMEOUT_US) {
+                Ok(0) | Err(Error::Timeout) => {}
+                Ok(n) => self.distribute(&buf[..n]),
+                Err(Error::Overflow) => log::warn!("fan-out stream overflow"),
+                Err(Error::Cancelled) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn distribute(&self, samples: &[Complex32]) {
+        let block: Arc<[Complex32]> = samples.into();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| match s.tx.try_send(block.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    s.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+impl Subscription {
+    /// Wait for the next block.
+    ///
+    /// Returns [`Error::Inactive`], once the fan-out is stopped.
+    pub fn recv(&self) -> Result<Arc<[Complex32]>, Error> {
+        self.rx.recv().or(Err(Error::Inactive))
+    }
+    /// Wait for the next block for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Arc<[Complex32]>, Error> {
+        self.rx.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => Error::Timeout,
+            RecvTimeoutError::Disconnected => Error::Inactive,
+        })
+    }
+    /// Number of blocks dropped, because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::Args;
+    use crate::DeviceTrait;
+
+    #[test]
+    fn slow_consumer_drops() {
+        let dev = crate::impls::Dummy::open(Args::new()).unwrap();
+        let rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let fanout = FanOut::start(rx, 256).unwrap();
+        let fast = fanout.subscribe(64);
+        let slow = fanout.subscribe(1);
+        for _ in 0..10 {
+            assert_eq!(
+                fast.recv_timeout(Duration::from_secs(1)).unwrap().len(),
+                256
+            );
+        }
+        assert!(slow.dropped() > 0);
+        fanout.stop().unwrap();
+        while fast.recv().is_ok() {}
+        assert!(slow.recv_timeout(Duration::ZERO).is_ok());
+        assert!(matches!(slow.recv(), Err(Error::Inactive)));
+    }
+}
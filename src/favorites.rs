@@ -0,0 +1,236 @@
+use serde::Deserialize;
+use serde::Serialize;
+#[cfg(feature = "favorites")]
+use std::collections::BTreeMap;
+#[cfg(feature = "favorites")]
+use std::path::Path;
+#[cfg(feature = "favorites")]
+use std::path::PathBuf;
+
+use crate::Args;
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Error;
+use crate::Frequency;
+use crate::LoopLatency;
+use crate::RxStreamer;
+use crate::SampleRate;
+use crate::TxStreamer;
+
+/// Named device bookmarks, stored in a TOML file (cargo feature `favorites`).
+///
+/// Bookmarks store the [`Args`] to open a device and a [`Profile`] that is applied after opening
+/// it. They are opened by name with [`Device::from_name`].
+///
+/// ```toml
+/// [rooftop-hackrf]
+/// args = { driver = "hackrf", serial = "0000000000000000457863c82b3b4d4f" }
+/// profile = { frequency = 1090e6, sample_rate = 4e6, gain = 30 }
+/// ```
+#[cfg(feature = "favorites")]
+#[derive(Clone, Debug, Default)]
+pub struct Favorites {
+    path: PathBuf,
+    devices: BTreeMap<String, Favorite>,
+}
+
+/// Device bookmark.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    /// Arguments to open the device.
+    pub args: Args,
+    /// Settings to apply after opening the device.
+    #[serde(default)]
+    pub profile: Profile,
+    /// Measured calibration values of the device.
+    #[serde(default)]
+    pub calibration: Calibration,
+}
+
+/// Calibration values, measured once and stored with a [`Favorite`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Calibration {
+    /// TX to RX loop latency, measured with [`measure_loop_latency`](crate::measure_loop_latency).
+    pub loop_latency: Option<LoopLatency>,
+}
+
+/// Settings of a channel, applied when a [`Favorite`] is opened.
+///
+/// They apply to the RX channel or, on TX-only devices, to the TX channel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Channel, the settings apply to.
+    pub channel: usize,
+    /// Center frequency in Hz.
+    pub frequency: Option<f64>,
+    /// Sample rate in samples per second.
+    pub sample_rate: Option<f64>,
+    /// Bandwidth in Hz.
+    pub bandwidth: Option<f64>,
+    /// Overall gain in dB.
+    pub gain: Option<f64>,
+    /// Automatic gain control.
+    pub agc: Option<bool>,
+    /// Antenna.
+    pub antenna: Option<String>,
+}
+
+impl Profile {
+    /// Apply the settings to a device.
+    pub fn apply<
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    >(
+        &self,
+        dev: &Device<D>,
+    ) -> Result<(), Error> {
+        let c = self.channel;
+        let direction = if dev.num_channels(Direction::Rx)? > 0 {
+            Direction::Rx
+        } else if c < dev.num_channels(Direction::Tx)? {
+            Direction::Tx
+        } else {
+            return Err(Error::NotFound);
+        };
+        if let Some(rate) = self.sample_rate {
+            dev.set_rate(direction, c, SampleRate::sps(rate))?;
+        }
+        if let Some(bw) = self.bandwidth {
+            dev.set_bandwidth(direction, c, bw)?;
+        }
+        if let Some(antenna) = &self.antenna {
+            dev.set_antenna(direction, c, antenna)?;
+        }
+        if let Some(agc) = self.agc {
+            dev.enable_agc(direction, c, agc)?;
+        }
+        if let Some(gain) = self.gain {
+            dev.set_gain(direction, c, gain)?;
+        }
+        if let Some(frequency) = self.frequency {
+            dev.tune(direction, c, Frequency::hz(frequency))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "favorites")]
+impl Favorites {
+    /// Default location of the store, `seify/devices.toml` in the user's configuration directory.
+    ///
+    /// This is `$XDG_CONFIG_HOME` or `~/.config` on Unix and `%APPDATA%` on Windows.
+    pub fn default_path() -> Result<PathBuf, Error> {
+        let var = |k: &str| std::env::var_os(k).filter(|v| !v.is_empty());
+        let dir = if cfg!(windows) {
+            var("APPDATA").map(PathBuf::from)
+        } else {
+            var("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| var("HOME").map(|h| Path::new(&h).join(".config")))
+        };
+        let dir = dir.ok_or_else(|| Error::Misc("no configuration directory".to_string()))?;
+        Ok(dir.join("seify").join("devices.toml"))
+    }
+
+    /// Load the store from the [default location](Self::default_path).
+    pub fn load() -> Result<Self, Error> {
+        Self::load_from(Self::default_path()?)
+    }
+
+    /// Load the store from `path`. A missing file is treated as an empty store.
+    pub fn load_from<P: Into<PathBuf>>(path: P) -> Result<Self, Error> {
+        let path = path.into();
+        let devices = match std::fs::read_to_string(&path) {
+            Ok(s) => toml::from_str(&s).map_err(|e| {
+                Error::Misc(format!("invalid device store {}: {e}", path.display()))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, devices })
+    }
+
+    /// Write the store back to the file it was loaded from.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let s = toml::to_string(&self.devices).map_err(|e| Error::Misc(e.to_string()))?;
+        Ok(std::fs::write(&self.path, s)?)
+    }
+
+    /// Get a bookmark.
+    pub fn get(&self, name: &str) -> Option<&Favorite> {
+        self.devices.get(name)
+    }
+    /// Add or replace a bookmark, returning the replaced one.
+    pub fn insert(&mut self, name: impl Into<String>, favorite: Favorite) -> Option<Favorite> {
+        self.devices.insert(name.into(), favorite)
+    }
+    /// Remove a bookmark.
+    pub fn remove(&mut self, name: &str) -> Option<Favorite> {
+        self.devices.remove(name)
+    }
+    /// Names of the bookmarks in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.devices.keys().map(|k| k.as_str())
+    }
+}
+
+#[cfg(all(test, feature = "favorites"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("seify-favorites-{}", std::process::id()))
+            .join("devices.toml");
+        let mut store = Favorites::load_from(&path).unwrap();
+        assert_eq!(store.names().count(), 0);
+        let favorite = Favorite {
+            args: "driver=dummy, serial=1234".parse().unwrap(),
+            profile: Profile {
+                frequency: Some(1090e6),
+                gain: Some(30.0),
+                ..Default::default()
+            },
+            calibration: Calibration {
+                loop_latency: Some(LoopLatency {
+                    samples: 87,
+                    sample_rate: 1e6,
+                    peak: 0.9,
+                }),
+            },
+        };
+        store.insert("rooftop", favorite.clone());
+        store.save().unwrap();
+
+        let store = Favorites::load_from(&path).unwrap();
+        assert_eq!(store.names().collect::<Vec<_>>(), ["rooftop"]);
+        assert_eq!(store.get("rooftop"), Some(&favorite));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn parse() {
+        let path = std::env::temp_dir().join(format!("seify-fav-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[a]\nargs = { driver = \"dummy\" }\nprofile = { frequency = 1e6, agc = true }\n",
+        )
+        .unwrap();
+        let store = Favorites::load_from(&path).unwrap();
+        let a = store.get("a").unwrap();
+        assert_eq!(a.args.get::<String>("driver").unwrap(), "dummy");
+        assert_eq!(a.profile.frequency, Some(1e6));
+        assert_eq!(a.profile.agc, Some(true));
+        std::fs::write(&path, "[a]\nargs = {}\nprofile = { freq = 1 }\n").unwrap();
+        assert!(Favorites::load_from(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use num_complex::Complex32;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Sample format of raw IQ recordings.
+///
+/// Samples are interleaved IQ pairs in little-endian byte order. Integer formats are scaled to
+/// `[-1, 1)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IqFormat {
+    /// 32-bit float, e.g., GNU Radio file sinks.
+    Cf32,
+    /// Signed 16-bit integer.
+    Cs16,
+    /// Signed 8-bit integer, e.g., HackRF captures.
+    Cs8,
+    /// Unsigned 8-bit integer with an offset of 127, e.g., `rtl_sdr` captures.
+    Cu8,
+}
+
+impl IqFormat {
+    /// Size of a complex sample in bytes.
+    pub fn sample_size(&self) -> usize {
+        match self {
+            IqFormat::Cf32 => 8,
+            IqFormat::Cs16 => 4,
+            IqFormat::Cs8 | IqFormat::Cu8 => 2,
+        }
+    }
+
+    /// Guess the format from the file extension, e.g., `.cu8` or `.cfile`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "cf32" | "fc32" | "cfile" => Some(IqFormat::Cf32),
+            "cs16" | "sc16" => Some(IqFormat::Cs16),
+            "cs8" | "sc8" => Some(IqFormat::Cs8),
+            "cu8" => Some(IqFormat::Cu8),
+            _ => None,
+        }
+    }
+
+    /// Convert raw samples to `Complex32`.
+    ///
+    /// `bytes` has to hold [`sample_size`](Self::sample_size) bytes per output sample.
+    pub fn decode(&self, bytes: &[u8], out: &mut [Complex32]) {
+        debug_assert_eq!(bytes.len(), out.len() * self.sample_size());
+        let chunks = bytes.chunks_exact(self.sample_size());
+        match self {
+            IqFormat::Cf32 => {
+                for (s, b) in out.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        f32::from_le_bytes(b[0..4].try_into().unwrap()),
+                        f32::from_le_bytes(b[4..8].try_into().unwrap()),
+                    );
+                }
+            }
+            IqFormat::Cs16 => {
+                for (s, b) in out.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+                        i16::from_le_bytes([b[2], b[3]]) as f32 / 32768.0,
+                    );
+                }
+            }
+            IqFormat::Cs8 => {
+                for (s, b) in out.iter_mut().zip(chunks) {
+                    *s = Complex32::new(b[0] as i8 as f32 / 128.0, b[1] as i8 as f32 / 128.0);
+                }
+            }
+            IqFormat::Cu8 => {
+                for (s, b) in out.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        (b[0] as f32 - 127.0) / 128.0,
+                        (b[1] as f32 - 127.0) / 128.0,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Convert `Complex32` samples to the raw format and append them to `out`.
+    ///
+    /// Integer formats round to the nearest step and saturate for values outside of `[-1, 1)`.
+    pub fn encode(&self, samples: &[Complex32], out: &mut Vec<u8>) {
+        out.reserve(samples.len() * self.sample_size());
+        for s in samples {
+            match self {
+                IqFormat::Cf32 => {
+                    out.extend_from_slice(&s.re.to_le_bytes());
+                    out.extend_from_slice(&s.im.to_le_bytes());
+                }
+                IqFormat::Cs16 => {
+                    out.extend_from_slice(&((s.re * 32768.0).round() as i16).to_le_bytes());
+                    out.extend_from_slice(&((s.im * 32768.0).round() as i16).to_le_bytes());
+                }
+                IqFormat::Cs8 => {
+                    out.push((s.re * 128.0).round() as i8 as u8);
+                    out.push((s.im * 128.0).round() as i8 as u8);
+                }
+                IqFormat::Cu8 => {
+                    out.push((s.re * 128.0 + 127.0).round().clamp(0.0, 255.0) as u8);
+                    out.push((s.im * 128.0 + 127.0).round().clamp(0.0, 255.0) as u8);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for IqFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cf32" | "fc32" => Ok(IqFormat::Cf32),
+            "cs16" | "sc16" => Ok(IqFormat::Cs16),
+            "cs8" | "sc8" => Ok(IqFormat::Cs8),
+            "cu8" => Ok(IqFormat::Cu8),
+            _ => Err(Error::ValueError),
+        }
+    }
+}
+
+impl std::fmt::Display for IqFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IqFormat::Cf32 => "cf32",
+            IqFormat::Cs16 => "cs16",
+            IqFormat::Cs8 => "cs8",
+            IqFormat::Cu8 => "cu8",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Header of a 2-channel (IQ) WAV file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WavHeader {
+    /// Sample format of the data chunk.
+    pub format: IqFormat,
+    /// Sample rate in samples per second.
+    pub sample_rate: u32,
+    /// Offset of the sample data in bytes.
+    pub data_offset: u64,
+    /// Length of the sample data in bytes.
+    pub data_len: u64,
+}
+
+impl WavHeader {
+    /// Parse the header of a WAV file, consuming the reader up to the start of the sample data.
+    ///
+    /// Supported are 2-channel files with 8-bit or 16-bit PCM or 32-bit float samples.
+    pub fn read<R: Read>(mut r: R) -> Result<Self, Error> {
+        fn invalid(msg: &str) -> Error {
+            Error::Misc(format!("invalid WAV file: {msg}"))
+        }
+        let mut riff = [0u8; 12];
+        r.read_exact(&mut riff)?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(invalid("missing RIFF/WAVE header"));
+        }
+        let mut offset = 12u64;
+        let mut format = None;
+        let mut sample_rate = 0;
+        loop {
+            let mut chunk = [0u8; 8];
+            r.read_exact(&mut chunk)?;
+            offset += 8;
+            let len = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as u64;
+            match &chunk[0..4] {
+                b"fmt " => {
+                    if len < 16 {
+                        return Err(invalid("short fmt chunk"));
+                    }
+                    let mut fmt = vec![0u8; len as usize + (len as usize & 1)];
+                    r.read_exact(&mut fmt)?;
+                    offset += fmt.len() as u64;
+                    let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    sample_rate = u32::from_le_bytes(fmt[4..8].try_into().unwrap());
+                    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                    if channels != 2 {
+                        return Err(invalid("IQ recordings need 2 channels"));
+                    }
+                    // 0xfffe is WAVE_FORMAT_EXTENSIBLE, which is used for the same formats
+                    format = Some(match (tag, bits) {
+                        (1 | 0xfffe, 8) => IqFormat::Cu8,
+                        (1 | 0xfffe, 16) => IqFormat::Cs16,
+                        (3 | 0xfffe, 32) => IqFormat::Cf32,
+                        _ => return Err(invalid("unsupported sample format")),
+                    });
+                }
+                b"data" => {
+                    let format = format.ok_or_else(|| invalid("data before fmt chunk"))?;
+                    return Ok(Self {
+                        format,
+                        sample_rate,
+                        data_offset: offset,
+                        data_len: len,
+                    });
+                }
+                _ => {
+                    let skip = len + (len & 1);
+                    std::io::copy(&mut (&mut r).take(skip), &mut std::io::sink())?;
+                    offset += skip;
+                }
+            }
+        }
+    }
+
+    /// Serialize the header for a recording with `data_len` bytes of samples.
+    ///
+    /// Only [`IqFormat::Cu8`], [`IqFormat::Cs16`], and [`IqFormat::Cf32`] can be stored in WAV
+    /// files.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let (tag, bits): (u16, u16) = match self.format {
+            IqFormat::Cu8 => (1, 8),
+            IqFormat::Cs16 => (1, 16),
+            IqFormat::Cf32 => (3, 32),
+            IqFormat::Cs8 => return Err(Error::ValueError),
+        };
+        let block = self.format.sample_size() as u32;
+        let data_len = u32::try_from(self.data_len).map_err(|_| Error::Overflow)?;
+        let mut h = Vec::with_capacity(44);
+        h.extend_from_slice(b"RIFF");
+        h.extend_from_slice(&(36 + data_len).to_le_bytes());
+        h.extend_from_slice(b"WAVEfmt ");
+        h.extend_from_slice(&16u32.to_le_bytes());
+        h.extend_from_slice(&tag.to_le_bytes());
+        h.extend_from_slice(&2u16.to_le_bytes());
+        h.extend_from_slice(&self.sample_rate.to_le_bytes());
+        h.extend_from_slice(&(self.sample_rate * block).to_le_bytes());
+        h.extend_from_slice(&(block as u16).to_le_bytes());
+        h.extend_from_slice(&bits.to_le_bytes());
+        h.extend_from_slice(b"data");
+        h.extend_from_slice(&data_len.to_le_bytes());
+        Ok(h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let samples = [
+            Complex32::new(0.5, -0.5),
+            Complex32::new(-1.0, 0.25),
+            Complex32::new(0.0, 0.0),
+        ];
+        for f in [IqFormat::Cf32, IqFormat::Cs16, IqFormat::Cs8, IqFormat::Cu8] {
+            let mut bytes = Vec::new();
+            f.encode(&samples, &mut bytes);
+            assert_eq!(bytes.len(), samples.len() * f.sample_size());
+            let mut out = [Complex32::new(9.0, 9.0); 3];
+            f.decode(&bytes, &mut out);
+            for (a, b) in samples.iter().zip(out.iter()) {
+                assert!((a - b).norm() < 0.01, "{f}: {a} != {b}");
+            }
+            assert_eq!(f.to_string().parse::<IqFormat>().unwrap(), f);
+        }
+        assert_eq!(IqFormat::from_path("capture.CU8"), Some(IqFormat::Cu8));
+        assert_eq!(IqFormat::from_path("capture"), None);
+    }
+
+    #[test]
+    fn wav_header() {
+        let h = WavHeader {
+            format: IqFormat::Cs16,
+            sample_rate: 48000,
+            data_offset: 44,
+            data_len: 400,
+        };
+        let mut bytes = h.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 44);
+        // insert an unknown chunk before the data
+        bytes.splice(36..36, b"LIST\x03\x00\x00\x00abc\x00".iter().copied());
+        let parsed = WavHeader::read(&bytes[..]).unwrap();
+        assert_eq!(parsed.format, IqFormat::Cs16);
+        assert_eq!(parsed.sample_rate, 48000);
+        assert_eq!(parsed.data_offset, 56);
+        assert_eq!(parsed.data_len, 400);
+        assert!(WavHeader::read(&b"RIFF\0\0\0\0AVI "[..]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Minimal HTTP/1.1 server for the optional network endpoints.
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use crate::access::Slot;
+use crate::access::Stream;
+use crate::access::REQUEST_TIMEOUT;
+use crate::Access;
+use crate::Error;
+use crate::Permission;
+
+const MAX_REQUEST: usize = 1 << 20;
+
+/// Parsed HTTP request.
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    #[cfg_attr(not(feature = "control-http"), allow(dead_code))]
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of a header, with the name matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// HTTP response.
+pub(crate) struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: &'static str, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+}
+
+/// Serve requests from background threads, over TLS, if `access` configures it.
+///
+/// Clients are authenticated with the request head, before the body is read, and the handler
+/// gets their permission.
+pub(crate) fn serve<A, F>(addr: A, access: Access, handler: F) -> Result<JoinHandle<()>, Error>
+where
+    A: ToSocketAddrs,
+    F: Fn(Request, Permission) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    let handler = Arc::new(handler);
+    let active = Arc::new(AtomicUsize::new(0));
+    Ok(std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let s = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    log::warn!("accepting HTTP client failed: {e}");
+                    continue;
+                }
+            };
+            let Some(slot) = Slot::take(&active) else {
+                log::warn!("too many HTTP clients, dropping connection");
+                continue;
+            };
+            let access = access.clone();
+            let handler = handler.clone();
+            let r = std::thread::Builder::new()
+                .name("seify-http".to_string())
+                .spawn(move || {
+                    let _slot = slot;
+                    if let Err(e) = handle(s, &access, &*handler) {
+                        log::warn!("serving HTTP request failed: {e}");
+                    }
+                });
+            if let Err(e) = r {
+                log::warn!("spawning HTTP thread failed: {e}");
+            }
+        }
+    }))
+}
+
+fn handle<F>(stream: TcpStream, access: &Access, handler: &F) -> Result<(), Error>
+where
+    F: Fn(Request, Permission) -> Response,
+{
+    let mut s = access.accept(stream)?;
+    s.set_deadline(Some(Instant::now() + REQUEST_TIMEOUT));
+    let (mut request, mut data) = read_head(&mut s)?;
+    let (_, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let token = crate::access::token(request.header("authorization"), query);
+    let response = match access.authenticate(token) {
+        Ok(permission) => {
+            request.body = read_body(&mut s, &request, &mut data)?;
+            handler(request, permission)
+        }
+        Err(_) => Response::new("401 Unauthorized", "text/plain", ""),
+    };
+    write_response(&mut s, &response)?;
+    s.finish()
+}
+
+/// Read the request line and the headers, returning the bytes read beyond them.
+fn read_head(stream: &mut Stream) -> Result<(Request, Vec<u8>), Error> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(i) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        if data.len() > MAX_REQUEST {
+            return Err(Error::Overflow);
+        }
+        match stream.read(&mut buf)? {
+            0 => return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    };
+    let header = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = header.lines();
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_string();
+    let path = request_line.next().unwrap_or("").to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let rest = data.split_off(header_end);
+    Ok((
+        Request {
+            method,
+            path,
+            headers,
+            body: Vec::new(),
+        },
+        rest,
+    ))
+}
+
+/// Read the body announced by the `Content-Length` header, starting with `data`.
+fn read_body(stream: &mut Stream, request: &Request, data: &mut Vec<u8>) -> Result<Vec<u8>, Error> {
+    let len = request
+        .header("content-length")
+        .map(|v| v.parse::<usize>().or(Err(Error::ValueError)))
+        .transpose()?
+        .unwrap_or(0);
+    if len > MAX_REQUEST {
+        return Err(Error::Overflow);
+    }
+    let mut body = std::mem::take(data);
+    let mut buf = [0u8; 4096];
+    while body.len() < len {
+        match stream.read(&mut buf)? {
+            0 => return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            n => body.extend_from_slice(&buf[..n]),
+        }
+    }
+    body.truncate(len);
+    Ok(body)
+}
+
+fn write_response(stream: &mut Stream, r: &Response) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        r.status,
+        r.content_type,
+        r.body.len()
+    )?;
+    stream.write_all(&r.body)?;
+    Ok(())
+}
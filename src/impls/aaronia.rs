@@ -2,9 +2,8 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 #![allow(dead_code)]
+use num_complex::Complex32;
 use std::any::Any;
-use std::sync::Arc;
-use std::sync::Mutex;
 
 use aaronia_rtsa::ApiHandle;
 use aaronia_rtsa::ConfigItem;
@@ -13,47 +12,90 @@ use aaronia_rtsa::DeviceInfo;
 use aaronia_rtsa::Packet;
 
 use crate::Args;
+use crate::ConfinedHandle;
 use crate::DeviceTrait;
 use crate::Direction;
 use crate::Direction::*;
 use crate::Driver;
 use crate::Error;
+use crate::GainElementInfo;
 use crate::Range;
 use crate::RangeItem;
+use crate::StreamCanceller;
+
+/// SDK device, which lives on the thread of a [`ConfinedHandle`], since the SDK handles hold raw
+/// pointers.
+struct Rtsa {
+    dev: Sdr,
+    // packet of the RX stream and the number of samples already read from it
+    packet: Option<(Packet, usize)>,
+}
+
+impl Rtsa {
+    /// Take up to `len` samples of the current packet, waiting for the next one if needed.
+    fn read(&mut self, len: usize) -> Result<Vec<Complex32>, Error> {
+        let (p, offset) = match self.packet.take() {
+            Some(p) => p,
+            None => (self.dev.packet(0).or(Err(Error::DeviceError))?, 0),
+        };
+        let cur = p.samples();
+        let total = cur.len();
+        let n = std::cmp::min(len, total - offset);
+        let samples = cur[offset..offset + n].to_vec();
+        if offset + n == total {
+            self.dev.consume(0).or(Err(Error::DeviceError))?;
+        } else {
+            self.packet = Some((p, offset + n));
+        }
+        Ok(samples)
+    }
+}
 
 /// Aaronia SpectranV6 driver, using the native SDK
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Aaronia {
-    dev: Arc<Mutex<Sdr>>,
+    dev: ConfinedHandle<Rtsa>,
     index: usize,
 }
 
-unsafe impl Send for Aaronia {}
-unsafe impl Sync for Aaronia {}
-
 /// Aaronia SpectranV6 RX Streamer
 pub struct RxStreamer {
-    dev: Arc<Mutex<Sdr>>,
-    packet: Option<(Packet, usize)>,
+    dev: ConfinedHandle<Rtsa>,
+    position: u64,
+    canceller: StreamCanceller,
+    active: bool,
+    scale: f32,
 }
 
-unsafe impl Send for RxStreamer {}
-
 impl RxStreamer {
-    fn new(dev: Arc<Mutex<Sdr>>) -> Self {
-        Self { dev, packet: None }
+    fn new(dev: ConfinedHandle<Rtsa>) -> Self {
+        Self {
+            dev,
+            position: 0,
+            canceller: StreamCanceller::new(),
+            active: false,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Drop for RxStreamer {
+    fn drop(&mut self) {
+        if self.active {
+            if let Err(e) = crate::RxStreamer::deactivate(self) {
+                log::warn!("failed to stop Aaronia RX stream on drop: {e}");
+            }
+        }
     }
 }
 
 /// Aaronia SpectranV6 TX Streamer
 pub struct TxStreamer {
-    dev: Arc<Mutex<Sdr>>,
+    dev: ConfinedHandle<Rtsa>,
 }
 
-unsafe impl Send for TxStreamer {}
-
 impl TxStreamer {
-    fn new(dev: Arc<Mutex<Sdr>>) -> Self {
+    fn new(dev: ConfinedHandle<Rtsa>) -> Self {
         Self { dev }
     }
 }
@@ -71,8 +113,8 @@ impl Aaronia {
         Ok(devs
             .iter()
             .enumerate()
-            .map(|(i, d)| format!("index={i}, driver=aaronia").parse().unwrap())
-            .collect())
+            .map(|(i, d)| format!("index={i}, driver=aaronia").parse())
+            .collect::<Result<Vec<_>, _>>()?)
     }
 
     /// Create an Aaronia SpectranV6 Device
@@ -82,26 +124,20 @@ impl Aaronia {
     /// If the devices were already [`scanned`](aaronia_rtsa::ApiHandle::rescan_devices) in a call
     /// to [`probe`](Self::probe), they are not rescanned to avoid changing the `index` identifier.
     pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
-        let mut api = ApiHandle::new().or(Err(Error::DeviceError))?;
-        api.rescan_devices().or(Err(Error::DeviceError))?;
-        let devs = api.devices().or(Err(Error::DeviceError))?;
-
-        if devs.is_empty() {
-            return Err(Error::NotFound);
-        }
-
-        let args = args.try_into().or(Err(Error::ValueError))?;
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
         let index = args.get::<usize>("index").unwrap_or(0);
 
-        let mut dev = api
-            .get_this_device(&devs[index])
-            .or(Err(Error::DeviceError))?;
-        dev.open().or(Err(Error::DeviceError))?;
-        #[allow(clippy::arc_with_non_send_sync)]
-        Ok(Aaronia {
-            dev: Arc::new(Mutex::new(dev)),
-            index,
-        })
+        let dev = ConfinedHandle::spawn("aaronia", move || {
+            let mut api = ApiHandle::new().or(Err(Error::DeviceError))?;
+            api.rescan_devices().or(Err(Error::DeviceError))?;
+            let devs = api.devices().or(Err(Error::DeviceError))?;
+            let info = devs.get(index).ok_or(Error::NotFound)?;
+
+            let mut dev = api.get_this_device(info).or(Err(Error::DeviceError))?;
+            dev.open().or(Err(Error::DeviceError))?;
+            Ok(Rtsa { dev, packet: None })
+        })?;
+        Ok(Aaronia { dev, index })
     }
 }
 
@@ -211,34 +247,38 @@ impl DeviceTrait for Aaronia {
     }
 
     fn enable_agc(&self, direction: Direction, channel: usize, agc: bool) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
         match (direction, channel) {
             (Rx, 0 | 1) => {
-                if agc {
-                    dev.set("device/gaincontrol", "power")
-                        .or(Err(Error::DeviceError))
-                } else {
-                    dev.set("device/gaincontrol", "manual")
+                let mode = if agc { "power" } else { "manual" };
+                self.dev.call(move |d| {
+                    d.dev
+                        .set("device/gaincontrol", mode)
                         .or(Err(Error::DeviceError))
-                }
+                })
             }
             _ => Err(Error::ValueError),
         }
     }
 
     fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
-        let mut dev = self.dev.lock().unwrap();
         match (direction, channel) {
-            (Rx, 0 | 1) => match dev.get("device/gaincontrol").or(Err(Error::DeviceError))? {
-                ConfigItem::Enum(0, _) => Ok(false),
-                _ => Ok(true),
-            },
+            (Rx, 0 | 1) => self.dev.call(|d| {
+                match d
+                    .dev
+                    .get("device/gaincontrol")
+                    .or(Err(Error::DeviceError))?
+                {
+                    ConfigItem::Enum(0, _) => Ok(false),
+                    _ => Ok(true),
+                }
+            }),
             _ => Err(Error::ValueError),
         }
     }
 
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
         self.set_gain_element(direction, channel, "TUNER", gain)
+            .map(drop)
     }
 
     fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
@@ -255,19 +295,26 @@ impl DeviceTrait for Aaronia {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
+    ) -> Result<f64, Error> {
         match (direction, channel, name) {
             (Rx, 0 | 1, "TUNER") | (Tx, 0, "TUNER") => {
                 if (0.0..=30.0).contains(&gain) {
-                    dev.set("main/reflevel", format!("{}", -8.0 - gain))
-                        .or(Err(Error::DeviceError))
+                    self.dev.call(move |d| {
+                        d.dev
+                            .set("main/reflevel", format!("{}", -8.0 - gain))
+                            .or(Err(Error::DeviceError))
+                    })?;
                 } else {
-                    Err(Error::ValueError)
+                    return Err(Error::ValueError);
                 }
             }
-            _ => Err(Error::DeviceError),
+            _ => return Err(Error::DeviceError),
         }
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
     }
 
     fn gain_element(
@@ -279,7 +326,7 @@ impl DeviceTrait for Aaronia {
         match (direction, channel) {
             (Rx, 0) => Ok(None),
             (Rx, 1) => Ok(None),
-            (Tx, 0) => todo!(),
+            (Tx, 0) => Err(Error::NotSupported),
             _ => Err(Error::ValueError),
         }
     }
@@ -297,6 +344,23 @@ impl DeviceTrait for Aaronia {
         }
     }
 
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        match (direction, channel, name) {
+            (Rx, 0 | 1, "TUNER") | (Tx, 0, "TUNER") => Ok(GainElementInfo::new(
+                name,
+                "Reference level",
+                "dB",
+                "A gain of g dB sets the reference level to -8 - g dBm, e.g., 0 dB is -8 dBm.",
+            )),
+            _ => Err(Error::ValueError),
+        }
+    }
+
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         self.component_frequency_range(direction, channel, "TUNER")
     }
@@ -347,14 +411,12 @@ impl DeviceTrait for Aaronia {
         name: &str,
     ) -> Result<f64, Error> {
         match (direction, channel, name) {
-            (Rx, 0 | 1, "TUNER") => {
-                let mut dev = self.dev.lock().unwrap();
-                let s = dev.get("main/centerfreq").or(Err(Error::DeviceError))?;
-                match s {
+            (Rx, 0 | 1, "TUNER") => self.dev.call(|d| {
+                match d.dev.get("main/centerfreq").or(Err(Error::DeviceError))? {
                     ConfigItem::Number(f) => Ok(f),
                     _ => Err(Error::ValueError),
                 }
-            }
+            }),
             _ => Err(Error::ValueError),
         }
     }
@@ -366,19 +428,20 @@ impl DeviceTrait for Aaronia {
         name: &str,
         frequency: f64,
     ) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
         match (channel, name) {
-            (0 | 1, "TUNER") => dev
-                .set("main/centerfreq", format!("{frequency}"))
-                .or(Err(Error::DeviceError)),
+            (0 | 1, "TUNER") => self.dev.call(move |d| {
+                d.dev
+                    .set("main/centerfreq", format!("{frequency}"))
+                    .or(Err(Error::DeviceError))
+            }),
             _ => Err(Error::ValueError),
         }
     }
 
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
         match (direction, channel) {
-            (Rx, 0 | 1) => {
-                let mut dev = self.dev.lock().unwrap();
+            (Rx, 0 | 1) => self.dev.call(|d| {
+                let dev = &mut d.dev;
                 let s = dev
                     .get("device/receiverclock")
                     .or(Err(Error::DeviceError))?;
@@ -405,7 +468,7 @@ impl DeviceTrait for Aaronia {
                 };
 
                 Ok(rate / s)
-            }
+            }),
             _ => Err(Error::ValueError),
         }
     }
@@ -416,22 +479,23 @@ impl DeviceTrait for Aaronia {
         channel: usize,
         rate: f64,
     ) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
         match (direction, channel) {
             (Rx, 0 | 1) => {
-                let dec = vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0];
-                for (i, d) in dec.into_iter().enumerate() {
-                    if (rate - 92e6 / d).abs() < 0.00001 {
-                        dev.set("device/receiverclock", "92MHz")
-                            .or(Err(Error::DeviceError))?;
-                        return dev
-                            .set_int("main/decimation", i as i64)
-                            .or(Err(Error::DeviceError));
-                    }
-                }
-                Err(Error::ValueError)
+                let dec = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0];
+                let i = dec
+                    .iter()
+                    .position(|d| (rate - 92e6 / d).abs() < 0.00001)
+                    .ok_or(Error::ValueError)?;
+                self.dev.call(move |d| {
+                    d.dev
+                        .set("device/receiverclock", "92MHz")
+                        .or(Err(Error::DeviceError))?;
+                    d.dev
+                        .set_int("main/decimation", i as i64)
+                        .or(Err(Error::DeviceError))
+                })
             }
-            (Tx, 0) => todo!(),
+            (Tx, 0) => Err(Error::NotSupported),
             _ => Err(Error::ValueError),
         }
     }
@@ -444,27 +508,66 @@ impl DeviceTrait for Aaronia {
                     .map(|v| RangeItem::Value(92e6 / v))
                     .collect(),
             )),
-            (Tx, 0) => todo!(),
+            (Tx, 0) => Err(Error::NotSupported),
             _ => Err(Error::ValueError),
         }
     }
+
+    fn bandwidth(&self, _direction: Direction, _channel: usize) -> Result<f64, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_bandwidth(&self, _direction: Direction, _channel: usize, _bw: f64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn get_bandwidth_range(&self, _direction: Direction, _channel: usize) -> Result<Range, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
 }
 
+impl crate::RxInterface for Aaronia {}
+
 impl crate::RxStreamer for RxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
         Ok(1024)
     }
 
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
-        dev.connect().or(Err(Error::DeviceError))?;
-        dev.start().or(Err(Error::DeviceError))
+        self.canceller.reset();
+        self.position = 0;
+        self.dev.call(|d| {
+            d.dev.connect().or(Err(Error::DeviceError))?;
+            d.dev.start().or(Err(Error::DeviceError))
+        })?;
+        self.active = true;
+        Ok(())
     }
 
     fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
-        let mut dev = self.dev.lock().unwrap();
-        dev.stop().or(Err(Error::DeviceError))?;
-        dev.disconnect().or(Err(Error::DeviceError))
+        self.active = false;
+        self.dev.call(|d| {
+            d.packet = None;
+            d.dev.stop().or(Err(Error::DeviceError))?;
+            d.dev.disconnect().or(Err(Error::DeviceError))
+        })
     }
 
     fn read(
@@ -472,41 +575,54 @@ impl crate::RxStreamer for RxStreamer {
         buffers: &mut [&mut [num_complex::Complex32]],
         _timeout_us: i64,
     ) -> Result<usize, Error> {
-        let mut dev = self.dev.lock().unwrap();
-        debug_assert_eq!(buffers.len(), 1);
-
         let mut i = 0;
         let len = buffers[0].len();
         while i < len {
-            match self.packet.take() {
-                None => {
-                    let p = dev.packet(0).or(Err(Error::DeviceError))?;
-                    let cur = p.samples();
-                    let n = std::cmp::min(len - i, cur.len());
-                    buffers[0][i..i + n].copy_from_slice(&cur[0..n]);
-                    i += n;
-                    if n == cur.len() {
-                        dev.consume(0).or(Err(Error::DeviceError))?;
-                    } else {
-                        self.packet = Some((p, n));
-                    }
-                }
-                Some((p, offset)) => {
-                    let cur = p.samples();
-                    let n = std::cmp::min(len - i, cur.len() - offset);
-                    buffers[0][i..i + n].copy_from_slice(&cur[offset..offset + n]);
-                    i += n;
-                    if offset + n == cur.len() {
-                        dev.consume(0).or(Err(Error::DeviceError))?;
-                    } else {
-                        self.packet = Some((p, offset + n));
-                    }
-                }
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
             }
+            let remaining = len - i;
+            let samples = self.dev.call(move |d| d.read(remaining))?;
+            buffers[0][i..i + samples.len()].copy_from_slice(&samples);
+            i += samples.len();
         }
 
+        if self.scale != 1.0 {
+            buffers[0].iter_mut().for_each(|s| *s *= self.scale);
+        }
+        self.position += len as u64;
         Ok(len)
     }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        // reconnecting drops the packets queued in the RTSA API
+        self.dev.call(|d| {
+            d.packet = None;
+            d.dev.stop().or(Err(Error::DeviceError))?;
+            d.dev.disconnect().or(Err(Error::DeviceError))?;
+            d.dev.connect().or(Err(Error::DeviceError))?;
+            d.dev.start().or(Err(Error::DeviceError))
+        })
+    }
 }
 
 impl crate::TxStreamer for TxStreamer {
@@ -515,11 +631,11 @@ impl crate::TxStreamer for TxStreamer {
     }
 
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn write(
@@ -529,7 +645,7 @@ impl crate::TxStreamer for TxStreamer {
         end_burst: bool,
         timeout_us: i64,
     ) -> Result<usize, Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn write_all(
@@ -539,6 +655,6 @@ impl crate::TxStreamer for TxStreamer {
         end_burst: bool,
         timeout_us: i64,
     ) -> Result<(), Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 }
@@ -5,7 +5,10 @@ use std::io::BufReader;
 use std::io::Read;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::sync::mpsc;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 use ureq::serde_json::json;
 use ureq::serde_json::Value;
@@ -17,16 +20,23 @@ use crate::Direction;
 use crate::Direction::*;
 use crate::Driver;
 use crate::Error;
+use crate::GainElementInfo;
 use crate::Range;
 use crate::RangeItem;
+use crate::Spectrum;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
 
 /// Aaronia SpectranV6 driver, using the HTTP interface
 #[derive(Clone)]
 pub struct AaroniaHttp {
     url: String,
     tx_url: String,
+    spectrum_url: String,
     agent: Agent,
     f_offset: f64,
+    /// TX frequency in Hz as bits of an `f64`.
     tx_frequency: Arc<AtomicU64>,
     tx_sample_rate: Arc<AtomicU64>,
 }
@@ -35,8 +45,155 @@ pub struct AaroniaHttp {
 pub struct RxStreamer {
     agent: Agent,
     url: String,
-    items_left: usize,
-    reader: Option<BufReader<Box<dyn Read + Send + Sync + 'static>>>,
+    // boxed, as the read buffer would otherwise dominate the size of `AnyRxStreamer`
+    parser: Option<Box<StreamParser<BufReader<Body>>>>,
+    canceller: StreamCanceller,
+    events: StreamEvents,
+    scale: f32,
+}
+
+/// Separator between the JSON header and the binary payload of a stream block.
+const RECORD_SEPARATOR: u8 = 0x1e;
+/// Granularity at which blocked reads check for timeouts and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Body of a stream response, which is read by a separate thread.
+///
+/// The HTTP body reader blocks until the server sends data. Reading it in a thread allows reads
+/// to fail with `ErrorKind::TimedOut` after [`POLL_INTERVAL`], so that the streamers can honor
+/// their timeout and cancellation. The thread ends with the next chunk after the body is dropped.
+struct Body {
+    data: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+    offset: usize,
+}
+
+impl Body {
+    fn get(agent: &Agent, url: &str) -> Result<Self, Error> {
+        let mut r = agent.get(url).call()?.into_reader();
+        let (tx, data) = mpsc::sync_channel(16);
+        std::thread::Builder::new()
+            .name("aaronia-http".to_string())
+            .spawn(move || loop {
+                let mut buf = vec![0; 65536];
+                let chunk = match r.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        buf.truncate(n);
+                        Ok(buf)
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).is_err() || failed {
+                    break;
+                }
+            })?;
+        Ok(Self {
+            data,
+            buf: Vec::new(),
+            offset: 0,
+        })
+    }
+}
+
+impl Read for Body {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.offset == self.buf.len() {
+            match self.data.recv_timeout(POLL_INTERVAL) {
+                Ok(buf) => {
+                    self.buf = buf?;
+                    self.offset = 0;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(std::io::ErrorKind::TimedOut.into())
+                }
+                // end of the stream or failed read, which was already returned
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let n = std::cmp::min(out.len(), self.buf.len() - self.offset);
+        out[..n].copy_from_slice(&self.buf[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// Retry `f` while the stream body times out, until `timeout_us` expires or the stream is
+/// cancelled. A negative `timeout_us` retries until data arrives.
+fn poll<T>(
+    canceller: &StreamCanceller,
+    timeout_us: i64,
+    mut f: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let deadline =
+        (timeout_us >= 0).then(|| Instant::now() + Duration::from_micros(timeout_us as u64));
+    loop {
+        if canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        match f() {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::TimedOut => {
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    return Err(Error::Timeout);
+                }
+            }
+            r => return r,
+        }
+    }
+}
+
+/// State of the [`StreamParser`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StreamState {
+    /// Waiting for (the rest of) a JSON header line.
+    Header,
+    /// Inside an IQ block with the given number of samples left.
+    Samples(usize),
+    /// Inside a non-IQ block with the given number of payload bytes left to discard.
+    Skip(usize),
+    /// Before the bins of a spectrum block with the given number of values.
+    Spectrum(usize),
+}
+
+/// Parser for the chunked RTSA stream.
+///
+/// Blocks consist of a JSON header line, a record separator, and a binary payload. The parser
+/// tolerates keep-alive blank lines, headers that are split across reads, and control blocks
+/// that carry no IQ samples. Malformed headers are reported as errors, after which the parser
+/// resynchronizes on the next header line.
+///
+/// The parser tracks the sample position. Gaps between the `endTime` of a block and the
+/// `startTime` of the next one are counted as dropped samples and reported as
+/// [`StreamEvent::Overflow`].
+///
+/// Spectrum blocks are skipped when reading IQ samples, and vice versa.
+struct StreamParser<R> {
+    reader: R,
+    header: Vec<u8>,
+    // payload bytes of an incomplete sample or spectrum, left by an interrupted read
+    partial: Vec<u8>,
+    state: StreamState,
+    // the record separator of the last parsed header may still be pending
+    separator: bool,
+    position: u64,
+    next_start: Option<f64>,
+    events: StreamEvents,
+    // metadata of the current spectrum block, the bins are filled when it is read
+    spectrum: Option<Spectrum>,
+}
+
+/// Aaronia SpectranV6 HTTP spectrum streamer
+///
+/// Receives the calibrated FFT frames of a spectrum analyzer block in the RTSA mission, see
+/// [`DeviceTrait::spectrum_streamer`]. Connects to the `spectrum_url` argument of the device,
+/// which defaults to `url`.
+pub struct SpectrumStreamer {
+    agent: Agent,
+    url: String,
+    parser: Option<StreamParser<BufReader<Body>>>,
+    canceller: StreamCanceller,
 }
 
 /// expected maximum delay for the transfer of samples between host and rf hardware, used to set the transmit start time to an achievalble but close value; in seconds
@@ -46,9 +203,12 @@ const STREAMING_DELAY: f64 = 0.01; // 0.2 is too much, 0.001 too little
 pub struct TxStreamer {
     agent: Agent,
     url: String,
+    /// TX frequency in Hz as bits of an `f64`, shared with the device.
     frequency: Arc<AtomicU64>,
     sample_rate: Arc<AtomicU64>,
     last_transmission_end_time: f64,
+    canceller: StreamCanceller,
+    scale: f32,
 }
 
 impl AaroniaHttp {
@@ -96,13 +256,17 @@ impl AaroniaHttp {
             let f_offset = a.get::<f64>("f_offset").unwrap_or(20e6);
             let url = a.get::<String>("url")?;
             let tx_url = a.get::<String>("tx_url").unwrap_or_else(|_| url.clone());
+            let spectrum_url = a
+                .get::<String>("spectrum_url")
+                .unwrap_or_else(|_| url.clone());
 
             Ok(Self {
                 agent: Agent::new(),
                 url,
                 tx_url,
+                spectrum_url,
                 f_offset,
-                tx_frequency: Arc::new(AtomicU64::new(2_450_000_000)),
+                tx_frequency: Arc::new(AtomicU64::new(2.45e9_f64.to_bits())),
                 tx_sample_rate: Arc::new(AtomicU64::new(1_000_000)),
             })
         }
@@ -120,8 +284,11 @@ impl AaroniaHttp {
         let config = self.config()?;
         let mut element = &config["config"];
         for p in path {
-            for i in element["items"].as_array().unwrap() {
-                if i["name"].as_str().unwrap() == p {
+            let items = element["items"]
+                .as_array()
+                .ok_or_else(|| Error::Misc(format!("aaronia_http: no config items at {p}")))?;
+            for i in items {
+                if i["name"].as_str() == Some(p) {
                     element = i;
                 }
             }
@@ -131,14 +298,21 @@ impl AaroniaHttp {
 
     fn get_enum(&self, path: Vec<&str>) -> Result<(u64, String), Error> {
         let element = self.get_element(path)?;
-        let i = element["value"].as_u64().unwrap();
-        let v: Vec<&str> = element["values"].as_str().unwrap().split(',').collect();
-        Ok((i, v[i as usize].to_string()))
+        let invalid = || Error::Misc(format!("aaronia_http: invalid enum {element}"));
+        let i = element["value"].as_u64().ok_or_else(invalid)?;
+        let v: Vec<&str> = element["values"]
+            .as_str()
+            .ok_or_else(invalid)?
+            .split(',')
+            .collect();
+        Ok((i, v.get(i as usize).ok_or_else(invalid)?.to_string()))
     }
 
     fn get_f64(&self, path: Vec<&str>) -> Result<f64, Error> {
         let element = self.get_element(path)?;
-        Ok(element["value"].as_f64().unwrap())
+        element["value"]
+            .as_f64()
+            .ok_or_else(|| Error::Misc(format!("aaronia_http: invalid number {element}")))
     }
     fn send_json(&self, json: Value) -> Result<(), Error> {
         self.agent
@@ -193,14 +367,33 @@ impl DeviceTrait for AaroniaHttp {
             Ok(RxStreamer {
                 url: self.url.clone(),
                 agent: self.agent.clone(),
-                items_left: 0,
-                reader: None,
+                parser: None,
+                canceller: StreamCanceller::new(),
+                events: StreamEvents::new(),
+                scale: 1.0,
             })
         } else {
             Err(Error::ValueError)
         }
     }
 
+    fn spectrum_streamer(
+        &self,
+        channel: usize,
+        _args: Args,
+    ) -> Result<Box<dyn crate::SpectrumStreamer>, Error> {
+        if channel == 0 {
+            Ok(Box::new(SpectrumStreamer {
+                agent: self.agent.clone(),
+                url: self.spectrum_url.clone(),
+                parser: None,
+                canceller: StreamCanceller::new(),
+            }))
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
     fn tx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
         if channels == [0] {
             Ok(TxStreamer {
@@ -210,8 +403,10 @@ impl DeviceTrait for AaroniaHttp {
                 sample_rate: self.tx_sample_rate.clone(),
                 last_transmission_end_time: SystemTime::now()
                     .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
+                    .unwrap_or_default()
                     .as_secs_f64(),
+                canceller: StreamCanceller::new(),
+                scale: 1.0,
             })
         } else {
             Err(Error::ValueError)
@@ -332,16 +527,16 @@ impl DeviceTrait for AaroniaHttp {
                     self.get_f64(vec!["Block_Spectran_V6B_0", "config", "main", "reflevel"])?;
                 Ok(Some(-lvl - 8.0))
             }
-            _ => {
-                todo!()
-            }
+            (Tx, 0) => Err(Error::NotSupported),
+            _ => Err(Error::ValueError),
         }
     }
 
     fn gain_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         match (direction, channel) {
             (Rx, 0 | 1) => Ok(Range::new(vec![RangeItem::Interval(0.0, 30.0)])),
-            _ => todo!(),
+            (Tx, 0) => Ok(Range::new(vec![RangeItem::Interval(-100.0, 10.0)])),
+            _ => Err(Error::ValueError),
         }
     }
 
@@ -351,8 +546,8 @@ impl DeviceTrait for AaroniaHttp {
         _channel: usize,
         _name: &str,
         _gain: f64,
-    ) -> Result<(), Error> {
-        todo!()
+    ) -> Result<f64, Error> {
+        Err(Error::NotSupported)
     }
 
     fn gain_element(
@@ -361,7 +556,7 @@ impl DeviceTrait for AaroniaHttp {
         _channel: usize,
         _name: &str,
     ) -> Result<Option<f64>, Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn gain_element_range(
@@ -370,11 +565,34 @@ impl DeviceTrait for AaroniaHttp {
         _channel: usize,
         _name: &str,
     ) -> Result<Range, Error> {
-        todo!()
+        Err(Error::NotSupported)
+    }
+
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        match (direction, channel, name) {
+            (Rx, 0 | 1, "TUNER") => Ok(GainElementInfo::new(
+                name,
+                "Reference level",
+                "dB",
+                "A gain of g dB sets the reference level to -8 - g dBm, e.g., 0 dB is -8 dBm.",
+            )),
+            (Tx, 0, "TUNER") => Ok(GainElementInfo::new(
+                name,
+                "Transmit attenuation",
+                "dB",
+                "The gain is passed unchanged as the transmitter attenuation (transattn).",
+            )),
+            _ => Err(Error::ValueError),
+        }
     }
 
     fn frequency_range(&self, _direction: Direction, _channel: usize) -> Result<Range, Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
@@ -385,7 +603,7 @@ impl DeviceTrait for AaroniaHttp {
                 "main",
                 "centerfreq",
             ]),
-            (Tx, 0) => Ok(self.tx_frequency.load(Ordering::SeqCst) as f64),
+            (Tx, 0) => Ok(f64::from_bits(self.tx_frequency.load(Ordering::SeqCst))),
             _ => Err(Error::ValueError),
         }
     }
@@ -415,7 +633,8 @@ impl DeviceTrait for AaroniaHttp {
     ) -> Result<Vec<String>, Error> {
         match (direction, channel) {
             (Rx, 0 | 1) => Ok(vec!["RF".to_string(), "DEMOD".to_string()]),
-            _ => todo!(),
+            (Tx, 0) => Ok(vec!["RF".to_string()]),
+            _ => Err(Error::ValueError),
         }
     }
 
@@ -425,7 +644,7 @@ impl DeviceTrait for AaroniaHttp {
         _channel: usize,
         _name: &str,
     ) -> Result<Range, Error> {
-        todo!()
+        Err(Error::NotSupported)
     }
 
     fn component_frequency(
@@ -449,7 +668,8 @@ impl DeviceTrait for AaroniaHttp {
             (Rx, 0 | 1, "RF") => {
                 self.get_f64(vec!["Block_Spectran_V6B_0", "config", "main", "centerfreq"])
             }
-            _ => todo!(),
+            (Tx, 0, "RF") => Ok(f64::from_bits(self.tx_frequency.load(Ordering::SeqCst))),
+            _ => Err(Error::ValueError),
         }
     }
 
@@ -486,7 +706,8 @@ impl DeviceTrait for AaroniaHttp {
                 self.send_json(json)
             }
             (Tx, 0, "RF") => {
-                self.tx_frequency.store(frequency as u64, Ordering::SeqCst);
+                self.tx_frequency
+                    .store(frequency.to_bits(), Ordering::SeqCst);
                 Ok(())
             }
             _ => Err(Error::ValueError),
@@ -536,7 +757,7 @@ impl DeviceTrait for AaroniaHttp {
     fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         match (direction, channel) {
             (Rx, 0 | 1) => Ok(Range::new(vec![RangeItem::Interval(0.0, 92.16e6)])),
-            (Tx, 0) => todo!(),
+            (Tx, 0) => Err(Error::NotSupported),
             _ => Err(Error::ValueError),
         }
     }
@@ -571,23 +792,233 @@ impl DeviceTrait for AaroniaHttp {
     }
 }
 
-impl RxStreamer {
+impl crate::RxInterface for AaroniaHttp {}
+impl crate::TxInterface for AaroniaHttp {}
+
+impl<R: BufRead> StreamParser<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            header: Vec::with_capacity(512),
+            partial: Vec::new(),
+            state: StreamState::Header,
+            separator: false,
+            position: 0,
+            next_start: None,
+            events: StreamEvents::new(),
+            spectrum: None,
+        }
+    }
+
+    /// Read IQ samples of the current block into `out`, parsing headers and skipping control
+    /// blocks as needed.
+    fn read(&mut self, out: &mut [Complex32]) -> Result<usize, Error> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.take_separator()?;
+            match self.state {
+                StreamState::Header => self.parse_header()?,
+                StreamState::Samples(0) | StreamState::Skip(0) => {
+                    self.state = StreamState::Header;
+                }
+                StreamState::Skip(n) => self.skip(n)?,
+                StreamState::Spectrum(n) => {
+                    let skip = n * std::mem::size_of::<f32>() - self.partial.len();
+                    self.partial.clear();
+                    self.state = StreamState::Skip(skip);
+                }
+                StreamState::Samples(left) => {
+                    let is = std::mem::size_of::<Complex32>();
+                    let n = std::cmp::min(left, out.len());
+                    let bytes = unsafe {
+                        std::slice::from_raw_parts_mut(out.as_mut_ptr() as *mut u8, n * is)
+                    };
+                    let carried = self.partial.len();
+                    bytes[..carried].copy_from_slice(&self.partial);
+                    self.partial.clear();
+                    let (k, err) = Self::fill(&mut self.reader, &mut bytes[carried..]);
+                    // only complete samples are returned, the rest is kept for the next read
+                    let filled = carried + k;
+                    let done = filled / is;
+                    self.partial.extend_from_slice(&bytes[done * is..filled]);
+                    self.state = StreamState::Samples(left - done);
+                    self.position += done as u64;
+                    return match err {
+                        Some(e) if done == 0 => Err(e),
+                        _ => Ok(done),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Read the next spectrum, skipping IQ and control blocks.
+    fn read_spectrum(&mut self) -> Result<Spectrum, Error> {
+        loop {
+            self.take_separator()?;
+            match self.state {
+                StreamState::Header => self.parse_header()?,
+                StreamState::Samples(0) | StreamState::Skip(0) => {
+                    self.state = StreamState::Header;
+                }
+                StreamState::Samples(n) => {
+                    let skip = n * std::mem::size_of::<Complex32>() - self.partial.len();
+                    self.partial.clear();
+                    self.state = StreamState::Skip(skip);
+                }
+                StreamState::Skip(n) => self.skip(n)?,
+                StreamState::Spectrum(n) => {
+                    let mut bytes = std::mem::take(&mut self.partial);
+                    let carried = bytes.len();
+                    bytes.resize(n * std::mem::size_of::<f32>(), 0);
+                    let (k, err) = Self::fill(&mut self.reader, &mut bytes[carried..]);
+                    if let Some(e) = err {
+                        bytes.truncate(carried + k);
+                        self.partial = bytes;
+                        return Err(e);
+                    }
+                    self.state = StreamState::Header;
+                    let mut spectrum = self.spectrum.take().ok_or(Error::DeviceError)?;
+                    spectrum.bins = bytes
+                        .chunks_exact(4)
+                        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                        .collect();
+                    return Ok(spectrum);
+                }
+            }
+        }
+    }
+
+    /// Read until `buf` is full. Returns the number of bytes read and the error that stopped the
+    /// read early, so that the caller can account for the consumed bytes.
+    fn fill(reader: &mut R, buf: &mut [u8]) -> (usize, Option<Error>) {
+        let mut n = 0;
+        while n < buf.len() {
+            match reader.read(&mut buf[n..]) {
+                Ok(0) => return (n, Some(Self::closed())),
+                Ok(k) => n += k,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return (n, Some(e.into())),
+            }
+        }
+        (n, None)
+    }
+
+    /// Discard up to `n` bytes of the current block.
+    fn skip(&mut self, n: usize) -> Result<(), Error> {
+        let available = self.reader.fill_buf()?.len();
+        if available == 0 {
+            return Err(Self::closed());
+        }
+        let k = std::cmp::min(n, available);
+        self.reader.consume(k);
+        self.state = StreamState::Skip(n - k);
+        Ok(())
+    }
+
     fn parse_header(&mut self) -> Result<(), Error> {
-        let mut buf = Vec::with_capacity(512);
-        self.reader.as_mut().unwrap().read_until(10, &mut buf)?;
-        let header: Value = serde_json::from_str(&String::from_utf8_lossy(&buf))?;
-        self.reader.as_mut().unwrap().consume(1);
-
-        let i = header
-            .get("samples")
-            .and_then(|x| x.to_string().parse::<usize>().ok())
-            .ok_or(Error::Misc(
-                "Parsing Samples from JSON Header failed".to_string(),
-            ))?;
-
-        self.items_left = i;
+        // Bytes of a partially received line stay in `self.header`, so an interrupted read
+        // continues with the same header on the next call.
+        let n = self.reader.read_until(b'\n', &mut self.header)?;
+        if n == 0 || self.header.last() != Some(&b'\n') {
+            return Err(Self::closed());
+        }
+        let line = std::mem::take(&mut self.header);
+        let line = line.trim_ascii();
+        let line = line.strip_prefix(&[RECORD_SEPARATOR]).unwrap_or(line);
+        if line.is_empty() {
+            // keep-alive
+            return Ok(());
+        }
+
+        let header: Value = serde_json::from_slice(line)
+            .map_err(|e| Error::Misc(format!("Malformed stream header: {e}")))?;
+        if !header.is_object() {
+            return Err(Error::Misc(format!("Malformed stream header: {header}")));
+        }
+        let samples = header.get("samples").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let size = header
+            .get("sampleSize")
+            .and_then(Value::as_u64)
+            .unwrap_or(2) as usize;
+        let depth = header
+            .get("sampleDepth")
+            .and_then(Value::as_u64)
+            .unwrap_or(1) as usize;
+        let payload = header.get("payload").and_then(Value::as_str);
+        let iq = matches!(payload, None | Some("iq"));
+
+        if iq && size == 2 {
+            self.account_gap(&header, samples);
+        }
+
+        self.state = if iq && size == 2 {
+            StreamState::Samples(samples * depth)
+        } else if payload == Some("spectra") {
+            let f64_field = |key| header.get(key).and_then(Value::as_f64);
+            self.spectrum = Some(Spectrum {
+                start_frequency: f64_field("startFrequency").unwrap_or(0.0),
+                stop_frequency: f64_field("endFrequency").unwrap_or(0.0),
+                // seconds since the UNIX epoch
+                time_ns: f64_field("startTime").map(|t| (t * 1e9) as i64),
+                unit: header
+                    .get("unit")
+                    .and_then(Value::as_str)
+                    .unwrap_or("dBm")
+                    .to_string(),
+                bins: Vec::new(),
+            });
+            StreamState::Spectrum(samples * size * depth)
+        } else {
+            StreamState::Skip(samples * size * depth * std::mem::size_of::<f32>())
+        };
+        self.separator = true;
+        Ok(())
+    }
+
+    /// Consume the record separator that follows a header.
+    ///
+    /// This is done after the header took effect, so that a read that times out in between does
+    /// not lose the block.
+    fn take_separator(&mut self) -> Result<(), Error> {
+        if self.separator {
+            if self.reader.fill_buf()?.first() == Some(&RECORD_SEPARATOR) {
+                self.reader.consume(1);
+            }
+            self.separator = false;
+        }
         Ok(())
     }
+
+    /// Add samples that were dropped between the previous and the current IQ block.
+    fn account_gap(&mut self, header: &Value, samples: usize) {
+        let start = header.get("startTime").and_then(Value::as_f64);
+        let end = header.get("endTime").and_then(Value::as_f64);
+        let (Some(start), Some(end)) = (start, end) else {
+            return;
+        };
+        if let Some(expected) = self.next_start {
+            if end > start && samples > 0 {
+                let rate = samples as f64 / (end - start);
+                let dropped = ((start - expected) * rate).round();
+                if dropped >= 1.0 {
+                    log::warn!("Aaronia HTTP stream dropped {dropped} samples");
+                    self.position += dropped as u64;
+                    self.events.send(StreamEvent::Overflow);
+                }
+            }
+        }
+        self.next_start = Some(end);
+    }
+
+    fn closed() -> Error {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "stream closed by server",
+        ))
+    }
 }
 
 impl crate::RxStreamer for RxStreamer {
@@ -596,42 +1027,84 @@ impl crate::RxStreamer for RxStreamer {
     }
 
     fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        let r = self
-            .agent
-            .get(&format!("{}/stream?format=float32", self.url))
-            .call()?
-            .into_reader();
-        self.reader = Some(BufReader::new(r));
+        let body = Body::get(&self.agent, &format!("{}/stream?format=float32", self.url))?;
+        let mut parser = StreamParser::new(BufReader::new(body));
+        parser.events = self.events.clone();
+        self.parser = Some(Box::new(parser));
+        self.canceller.reset();
         Ok(())
     }
 
     fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        self.reader = None;
+        self.parser = None;
         Ok(())
     }
 
     fn read(
         &mut self,
         buffers: &mut [&mut [num_complex::Complex32]],
-        _timeout_us: i64,
+        timeout_us: i64,
     ) -> Result<usize, Error> {
-        if self.items_left == 0 {
-            self.parse_header()?;
+        let parser = self.parser.as_mut().ok_or(Error::Inactive)?;
+        let n = poll(&self.canceller, timeout_us, || parser.read(buffers[0]))?;
+        if self.scale != 1.0 {
+            buffers[0][..n].iter_mut().for_each(|s| *s *= self.scale);
         }
+        Ok(n)
+    }
 
-        let is = std::mem::size_of::<Complex32>();
-        let n = std::cmp::min(self.items_left, buffers[0].len());
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.parser.as_ref().ok_or(Error::Inactive)?.position)
+    }
 
-        let out =
-            unsafe { std::slice::from_raw_parts_mut(buffers[0].as_mut_ptr() as *mut u8, n * is) };
-        self.reader
-            .as_mut()
-            .unwrap()
-            .read_exact(&mut out[0..n * is])?;
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
 
-        self.items_left -= n;
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.events.subscribe())
+    }
 
-        Ok(n)
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let position = self.parser.as_ref().ok_or(Error::Inactive)?.position;
+        // a new request starts with the latest samples of the server
+        let body = Body::get(&self.agent, &format!("{}/stream?format=float32", self.url))?;
+        let mut parser = StreamParser::new(BufReader::new(body));
+        parser.position = position;
+        parser.events = self.events.clone();
+        self.parser = Some(Box::new(parser));
+        Ok(())
+    }
+}
+
+impl crate::SpectrumStreamer for SpectrumStreamer {
+    fn activate(&mut self) -> Result<(), Error> {
+        let body = Body::get(&self.agent, &format!("{}/stream?format=float32", self.url))?;
+        self.parser = Some(StreamParser::new(BufReader::new(body)));
+        self.canceller.reset();
+        Ok(())
+    }
+
+    fn deactivate(&mut self) -> Result<(), Error> {
+        self.parser = None;
+        Ok(())
+    }
+
+    fn read(&mut self, timeout_us: i64) -> Result<Spectrum, Error> {
+        let parser = self.parser.as_mut().ok_or(Error::Inactive)?;
+        poll(&self.canceller, timeout_us, || parser.read_spectrum())
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
     }
 }
 
@@ -641,6 +1114,7 @@ impl crate::TxStreamer for TxStreamer {
     }
 
     fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        self.canceller.reset();
         Ok(())
     }
 
@@ -655,16 +1129,116 @@ impl crate::TxStreamer for TxStreamer {
         end_burst: bool,
         _timeout_us: i64,
     ) -> Result<usize, Error> {
-        debug_assert_eq!(buffers.len(), 1);
         debug_assert_eq!(at_ns, None);
+        self.send(&buffers[..1], end_burst)
+    }
+
+    fn write_all(
+        &mut self,
+        buffers: &[&[num_complex::Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let deadline =
+            (timeout_us >= 0).then(|| Instant::now() + Duration::from_micros(timeout_us as u64));
+        let mut n = 0;
+        while n < buffers[0].len() {
+            // `send` fails once the stream is cancelled
+            let written = self.write(&[&buffers[0][n..]], at_ns, end_burst, timeout_us)?;
+            n += written;
+            if written == 0 {
+                // transmit queue of the device is full
+                let mut wait = Duration::from_millis(10);
+                if let Some(d) = deadline {
+                    let remaining = d.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(Error::Timeout);
+                    }
+                    wait = wait.min(remaining);
+                }
+                std::thread::sleep(wait);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<usize, Error> {
+        debug_assert_eq!(segments.len(), 1);
+        debug_assert_eq!(at_ns, None);
+        self.send(segments[0], end_burst)
+    }
 
-        let frequency = self.frequency.load(Ordering::SeqCst) as f64;
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        let deadline =
+            (timeout_us >= 0).then(|| Instant::now() + Duration::from_micros(timeout_us as u64));
+        loop {
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let queued = self.queued_time();
+            if queued <= 0.0 {
+                return Ok(());
+            }
+            let mut wait = Duration::from_secs_f64(queued).min(Duration::from_millis(10));
+            if let Some(d) = deadline {
+                let remaining = d.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(Error::Timeout);
+                }
+                wait = wait.min(remaining);
+            }
+            std::thread::sleep(wait);
+        }
+    }
+
+    fn queued_samples(&self) -> Result<usize, Error> {
         let sample_rate = self.sample_rate.load(Ordering::SeqCst) as f64;
-        let len: usize = buffers[0].len();
+        Ok((self.queued_time() * sample_rate).round() as usize)
+    }
+
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+}
+
+impl TxStreamer {
+    /// Time in seconds until the samples queued on the device are transmitted.
+    fn queued_time(&self) -> f64 {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        (self.last_transmission_end_time - now).max(0.0)
+    }
+
+    /// Send the samples of one channel, given as consecutive segments.
+    fn send(&mut self, segments: &[&[Complex32]], end_burst: bool) -> Result<usize, Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let frequency = f64::from_bits(self.frequency.load(Ordering::SeqCst));
+        let sample_rate = self.sample_rate.load(Ordering::SeqCst) as f64;
+        let len: usize = segments.iter().map(|s| s.len()).sum();
 
         let start = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
+            .unwrap_or_default()
             .as_secs_f64()
             + STREAMING_DELAY;
         let num_streamable_samples = if start < self.last_transmission_end_time {
@@ -694,12 +1268,14 @@ impl crate::TxStreamer for TxStreamer {
         let stop = start + num_streamable_samples as f64 / sample_rate;
         self.last_transmission_end_time = stop + 1.0_f64 / sample_rate; // use one sample spacing between queued requests
 
-        let samples = unsafe {
-            std::slice::from_raw_parts(
-                buffers[0].as_ptr() as *const f32,
-                num_streamable_samples * 2,
-            )
-        };
+        // serialize the segments directly, without concatenating them
+        let samples: Vec<Value> = segments
+            .iter()
+            .flat_map(|s| s.iter())
+            .take(num_streamable_samples)
+            .map(|s| s * self.scale)
+            .flat_map(|s| [Value::from(s.re), Value::from(s.im)])
+            .collect();
 
         // log::debug!(
         //     "sending {}{} samples with delay of {}s",
@@ -736,14 +1312,232 @@ impl crate::TxStreamer for TxStreamer {
 
         Ok(num_streamable_samples)
     }
+}
 
-    fn write_all(
-        &mut self,
-        _buffers: &[&[num_complex::Complex32]],
-        _at_ns: Option<i64>,
-        _end_burst: bool,
-        _timeout_us: i64,
-    ) -> Result<(), Error> {
-        unimplemented!()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn block(header: &str, samples: &[Complex32]) -> Vec<u8> {
+        let mut v = header.as_bytes().to_vec();
+        v.push(b'\n');
+        v.push(RECORD_SEPARATOR);
+        for s in samples {
+            v.extend_from_slice(&s.re.to_ne_bytes());
+            v.extend_from_slice(&s.im.to_ne_bytes());
+        }
+        v
+    }
+
+    #[test]
+    fn keep_alive_and_control_blocks() {
+        let a = Complex32::new(1.0, -1.0);
+        let b = Complex32::new(0.5, 0.25);
+        let mut data = b"\r\n\n".to_vec();
+        data.extend(block(
+            r#"{"payload": "status", "samples": 1, "sampleSize": 1}"#,
+            &[],
+        ));
+        data.extend([0u8; 4]);
+        data.extend(block(
+            r#"{"payload": "iq", "samples": 2, "sampleSize": 2}"#,
+            &[a, b],
+        ));
+        data.extend(b"\n");
+        data.extend(block(r#"{"state": "running"}"#, &[]));
+        data.extend(block(r#"{"samples": 1}"#, &[b]));
+
+        let mut p = StreamParser::new(Cursor::new(data));
+        let mut out = [Complex32::new(0.0, 0.0); 8];
+        assert_eq!(p.read(&mut out).unwrap(), 2);
+        assert_eq!(&out[0..2], &[a, b]);
+        assert_eq!(p.read(&mut out).unwrap(), 1);
+        assert_eq!(out[0], b);
+        assert!(matches!(p.read(&mut out), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn block_split_across_reads() {
+        let a = Complex32::new(1.0, 2.0);
+        let data = block(r#"{"samples": 3}"#, &[a, a, a]);
+        let mut p = StreamParser::new(Cursor::new(data));
+        let mut out = [Complex32::new(0.0, 0.0); 2];
+        assert_eq!(p.read(&mut out).unwrap(), 2);
+        assert_eq!(p.read(&mut out).unwrap(), 1);
+        assert_eq!(out[0], a);
+    }
+
+    #[test]
+    fn malformed_header_is_recoverable() {
+        let a = Complex32::new(3.0, 4.0);
+        let mut data = b"{\"samples\": 1,\n".to_vec();
+        data.extend(block(r#"{"samples": 1}"#, &[a]));
+        let mut p = StreamParser::new(Cursor::new(data));
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        assert!(matches!(p.read(&mut out), Err(Error::Misc(_))));
+        assert_eq!(p.read(&mut out).unwrap(), 1);
+        assert_eq!(out[0], a);
+    }
+
+    /// Reader that returns the given reads one after the other.
+    struct Parts(Vec<std::io::Result<Vec<u8>>>);
+
+    impl Read for Parts {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let part = self.0.remove(0)?;
+            buf[..part.len()].copy_from_slice(&part);
+            Ok(part.len())
+        }
+    }
+
+    #[test]
+    fn interrupted_payload_read() {
+        let s = [
+            Complex32::new(1.0, 2.0),
+            Complex32::new(3.0, 4.0),
+            Complex32::new(5.0, 6.0),
+        ];
+        let data = block(r#"{"samples": 3}"#, &s);
+        // the read times out within the first sample
+        let (head, tail) = data.split_at(data.len() - 20);
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        let reader = Parts(vec![Ok(head.to_vec()), Err(timeout), Ok(tail.to_vec())]);
+        let mut p = StreamParser::new(BufReader::new(reader));
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        assert!(matches!(p.read(&mut out), Err(Error::Io(_))));
+        assert_eq!(p.position, 0);
+        assert_eq!(p.read(&mut out).unwrap(), 3);
+        assert_eq!(&out[..3], &s);
+        assert_eq!(p.position, 3);
+    }
+
+    #[test]
+    fn timeout_before_separator() {
+        let a = Complex32::new(1.0, 2.0);
+        let data = block(r#"{"samples": 1}"#, &[a]);
+        let (head, tail) = data.split_at(data.iter().position(|b| *b == b'\n').unwrap() + 1);
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        let reader = Parts(vec![Ok(head.to_vec()), Err(timeout), Ok(tail.to_vec())]);
+        let mut p = StreamParser::new(BufReader::new(reader));
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        assert!(matches!(p.read(&mut out), Err(Error::Io(_))));
+        assert_eq!(p.read(&mut out).unwrap(), 1);
+        assert_eq!(out[0], a);
+    }
+
+    #[test]
+    fn blocked_read() {
+        let (tx, data) = mpsc::sync_channel(1);
+        let body = Body {
+            data,
+            buf: Vec::new(),
+            offset: 0,
+        };
+        let mut p = StreamParser::new(BufReader::new(body));
+        let canceller = StreamCanceller::new();
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        assert!(matches!(
+            poll(&canceller, 20_000, || p.read(&mut out)),
+            Err(Error::Timeout)
+        ));
+
+        let c = canceller.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            c.cancel();
+        });
+        assert!(matches!(
+            poll(&canceller, -1, || p.read(&mut out)),
+            Err(Error::Cancelled)
+        ));
+
+        let a = Complex32::new(1.0, 2.0);
+        tx.send(Ok(block(r#"{"samples": 1}"#, &[a]))).unwrap();
+        canceller.reset();
+        assert_eq!(poll(&canceller, -1, || p.read(&mut out)).unwrap(), 1);
+        assert_eq!(out[0], a);
+    }
+
+    #[test]
+    fn position_counts_dropped_samples() {
+        let a = Complex32::new(1.0, 0.0);
+        let mut data = block(
+            r#"{"samples": 2, "startTime": 0.0, "endTime": 2.0}"#,
+            &[a, a],
+        );
+        data.extend(block(
+            r#"{"samples": 2, "startTime": 2.0, "endTime": 4.0}"#,
+            &[a, a],
+        ));
+        data.extend(block(
+            r#"{"samples": 2, "startTime": 7.0, "endTime": 9.0}"#,
+            &[a, a],
+        ));
+        let mut p = StreamParser::new(Cursor::new(data));
+        let events = p.events.subscribe();
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        p.read(&mut out).unwrap();
+        p.read(&mut out).unwrap();
+        assert_eq!(p.position, 4);
+        assert!(events.try_recv().is_err());
+        p.read(&mut out).unwrap();
+        assert_eq!(p.position, 9);
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Overflow));
+    }
+
+    #[test]
+    fn tx_queue() {
+        use crate::TxStreamer as _;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        let mut tx = TxStreamer {
+            agent: Agent::new(),
+            url: String::new(),
+            frequency: Arc::new(AtomicU64::new(1e9f64.to_bits())),
+            sample_rate: Arc::new(AtomicU64::new(1_000_000)),
+            last_transmission_end_time: now + 0.05,
+            canceller: StreamCanceller::new(),
+            scale: 1.0,
+        };
+        let queued = tx.queued_samples().unwrap();
+        assert!(queued > 30_000 && queued <= 50_000, "{queued}");
+        assert!(matches!(tx.flush(0), Err(Error::Timeout)));
+        tx.flush(-1).unwrap();
+        assert_eq!(tx.queued_samples().unwrap(), 0);
+    }
+
+    #[test]
+    fn spectra_and_iq_interleaved() {
+        let a = Complex32::new(1.0, 0.0);
+        let header = r#"{"payload": "spectra", "samples": 2, "sampleSize": 1, "unit": "dBm",
+            "startFrequency": 1e9, "endFrequency": 1.1e9, "startTime": 5.0}"#;
+        let mut data = block(&header.replace('\n', ""), &[]);
+        data.extend((-80.0f32).to_ne_bytes());
+        data.extend((-40.5f32).to_ne_bytes());
+        data.extend(block(r#"{"samples": 1}"#, &[a]));
+        data.extend(block(
+            r#"{"payload": "spectra", "samples": 1, "sampleSize": 1}"#,
+            &[],
+        ));
+        data.extend((-90.0f32).to_ne_bytes());
+
+        let mut p = StreamParser::new(Cursor::new(data.clone()));
+        let s = p.read_spectrum().unwrap();
+        assert_eq!(s.bins, vec![-80.0, -40.5]);
+        assert_eq!((s.start_frequency, s.stop_frequency), (1e9, 1.1e9));
+        assert_eq!((s.time_ns, s.unit.as_str()), (Some(5_000_000_000), "dBm"));
+        assert_eq!(p.read_spectrum().unwrap().bins, vec![-90.0]);
+
+        // IQ reads skip the spectra
+        let mut p = StreamParser::new(Cursor::new(data));
+        let mut out = [Complex32::new(0.0, 0.0); 4];
+        assert_eq!(p.read(&mut out).unwrap(), 1);
+        assert_eq!(out[0], a);
     }
 }
@@ -1,16 +1,34 @@
 //! Dummy SDR for CI
+use num_complex::Complex32;
+use std::collections::VecDeque;
+use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::Args;
+use crate::ChannelEnable;
+use crate::CoherencyGroup;
 use crate::DeviceTrait;
 use crate::Direction;
 use crate::Direction::Rx;
 use crate::Direction::Tx;
 use crate::Driver;
 use crate::Error;
+use crate::Extensions;
+use crate::PowerState;
 use crate::Range;
 use crate::RangeItem;
+use crate::SampleRateClock;
+use crate::Sensors;
+use crate::Spur;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
 
 /// Dummy Device
 #[derive(Clone)]
@@ -25,13 +43,93 @@ pub struct Dummy {
     tx_freq: Arc<Mutex<f64>>,
     tx_gain: Arc<Mutex<f64>>,
     tx_rate: Arc<Mutex<f64>>,
+    rx_active: Arc<Mutex<bool>>,
+    tx_active: Arc<Mutex<bool>>,
+    rx_enabled: Arc<Mutex<bool>>,
+    tx_enabled: Arc<Mutex<bool>>,
+    loopback: Arc<Mutex<Option<VecDeque<Complex32>>>>,
+    /// Task polling the RX streamer for loopback samples.
+    rx_waker: Arc<Mutex<Option<Waker>>>,
+    /// Events of the RX streamer, like loopback overflows.
+    rx_events: StreamEvents,
+    time_offset: Arc<Mutex<i64>>,
+    power: Arc<Mutex<PowerState>>,
+    rx_channels: usize,
+    tx_channels: usize,
+    /// Simulate one clock for the sample rates of both directions.
+    shared_sample_rate: bool,
+    channel: Option<ChannelModel>,
+}
+
+/// Maximum number of samples buffered in internal loopback mode.
+const LOOPBACK_CAPACITY: usize = 1 << 20;
+/// Granularity at which a read waiting for loopback samples checks for timeouts and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Propagation channel between the TX and the RX streamer in internal loopback mode.
+#[derive(Clone, Debug, PartialEq)]
+struct ChannelModel {
+    /// Standard deviation of the noise of the I and Q components.
+    noise: f32,
+    /// Frequency offset in Hz.
+    cfo: f64,
+    /// Impulse response, starting with the delay as zero taps.
+    taps: Vec<Complex32>,
+    /// Seed of the noise generator.
+    seed: u32,
+}
+
+/// State of the channel model of a TX streamer.
+struct Channel {
+    model: ChannelModel,
+    rate: Arc<Mutex<f64>>,
+    history: VecDeque<Complex32>,
+    phase: f64,
+    rng: Rng,
+}
+
+/// Deterministic xorshift generator for the channel noise.
+struct Rng {
+    state: u32,
 }
 
 /// Dummy RX Streamer
-pub struct RxStreamer;
+pub struct RxStreamer {
+    canceller: StreamCanceller,
+    active: Arc<Mutex<bool>>,
+    position: u64,
+    loopback: Arc<Mutex<Option<VecDeque<Complex32>>>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    events: StreamEvents,
+    power: Arc<Mutex<PowerState>>,
+    scale: f32,
+    activation_error_ns: Option<i64>,
+}
 
 /// Dummy TX Streamer
-pub struct TxStreamer;
+pub struct TxStreamer {
+    canceller: StreamCanceller,
+    active: Arc<Mutex<bool>>,
+    loopback: Arc<Mutex<Option<VecDeque<Complex32>>>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    rx_events: StreamEvents,
+    power: Arc<Mutex<PowerState>>,
+    scale: f32,
+    activation_error_ns: Option<i64>,
+    channel: Option<Channel>,
+}
+
+impl Drop for RxStreamer {
+    fn drop(&mut self) {
+        *self.active.lock().unwrap() = false;
+    }
+}
+
+impl Drop for TxStreamer {
+    fn drop(&mut self) {
+        *self.active.lock().unwrap() = false;
+    }
+}
 
 impl Dummy {
     /// Get a list of Devices
@@ -42,13 +140,53 @@ impl Dummy {
             Ok("dummy") => {
                 let mut a = Args::new();
                 a.set("driver", "dummy");
+                for key in ["rx_channels", "tx_channels"] {
+                    if let Ok(n) = args.get::<usize>(key) {
+                        a.set(key, n.to_string());
+                    }
+                }
+                for key in CHANNEL_ARGS
+                    .into_iter()
+                    .chain(["seed", "shared_sample_rate"])
+                {
+                    if let Ok(v) = args.get::<String>(key) {
+                        a.set(key, v);
+                    }
+                }
                 Ok(vec![a])
             }
             _ => Ok(Vec::new()),
         }
     }
     /// Create a Dummy Device
-    pub fn open<A: TryInto<Args>>(_args: A) -> Result<Self, Error> {
+    ///
+    /// `rx_channels=0` or `tx_channels=0` create a TX-only or RX-only device.
+    /// `shared_sample_rate=true` simulates a device that derives the RX and TX sample rates
+    /// from one clock, see [`SampleRateClock`].
+    ///
+    /// In internal loopback mode, the transmitted samples pass through a channel model before
+    /// they are received, configured with:
+    /// - `channel_noise`: power of white Gaussian noise in dBFS, e.g., `-30`.
+    /// - `channel_cfo`: frequency offset in Hz, which needs a TX sample rate.
+    /// - `channel_taps`: impulse response of a multipath channel as `re[:im]` taps, separated by
+    ///   `;`, e.g., `1;0;0.3:-0.1`.
+    /// - `channel_delay`: delay in samples.
+    /// - `seed`: seed of the pseudo-random noise, a non-zero 32-bit integer.
+    ///
+    /// The noise restarts from the seed with every activation of the TX streamer, so that runs,
+    /// e.g., failing tests, can be replayed exactly.
+    pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        let channels = |key| match args.get::<usize>(key) {
+            Ok(n @ (0 | 1)) => Ok(n),
+            Err(Error::NotFound) => Ok(1),
+            _ => Err(Error::ValueError),
+        };
+        let shared_sample_rate = match args.get::<bool>("shared_sample_rate") {
+            Ok(shared) => shared,
+            Err(Error::NotFound) => false,
+            Err(_) => return Err(Error::ValueError),
+        };
         Ok(Self {
             rx_agc: Arc::new(Mutex::new(false)),
             rx_gain: Arc::new(Mutex::new(0.0)),
@@ -60,8 +198,35 @@ impl Dummy {
             tx_freq: Arc::new(Mutex::new(0.0)),
             tx_rate: Arc::new(Mutex::new(0.0)),
             tx_bw: Arc::new(Mutex::new(0.0)),
+            rx_active: Arc::new(Mutex::new(false)),
+            tx_active: Arc::new(Mutex::new(false)),
+            rx_enabled: Arc::new(Mutex::new(true)),
+            tx_enabled: Arc::new(Mutex::new(true)),
+            loopback: Arc::new(Mutex::new(None)),
+            rx_waker: Arc::new(Mutex::new(None)),
+            rx_events: StreamEvents::new(),
+            time_offset: Arc::new(Mutex::new(0)),
+            power: Arc::new(Mutex::new(PowerState::On)),
+            rx_channels: channels("rx_channels")?,
+            tx_channels: channels("tx_channels")?,
+            shared_sample_rate,
+            channel: ChannelModel::from_args(&args)?,
         })
     }
+
+    fn channels(&self, direction: Direction) -> usize {
+        match direction {
+            Rx => self.rx_channels,
+            Tx => self.tx_channels,
+        }
+    }
+
+    fn enabled(&self, direction: Direction) -> &Mutex<bool> {
+        match direction {
+            Rx => &self.rx_enabled,
+            Tx => &self.tx_enabled,
+        }
+    }
 }
 
 impl DeviceTrait for Dummy {
@@ -90,24 +255,68 @@ impl DeviceTrait for Dummy {
         Ok(a)
     }
 
-    fn num_channels(&self, _direction: Direction) -> Result<usize, Error> {
-        Ok(1)
+    fn extensions(&self) -> Extensions {
+        let mut e = Extensions::new();
+        e.insert::<dyn Sensors>(Arc::new(self.clone()));
+        e.insert::<dyn ChannelEnable>(Arc::new(self.clone()));
+        e.insert::<dyn SampleRateClock>(Arc::new(self.clone()));
+        e
     }
 
-    fn full_duplex(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
-        Ok(true)
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        Ok(self.channels(direction))
+    }
+
+    fn full_duplex(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        if channel < self.channels(direction) {
+            Ok(self.rx_channels > 0 && self.tx_channels > 0)
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        // one simulated clock, but RX and TX are tuned independently
+        let channels = [Rx, Tx]
+            .into_iter()
+            .flat_map(|d| (0..self.channels(d)).map(move |c| (d, c)))
+            .collect();
+        Ok(vec![CoherencyGroup::new(channels, false)])
     }
 
     fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
         match channels {
-            &[0] => Ok(RxStreamer),
+            &[0] if self.rx_channels > 0 => Ok(RxStreamer {
+                canceller: StreamCanceller::new(),
+                active: self.rx_active.clone(),
+                position: 0,
+                loopback: self.loopback.clone(),
+                waker: self.rx_waker.clone(),
+                events: self.rx_events.clone(),
+                power: self.power.clone(),
+                scale: 1.0,
+                activation_error_ns: None,
+            }),
             _ => Err(Error::ValueError),
         }
     }
 
     fn tx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
         match channels {
-            &[0] => Ok(TxStreamer),
+            &[0] if self.tx_channels > 0 => Ok(TxStreamer {
+                canceller: StreamCanceller::new(),
+                active: self.tx_active.clone(),
+                loopback: self.loopback.clone(),
+                waker: self.rx_waker.clone(),
+                rx_events: self.rx_events.clone(),
+                power: self.power.clone(),
+                scale: 1.0,
+                activation_error_ns: None,
+                channel: self
+                    .channel
+                    .clone()
+                    .map(|m| Channel::new(m, self.tx_rate.clone())),
+            }),
             _ => Err(Error::ValueError),
         }
     }
@@ -173,6 +382,38 @@ impl DeviceTrait for Dummy {
         }
     }
 
+    fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        match name {
+            "RF" => self.supports_agc(direction, channel),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        match name {
+            "RF" => self.enable_agc(direction, channel, agc),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn agc_element(&self, direction: Direction, channel: usize, name: &str) -> Result<bool, Error> {
+        match name {
+            "RF" => self.agc(direction, channel),
+            _ => Err(Error::ValueError),
+        }
+    }
+
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
         if channel == 0 && gain >= 0.0 {
             match direction {
@@ -219,16 +460,19 @@ impl DeviceTrait for Dummy {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
-        if channel == 0 && name == "RF" && gain >= 0.0 {
-            match direction {
-                Rx => *self.rx_gain.lock().unwrap() = gain,
-                Tx => *self.tx_gain.lock().unwrap() = gain,
-            }
-            Ok(())
-        } else {
-            Err(Error::ValueError)
+    ) -> Result<f64, Error> {
+        if channel != 0 || name != "RF" || gain < 0.0 {
+            return Err(Error::ValueError);
+        }
+        match direction {
+            Rx => *self.rx_gain.lock().unwrap() = gain,
+            Tx => *self.tx_gain.lock().unwrap() = gain,
         }
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
     }
 
     fn gain_element(
@@ -367,6 +611,15 @@ impl DeviceTrait for Dummy {
         }
     }
 
+    fn spurs(&self, _direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        if channel == 0 {
+            // pretend to be a zero-IF receiver
+            Ok(vec![Spur::LoOffset(0.0)])
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
         match (channel, direction) {
             (0, Rx) => Ok(*self.rx_rate.lock().unwrap()),
@@ -444,31 +697,180 @@ impl DeviceTrait for Dummy {
     fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
         Err(Error::NotSupported)
     }
+
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        Ok(vec!["none".to_string(), "internal".to_string()])
+    }
+
+    fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        let mut loopback = self.loopback.lock().unwrap();
+        match mode {
+            "none" => *loopback = None,
+            "internal" => *loopback = Some(VecDeque::new()),
+            _ => return Err(Error::ValueError),
+        }
+        Ok(())
+    }
+
+    fn loopback_mode(&self) -> Result<String, Error> {
+        match *self.loopback.lock().unwrap() {
+            Some(_) => Ok("internal".to_string()),
+            None => Ok("none".to_string()),
+        }
+    }
+
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        Ok(vec!["internal".to_string()])
+    }
+
+    fn time_source(&self) -> Result<String, Error> {
+        Ok("internal".to_string())
+    }
+
+    fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        match name {
+            "internal" => Ok(()),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn hardware_time(&self) -> Result<i64, Error> {
+        Ok(crate::time::host_time_ns()? + *self.time_offset.lock().unwrap())
+    }
+
+    fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        *self.time_offset.lock().unwrap() = time_ns - crate::time::host_time_ns()?;
+        Ok(())
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        let streaming = *self.rx_active.lock().unwrap() || *self.tx_active.lock().unwrap();
+        if streaming && state != PowerState::On {
+            return Err(Error::Misc(
+                "dummy: deactivate streams before powering down".to_string(),
+            ));
+        }
+        *self.power.lock().unwrap() = state;
+        Ok(())
+    }
+
+    fn power_state(&self) -> Result<PowerState, Error> {
+        Ok(*self.power.lock().unwrap())
+    }
 }
 
+impl crate::RxInterface for Dummy {}
+impl crate::TxInterface for Dummy {}
+
 impl crate::RxStreamer for RxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
         Ok(1500)
     }
 
-    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if *self.power.lock().unwrap() != PowerState::On {
+            return Err(Error::Inactive);
+        }
+        let deadline = time_ns.map(crate::time::deadline);
+        if let Some(deadline) = deadline {
+            crate::time::wait_until(deadline);
+        }
+        self.canceller.reset();
+        self.position = 0;
+        *self.active.lock().unwrap() = true;
+        self.activation_error_ns = deadline.map(crate::time::late_ns);
         Ok(())
     }
 
     fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        *self.active.lock().unwrap() = false;
         Ok(())
     }
 
     fn read(
         &mut self,
         buffers: &mut [&mut [num_complex::Complex32]],
-        _timeout_us: i64,
+        timeout_us: i64,
     ) -> Result<usize, Error> {
+        // in internal loopback mode, reads wait up to the timeout for the TX streamer
+        let timeout = (timeout_us >= 0).then(|| Duration::from_micros(timeout_us as u64));
+        // the clock is only read when waiting, it is not available on wasm32
+        let mut deadline = None;
+        loop {
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let mut loopback = self.loopback.lock().unwrap();
+            let Some(queue) = loopback.as_mut() else {
+                break;
+            };
+            if queue.is_empty() {
+                let d = timeout.map(|t| *deadline.get_or_insert_with(|| Instant::now() + t));
+                if d.is_none_or(|d| Instant::now() < d) {
+                    drop(loopback);
+                    std::thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+            }
+            let n = buffers[0].len().min(queue.len());
+            for (o, i) in buffers[0].iter_mut().zip(queue.drain(..n)) {
+                *o = i * self.scale;
+            }
+            self.position += n as u64;
+            return Ok(n);
+        }
         for b in buffers.iter_mut() {
             b.fill(num_complex::Complex32::new(0.0, 0.0))
         }
+        self.position += buffers[0].len() as u64;
         Ok(buffers[0].len())
     }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if self.canceller.is_cancelled() {
+            return Poll::Ready(Err(Error::Cancelled));
+        }
+        // the TX streamer wakes the task under the same lock, after queueing samples
+        match self.loopback.lock().unwrap().as_ref() {
+            Some(queue) if queue.is_empty() => {
+                *self.waker.lock().unwrap() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.events.subscribe())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.activation_error_ns.ok_or(Error::NotFound)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if let Some(queue) = self.loopback.lock().unwrap().as_mut() {
+            self.position += queue.len() as u64;
+            queue.clear();
+        }
+        Ok(())
+    }
 }
 
 impl crate::TxStreamer for TxStreamer {
@@ -476,11 +878,25 @@ impl crate::TxStreamer for TxStreamer {
         Ok(1500)
     }
 
-    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if *self.power.lock().unwrap() != PowerState::On {
+            return Err(Error::Inactive);
+        }
+        let deadline = time_ns.map(crate::time::deadline);
+        if let Some(deadline) = deadline {
+            crate::time::wait_until(deadline);
+        }
+        self.canceller.reset();
+        if let Some(channel) = self.channel.as_mut() {
+            channel.reset();
+        }
+        *self.active.lock().unwrap() = true;
+        self.activation_error_ns = deadline.map(crate::time::late_ns);
         Ok(())
     }
 
     fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        *self.active.lock().unwrap() = false;
         Ok(())
     }
 
@@ -491,16 +907,667 @@ impl crate::TxStreamer for TxStreamer {
         _end_burst: bool,
         _timeout_us: i64,
     ) -> Result<usize, Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        if let Some(queue) = self.loopback.lock().unwrap().as_mut() {
+            let samples = buffers[0].iter().map(|s| s * self.scale);
+            match self.channel.as_mut() {
+                Some(channel) => channel.apply(samples, queue)?,
+                None => queue.extend(samples),
+            }
+            let overflow = queue.len().saturating_sub(LOOPBACK_CAPACITY);
+            if overflow > 0 {
+                queue.drain(..overflow);
+                self.rx_events.send(StreamEvent::Overflow);
+            }
+            if !queue.is_empty() {
+                if let Some(waker) = self.waker.lock().unwrap().take() {
+                    waker.wake();
+                }
+            }
+        }
         Ok(buffers[0].len())
     }
 
     fn write_all(
         &mut self,
-        _buffers: &[&[num_complex::Complex32]],
-        _at_ns: Option<i64>,
-        _end_burst: bool,
-        _timeout_us: i64,
+        buffers: &[&[num_complex::Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
     ) -> Result<(), Error> {
+        self.write(buffers, at_ns, end_burst, timeout_us)?;
+        Ok(())
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
         Ok(())
     }
+
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.activation_error_ns.ok_or(Error::NotFound)
+    }
+
+    fn flush(&mut self, _timeout_us: i64) -> Result<(), Error> {
+        // samples are transmitted, i.e., looped back or discarded, when they are written
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn queued_samples(&self) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
+
+/// Open arguments of the channel model.
+const CHANNEL_ARGS: [&str; 4] = [
+    "channel_noise",
+    "channel_cfo",
+    "channel_taps",
+    "channel_delay",
+];
+
+impl ChannelModel {
+    /// Parse the channel arguments, returning `None` for an ideal channel.
+    fn from_args(args: &Args) -> Result<Option<Self>, Error> {
+        if CHANNEL_ARGS.iter().all(|k| args.get::<String>(k).is_err()) {
+            return Ok(None);
+        }
+        let get = |key| match args.get::<f64>(key) {
+            Ok(v) if v.is_finite() => Ok(Some(v)),
+            Err(Error::NotFound) => Ok(None),
+            _ => Err(Error::ValueError),
+        };
+        let delay = match args.get::<usize>("channel_delay") {
+            Ok(d) => d,
+            Err(Error::NotFound) => 0,
+            Err(_) => return Err(Error::ValueError),
+        };
+        let mut taps = vec![Complex32::new(0.0, 0.0); delay];
+        match args.get::<String>("channel_taps") {
+            Ok(t) => {
+                for tap in t.split(';') {
+                    let (re, im) = tap.split_once(':').unwrap_or((tap, "0"));
+                    let re = re.trim().parse().or(Err(Error::ValueError))?;
+                    let im = im.trim().parse().or(Err(Error::ValueError))?;
+                    taps.push(Complex32::new(re, im));
+                }
+            }
+            Err(Error::NotFound) => taps.push(Complex32::new(1.0, 0.0)),
+            Err(_) => return Err(Error::ValueError),
+        }
+        // the power in dBFS is split between I and Q
+        let noise = get("channel_noise")?.map_or(0.0, |db| (10f64.powf(db / 10.0) / 2.0).sqrt());
+        // xorshift is stuck at zero
+        let seed = match args.get::<u32>("seed") {
+            Ok(s) if s != 0 => s,
+            Err(Error::NotFound) => Rng::DEFAULT_SEED,
+            _ => return Err(Error::ValueError),
+        };
+        Ok(Some(Self {
+            noise: noise as f32,
+            cfo: get("channel_cfo")?.unwrap_or(0.0),
+            taps,
+            seed,
+        }))
+    }
+}
+
+impl Channel {
+    fn new(model: ChannelModel, rate: Arc<Mutex<f64>>) -> Self {
+        let mut channel = Self {
+            model,
+            rate,
+            history: VecDeque::new(),
+            phase: 0.0,
+            rng: Rng::new(Rng::DEFAULT_SEED),
+        };
+        channel.reset();
+        channel
+    }
+
+    /// Start with an empty channel, as for a new transmission.
+    fn reset(&mut self) {
+        self.history.clear();
+        self.history
+            .resize(self.model.taps.len(), Complex32::new(0.0, 0.0));
+        self.phase = 0.0;
+        self.rng = Rng::new(self.model.seed);
+    }
+
+    fn apply(
+        &mut self,
+        samples: impl Iterator<Item = Complex32>,
+        out: &mut VecDeque<Complex32>,
+    ) -> Result<(), Error> {
+        let step = if self.model.cfo != 0.0 {
+            let rate = *self.rate.lock().unwrap();
+            if rate <= 0.0 {
+                return Err(Error::ValueError);
+            }
+            std::f64::consts::TAU * self.model.cfo / rate
+        } else {
+            0.0
+        };
+        for x in samples {
+            self.history.pop_back();
+            self.history.push_front(x);
+            let mut y: Complex32 = self
+                .history
+                .iter()
+                .zip(&self.model.taps)
+                .map(|(x, t)| x * t)
+                .sum();
+            if step != 0.0 {
+                y *= Complex32::from_polar(1.0, self.phase as f32);
+                self.phase = (self.phase + step) % std::f64::consts::TAU;
+            }
+            if self.model.noise > 0.0 {
+                let (re, im) = self.rng.gaussian();
+                y += Complex32::new(re, im) * self.model.noise;
+            }
+            out.push_back(y);
+        }
+        Ok(())
+    }
+}
+
+impl Rng {
+    const DEFAULT_SEED: u32 = 0x2545_f491;
+
+    fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    /// Uniform in `(0, 1]`.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        ((self.state >> 8) + 1) as f32 / (1 << 24) as f32
+    }
+
+    /// Pair of independent standard normal values (Box-Muller).
+    fn gaussian(&mut self) -> (f32, f32) {
+        let r = (-2.0 * self.uniform().ln()).sqrt();
+        let theta = std::f32::consts::TAU * self.uniform();
+        (r * theta.cos(), r * theta.sin())
+    }
+}
+
+impl Sensors for Dummy {
+    fn list_sensors(&self) -> Result<Vec<String>, Error> {
+        Ok(vec!["temperature".to_string(), "streaming".to_string()])
+    }
+
+    fn read_sensor(&self, name: &str) -> Result<String, Error> {
+        match name {
+            "temperature" => Ok("25.0".to_string()),
+            "streaming" => {
+                let streaming = *self.rx_active.lock().unwrap() || *self.tx_active.lock().unwrap();
+                Ok(streaming.to_string())
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+impl SampleRateClock for Dummy {
+    fn independent_sample_rates(&self) -> Result<bool, Error> {
+        Ok(!self.shared_sample_rate)
+    }
+}
+
+impl ChannelEnable for Dummy {
+    fn channel_enabled(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        if channel < self.channels(direction) {
+            Ok(*self.enabled(direction).lock().unwrap())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn set_channel_enabled(
+        &self,
+        direction: Direction,
+        channel: usize,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        if channel < self.channels(direction) {
+            *self.enabled(direction).lock().unwrap() = enabled;
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frequency;
+    use crate::RxStreamer as _;
+    use crate::SampleRate;
+    use crate::TxStreamer as _;
+    use num_complex::Complex32;
+
+    #[test]
+    fn tx_only() {
+        let dev = crate::Device::from_args("driver=dummy, rx_channels=0").unwrap();
+        assert_eq!(dev.num_channels(Rx).unwrap(), 0);
+        assert_eq!(dev.num_channels(Tx).unwrap(), 1);
+        assert!(dev.rx_streamer(&[0]).is_err());
+        assert!(!dev.full_duplex(Tx, 0).unwrap());
+
+        let report = dev.probe_capabilities().unwrap();
+        assert!(report.rx.is_empty());
+        assert_eq!(report.tx.len(), 1);
+        assert!(dev.self_test().unwrap().passed());
+
+        let profile = crate::Profile {
+            frequency: Some(100e6),
+            ..Default::default()
+        };
+        profile.apply(&dev).unwrap();
+        assert_eq!(dev.center_frequency(Tx, 0).unwrap().as_hz(), 100e6);
+    }
+
+    #[test]
+    fn disabled_channel() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        let enable = dev.extension::<dyn ChannelEnable>().unwrap();
+        assert!(enable.channel_enabled(Rx, 0).unwrap());
+        enable.set_channel_enabled(Rx, 0, false).unwrap();
+        assert!(!enable.channel_enabled(Rx, 0).unwrap());
+        assert!(matches!(dev.rx_streamer(&[0]), Err(Error::Inactive)));
+        assert!(dev.tx_streamer(&[0]).is_ok());
+        enable.set_channel_enabled(Rx, 0, true).unwrap();
+        assert!(dev.rx_streamer(&[0]).is_ok());
+        assert!(enable.set_channel_enabled(Rx, 1, false).is_err());
+    }
+
+    #[test]
+    fn shared_sample_rate() {
+        let dev = crate::Device::from_args("driver=dummy, shared_sample_rate=true").unwrap();
+        let clock = dev.extension::<dyn SampleRateClock>().unwrap();
+        assert!(!clock.independent_sample_rates().unwrap());
+        dev.set_rate(Rx, 0, SampleRate::sps(2e6)).unwrap();
+        assert_eq!(dev.rate(Tx, 0).unwrap().as_sps(), 2e6);
+        assert!(matches!(
+            dev.set_rate(Tx, 0, SampleRate::sps(-1.0)),
+            Err(Error::OutOfRange(..))
+        ));
+        assert_eq!(dev.rate(Rx, 0).unwrap().as_sps(), 2e6);
+
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        let clock = dev.extension::<dyn SampleRateClock>().unwrap();
+        assert!(clock.independent_sample_rates().unwrap());
+        dev.set_rate(Rx, 0, SampleRate::sps(2e6)).unwrap();
+        assert_eq!(dev.rate(Tx, 0).unwrap().as_sps(), 0.0);
+    }
+
+    #[test]
+    fn cancel_blocked_reader() {
+        let dev = Dummy::open(Args::new()).unwrap();
+        // without a transmitter, a read in loopback mode blocks until it times out
+        dev.set_loopback_mode("internal").unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let canceller = rx.canceller().unwrap();
+        rx.activate().unwrap();
+
+        let (started, running) = mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let mut buf = [Complex32::new(0.0, 0.0); 128];
+            started.send(()).unwrap();
+            let e = rx.read(&mut [&mut buf], -1).unwrap_err();
+            (rx, e)
+        });
+        running.recv().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!reader.is_finished());
+        canceller.cancel();
+        let (mut rx, e) = reader.join().unwrap();
+        assert!(matches!(e, Error::Cancelled));
+        rx.deactivate().unwrap();
+
+        rx.activate().unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        tx.activate().unwrap();
+        let samples = [Complex32::new(1.0, 0.0); 16];
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 16];
+        assert_eq!(rx.read(&mut [&mut buf], -1).unwrap(), 16);
+    }
+
+    #[test]
+    fn cancel_writer() {
+        let dev = Dummy::open(Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        tx.activate().unwrap();
+        let buf = [Complex32::new(0.0, 0.0); 16];
+        assert_eq!(tx.write(&[&buf], None, false, 1000).unwrap(), 16);
+        tx.canceller().unwrap().cancel();
+        assert!(matches!(
+            tx.write(&[&buf], None, false, 1000),
+            Err(Error::Cancelled)
+        ));
+        assert!(matches!(
+            tx.write_all(&[&buf], None, false, 1000),
+            Err(Error::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn into_generic() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        dev.tune(Rx, 0, Frequency::hz(123.0)).unwrap();
+        let mut dev = dev.into_generic();
+        assert_eq!(dev.driver(), Driver::Dummy);
+        assert_eq!(dev.center_frequency(Rx, 0).unwrap().as_hz(), 123.0);
+        assert!(dev.impl_ref::<Dummy>().is_ok());
+        assert!(dev.impl_mut::<Dummy>().is_ok());
+        let _shared = dev.clone();
+        assert!(dev.impl_mut::<Dummy>().is_err());
+    }
+
+    #[test]
+    fn drop_deactivates() {
+        let dev = Dummy::open(Args::new()).unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        assert!(*dev.rx_active.lock().unwrap());
+        assert!(*dev.tx_active.lock().unwrap());
+
+        // dropping during a panic must release the stream as well
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _rx = rx;
+            panic!("streaming failed");
+        }));
+        assert!(r.is_err());
+        assert!(!*dev.rx_active.lock().unwrap());
+
+        drop(tx);
+        assert!(!*dev.tx_active.lock().unwrap());
+    }
+
+    #[test]
+    fn agc_element() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        assert!(dev.supports_agc_element(Rx, 0, "RF").unwrap());
+        dev.enable_agc_element(Rx, 0, "RF", true).unwrap();
+        assert!(dev.agc_element(Rx, 0, "RF").unwrap());
+        assert!(dev.agc(Rx, 0).unwrap());
+        assert!(!dev.agc_element(Tx, 0, "RF").unwrap());
+        assert!(matches!(
+            dev.enable_agc_element(Rx, 0, "IF", true),
+            Err(Error::ValueError)
+        ));
+    }
+
+    #[test]
+    fn internal_loopback() {
+        let dev = Dummy::open(Args::new()).unwrap();
+        assert_eq!(dev.loopback_mode().unwrap(), "none");
+        dev.set_loopback_mode("internal").unwrap();
+        assert!(matches!(
+            dev.set_loopback_mode("rf"),
+            Err(Error::ValueError)
+        ));
+
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        let samples: Vec<Complex32> = (0..64).map(|i| Complex32::new(i as f32, 0.0)).collect();
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+
+        let mut buf = [Complex32::new(0.0, 0.0); 128];
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 64);
+        assert_eq!(&buf[..64], &samples[..]);
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 0);
+        assert_eq!(rx.sample_position().unwrap(), 64);
+
+        tx.set_input_scale(0.5).unwrap();
+        rx.set_output_scale(4.0).unwrap();
+        assert!(rx.set_output_scale(f32::NAN).is_err());
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 64);
+        assert_eq!(buf[3], samples[3] * 2.0);
+    }
+
+    #[test]
+    fn poll_read() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        let mut tx = dev.tx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 8];
+        assert_eq!(rx.try_read(&mut [&mut buf]).unwrap(), 0);
+
+        // the TX streamer wakes the task waiting for the samples
+        let reader = std::thread::spawn(move || {
+            let n = futures::executor::block_on(std::future::poll_fn(|cx| {
+                rx.poll_read(cx, &mut [&mut buf])
+            }))
+            .unwrap();
+            (n, buf[0])
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let samples = [Complex32::new(1.0, 0.0); 4];
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        assert_eq!(reader.join().unwrap(), (4, samples[0]));
+    }
+
+    #[test]
+    fn loopback_channel() {
+        let args = "channel_taps=0.5;0:0.5, channel_delay=3, channel_cfo=1000";
+        let dev = Dummy::open(args).unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        dev.set_sample_rate(Tx, 0, 4000.0).unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        let mut impulse = [Complex32::new(0.0, 0.0); 8];
+        impulse[0] = Complex32::new(1.0, 0.0);
+        tx.write_all(&[&impulse], None, false, 1000).unwrap();
+
+        let mut buf = [Complex32::new(0.0, 0.0); 8];
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 8);
+        // taps delayed by 3 samples, rotated by a quarter turn per sample
+        let rotation = |n: i32| Complex32::new(0.0, 1.0).powi(n);
+        assert!((buf[3] - rotation(3) * 0.5).norm() < 1e-5);
+        assert!((buf[4] - rotation(4) * Complex32::new(0.0, 0.5)).norm() < 1e-5);
+        assert!(buf[..3].iter().chain(&buf[5..]).all(|s| s.norm() < 1e-5));
+
+        let noisy = Dummy::open("channel_noise=-20").unwrap();
+        noisy.set_loopback_mode("internal").unwrap();
+        let mut rx = noisy.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = noisy.tx_streamer(&[0], Args::new()).unwrap();
+        tx.activate().unwrap();
+        let zeros = vec![Complex32::new(0.0, 0.0); 10000];
+        tx.write_all(&[&zeros], None, false, 1000).unwrap();
+        let mut buf = vec![Complex32::new(0.0, 0.0); 10000];
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        let power = buf.iter().map(|s| s.norm_sqr()).sum::<f32>() / buf.len() as f32;
+        assert!((10.0 * power.log10() + 20.0).abs() < 0.5, "{power}");
+
+        assert!(Dummy::open("channel_taps=1;x").is_err());
+        assert!(Dummy::open("channel_noise=-20, seed=0").is_err());
+    }
+
+    #[test]
+    fn tx_flush() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        let mut tx = dev.tx_streamer(&[0]).unwrap();
+        tx.activate().unwrap();
+        tx.write_all(&[&[Complex32::new(0.5, 0.0); 64]], None, true, 1000)
+            .unwrap();
+        tx.flush(0).unwrap();
+        assert_eq!(tx.queued_samples().unwrap(), 0);
+        tx.canceller().unwrap().cancel();
+        assert!(matches!(tx.flush(0), Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn stream_events() {
+        let dev = Dummy::open("").unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        let events = rx.stream_events().unwrap();
+        tx.activate().unwrap();
+        let samples = vec![Complex32::new(0.0, 0.0); LOOPBACK_CAPACITY];
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        assert!(events.try_recv().is_err());
+        tx.write_all(&[&samples[..16]], None, false, 1000).unwrap();
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Overflow));
+    }
+
+    #[test]
+    fn seeded_noise() {
+        let receive = |args: &str| {
+            let dev = crate::Device::from_args(args).unwrap();
+            dev.set_loopback_mode("internal").unwrap();
+            let mut rx = dev.rx_streamer(&[0]).unwrap();
+            let mut tx = dev.tx_streamer(&[0]).unwrap();
+            let zeros = [Complex32::new(0.0, 0.0); 64];
+            let mut buf = [Complex32::new(0.0, 0.0); 64];
+            let mut runs = Vec::new();
+            for _ in 0..2 {
+                tx.activate().unwrap();
+                tx.write_all(&[&zeros], None, false, 1000).unwrap();
+                tx.deactivate().unwrap();
+                rx.read(&mut [&mut buf], 1000).unwrap();
+                runs.push(buf);
+            }
+            assert_eq!(runs[0], runs[1]);
+            runs[0]
+        };
+        let a = receive("driver=dummy, channel_noise=-10, seed=42");
+        assert_eq!(a, receive("driver=dummy, channel_noise=-10, seed=42"));
+        assert_ne!(a, receive("driver=dummy, channel_noise=-10, seed=43"));
+    }
+
+    #[test]
+    fn flush() {
+        let dev = Dummy::open(Args::new()).unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        let stale = [Complex32::new(1.0, 0.0); 32];
+        let fresh = [Complex32::new(2.0, 0.0); 16];
+        tx.write_all(&[&stale], None, false, 1000).unwrap();
+        rx.flush().unwrap();
+        assert_eq!(rx.sample_position().unwrap(), 32);
+
+        tx.write_all(&[&stale], None, false, 1000).unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 64];
+        let ctl = crate::StreamCtl::new().skip_to_latest();
+        assert_eq!(rx.read_with_ctl(&mut [&mut buf], 1000, ctl).unwrap().0, 0);
+        tx.write_all(&[&fresh], None, false, 1000).unwrap();
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 16);
+        assert_eq!(&buf[..16], &fresh[..]);
+        assert_eq!(rx.sample_position().unwrap(), 80);
+    }
+
+    #[test]
+    fn self_test() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        let report = dev.self_test().unwrap();
+        assert!(report.passed(), "{report:?}");
+        assert!(report.checks.iter().all(|c| c.result.is_ok()));
+        assert_eq!(dev.loopback_mode().unwrap(), "none");
+    }
+
+    #[test]
+    fn power_state() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        assert!(matches!(
+            dev.set_power_state(PowerState::Standby),
+            Err(Error::Misc(_))
+        ));
+        rx.deactivate().unwrap();
+        dev.set_power_state(PowerState::Standby).unwrap();
+        assert_eq!(dev.power_state().unwrap(), PowerState::Standby);
+        assert!(matches!(rx.activate(), Err(Error::Inactive)));
+        dev.set_power_state(PowerState::On).unwrap();
+        rx.activate().unwrap();
+    }
+
+    #[test]
+    fn time_discipline() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        dev.set_hardware_time(0).unwrap();
+        let t = dev.time_discipline().unwrap();
+        assert_eq!(t.time_source.as_deref(), Some("internal"));
+        assert_eq!(t.gps_locked, None);
+        assert!(t.hardware_time_ns >= 0);
+        assert!(t.host_offset_ns < -1_000_000_000);
+        assert!(matches!(
+            dev.set_hardware_time_next_pps(0),
+            Err(Error::NotSupported)
+        ));
+    }
+
+    #[cfg(not(feature = "soapy"))]
+    #[test]
+    fn driver_not_enabled() {
+        assert!(Driver::available_drivers().contains(&Driver::Dummy));
+        assert!(!Driver::Soapy.is_available());
+        assert!(matches!(
+            crate::Device::from_args("driver=soapy"),
+            Err(Error::FeatureNotEnabled("soapy"))
+        ));
+        assert!(matches!(
+            crate::enumerate_with_args("driver=soapysdr"),
+            Err(Error::FeatureNotEnabled("soapy"))
+        ));
+    }
+
+    #[test]
+    fn events() {
+        let dev = crate::Device::from_impl(Dummy::open(Args::new()).unwrap());
+        // sub-Hz requests keep their precision up to the driver
+        assert_eq!(
+            dev.tune(Rx, 0, Frequency::hz(100e6 + 0.25)).unwrap(),
+            Frequency::hz(100e6 + 0.25)
+        );
+        dev.enable_agc(Rx, 0, true).unwrap();
+        assert!(dev.tune(Rx, 5, Frequency::hz(100e6)).is_err());
+        let generic = dev.clone().into_generic();
+        generic
+            .event_log()
+            .push(crate::EventKind::Overflow, "lost samples");
+        let kinds: Vec<_> = dev.events().iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                crate::EventKind::Retune,
+                crate::EventKind::Agc,
+                crate::EventKind::Overflow
+            ]
+        );
+        generic.clear_events();
+        assert!(dev.events().is_empty());
+    }
 }
@@ -0,0 +1,1085 @@
+//! File-backed SDR, replaying recorded IQ samples
+//!
+//! Raw recordings in the formats of [`IqFormat`] and 2-channel WAV files are supported. The
+//! format is set with the `format` argument (`cf32`, `cs16`, `cs8`, `cu8`, or `wav`). Otherwise,
+//! WAV files are detected from their header and raw formats from the file extension, falling back
+//! to `cf32`. On native targets, the recording is opened from a `path`. On wasm32, where there is
+//! no file system, the recording can be fetched from a URL with [`File::fetch`] or passed in with
+//! [`File::from_bytes`].
+//!
+//! Playback is controlled with the arguments `loop` (or `repeat`), `speed`, `start_offset`, and
+//! `duration`, see [`File::open`].
+//!
+//! With a `tx_path`, the TX streamer writes the transmitted samples to a file, e.g., to replay
+//! them with `hackrf_transfer -t`. The format is set with `tx_format`, using the same formats and
+//! detection from the file extension as for recordings. WAV files are written with 16-bit
+//! samples.
+use num_complex::Complex32;
+use std::io::BufWriter;
+use std::io::Cursor;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Args;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Direction::Rx;
+use crate::Direction::Tx;
+use crate::Driver;
+use crate::Error;
+use crate::IqFormat;
+use crate::Range;
+use crate::RangeItem;
+use crate::StreamCanceller;
+use crate::WavHeader;
+
+const MTU: usize = 8192;
+
+/// File Device
+#[derive(Clone)]
+pub struct File {
+    source: Option<Source>,
+    layout: Layout,
+    playback: Playback,
+    sink: Option<Sink>,
+    freq: Arc<Mutex<f64>>,
+    rate: Arc<Mutex<f64>>,
+}
+
+#[derive(Clone)]
+enum Source {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+/// Playback options of a recording.
+#[derive(Clone, Copy, Debug, Default)]
+struct Playback {
+    repeat: bool,
+    speed: Option<f64>,
+    start_offset: f64,
+    duration: Option<f64>,
+}
+
+/// Location and format of the samples in a recording.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    format: IqFormat,
+    wav: bool,
+    offset: u64,
+    len: Option<u64>,
+}
+
+/// Location and format of the file, written by the TX streamer.
+#[derive(Clone, Debug)]
+struct Sink {
+    path: PathBuf,
+    format: IqFormat,
+    wav: bool,
+    dither: bool,
+}
+
+impl Sink {
+    fn from_args(args: &Args) -> Result<Option<Self>, Error> {
+        let path = match args.get::<String>("tx_path") {
+            Ok(p) => PathBuf::from(p),
+            Err(Error::NotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let format = args.get::<String>("tx_format").ok();
+        let wav = match format.as_deref() {
+            Some(f) => f.eq_ignore_ascii_case("wav"),
+            None => path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("wav")),
+        };
+        let format = match format {
+            _ if wav => IqFormat::Cs16,
+            Some(f) => f.parse()?,
+            None => IqFormat::from_path(&path).unwrap_or(IqFormat::Cf32),
+        };
+        let dither = match args.get::<String>("dither").as_deref() {
+            Ok("tpdf") => true,
+            Ok("none") | Err(Error::NotFound) => false,
+            _ => return Err(Error::ValueError),
+        };
+        Ok(Some(Self {
+            path,
+            format,
+            wav,
+            dither,
+        }))
+    }
+}
+
+impl Source {
+    fn reader(&self) -> Result<Box<dyn Read + Send>, Error> {
+        match self {
+            Source::Path(p) => Ok(Box::new(std::io::BufReader::new(std::fs::File::open(p)?))),
+            Source::Bytes(b) => Ok(Box::new(Cursor::new(b.clone()))),
+        }
+    }
+
+    /// Open a reader for the sample data.
+    fn samples(&self, layout: &Layout) -> Result<Box<dyn Read + Send>, Error> {
+        let mut r = self.reader()?;
+        std::io::copy(&mut (&mut r).take(layout.offset), &mut std::io::sink())?;
+        match layout.len {
+            Some(len) => Ok(Box::new(r.take(len))),
+            None => Ok(r),
+        }
+    }
+
+    fn is_wav(&self) -> Result<bool, Error> {
+        let mut magic = Vec::with_capacity(4);
+        self.reader()?.take(4).read_to_end(&mut magic)?;
+        Ok(magic == b"RIFF")
+    }
+}
+
+/// File RX Streamer
+pub struct RxStreamer {
+    source: Source,
+    layout: Layout,
+    repeat: bool,
+    reader: Option<Box<dyn Read + Send>>,
+    buf: Vec<u8>,
+    position: u64,
+    /// Samples per second of real time, if playback is paced.
+    pace: Option<f64>,
+    started: Option<Instant>,
+    canceller: StreamCanceller,
+    scale: f32,
+}
+
+/// File TX Streamer, writing the samples to `tx_path`
+pub struct TxStreamer {
+    sink: Sink,
+    sample_rate: f64,
+    writer: Option<BufWriter<std::fs::File>>,
+    buf: Vec<u8>,
+    /// Bytes of samples written since activation.
+    len: u64,
+    dither: Option<Dither>,
+    canceller: StreamCanceller,
+    scale: f32,
+}
+
+/// Triangular (TPDF) dither of one quantization step, based on a xorshift generator.
+struct Dither {
+    state: u32,
+    step: f32,
+}
+
+impl File {
+    /// Get a list of Devices
+    ///
+    /// Will only return a device, if `file` is set as driver and a `path` or `tx_path` is given.
+    pub fn probe(args: &Args) -> Result<Vec<Args>, Error> {
+        if !matches!(args.get::<String>("driver").as_deref(), Ok("file")) {
+            return Ok(Vec::new());
+        }
+        let mut a = Args::new();
+        a.set("driver", "file");
+        for key in ["path", "tx_path"] {
+            if let Ok(v) = args.get::<String>(key) {
+                a.set(key, v);
+            }
+        }
+        if a.get::<String>("path").is_err() && a.get::<String>("tx_path").is_err() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![a])
+    }
+
+    /// Create a File device, replaying the recording at `path`
+    ///
+    /// The `frequency` and `sample_rate` arguments set the values that are reported for the
+    /// recording. For WAV files, the sample rate defaults to the one in the header.
+    ///
+    /// Playback is controlled with:
+    /// - `loop` or `repeat`: restart the recording once it ends.
+    /// - `speed`: pace reads to the sample rate times `speed`, e.g., `1` for real time or `0.5`
+    ///   for half speed. By default, samples are read as fast as possible.
+    /// - `start_offset`: skip the given number of seconds at the start of the recording.
+    /// - `duration`: only play the given number of seconds.
+    ///
+    /// Pacing, `start_offset`, and `duration` need the sample rate of the recording.
+    ///
+    /// Transmitted samples are written to `tx_path`, if given, which is created or truncated
+    /// when the TX streamer is activated. The device can be opened with only a `tx_path`, having
+    /// no RX channel then. The file is written with:
+    /// - `tx_format`: `cf32`, `cs16`, `cs8`, `cu8`, or `wav`, by default detected from the file
+    ///   extension, falling back to `cf32`.
+    /// - `dither`: `tpdf` to add triangular dither of one quantization step before converting to
+    ///   integer formats, or `none` (default) to round to the nearest step.
+    pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        let path = match args.get::<String>("path") {
+            Ok(p) => PathBuf::from(p),
+            Err(Error::NotFound) if args.get::<String>("tx_path").is_ok() => {
+                return Self::with_source(None, &args, None);
+            }
+            Err(e) => return Err(e),
+        };
+        if !path.is_file() {
+            return Err(Error::NotFound);
+        }
+        let format = IqFormat::from_path(&path);
+        Self::with_source(Some(Source::Path(path)), &args, format)
+    }
+
+    /// Create a File device, replaying the given recording
+    pub fn from_bytes<A: TryInto<Args>>(
+        data: impl Into<Arc<[u8]>>,
+        args: A,
+    ) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        Self::with_source(Some(Source::Bytes(data.into())), &args, None)
+    }
+
+    /// Create a File device, replaying a recording fetched from `url`
+    #[cfg(target_arch = "wasm32")]
+    pub async fn fetch<A: TryInto<Args>>(url: &str, args: A) -> Result<Self, Error> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        fn js_err(e: wasm_bindgen::JsValue) -> Error {
+            Error::Misc(format!("{e:?}"))
+        }
+
+        let global = js_sys::global();
+        let promise = if let Some(w) = global.dyn_ref::<web_sys::Window>() {
+            w.fetch_with_str(url)
+        } else if let Some(w) = global.dyn_ref::<web_sys::WorkerGlobalScope>() {
+            w.fetch_with_str(url)
+        } else {
+            return Err(Error::NotSupported);
+        };
+        let resp: web_sys::Response = JsFuture::from(promise)
+            .await
+            .map_err(js_err)?
+            .dyn_into()
+            .map_err(js_err)?;
+        if !resp.ok() {
+            return Err(Error::NotFound);
+        }
+        let buf = JsFuture::from(resp.array_buffer().map_err(js_err)?)
+            .await
+            .map_err(js_err)?;
+        Self::from_bytes(js_sys::Uint8Array::new(&buf).to_vec(), args)
+    }
+
+    fn with_source(
+        source: Option<Source>,
+        args: &Args,
+        guess: Option<IqFormat>,
+    ) -> Result<Self, Error> {
+        let format = args.get::<String>("format").ok();
+        let wav = match (format.as_deref(), &source) {
+            (Some(f), _) => f.eq_ignore_ascii_case("wav"),
+            (None, Some(source)) => source.is_wav()?,
+            (None, None) => false,
+        };
+        let (layout, rate) = if let (true, Some(source)) = (wav, &source) {
+            let h = WavHeader::read(source.reader()?)?;
+            let layout = Layout {
+                format: h.format,
+                wav: true,
+                offset: h.data_offset,
+                // streaming writers leave the length at zero or the maximum
+                len: (h.data_len != 0 && h.data_len != u32::MAX as u64).then_some(h.data_len),
+            };
+            (layout, h.sample_rate as f64)
+        } else {
+            let format = match format {
+                Some(f) => f.parse()?,
+                None => guess.unwrap_or(IqFormat::Cf32),
+            };
+            let layout = Layout {
+                format,
+                wav: false,
+                offset: 0,
+                len: None,
+            };
+            (layout, 0.0)
+        };
+        let playback = Playback {
+            repeat: flag(args, "loop")? || flag(args, "repeat")?,
+            speed: optional(args, "speed")?,
+            start_offset: optional(args, "start_offset")?.unwrap_or(0.0),
+            duration: optional(args, "duration")?,
+        };
+        if playback.speed.is_some_and(|s| !s.is_finite() || s <= 0.0)
+            || playback.start_offset.is_nan()
+            || playback.start_offset < 0.0
+            || playback.duration.is_some_and(|d| d.is_nan() || d < 0.0)
+        {
+            return Err(Error::ValueError);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if playback.speed.is_some() {
+            return Err(Error::NotSupported);
+        }
+        Ok(Self {
+            source,
+            layout,
+            playback,
+            sink: Sink::from_args(args)?,
+            freq: Arc::new(Mutex::new(args.get("frequency").unwrap_or(0.0))),
+            rate: Arc::new(Mutex::new(args.get("sample_rate").unwrap_or(rate))),
+        })
+    }
+}
+
+impl DeviceTrait for File {
+    type RxStreamer = RxStreamer;
+    type TxStreamer = TxStreamer;
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn driver(&self) -> Driver {
+        Driver::File
+    }
+
+    fn id(&self) -> Result<String, Error> {
+        match (&self.source, &self.sink) {
+            (Some(Source::Path(p)), _) => Ok(p.display().to_string()),
+            (Some(Source::Bytes(_)), _) => Ok("memory".to_string()),
+            (None, Some(sink)) => Ok(sink.path.display().to_string()),
+            (None, None) => Err(Error::NotFound),
+        }
+    }
+
+    fn info(&self) -> Result<Args, Error> {
+        let mut a = Args::new();
+        a.set("driver", "file");
+        if let Some(Source::Path(p)) = &self.source {
+            a.set("path", p.display().to_string());
+        }
+        if self.source.is_some() {
+            if self.layout.wav {
+                a.set("format", "wav");
+            } else {
+                a.set("format", self.layout.format.to_string());
+            }
+        }
+        if let Some(sink) = &self.sink {
+            a.set("tx_path", sink.path.display().to_string());
+            if sink.wav {
+                a.set("tx_format", "wav");
+            } else {
+                a.set("tx_format", sink.format.to_string());
+            }
+        }
+        Ok(a)
+    }
+
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        match direction {
+            Rx => Ok(self.source.is_some() as usize),
+            Tx => Ok(self.sink.is_some() as usize),
+        }
+    }
+
+    fn full_duplex(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+        if channels != [0] {
+            return Err(Error::ValueError);
+        }
+        let source = self.source.clone().ok_or(Error::NotSupported)?;
+        let p = self.playback;
+        let rate = *self.rate.lock().unwrap();
+        let timed = p.speed.is_some() || p.start_offset > 0.0 || p.duration.is_some();
+        if timed && rate <= 0.0 {
+            return Err(Error::ValueError);
+        }
+        let size = self.layout.format.sample_size() as u64;
+        let mut layout = self.layout;
+        let skip = (p.start_offset * rate).round() as u64 * size;
+        layout.offset += skip;
+        layout.len = layout.len.map(|l| l.saturating_sub(skip));
+        if let Some(d) = p.duration {
+            let len = (d * rate).round() as u64 * size;
+            layout.len = Some(layout.len.map_or(len, |l| l.min(len)));
+        }
+        Ok(RxStreamer {
+            source,
+            layout,
+            repeat: p.repeat,
+            reader: None,
+            buf: Vec::new(),
+            position: 0,
+            pace: p.speed.map(|s| s * rate),
+            started: None,
+            canceller: StreamCanceller::new(),
+            scale: 1.0,
+        })
+    }
+
+    fn tx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
+        if channels != [0] {
+            return Err(Error::ValueError);
+        }
+        let sink = self.sink.clone().ok_or(Error::NotSupported)?;
+        let dither = match sink.format {
+            _ if !sink.dither => None,
+            IqFormat::Cf32 => None,
+            IqFormat::Cs16 => Some(Dither::new(1.0 / 32768.0)),
+            IqFormat::Cs8 | IqFormat::Cu8 => Some(Dither::new(1.0 / 128.0)),
+        };
+        Ok(TxStreamer {
+            sink,
+            sample_rate: *self.rate.lock().unwrap(),
+            writer: None,
+            buf: Vec::new(),
+            len: 0,
+            dither,
+            canceller: StreamCanceller::new(),
+            scale: 1.0,
+        })
+    }
+
+    fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.antenna(direction, channel).map(|a| vec![a])
+    }
+
+    fn antenna(&self, direction: Direction, channel: usize) -> Result<String, Error> {
+        self.check_channel(direction, channel)?;
+        match direction {
+            Rx => Ok("RX".to_string()),
+            Tx => Ok("TX".to_string()),
+        }
+    }
+
+    fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
+        if self.antenna(direction, channel)? == name {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_elements(&self, _direction: Direction, _channel: usize) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn supports_agc(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn enable_agc(&self, _direction: Direction, _channel: usize, _agc: bool) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn agc(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_gain(&self, _direction: Direction, _channel: usize, _gain: f64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn gain(&self, _direction: Direction, _channel: usize) -> Result<Option<f64>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn gain_range(&self, _direction: Direction, _channel: usize) -> Result<Range, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_gain_element(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+        _gain: f64,
+    ) -> Result<f64, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn gain_element(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+    ) -> Result<Option<f64>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn gain_element_range(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _name: &str,
+    ) -> Result<Range, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        let f = self.frequency(direction, channel)?;
+        Ok(Range::new(vec![RangeItem::Value(f)]))
+    }
+
+    fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.check_channel(direction, channel)?;
+        Ok(*self.freq.lock().unwrap())
+    }
+
+    fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        _args: Args,
+    ) -> Result<(), Error> {
+        self.check_channel(direction, channel)?;
+        if frequency < 0.0 {
+            return Err(Error::ValueError);
+        }
+        *self.freq.lock().unwrap() = frequency;
+        Ok(())
+    }
+
+    fn frequency_components(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Vec<String>, Error> {
+        self.check_channel(direction, channel)?;
+        Ok(vec!["freq".to_string()])
+    }
+
+    fn component_frequency_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        match name {
+            "freq" => self.frequency_range(direction, channel),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        match name {
+            "freq" => self.frequency(direction, channel),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn set_component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        frequency: f64,
+    ) -> Result<(), Error> {
+        match name {
+            "freq" => self.set_frequency(direction, channel, frequency, Args::new()),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.check_channel(direction, channel)?;
+        Ok(*self.rate.lock().unwrap())
+    }
+
+    fn set_sample_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    ) -> Result<(), Error> {
+        self.check_channel(direction, channel)?;
+        if rate < 0.0 {
+            return Err(Error::ValueError);
+        }
+        *self.rate.lock().unwrap() = rate;
+        Ok(())
+    }
+
+    fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        let r = self.sample_rate(direction, channel)?;
+        Ok(Range::new(vec![RangeItem::Value(r)]))
+    }
+
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.sample_rate(direction, channel)
+    }
+
+    fn set_bandwidth(&self, _direction: Direction, _channel: usize, _bw: f64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.get_sample_rate_range(direction, channel)
+    }
+
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+impl crate::RxInterface for File {}
+impl crate::TxInterface for File {}
+
+impl File {
+    /// Check that the channel exists. Frequency and sample rate are shared by RX and TX.
+    fn check_channel(&self, direction: Direction, channel: usize) -> Result<(), Error> {
+        if channel < self.num_channels(direction)? {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+}
+
+impl RxStreamer {
+    /// Fill `buf` from the recording, restarting it once if `repeat` is set.
+    ///
+    /// Returns the number of bytes read, which is zero at the end of the recording.
+    fn fill(&mut self, len: usize) -> Result<usize, Error> {
+        let size = self.layout.format.sample_size();
+        self.buf.resize(len, 0);
+        let mut restarted = false;
+        loop {
+            let reader = self.reader.as_mut().ok_or(Error::Inactive)?;
+            let mut n = 0;
+            while n < len {
+                match reader.read(&mut self.buf[n..len])? {
+                    0 => break,
+                    k => n += k,
+                }
+            }
+            // only hand out complete samples
+            let n = n - n % size;
+            if n > 0 || !self.repeat || restarted {
+                return Ok(n);
+            }
+            self.reader = Some(self.source.samples(&self.layout)?);
+            restarted = true;
+        }
+    }
+
+    /// Wait until the samples up to the current position are due.
+    fn wait(&self) {
+        if let (Some(pace), Some(started)) = (self.pace, self.started) {
+            let due = started + Duration::from_secs_f64(self.position as f64 / pace);
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+    }
+}
+
+/// Parse an optional boolean argument, accepting `true`/`false` and `1`/`0`.
+fn flag(args: &Args, key: &str) -> Result<bool, Error> {
+    match args.get::<String>(key) {
+        Ok(v) => match v.to_ascii_lowercase().as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(Error::ValueError),
+        },
+        Err(Error::NotFound) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse an optional numeric argument.
+fn optional(args: &Args, key: &str) -> Result<Option<f64>, Error> {
+    match args.get(key) {
+        Ok(v) => Ok(Some(v)),
+        Err(Error::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl crate::RxStreamer for RxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(MTU)
+    }
+
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        self.reader = Some(self.source.samples(&self.layout)?);
+        self.position = 0;
+        self.started = self.pace.map(|_| Instant::now());
+        self.canceller.reset();
+        Ok(())
+    }
+
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        self.reader = None;
+        Ok(())
+    }
+
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], _timeout_us: i64) -> Result<usize, Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let size = self.layout.format.sample_size();
+        let n = self.fill(buffers[0].len() * size)? / size;
+        if n == 0 && !buffers[0].is_empty() {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        self.layout
+            .format
+            .decode(&self.buf[..n * size], &mut buffers[0][..n]);
+        if self.scale != 1.0 {
+            buffers[0][..n].iter_mut().for_each(|s| *s *= self.scale);
+        }
+        self.position += n as u64;
+        self.wait();
+        Ok(n)
+    }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+}
+
+impl Dither {
+    fn new(step: f32) -> Self {
+        Self {
+            state: 0x2545_f491,
+            step,
+        }
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Triangular in `(-step, step)`.
+    fn next(&mut self) -> f32 {
+        (self.uniform() - self.uniform()) * self.step
+    }
+}
+
+impl TxStreamer {
+    fn header(&self, len: u64) -> Result<Vec<u8>, Error> {
+        WavHeader {
+            format: self.sink.format,
+            sample_rate: self.sample_rate.round() as u32,
+            data_offset: 44,
+            // too long for the header, leave it unknown like streaming writers do
+            data_len: if len <= (u32::MAX - 36) as u64 {
+                len
+            } else {
+                0
+            },
+        }
+        .to_bytes()
+    }
+
+    /// Flush the file and, for WAV files, write the final length to the header.
+    fn finish(&mut self) -> Result<(), Error> {
+        if let Some(mut w) = self.writer.take() {
+            w.flush()?;
+            if self.sink.wav {
+                let header = self.header(self.len)?;
+                let mut f = w.into_inner().map_err(|e| e.into_error())?;
+                f.seek(SeekFrom::Start(0))?;
+                f.write_all(&header)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl crate::TxStreamer for TxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(MTU)
+    }
+
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        self.finish()?;
+        let mut w = BufWriter::new(std::fs::File::create(&self.sink.path)?);
+        if self.sink.wav {
+            w.write_all(&self.header(0)?)?;
+        }
+        self.writer = Some(w);
+        self.len = 0;
+        self.canceller.reset();
+        Ok(())
+    }
+
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        self.finish()
+    }
+
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        _end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let writer = self.writer.as_mut().ok_or(Error::Inactive)?;
+        self.buf.clear();
+        let scale = self.scale;
+        match self.dither.as_mut() {
+            Some(d) => {
+                let dithered: Vec<Complex32> = buffers[0]
+                    .iter()
+                    .map(|s| s * scale + Complex32::new(d.next(), d.next()))
+                    .collect();
+                self.sink.format.encode(&dithered, &mut self.buf);
+            }
+            None if scale != 1.0 => {
+                let scaled: Vec<Complex32> = buffers[0].iter().map(|s| s * scale).collect();
+                self.sink.format.encode(&scaled, &mut self.buf);
+            }
+            None => self.sink.format.encode(buffers[0], &mut self.buf),
+        }
+        writer.write_all(&self.buf)?;
+        self.len += self.buf.len() as u64;
+        Ok(buffers[0].len())
+    }
+
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        self.write(buffers, at_ns, end_burst, timeout_us)
+            .map(|_| ())
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+}
+
+impl Drop for TxStreamer {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::warn!("failed to finish {}: {e}", self.sink.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RxStreamer as _;
+    use crate::TxStreamer as _;
+
+    fn recording(n: usize) -> Vec<u8> {
+        (0..n)
+            .flat_map(|i| {
+                let mut b = (i as f32).to_le_bytes().to_vec();
+                b.extend_from_slice(&(-(i as f32)).to_le_bytes());
+                b
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replay_until_end() {
+        let dev = File::from_bytes(recording(5), "sample_rate=1e6").unwrap();
+        assert_eq!(dev.sample_rate(Rx, 0).unwrap(), 1e6);
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 3];
+        assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 3);
+        assert_eq!(buf[2], Complex32::new(2.0, -2.0));
+        assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 2);
+        assert_eq!(buf[1], Complex32::new(4.0, -4.0));
+        assert!(matches!(rx.read(&mut [&mut buf], 0), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn replay_repeated() {
+        let dev = File::from_bytes(recording(2), "repeat=true").unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 2];
+        for _ in 0..3 {
+            assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 2);
+            assert_eq!(buf[1], Complex32::new(1.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let samples = [Complex32::new(0.5, -0.5), Complex32::new(-0.25, 0.0)];
+        for (format, args) in [
+            (IqFormat::Cu8, "format=cu8"),
+            (IqFormat::Cs16, "format=cs16"),
+            (IqFormat::Cs8, "format=cs8"),
+        ] {
+            let mut data = Vec::new();
+            format.encode(&samples, &mut data);
+            let dev = File::from_bytes(data, args).unwrap();
+            assert_eq!(
+                dev.info().unwrap().get::<String>("format").unwrap(),
+                format.to_string()
+            );
+            let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+            rx.activate().unwrap();
+            let mut buf = [Complex32::new(0.0, 0.0); 4];
+            assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 2);
+            assert!((buf[0] - samples[0]).norm() < 0.01);
+            assert!((buf[1] - samples[1]).norm() < 0.01);
+        }
+    }
+
+    #[test]
+    fn wav() {
+        let samples = [Complex32::new(0.5, -0.5); 3];
+        let mut data = Vec::new();
+        IqFormat::Cs16.encode(&samples, &mut data);
+        let header = WavHeader {
+            format: IqFormat::Cs16,
+            sample_rate: 96000,
+            data_offset: 44,
+            data_len: data.len() as u64,
+        };
+        let mut wav = header.to_bytes().unwrap();
+        wav.extend(data);
+        // trailing chunk that must not be read as samples
+        wav.extend(b"LIST\x04\x00\x00\x00abcd");
+
+        let dev = File::from_bytes(wav, Args::new()).unwrap();
+        assert_eq!(dev.sample_rate(Rx, 0).unwrap(), 96000.0);
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 8];
+        assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 3);
+        assert_eq!(buf[2], samples[2]);
+        assert!(rx.read(&mut [&mut buf], 0).is_err());
+    }
+
+    #[test]
+    fn playback_window() {
+        let args = "sample_rate=10,start_offset=0.2,duration=0.3,loop=1";
+        let dev = File::from_bytes(recording(10), args).unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 8];
+        for _ in 0..2 {
+            assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 3);
+            assert_eq!(buf[0], Complex32::new(2.0, -2.0));
+            assert_eq!(buf[2], Complex32::new(4.0, -4.0));
+        }
+
+        // offsets need the sample rate
+        let dev = File::from_bytes(recording(10), "start_offset=1").unwrap();
+        assert!(dev.rx_streamer(&[0], Args::new()).is_err());
+        assert!(File::from_bytes(recording(10), "loop=maybe").is_err());
+        assert!(File::from_bytes(recording(10), "speed=0").is_err());
+    }
+
+    #[test]
+    fn paced_playback() {
+        let dev = File::from_bytes(recording(100), "sample_rate=1000,speed=2").unwrap();
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        rx.activate().unwrap();
+        let start = Instant::now();
+        let mut buf = [Complex32::new(0.0, 0.0); 50];
+        rx.read(&mut [&mut buf], 0).unwrap();
+        rx.read(&mut [&mut buf], 0).unwrap();
+        // 100 samples at 2000 samples per second
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn tx_formats() {
+        let samples = [Complex32::new(0.5, -0.5), Complex32::new(-0.25, 0.999)];
+        let dir = std::env::temp_dir();
+        for (name, args, format) in [
+            ("seify_tx.cs8", "", "cs8"),
+            ("seify_tx.cs16", "dither=tpdf", "cs16"),
+            ("seify_tx.bin", "tx_format=cu8,dither=tpdf", "cu8"),
+            ("seify_tx.wav", "", "wav"),
+        ] {
+            let path = dir.join(format!("{}_{name}", std::process::id()));
+            let args = format!("tx_path={},sample_rate=8000,{args}", path.display());
+            let dev = File::open(args.as_str()).unwrap();
+            assert_eq!(dev.num_channels(Rx).unwrap(), 0);
+            assert_eq!(dev.sample_rate(Tx, 0).unwrap(), 8000.0);
+            let mut tx = dev.tx_streamer(&[0], Args::new()).unwrap();
+            tx.activate().unwrap();
+            tx.write_all(&[&samples], None, true, 0).unwrap();
+            drop(tx);
+
+            let dev = File::open(format!("path={},format={format}", path.display()).as_str());
+            let mut rx = dev.unwrap().rx_streamer(&[0], Args::new()).unwrap();
+            rx.activate().unwrap();
+            let mut buf = [Complex32::new(0.0, 0.0); 4];
+            assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 2, "{name}");
+            std::fs::remove_file(&path).unwrap();
+            for (a, b) in samples.iter().zip(buf.iter()) {
+                assert!((a - b).norm() < 0.02, "{name}: {a} != {b}");
+            }
+        }
+        assert!(File::open("tx_path=x.cs8,dither=maybe").is_err());
+    }
+}
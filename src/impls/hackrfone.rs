@@ -1,15 +1,156 @@
-use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 use num_complex::Complex32;
 use seify_hackrfone::Config;
 
-use crate::{Args, Direction, Error, Range, RangeItem};
+use super::usb_reader::UsbReader;
+use super::UsbConfig;
+use crate::{
+    Args, BufferConfig, CoherencyGroup, Direction, Error, GainElementInfo, Latency, PowerState,
+    Range, RangeItem, ReferenceDoc, Spur, StreamCanceller, StreamFormat,
+};
 
+#[derive(Clone)]
 pub struct HackRfOne {
     inner: Arc<HackRfInner>,
 }
 
-const MTU: usize = 64 * 1024;
+/// Size of a USB bulk packet in bytes. The length of transfers has to be a multiple of it.
+const USB_PACKET: usize = 512;
+/// Default USB transfer settings for a latency hint, overridden with the `usb_*` arguments.
+///
+/// `transfers` is the number of transfers buffered between the reader thread and `read()`.
+fn usb_defaults(latency: Latency) -> UsbConfig {
+    let (transfers, transfer_size) = match latency {
+        Latency::Low => (4, 16 * 1024),
+        Latency::Balanced => (16, 128 * 1024),
+        Latency::Throughput => (32, 256 * 1024),
+    };
+    UsbConfig {
+        transfers,
+        transfer_size,
+        timeout: None,
+    }
+}
+/// Granularity at which a delayed TX start checks for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Time to wait for the reader thread to finish its pending transfer, when the stream is
+/// stopped without a USB timeout.
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+/// Range of the sample clock in Hz.
+const SAMPLE_CLOCK: (u32, u32) = (1_000_000, 20_000_000);
+/// Largest divider of the sample clock, for rates below the minimum clock.
+const MAX_SAMPLE_RATE_DIV: u32 = 32;
+/// Bandwidths of the MAX2837 baseband filter in Hz.
+const BASEBAND_FILTERS: [u32; 16] = [
+    1_750_000, 2_500_000, 3_500_000, 5_000_000, 5_500_000, 6_000_000, 7_000_000, 8_000_000,
+    9_000_000, 10_000_000, 12_000_000, 14_000_000, 15_000_000, 20_000_000, 24_000_000, 28_000_000,
+];
+/// Gain of the RF amplifier in dB, when it is enabled.
+const AMP_GAIN: f64 = 14.0;
+/// Highest frequency, for which the RF amplifier is specified.
+const AMP_MAX_FREQUENCY: f64 = 6_000_000_000.0;
+/// Maximum TX IF gain in dB.
+const MAX_TX_GAIN: u16 = 47;
+/// Tuning range of the RFFC5072 mixer LO in Hz.
+const MIXER_LO: (f64, f64) = (84_375_000.0, 5_400_000_000.0);
+/// Tuning range of the MAX2837 transceiver in Hz, as used by the frequency plan.
+const TRANSCEIVER: (f64, f64) = (2_150_000_000.0, 2_750_000_000.0);
+
+/// Signal path of the HackRF front end, which depends on the frequency.
+///
+/// The MAX2837 transceiver operates at 2.3 to 2.7 GHz. Other frequencies are converted by the
+/// RFFC5072 mixer, with the low-pass or high-pass filter in front of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalPath {
+    /// Below 2170 MHz, mixer with low-pass filter.
+    LowPass,
+    /// 2170 to 2740 MHz, mixer bypassed.
+    Bypass,
+    /// Above 2740 MHz, mixer with high-pass filter.
+    HighPass,
+}
+
+impl SignalPath {
+    /// Frequency ranges of the signal paths.
+    pub const RANGES: [(SignalPath, f64, f64); 3] = [
+        (SignalPath::LowPass, 0.0, 2_170_000_000.0),
+        (SignalPath::Bypass, 2_170_000_000.0, 2_740_000_000.0),
+        (SignalPath::HighPass, 2_740_000_000.0, 7_270_000_000.0),
+    ];
+
+    /// Signal path serving `frequency`, `None` if it is out of range.
+    pub fn for_frequency(frequency: f64) -> Option<Self> {
+        Self::RANGES
+            .iter()
+            .find(|(_, start, stop)| (*start..*stop).contains(&frequency))
+            .or_else(|| Self::RANGES.last().filter(|r| r.2 == frequency))
+            .map(|r| r.0)
+    }
+
+    /// Frequencies of the front end tuned to `frequency`, following the plan of the firmware.
+    ///
+    /// `None` if the frequency is out of range.
+    pub fn plan(frequency: f64) -> Option<FrequencyPlan> {
+        const MHZ: u64 = 1_000_000;
+        let path = Self::for_frequency(frequency)?;
+        let rf = frequency.round() as u64;
+        // the firmware tunes the mixer in steps of 1 MHz and the transceiver to the rest
+        let (lo, intermediate) = match path {
+            SignalPath::LowPass => {
+                // IF from 2650 MHz down to 2340 MHz
+                let nominal = 2_650_000_000 - rf / 7;
+                let lo = (nominal / MHZ + rf / MHZ) * MHZ;
+                (lo, lo - rf)
+            }
+            SignalPath::Bypass => (0, rf),
+            SignalPath::HighPass => {
+                // IF from 2150 MHz up to 2750 MHz, 2350 to 2650 MHz, and 2500 to 2741 MHz
+                let nominal = if rf < 3_600_000_000 {
+                    2_150_000_000 + (rf - 2_740_000_000) * 60 / 86
+                } else if rf < 5_100_000_000 {
+                    2_350_000_000 + (rf - 3_600_000_000) / 5
+                } else {
+                    2_500_000_000 + (rf - 5_100_000_000) / 9
+                };
+                let lo = (rf / MHZ - nominal / MHZ) * MHZ;
+                (lo, rf - lo)
+            }
+        };
+        Some(FrequencyPlan {
+            path,
+            lo: lo as f64,
+            intermediate: intermediate as f64,
+        })
+    }
+}
+
+/// Tuning of the HackRF front end, reported as `LO` and `IF` frequency components.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrequencyPlan {
+    /// Signal path selected for the frequency.
+    pub path: SignalPath,
+    /// Frequency of the RFFC5072 mixer LO in Hz, 0 if the mixer is bypassed.
+    pub lo: f64,
+    /// Frequency of the MAX2837 transceiver in Hz.
+    pub intermediate: f64,
+}
+
+/// Sample clock in Hz and divider for an effective sample `rate`.
+///
+/// Rates below the minimum clock use the smallest divider that brings the clock into range.
+/// Both have to be integers, so the rate has to be an integer, too.
+fn sample_clock(rate: f64) -> Option<(u32, u32)> {
+    let (min, max) = SAMPLE_CLOCK;
+    if rate.fract() != 0.0 || rate < 1.0 || rate > max as f64 {
+        return None;
+    }
+    let rate = rate as u32;
+    let div = min.div_ceil(rate);
+    (div <= MAX_SAMPLE_RATE_DIV).then_some((rate * div, div))
+}
 
 impl HackRfOne {
     pub fn probe(_args: &Args) -> Result<Vec<Args>, Error> {
@@ -28,6 +169,27 @@ impl HackRfOne {
     }
 
     /// Create a Hackrf One devices
+    ///
+    /// Without `bus_number` and `address`, the first device is opened. With `usb_wait_ms`, opening
+    /// waits for a device to show up.
+    ///
+    /// A bandwidth set with `set_bandwidth` is kept, when the sample rate changes. With
+    /// `baseband_filter=auto`, a sample rate change resets it to the one derived from the rate.
+    ///
+    /// Settings that risk damaging the front end, like the RF amplifier with maximum TX gain, are
+    /// refused, unless opened with `safety=off`.
+    ///
+    /// USB transfers are tuned with `usb_xfers` (default 16), `usb_xfer_size` (default 131072
+    /// bytes), and `usb_timeout_ms` (default none), which can be overridden per stream with the
+    /// streamer arguments. The defaults follow the `latency` streamer argument. The number of
+    /// transfers only applies to RX, which is read ahead in a separate thread. Deactivating RX
+    /// waits up to `usb_timeout_ms` (or one second) for that thread to finish its transfer and
+    /// fails with [`Error::Timeout`] if the device stalled; the stream cannot be activated again
+    /// until the transfer returns.
+    ///
+    /// The HackRF is half duplex. Activating a stream, while the other direction is active, fails
+    /// with [`Error::Busy`], unless `mode_wait_ms` is set, which queues the activation for up to
+    /// that time until the other stream is deactivated.
     pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
         let args: Args = args.try_into().or(Err(Error::ValueError))?;
 
@@ -42,6 +204,15 @@ impl HackRfOne {
                     dev: seify_hackrfone::HackRf::from_fd(fd)?,
                     tx_config: Mutex::new(Config::tx_default()),
                     rx_config: Mutex::new(Config::rx_default()),
+                    tx_applied: Mutex::new(None),
+                    rx_applied: Mutex::new(None),
+                    tx_bandwidth: Mutex::new(None),
+                    rx_bandwidth: Mutex::new(None),
+                    auto_baseband_filter: false,
+                    safety: true,
+                    usb_args: args,
+                    power: Mutex::new(PowerState::On),
+                    mode: ModeManager::new(None),
                 }),
             });
         }
@@ -50,12 +221,20 @@ impl HackRfOne {
         let bus_number = args.get("bus_number");
         let address = args.get("address");
         let dev = match (bus_number, address) {
-            (Ok(bus_number), Ok(address)) => {
-                seify_hackrfone::HackRf::open_bus(bus_number, address)?
-            }
+            (Ok(bus_number), Ok(address)) => seify_hackrfone::HackRf::open_bus(bus_number, address)
+                .map_err(|e| {
+                    super::usb_open_error(
+                        format!("hackrfone (bus {bus_number}, address {address})"),
+                        e.into(),
+                    )
+                })?,
             (Err(Error::NotFound), Err(Error::NotFound)) => {
                 log::debug!("Opening first hackrf device");
-                seify_hackrfone::HackRf::open_first()?
+                if super::wait_for_usb(&args, || Ok(seify_hackrfone::HackRf::scan()?))?.is_empty() {
+                    return Err(Error::NotFound);
+                }
+                seify_hackrfone::HackRf::open_first()
+                    .map_err(|e| super::usb_open_error("hackrfone".to_string(), e.into()))?
             }
             (bus_number, address) => {
                 log::warn!("HackRfOne::open received invalid args: bus_number: {bus_number:?}, address: {address:?}");
@@ -63,154 +242,828 @@ impl HackRfOne {
             }
         };
 
+        let auto_baseband_filter = match args.get::<String>("baseband_filter") {
+            Ok(mode) if mode == "auto" => true,
+            Ok(mode) if mode == "manual" => false,
+            Err(Error::NotFound) => false,
+            _ => {
+                log::warn!("HackRfOne::open: baseband_filter has to be auto or manual");
+                return Err(Error::ValueError);
+            }
+        };
+
+        let safety = match args.get::<String>("safety") {
+            Ok(mode) if mode == "on" => true,
+            Ok(mode) if mode == "off" => false,
+            Err(Error::NotFound) => true,
+            _ => {
+                log::warn!("HackRfOne::open: safety has to be on or off");
+                return Err(Error::ValueError);
+            }
+        };
+
+        usb_defaults(Latency::Balanced).with_args(&args, USB_PACKET)?;
+
+        let mode_wait = match args.get::<u64>("mode_wait_ms") {
+            Ok(ms) => Some(Duration::from_millis(ms)),
+            Err(Error::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+
         Ok(Self {
             inner: Arc::new(HackRfInner {
                 dev,
                 tx_config: Mutex::new(Config::tx_default()),
                 rx_config: Mutex::new(Config::rx_default()),
+                tx_applied: Mutex::new(None),
+                rx_applied: Mutex::new(None),
+                tx_bandwidth: Mutex::new(None),
+                rx_bandwidth: Mutex::new(None),
+                auto_baseband_filter,
+                safety,
+                usb_args: args,
+                power: Mutex::new(PowerState::On),
+                mode: ModeManager::new(mode_wait),
             }),
         })
     }
 
+    /// Apply `f` to the configuration.
+    ///
+    /// Changes made while the direction streams are written to the device with the next setter
+    /// call or stream activation.
     pub fn with_config<F, R>(&self, direction: Direction, f: F) -> R
     where
         F: FnOnce(&mut Config) -> R,
     {
-        let config = match direction {
-            Direction::Tx => self.inner.tx_config.lock(),
-            Direction::Rx => self.inner.rx_config.lock(),
-        };
-        f(&mut config.unwrap())
+        f(&mut self.inner.config(direction).lock().unwrap())
     }
+
+    /// Apply `f` to the configuration, if the result passes the safety checks, and write the
+    /// changes to the device, if the direction streams.
+    fn update_config<F>(&self, direction: Direction, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Config),
+    {
+        self.with_config(direction, |config| {
+            let mut next = config.clone();
+            f(&mut next);
+            if self.inner.safety {
+                check_safety(direction, &next)?;
+            }
+            *config = next;
+            Ok::<_, Error>(())
+        })?;
+        self.inner.sync(direction)
+    }
+
+    /// Is antenna power (bias) enabled?
+    pub fn antenna_power(&self, direction: Direction) -> bool {
+        self.with_config(direction, |config| config.antenna_enable)
+    }
+
+    /// Enable or disable antenna power (bias) on the antenna port, once the stream is activated.
+    ///
+    /// Unless opened with `safety=off`, it is refused for TX, where the DC is applied to an
+    /// unknown load.
+    pub fn set_antenna_power(&self, direction: Direction, enable: bool) -> Result<(), Error> {
+        self.update_config(direction, |config| config.antenna_enable = enable)
+    }
+
+    /// Underlying HackRF handle, as an escape hatch for features that Seify does not expose.
+    ///
+    /// Settings changed through the handle bypass Seify and its safety checks. Seify writes its
+    /// own [configuration](Self::with_config) again with the next setter call or stream
+    /// activation.
+    pub fn hackrf_device(&self) -> &seify_hackrfone::HackRf {
+        &self.inner.dev
+    }
+}
+
+/// Refuse configurations that risk damaging the front end.
+///
+/// They can be allowed by opening the device with `safety=off`.
+fn check_safety(direction: Direction, config: &Config) -> Result<(), Error> {
+    if config.amp_enable && config.frequency_hz as f64 > AMP_MAX_FREQUENCY {
+        log::warn!("HackRfOne: RF amplifier is not specified above 6 GHz");
+        return Err(Error::ValueError);
+    }
+    if direction == Direction::Tx && config.amp_enable && config.txvga_db >= MAX_TX_GAIN {
+        log::warn!("HackRfOne: RF amplifier with maximum TX gain");
+        return Err(Error::ValueError);
+    }
+    if direction == Direction::Tx && config.antenna_enable {
+        log::warn!("HackRfOne: antenna power on TX");
+        return Err(Error::ValueError);
+    }
+    Ok(())
 }
 
 struct HackRfInner {
     dev: seify_hackrfone::HackRf,
     tx_config: Mutex<seify_hackrfone::Config>,
     rx_config: Mutex<seify_hackrfone::Config>,
+    /// Configuration on the device, while the direction streams.
+    tx_applied: Mutex<Option<seify_hackrfone::Config>>,
+    rx_applied: Mutex<Option<seify_hackrfone::Config>>,
+    /// Baseband filter bandwidth set by the user, `None` to derive it from the sample rate.
+    tx_bandwidth: Mutex<Option<u32>>,
+    rx_bandwidth: Mutex<Option<u32>>,
+    /// Discard the user-set bandwidth, when the sample rate changes (`baseband_filter=auto`).
+    auto_baseband_filter: bool,
+    /// Refuse unsafe configurations, see `check_safety`.
+    safety: bool,
+    /// Arguments of `open`, whose USB transfer settings apply unless overridden by the streamer
+    /// arguments.
+    usb_args: Args,
+    power: Mutex<PowerState>,
+    mode: ModeManager,
+}
+
+impl HackRfInner {
+    fn config(&self, direction: Direction) -> &Mutex<Config> {
+        match direction {
+            Direction::Tx => &self.tx_config,
+            Direction::Rx => &self.rx_config,
+        }
+    }
+
+    fn applied(&self, direction: Direction) -> &Mutex<Option<Config>> {
+        match direction {
+            Direction::Tx => &self.tx_applied,
+            Direction::Rx => &self.rx_applied,
+        }
+    }
+
+    /// Apply `f` to the configuration on the device, while the direction streams, and to the
+    /// configuration that will be applied on activation otherwise.
+    fn current<R>(&self, direction: Direction, f: impl FnOnce(&Config) -> R) -> R {
+        let config = self.config(direction).lock().unwrap();
+        match &*self.applied(direction).lock().unwrap() {
+            Some(applied) => f(applied),
+            None => f(&config),
+        }
+    }
+
+    /// Write the dirty settings to the device, if the direction streams.
+    ///
+    /// Settings that fail to write stay dirty, so that they are retried with the next change.
+    /// Activating a stream writes the complete configuration.
+    fn sync(&self, direction: Direction) -> Result<(), Error> {
+        let config = self.config(direction).lock().unwrap();
+        let mut applied = self.applied(direction).lock().unwrap();
+        let Some(applied) = applied.as_mut() else {
+            return Ok(());
+        };
+        let dirty = Dirty::new(applied, &config);
+        if dirty.frequency {
+            self.dev.set_freq(config.frequency_hz)?;
+            applied.frequency_hz = config.frequency_hz;
+        }
+        if dirty.sample_rate {
+            self.dev
+                .set_sample_rate(config.sample_rate_hz, config.sample_rate_div)?;
+            applied.sample_rate_hz = config.sample_rate_hz;
+            applied.sample_rate_div = config.sample_rate_div;
+            let rate = config.sample_rate_hz as f64 / config.sample_rate_div as f64;
+            let bw = self.bandwidth(direction).lock().unwrap();
+            self.dev
+                .set_baseband_filter_bandwidth(bw.unwrap_or_else(|| auto_bandwidth(rate)))?;
+        }
+        if dirty.lna {
+            self.dev.set_lna_gain(config.lna_db)?;
+            applied.lna_db = config.lna_db;
+        }
+        if dirty.vga {
+            self.dev.set_vga_gain(config.vga_db)?;
+            applied.vga_db = config.vga_db;
+        }
+        if dirty.txvga {
+            self.dev.set_txvga_gain(config.txvga_db)?;
+            applied.txvga_db = config.txvga_db;
+        }
+        if dirty.amp {
+            self.dev.set_amp_enable(config.amp_enable)?;
+            applied.amp_enable = config.amp_enable;
+        }
+        if dirty.antenna {
+            self.dev.set_antenna_enable(config.antenna_enable as u8)?;
+            applied.antenna_enable = config.antenna_enable;
+        }
+        Ok(())
+    }
+
+    fn bandwidth(&self, direction: Direction) -> &Mutex<Option<u32>> {
+        match direction {
+            Direction::Tx => &self.tx_bandwidth,
+            Direction::Rx => &self.rx_bandwidth,
+        }
+    }
+
+    /// USB transfer settings of a stream, created with `args`.
+    fn usb_config(&self, args: &Args) -> Result<UsbConfig, Error> {
+        usb_defaults(Latency::from_args(args)?)
+            .with_args(&self.usb_args, USB_PACKET)?
+            .with_args(args, USB_PACKET)
+    }
+
+    /// Switch to `direction` and start the transceiver with `start`, if powered on.
+    fn start(
+        &self,
+        direction: Direction,
+        start: impl FnOnce() -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.mode.acquire(direction)?;
+        let r = if *self.power.lock().unwrap() != PowerState::On {
+            Err(Error::Inactive)
+        } else {
+            start()
+        };
+        if r.is_err() {
+            self.mode.release(direction);
+        }
+        r
+    }
+
+    /// Restore the user-set bandwidth, after starting a stream set the filter for the sample rate.
+    fn apply_bandwidth(&self, direction: Direction) -> Result<(), Error> {
+        if let Some(bw) = *self.bandwidth(direction).lock().unwrap() {
+            self.dev.set_baseband_filter_bandwidth(bw)?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings that differ between the device and the configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Dirty {
+    frequency: bool,
+    sample_rate: bool,
+    lna: bool,
+    vga: bool,
+    txvga: bool,
+    amp: bool,
+    antenna: bool,
+}
+
+impl Dirty {
+    fn new(applied: &Config, config: &Config) -> Self {
+        Self {
+            frequency: applied.frequency_hz != config.frequency_hz,
+            sample_rate: applied.sample_rate_hz != config.sample_rate_hz
+                || applied.sample_rate_div != config.sample_rate_div,
+            lna: applied.lna_db != config.lna_db,
+            vga: applied.vga_db != config.vga_db,
+            txvga: applied.txvga_db != config.txvga_db,
+            amp: applied.amp_enable != config.amp_enable,
+            antenna: applied.antenna_enable != config.antenna_enable,
+        }
+    }
+}
+
+/// Transceiver mode of the HackRF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Off,
+    Streaming(Direction),
+}
+
+struct ModeState {
+    mode: Mode,
+    /// Tickets of queued mode switches, served in order.
+    queue: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+/// Serializes the mode switches of the RX and TX streamers.
+///
+/// The HackRF is half duplex, and starting one direction while the other streams confuses the
+/// firmware. Switches are only allowed from `Off`. Others fail with `Error::Busy` or, with a
+/// `wait` time, are queued until the active stream stops.
+struct ModeManager {
+    state: Mutex<ModeState>,
+    changed: Condvar,
+    wait: Option<Duration>,
+}
+
+impl ModeManager {
+    fn new(wait: Option<Duration>) -> Self {
+        Self {
+            state: Mutex::new(ModeState {
+                mode: Mode::Off,
+                queue: VecDeque::new(),
+                next_ticket: 0,
+            }),
+            changed: Condvar::new(),
+            wait,
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        self.state.lock().unwrap().mode
+    }
+
+    /// Switch to streaming in `direction`.
+    fn acquire(&self, direction: Direction) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let busy = match state.mode {
+            Mode::Streaming(d) if d == direction => return Err(Error::Busy(d)),
+            Mode::Streaming(d) => Some(d),
+            Mode::Off => None,
+        };
+        if let (Some(d), None) = (busy, self.wait) {
+            return Err(Error::Busy(d));
+        }
+        if busy.is_none() && state.queue.is_empty() {
+            state.mode = Mode::Streaming(direction);
+            return Ok(());
+        }
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.queue.push_back(ticket);
+        let deadline = Instant::now() + self.wait.unwrap_or_default();
+        loop {
+            if state.mode == Mode::Off && state.queue.front() == Some(&ticket) {
+                state.queue.pop_front();
+                state.mode = Mode::Streaming(direction);
+                return Ok(());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                state.queue.retain(|t| *t != ticket);
+                let mode = state.mode;
+                drop(state);
+                self.changed.notify_all();
+                return Err(match mode {
+                    Mode::Streaming(d) => Error::Busy(d),
+                    Mode::Off => Error::Timeout,
+                });
+            }
+            state = self.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+
+    /// Leave streaming in `direction`.
+    fn release(&self, direction: Direction) {
+        let mut state = self.state.lock().unwrap();
+        if state.mode == Mode::Streaming(direction) {
+            state.mode = Mode::Off;
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// Baseband filter that the HackRF selects for a sample rate: the widest one up to 75% of it.
+fn auto_bandwidth(rate: f64) -> u32 {
+    BASEBAND_FILTERS
+        .iter()
+        .rev()
+        .find(|bw| **bw as f64 <= 0.75 * rate)
+        .copied()
+        .unwrap_or(BASEBAND_FILTERS[0])
 }
 
 pub struct RxStreamer {
     inner: Arc<HackRfInner>,
-    stream: Option<seify_hackrfone::RxStream>,
+    usb: UsbConfig,
+    worker: Option<UsbReader>,
+    // reader thread that did not stop, as it is stuck in a transfer of a stalled device
+    stalled: Option<UsbReader>,
+    canceller: StreamCanceller,
+    buf: Vec<u8>,
+    offset: usize,
+    position: u64,
+    scale: f32,
+    // how late the last timed activation started
+    activation_error_ns: Option<i64>,
 }
 
 impl RxStreamer {
-    fn new(inner: Arc<HackRfInner>) -> Self {
+    fn new(inner: Arc<HackRfInner>, usb: UsbConfig) -> Self {
         Self {
             inner,
-            stream: None,
+            usb,
+            worker: None,
+            stalled: None,
+            canceller: StreamCanceller::new(),
+            buf: Vec::new(),
+            offset: 0,
+            position: 0,
+            scale: 1.0,
+            activation_error_ns: None,
+        }
+    }
+
+    /// Wait for the next transfer from the reader thread.
+    ///
+    /// A negative `timeout_us` blocks until data arrives, the stream is cancelled, or the USB
+    /// timeout expires.
+    fn next_transfer(&mut self, timeout_us: i64) -> Result<(), Error> {
+        let worker = self.worker.as_ref().ok_or(Error::Inactive)?;
+        let buf = worker.recv(&self.canceller, timeout_us, self.usb.timeout)?;
+        worker.recycle(std::mem::replace(&mut self.buf, buf));
+        self.offset = 0;
+        Ok(())
+    }
+}
+
+impl Drop for RxStreamer {
+    fn drop(&mut self) {
+        if self.worker.is_some() {
+            if let Err(e) = crate::RxStreamer::deactivate(self) {
+                log::warn!("failed to stop HackRF RX stream on drop: {e}");
+            }
         }
     }
 }
 
 impl crate::RxStreamer for RxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
-        Ok(MTU)
+        Ok(self.usb.transfer_size / 2)
     }
 
-    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        // TODO: sleep precisely for `time_ns`
-        let config = self.inner.rx_config.lock().unwrap();
-        self.inner.dev.start_rx(&config)?;
-
-        self.stream = Some(self.inner.dev.start_rx_stream(MTU)?);
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        // never start a second stream next to a reader that still owns the previous one
+        if let Some(worker) = self.stalled.as_mut() {
+            if !worker.stop(Duration::ZERO) {
+                return Err(Error::Busy(Direction::Rx));
+            }
+            self.stalled = None;
+        }
+        // no hardware timing, so the stream is started on the host clock
+        let deadline = time_ns.map(crate::time::deadline);
+        if let Some(deadline) = deadline {
+            crate::time::wait_until(deadline);
+        }
+        let inner = self.inner.clone();
+        let usb = self.usb;
+        let mut worker = None;
+        self.inner.start(Direction::Rx, || {
+            let config = inner.rx_config.lock().unwrap();
+            let r = inner
+                .dev
+                .start_rx(&config)
+                .map_err(Error::from)
+                .and_then(|_| inner.apply_bandwidth(Direction::Rx))
+                .and_then(|_| Ok(inner.dev.start_rx_stream(usb.transfer_size)?))
+                .and_then(|mut stream| {
+                    UsbReader::spawn("hackrf-rx", usb.transfers, move |buf| {
+                        buf.extend_from_slice(stream.read_sync(usb.transfer_size)?);
+                        Ok(())
+                    })
+                });
+            match r {
+                Ok(w) => {
+                    worker = Some(w);
+                    *inner.applied(Direction::Rx).lock().unwrap() = Some(config.clone());
+                    Ok(())
+                }
+                Err(e) => {
+                    let _ = inner.dev.stop_rx();
+                    Err(e)
+                }
+            }
+        })?;
+        self.worker = worker;
+        self.activation_error_ns = deadline.map(crate::time::late_ns);
+        self.canceller.reset();
+        self.buf.clear();
+        self.offset = 0;
+        self.position = 0;
 
         Ok(())
     }
 
-    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        // TODO: sleep precisely for `time_ns`
-
-        let _ = self.stream.take().unwrap();
-        self.inner.dev.stop_rx()?;
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if let Some(time_ns) = time_ns {
+            crate::time::wait_until(crate::time::deadline(time_ns));
+        }
+        if let Some(mut worker) = self.worker.take() {
+            // the pending transfer only completes while the device is streaming
+            let stopped = worker.stop(self.usb.timeout.unwrap_or(STOP_TIMEOUT));
+            self.inner.applied(Direction::Rx).lock().unwrap().take();
+            let r = self.inner.dev.stop_rx();
+            self.inner.mode.release(Direction::Rx);
+            if !stopped {
+                self.stalled = Some(worker);
+                return Err(Error::Timeout);
+            }
+            r?;
+        }
         Ok(())
     }
 
     fn read(
         &mut self,
         buffers: &mut [&mut [num_complex::Complex32]],
-        _timeout_us: i64,
+        timeout_us: i64,
     ) -> Result<usize, Error> {
-        debug_assert_eq!(buffers.len(), 1);
-
         if buffers[0].is_empty() {
             return Ok(0);
         }
-        let buf = self.stream.as_mut().unwrap().read_sync(buffers[0].len())?;
+        if self.buf.len() - self.offset < 2 {
+            self.next_transfer(timeout_us)?;
+        }
 
-        let samples = buf.len() / 2;
-        for i in 0..samples {
-            buffers[0][i] = Complex32::new(
-                (buf[i * 2] as f32 - 127.0) / 128.0,
-                (buf[i * 2 + 1] as f32 - 127.0) / 128.0,
-            );
+        let buf = &self.buf[self.offset..];
+        let samples = std::cmp::min(buffers[0].len(), buf.len() / 2);
+        // the HackRF delivers signed 8-bit samples
+        let scale = self.scale / 128.0;
+        for (s, iq) in buffers[0][..samples].iter_mut().zip(buf.chunks_exact(2)) {
+            *s = Complex32::new(iq[0] as i8 as f32 * scale, iq[1] as i8 as f32 * scale);
+        }
+        self.offset += samples * 2;
+        self.position += samples as u64;
+        Ok(samples)
+    }
+
+    fn native_format(&self) -> StreamFormat {
+        StreamFormat::Cs8
+    }
+
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if format != StreamFormat::Cs8 {
+            return crate::sample::read_converted(self, format, buffers, timeout_us);
+        }
+        if buffers[0].len() < 2 {
+            return Ok(0);
         }
+        if self.buf.len() - self.offset < 2 {
+            self.next_transfer(timeout_us)?;
+        }
+
+        // raw samples of the ADC, the output scale only applies to converted samples
+        let buf = &self.buf[self.offset..];
+        let samples = std::cmp::min(buffers[0].len(), buf.len()) / 2;
+        buffers[0][..samples * 2].copy_from_slice(&buf[..samples * 2]);
+        self.offset += samples * 2;
+        self.position += samples as u64;
         Ok(samples)
     }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        Ok(BufferConfig {
+            mtu: self.usb.transfer_size / 2,
+            granularity: self.usb.transfer_size / 2,
+        })
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.activation_error_ns.ok_or(Error::NotFound)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let worker = self.worker.as_ref().ok_or(Error::Inactive)?;
+        // the transfers are only dropped, so the sample position stays exact
+        let mut dropped = self.buf.len().saturating_sub(self.offset) / 2;
+        self.offset = self.buf.len();
+        while let Some(buf) = worker.try_recv() {
+            let buf = buf?;
+            dropped += buf.len() / 2;
+            worker.recycle(buf);
+        }
+        self.position += dropped as u64;
+        Ok(())
+    }
 }
 
 pub struct TxStreamer {
     inner: Arc<HackRfInner>,
+    usb: UsbConfig,
+    active: bool,
+    // samples staged for the next transfer
+    buf: Vec<u8>,
+    scale: f32,
+    // start of a timed activation, deferred until the first transfer is staged
+    start_at: Option<Instant>,
+    activation_error_ns: Option<i64>,
+    canceller: StreamCanceller,
 }
 
 impl TxStreamer {
-    fn new(inner: Arc<HackRfInner>) -> Self {
-        Self { inner }
+    fn new(inner: Arc<HackRfInner>, usb: UsbConfig) -> Self {
+        Self {
+            inner,
+            usb,
+            active: false,
+            buf: Vec::with_capacity(usb.transfer_size),
+            scale: 1.0,
+            start_at: None,
+            activation_error_ns: None,
+            canceller: StreamCanceller::new(),
+        }
+    }
+
+    fn start(&self) -> Result<(), Error> {
+        let config = self.inner.tx_config.lock().unwrap();
+        self.inner.dev.start_tx(&config)?;
+        *self.inner.applied(Direction::Tx).lock().unwrap() = Some(config.clone());
+        self.inner.apply_bandwidth(Direction::Tx)
+    }
+
+    /// Transmit the staged samples, padding them with zeros to a full USB packet.
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        if let Some(deadline) = self.start_at {
+            // sleep in slices, so that a cancelled stream does not wait for the start time
+            while deadline.saturating_duration_since(Instant::now()) > POLL_INTERVAL {
+                if self.canceller.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            self.start_at = None;
+            crate::time::wait_until(deadline);
+            self.start()?;
+            self.activation_error_ns = Some(crate::time::late_ns(deadline));
+        }
+        let len = self.buf.len().next_multiple_of(USB_PACKET);
+        self.buf.resize(len, 0);
+        let n = self.inner.dev.write(&self.buf);
+        self.buf.clear();
+        if n? != len {
+            return Err(Error::DeviceError);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TxStreamer {
+    fn drop(&mut self) {
+        if self.active {
+            if let Err(e) = crate::TxStreamer::deactivate(self) {
+                log::warn!("failed to stop HackRF TX stream on drop: {e}");
+            }
+        }
     }
 }
 
 impl crate::TxStreamer for TxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
-        Ok(MTU)
+        Ok(self.usb.transfer_size / 2)
     }
 
-    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        // TODO: sleep precisely for `time_ns`
-
-        let config = self.inner.tx_config.lock().unwrap();
-        self.inner.dev.start_rx(&config)?;
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if self.active {
+            return Err(Error::Busy(Direction::Tx));
+        }
+        // without hardware timing, the samples are buffered and the first transfer waits for the
+        // start time on the host clock. The mode is switched right away, so that RX cannot start
+        // in between.
+        self.inner.start(Direction::Tx, || match time_ns {
+            Some(_) => Ok(()),
+            None => self.start(),
+        })?;
+        self.start_at = time_ns.map(crate::time::deadline);
+        self.activation_error_ns = None;
+        self.active = true;
+        self.buf.clear();
+        self.canceller.reset();
 
         Ok(())
     }
 
-    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        // TODO: sleep precisely for `time_ns`
-
-        self.inner.dev.stop_tx()?;
-        Ok(())
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if !self.active {
+            return Ok(());
+        }
+        self.active = false;
+        let r = self.flush();
+        if let Some(time_ns) = time_ns {
+            crate::time::wait_until(crate::time::deadline(time_ns));
+        }
+        // the transmitter was never started, if nothing was written after a timed activation
+        self.inner.applied(Direction::Tx).lock().unwrap().take();
+        let stopped = match self.start_at.take() {
+            None => self.inner.dev.stop_tx().map_err(Error::from),
+            Some(_) => Ok(()),
+        };
+        self.inner.mode.release(Direction::Tx);
+        stopped?;
+        r
     }
 
     fn write(
         &mut self,
         buffers: &[&[num_complex::Complex32]],
         _at_ns: Option<i64>,
-        _end_burst: bool,
+        end_burst: bool,
         _timeout_us: i64,
     ) -> Result<usize, Error> {
-        debug_assert_eq!(buffers.len(), 1);
-        todo!();
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        // the device only accepts whole USB packets, so samples are staged until a transfer is
+        // full or the burst ends
+        let samples = std::cmp::min(
+            buffers[0].len(),
+            (self.usb.transfer_size - self.buf.len()) / 2,
+        );
+        let scale = self.scale * 128.0;
+        for s in &buffers[0][..samples] {
+            self.buf
+                .push((s.re * scale).round().clamp(-128.0, 127.0) as i8 as u8);
+            self.buf
+                .push((s.im * scale).round().clamp(-128.0, 127.0) as i8 as u8);
+        }
+        if self.buf.len() == self.usb.transfer_size || (end_burst && samples == buffers[0].len()) {
+            self.flush()?;
+        }
+        Ok(samples)
+    }
+
+    fn native_format(&self) -> StreamFormat {
+        StreamFormat::Cs8
+    }
 
-        // self.inner.dev.write(samples)
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if format != StreamFormat::Cs8 {
+            return crate::sample::write_converted(
+                self, format, buffers, at_ns, end_burst, timeout_us,
+            );
+        }
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        // raw samples for the DAC, the input scale only applies to converted samples
+        let len = buffers[0].len() / 2;
+        let samples = std::cmp::min(len, (self.usb.transfer_size - self.buf.len()) / 2);
+        self.buf.extend_from_slice(&buffers[0][..samples * 2]);
+        if self.buf.len() == self.usb.transfer_size || (end_burst && samples == len) {
+            self.flush()?;
+        }
+        Ok(samples)
     }
 
     fn write_all(
         &mut self,
         buffers: &[&[num_complex::Complex32]],
         _at_ns: Option<i64>,
-        _end_burst: bool,
-        _timeout_us: i64,
+        end_burst: bool,
+        timeout_us: i64,
     ) -> Result<(), Error> {
-        debug_assert_eq!(buffers.len(), 1);
-
+        // `timeout_us` bounds the whole call and is checked between transfers
+        let deadline =
+            (timeout_us >= 0).then(|| Instant::now() + Duration::from_micros(timeout_us as u64));
         let mut n = 0;
         while n < buffers[0].len() {
             let buf = &buffers[0][n..];
-            n += self.write(&[buf], None, false, 0)?;
+            n += self.write(&[buf], None, end_burst, 0)?;
+            if n < buffers[0].len() && deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err(Error::Timeout);
+            }
         }
 
         Ok(())
     }
+
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.activation_error_ns.ok_or(Error::NotFound)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
 }
 
 impl crate::DeviceTrait for HackRfOne {
@@ -240,6 +1093,13 @@ impl crate::DeviceTrait for HackRfOne {
         Ok(args)
     }
 
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(vec![ReferenceDoc::new(
+            "HackRF gain controls",
+            "https://hackrf.readthedocs.io/en/latest/faq.html#what-gain-controls-are-provided-by-hackrf",
+        )])
+    }
+
     fn num_channels(&self, _: crate::Direction) -> Result<usize, Error> {
         Ok(1)
     }
@@ -248,19 +1108,29 @@ impl crate::DeviceTrait for HackRfOne {
         Ok(false)
     }
 
-    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        // RX and TX use the same synthesizers
+        Ok(vec![CoherencyGroup::new(
+            vec![(Direction::Rx, 0), (Direction::Tx, 0)],
+            true,
+        )])
+    }
+
+    fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
         if channels != [0] {
             Err(Error::ValueError)
         } else {
-            Ok(RxStreamer::new(Arc::clone(&self.inner)))
+            let usb = self.inner.usb_config(&args)?;
+            Ok(RxStreamer::new(Arc::clone(&self.inner), usb))
         }
     }
 
-    fn tx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
+    fn tx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::TxStreamer, Error> {
         if channels != [0] {
             Err(Error::ValueError)
         } else {
-            Ok(TxStreamer::new(Arc::clone(&self.inner)))
+            let usb = self.inner.usb_config(&args)?;
+            Ok(TxStreamer::new(Arc::clone(&self.inner), usb))
         }
     }
 
@@ -298,9 +1168,9 @@ impl crate::DeviceTrait for HackRfOne {
             // TODO: add support for other gains (RF and baseband)
             // See: https://hackrf.readthedocs.io/en/latest/faq.html#what-gain-controls-are-provided-by-hackrf
             match direction {
-                Direction::Tx => Ok(vec!["IF".into()]),
+                Direction::Tx => Ok(vec!["IF".into(), "AMP".into()]),
                 // TODO: add rest
-                Direction::Rx => Ok(vec!["IF".into()]),
+                Direction::Rx => Ok(vec!["IF".into(), "AMP".into()]),
             }
         } else {
             Err(Error::ValueError)
@@ -333,6 +1203,7 @@ impl crate::DeviceTrait for HackRfOne {
 
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
         self.set_gain_element(direction, channel, "IF", gain)
+            .map(drop)
     }
 
     fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
@@ -349,21 +1220,24 @@ impl crate::DeviceTrait for HackRfOne {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
-        let r = self.gain_range(direction, channel)?;
+    ) -> Result<f64, Error> {
+        let r = self.gain_element_range(direction, channel, name)?;
         if r.contains(gain) && name == "IF" {
-            match direction {
-                Direction::Tx => todo!(),
-                Direction::Rx => {
-                    let mut config = self.inner.rx_config.lock().unwrap();
-                    config.lna_db = gain as u16;
-                    Ok(())
-                }
-            }
+            self.update_config(direction, |config| match direction {
+                Direction::Tx => config.txvga_db = gain as u16,
+                Direction::Rx => config.lna_db = gain as u16,
+            })?;
+        } else if r.contains(gain) && name == "AMP" {
+            self.update_config(direction, |config| config.amp_enable = gain > 0.0)?;
         } else {
             log::warn!("Gain out of range");
-            Err(Error::OutOfRange(r, gain))
+            return Err(Error::OutOfRange(r, gain));
         }
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
     }
 
     fn gain_element(
@@ -373,13 +1247,14 @@ impl crate::DeviceTrait for HackRfOne {
         name: &str,
     ) -> Result<Option<f64>, Error> {
         if channel == 0 && name == "IF" {
-            match direction {
-                Direction::Tx => todo!(),
-                Direction::Rx => {
-                    let config = self.inner.rx_config.lock().unwrap();
-                    Ok(Some(config.lna_db as f64))
-                }
-            }
+            self.inner.current(direction, |config| match direction {
+                Direction::Tx => Ok(Some(config.txvga_db as f64)),
+                Direction::Rx => Ok(Some(config.lna_db as f64)),
+            })
+        } else if channel == 0 && name == "AMP" {
+            self.inner.current(direction, |config| {
+                Ok(Some(if config.amp_enable { AMP_GAIN } else { 0.0 }))
+            })
         } else {
             Err(Error::ValueError)
         }
@@ -397,11 +1272,45 @@ impl crate::DeviceTrait for HackRfOne {
                 Direction::Tx => Ok(Range::new(vec![RangeItem::Step(0.0, 47.0, 1.0)])),
                 Direction::Rx => Ok(Range::new(vec![RangeItem::Step(0.0, 40.0, 8.0)])),
             }
+        } else if channel == 0 && name == "AMP" {
+            Ok(Range::new(vec![
+                RangeItem::Value(0.0),
+                RangeItem::Value(AMP_GAIN),
+            ]))
         } else {
             Err(Error::ValueError)
         }
     }
 
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        match (direction, channel, name) {
+            (Direction::Rx, 0, "IF") => Ok(GainElementInfo::new(
+                name,
+                "LNA",
+                "dB",
+                "Gain of the IF low-noise amplifier of the MAX2837, in steps of 8 dB.",
+            )),
+            (Direction::Tx, 0, "IF") => Ok(GainElementInfo::new(
+                name,
+                "TX VGA",
+                "dB",
+                "Gain of the TX variable-gain amplifier of the MAX2837, in steps of 1 dB.",
+            )),
+            (_, 0, "AMP") => Ok(GainElementInfo::new(
+                name,
+                "RF amplifier",
+                "dB",
+                "RF amplifier, off at 0 dB and on at 14 dB.",
+            )),
+            _ => Err(Error::ValueError),
+        }
+    }
+
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         self.component_frequency_range(direction, channel, "TUNER")
     }
@@ -426,7 +1335,11 @@ impl crate::DeviceTrait for HackRfOne {
         channel: usize,
     ) -> Result<Vec<String>, Error> {
         if channel == 0 {
-            Ok(vec!["TUNER".to_string()])
+            Ok(vec![
+                "TUNER".to_string(),
+                "LO".to_string(),
+                "IF".to_string(),
+            ])
         } else {
             Err(Error::ValueError)
         }
@@ -438,11 +1351,24 @@ impl crate::DeviceTrait for HackRfOne {
         channel: usize,
         name: &str,
     ) -> Result<Range, Error> {
-        if channel == 0 && name == "TUNER" {
-            // up to 7.25GHz
-            Ok(Range::new(vec![RangeItem::Interval(0.0, 7_270_000_000.0)]))
-        } else {
-            Err(Error::ValueError)
+        match (channel, name) {
+            // one interval per signal path, see `SignalPath`
+            (0, "TUNER") => Ok(Range::new(
+                SignalPath::RANGES
+                    .iter()
+                    .map(|(_, start, stop)| RangeItem::Interval(*start, *stop))
+                    .collect(),
+            )),
+            // 0 Hz, if the mixer is bypassed
+            (0, "LO") => Ok(Range::new(vec![
+                RangeItem::Value(0.0),
+                RangeItem::Interval(MIXER_LO.0, MIXER_LO.1),
+            ])),
+            (0, "IF") => Ok(Range::new(vec![RangeItem::Interval(
+                TRANSCEIVER.0,
+                TRANSCEIVER.1,
+            )])),
+            _ => Err(Error::ValueError),
         }
     }
 
@@ -452,10 +1378,18 @@ impl crate::DeviceTrait for HackRfOne {
         channel: usize,
         name: &str,
     ) -> Result<f64, Error> {
-        if channel == 0 && name == "TUNER" {
-            self.with_config(direction, |config| Ok(config.frequency_hz as f64))
-        } else {
-            Err(Error::ValueError)
+        if channel != 0 || !["TUNER", "LO", "IF"].contains(&name) {
+            return Err(Error::ValueError);
+        }
+        let frequency = self
+            .inner
+            .current(direction, |config| config.frequency_hz as f64);
+        // the firmware selects the signal path and the mixer frequency for the tuner frequency
+        let plan = SignalPath::plan(frequency).ok_or(Error::ValueError)?;
+        match name {
+            "LO" => Ok(plan.lo),
+            "IF" => Ok(plan.intermediate),
+            _ => Ok(frequency),
         }
     }
 
@@ -466,27 +1400,38 @@ impl crate::DeviceTrait for HackRfOne {
         name: &str,
         frequency: f64,
     ) -> Result<(), Error> {
-        if channel == 0
-            && self
-                .frequency_range(direction, channel)?
-                .contains(frequency)
-            && name == "TUNER"
-        {
-            self.with_config(direction, |config| {
-                config.frequency_hz = frequency as u64;
-                self.inner.dev.set_freq(frequency as u64)?;
-                Ok(())
-            })
+        match (channel, name) {
+            (0, "TUNER")
+                if self
+                    .frequency_range(direction, channel)?
+                    .contains(frequency) =>
+            {
+                // the firmware takes whole Hz
+                self.update_config(direction, |config| {
+                    config.frequency_hz = frequency.round() as u64
+                })
+            }
+            // planned by the firmware
+            (0, "LO" | "IF") => Err(Error::NotSupported),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn spurs(&self, _direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        if channel == 0 {
+            // LO leakage of the zero-IF MAX2837 and harmonics of its 40 MHz reference
+            Ok(vec![Spur::LoOffset(0.0), Spur::Harmonics(40e6)])
         } else {
             Err(Error::ValueError)
         }
     }
 
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
-        // NOTE: same state for both "directions" lets hope future sdr doesnt assume there are two
-        // values here, should be fine since we told it we're not full duplex
+        // the rate of a direction that does not stream is applied when it is activated
         if channel == 0 {
-            self.with_config(direction, |config| Ok(config.sample_rate_hz as f64))
+            self.inner.current(direction, |config| {
+                Ok(config.sample_rate_hz as f64 / config.sample_rate_div as f64)
+            })
         } else {
             Err(Error::ValueError)
         }
@@ -503,12 +1448,14 @@ impl crate::DeviceTrait for HackRfOne {
                 .get_sample_rate_range(direction, channel)?
                 .contains(rate)
         {
-            self.with_config(direction, |config| {
-                // TODO: use sample rate div to enable lower effective sampling rate
-                config.sample_rate_hz = rate as u32;
-                config.sample_rate_div = 1;
-            });
-            Ok(())
+            let (hz, div) = sample_clock(rate).ok_or(Error::ValueError)?;
+            if self.inner.auto_baseband_filter {
+                self.inner.bandwidth(direction).lock().unwrap().take();
+            }
+            self.update_config(direction, |config| {
+                config.sample_rate_hz = hz;
+                config.sample_rate_div = div;
+            })
         } else {
             Err(Error::ValueError)
         }
@@ -516,24 +1463,242 @@ impl crate::DeviceTrait for HackRfOne {
 
     fn get_sample_rate_range(&self, _direction: Direction, channel: usize) -> Result<Range, Error> {
         if channel == 0 {
-            Ok(Range::new(vec![RangeItem::Interval(
-                1_000_000.0,
-                20_000_000.0,
-            )]))
+            let (min, max) = SAMPLE_CLOCK;
+            Ok(Range::new(vec![
+                // divided sample clock
+                RangeItem::Step(
+                    min.div_ceil(MAX_SAMPLE_RATE_DIV) as f64,
+                    (min - 1) as f64,
+                    1.0,
+                ),
+                // sample clock
+                RangeItem::Step(min as f64, max as f64, 1.0),
+            ]))
         } else {
             Err(Error::ValueError)
         }
     }
 
-    fn bandwidth(&self, _direction: Direction, _channel: usize) -> Result<f64, Error> {
-        Err(Error::NotSupported)
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        if channel == 0 {
+            match *self.inner.bandwidth(direction).lock().unwrap() {
+                Some(bw) => Ok(bw as f64),
+                None => Ok(auto_bandwidth(self.sample_rate(direction, channel)?) as f64),
+            }
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
+        if channel == 0 && self.get_bandwidth_range(direction, channel)?.contains(bw) {
+            // the filter is shared, so it is only written while the direction streams
+            let streaming = self.inner.applied(direction).lock().unwrap().is_some();
+            let mut bandwidth = self.inner.bandwidth(direction).lock().unwrap();
+            if streaming {
+                self.inner.dev.set_baseband_filter_bandwidth(bw as u32)?;
+            }
+            *bandwidth = Some(bw as u32);
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn get_bandwidth_range(&self, _direction: Direction, channel: usize) -> Result<Range, Error> {
+        if channel == 0 {
+            Ok(Range::new(
+                BASEBAND_FILTERS
+                    .iter()
+                    .map(|bw| RangeItem::Value(*bw as f64))
+                    .collect(),
+            ))
+        } else {
+            Err(Error::ValueError)
+        }
     }
 
-    fn set_bandwidth(&self, _direction: Direction, _channel: usize, bw: f64) -> Result<(), Error> {
-        Ok(self.inner.dev.set_baseband_filter_bandwidth(bw as _)?)
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
     }
 
-    fn get_bandwidth_range(&self, _direction: Direction, _channel: usize) -> Result<Range, Error> {
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
         Err(Error::NotSupported)
     }
+
+    /// The HackRF is bus powered and cannot be switched off. `Standby` and `Off` both turn off
+    /// the transceiver, the RF amplifier, and antenna power, until a stream is activated again.
+    /// Disabling the UI (LEDs) is not exposed by the USB library.
+    fn set_power_state(&self, state: PowerState) -> Result<(), Error> {
+        let mut power = self.inner.power.lock().unwrap();
+        if state == *power {
+            return Ok(());
+        }
+        if state != PowerState::On {
+            if let Mode::Streaming(d) = self.inner.mode.mode() {
+                log::warn!("HackRfOne: deactivate streams before powering down");
+                return Err(Error::Busy(d));
+            }
+            self.inner.dev.set_amp_enable(false)?;
+            self.inner.dev.set_antenna_enable(0)?;
+            self.inner.dev.stop_rx()?;
+        }
+        *power = state;
+        Ok(())
+    }
+
+    fn power_state(&self) -> Result<PowerState, Error> {
+        Ok(*self.inner.power.lock().unwrap())
+    }
+}
+
+impl crate::RxInterface for HackRfOne {}
+impl crate::TxInterface for HackRfOne {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_paths() {
+        assert_eq!(SignalPath::for_frequency(100e6), Some(SignalPath::LowPass));
+        assert_eq!(SignalPath::for_frequency(2.17e9), Some(SignalPath::Bypass));
+        assert_eq!(SignalPath::for_frequency(2.45e9), Some(SignalPath::Bypass));
+        assert_eq!(SignalPath::for_frequency(5.8e9), Some(SignalPath::HighPass));
+        assert_eq!(
+            SignalPath::for_frequency(7.27e9),
+            Some(SignalPath::HighPass)
+        );
+        assert_eq!(SignalPath::for_frequency(8e9), None);
+
+        // high-side LO below the bypass path, low-side LO above it
+        let plan = SignalPath::plan(100e6).unwrap();
+        assert_eq!((plan.lo, plan.intermediate), (2735e6, 2635e6));
+        let plan = SignalPath::plan(2.45e9).unwrap();
+        assert_eq!((plan.lo, plan.intermediate), (0.0, 2.45e9));
+        let plan = SignalPath::plan(5.8e9).unwrap();
+        assert_eq!(plan.path, SignalPath::HighPass);
+        assert_eq!((plan.lo, plan.intermediate), (3223e6, 2577e6));
+    }
+
+    #[test]
+    fn sample_clock_divider() {
+        assert_eq!(sample_clock(10e6), Some((10_000_000, 1)));
+        assert_eq!(sample_clock(1e6), Some((1_000_000, 1)));
+        assert_eq!(sample_clock(250e3), Some((1_000_000, 4)));
+        assert_eq!(sample_clock(48e3), Some((1_008_000, 21)));
+        assert_eq!(sample_clock(31_250.0), Some((1_000_000, 32)));
+        assert_eq!(sample_clock(31_249.0), None);
+        assert_eq!(sample_clock(100_000.5), None);
+        assert_eq!(sample_clock(21e6), None);
+    }
+
+    #[test]
+    fn safety_checks() {
+        let config = Config {
+            vga_db: 0,
+            txvga_db: 20,
+            lna_db: 0,
+            amp_enable: true,
+            antenna_enable: false,
+            frequency_hz: 2_400_000_000,
+            sample_rate_hz: 10_000_000,
+            sample_rate_div: 1,
+        };
+        assert!(check_safety(Direction::Tx, &config).is_ok());
+        let high = Config {
+            frequency_hz: 6_500_000_000,
+            ..config.clone()
+        };
+        assert!(check_safety(Direction::Rx, &high).is_err());
+        let maxed = Config {
+            txvga_db: MAX_TX_GAIN,
+            ..config.clone()
+        };
+        assert!(check_safety(Direction::Tx, &maxed).is_err());
+        assert!(check_safety(Direction::Rx, &maxed).is_ok());
+        let bias = Config {
+            antenna_enable: true,
+            ..config
+        };
+        assert!(check_safety(Direction::Tx, &bias).is_err());
+        assert!(check_safety(Direction::Rx, &bias).is_ok());
+    }
+
+    #[test]
+    fn mode_switches() {
+        let mode = ModeManager::new(None);
+        mode.acquire(Direction::Rx).unwrap();
+        assert!(matches!(
+            mode.acquire(Direction::Tx),
+            Err(Error::Busy(Direction::Rx))
+        ));
+        assert!(matches!(
+            mode.acquire(Direction::Rx),
+            Err(Error::Busy(Direction::Rx))
+        ));
+        mode.release(Direction::Tx);
+        assert_eq!(mode.mode(), Mode::Streaming(Direction::Rx));
+        mode.release(Direction::Rx);
+        mode.acquire(Direction::Tx).unwrap();
+
+        // queued switch, once the other direction stops
+        let mode = Arc::new(ModeManager::new(Some(Duration::from_secs(5))));
+        mode.acquire(Direction::Rx).unwrap();
+        let queued = std::thread::spawn({
+            let mode = mode.clone();
+            move || mode.acquire(Direction::Tx)
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        mode.release(Direction::Rx);
+        queued.join().unwrap().unwrap();
+        assert_eq!(mode.mode(), Mode::Streaming(Direction::Tx));
+    }
+
+    #[test]
+    fn dirty_settings() {
+        let applied = Config {
+            vga_db: 20,
+            txvga_db: 0,
+            lna_db: 16,
+            amp_enable: false,
+            antenna_enable: false,
+            frequency_hz: 100_000_000,
+            sample_rate_hz: 10_000_000,
+            sample_rate_div: 1,
+        };
+        assert_eq!(Dirty::new(&applied, &applied), Dirty::default());
+        let config = Config {
+            frequency_hz: 433_000_000,
+            sample_rate_div: 2,
+            lna_db: 24,
+            ..applied.clone()
+        };
+        assert_eq!(
+            Dirty::new(&applied, &config),
+            Dirty {
+                frequency: true,
+                sample_rate: true,
+                lna: true,
+                ..Dirty::default()
+            }
+        );
+    }
+
+    #[test]
+    fn baseband_filter() {
+        assert_eq!(auto_bandwidth(20e6), 15_000_000);
+        assert_eq!(auto_bandwidth(10e6), 7_000_000);
+        assert_eq!(auto_bandwidth(2e6), 1_750_000);
+        assert_eq!(auto_bandwidth(250e3), 1_750_000);
+    }
 }
@@ -0,0 +1,1484 @@
+//! OpenHPSDR protocol 1 (Metis/Hermes) client
+use num_complex::Complex32;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Direction::*;
+use crate::Driver;
+use crate::Error;
+use crate::GainElementInfo;
+use crate::Range;
+use crate::RangeItem;
+use crate::ReferenceDoc;
+use crate::RxFlags;
+use crate::RxMetadata;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
+
+/// UDP port of the radio.
+const PORT: u16 = 1024;
+/// Packet types following the `EF FE` header.
+const DATA: u8 = 0x01;
+const DISCOVER: u8 = 0x02;
+const DISCOVER_BUSY: u8 = 0x03;
+const START_STOP: u8 = 0x04;
+/// Endpoints of data packets, host to radio and radio to host.
+const EP2: u8 = 0x02;
+const EP6: u8 = 0x06;
+/// A data packet carries two USB frames after an 8 byte header.
+const PACKET: usize = 1032;
+const FRAME: usize = 512;
+const SYNC: [u8; 3] = [0x7f; 3];
+/// TX samples per data packet.
+const TX_SAMPLES: usize = 126;
+/// The TX stream always runs at 48 kHz, independent of the RX rate.
+const TX_RATE: f64 = 48000.0;
+/// RX rates, selected by their index.
+const RATES: [f64; 4] = [48000.0, 96000.0, 192000.0, 384000.0];
+/// Time to wait for replies to a discovery broadcast.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(200);
+/// Interval, in which blocking socket calls check for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// TX samples sent ahead of the host clock to bridge scheduling jitter.
+const TX_LEAD: u64 = 1024;
+/// Output levels of the TX DAC are mapped to dB below full scale.
+const DRIVE_RANGE: (f64, f64) = (-48.0, 0.0);
+
+/// Addresses of the command and control registers (C0 without the MOX bit).
+mod reg {
+    pub const CONFIG: u8 = 0x00;
+    pub const TX_FREQUENCY: u8 = 0x02;
+    /// RX frequencies follow for each receiver.
+    pub const RX_FREQUENCY: u8 = 0x04;
+    pub const DRIVE: u8 = 0x12;
+    pub const ATTENUATOR: u8 = 0x14;
+}
+
+/// Radio speaking the OpenHPSDR protocol 1, e.g., a Hermes, ANAN, or Hermes-Lite 2.
+///
+/// Samples and the command and control registers are exchanged in UDP packets. The host cycles
+/// through the registers in the packets it sends, so settings are applied with the next packet,
+/// also while streaming.
+#[derive(Clone)]
+pub struct Hpsdr {
+    radio: Arc<Radio>,
+}
+
+/// Socket and settings of a radio, shared with the streamers.
+struct Radio {
+    socket: UdpSocket,
+    discovery: Discovery,
+    state: Mutex<State>,
+}
+
+/// Settings of the radio and the streams using them.
+struct State {
+    settings: Settings,
+    /// Registers to send with the next packets.
+    pending: VecDeque<u8>,
+    /// Position in the cycle through all registers.
+    cycle: usize,
+    sequence: u32,
+    running: bool,
+    rx: bool,
+    tx: bool,
+}
+
+/// Values of the command and control registers.
+#[derive(Clone, Debug, PartialEq)]
+struct Settings {
+    board: Board,
+    /// Index into [`RATES`].
+    rate: usize,
+    /// Number of receivers in the RX stream.
+    receivers: usize,
+    rx_frequency: Vec<u32>,
+    tx_frequency: u32,
+    /// LNA gain of a Hermes-Lite or negative attenuation of other boards in dB.
+    gain: i32,
+    drive: f64,
+    mox: bool,
+}
+
+/// Board ID reported in discovery replies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Board {
+    Metis,
+    Hermes,
+    Griffin,
+    Angelia,
+    Orion,
+    HermesLite,
+    OrionMk2,
+    Unknown(u8),
+}
+
+/// Reply of a radio to a discovery request.
+#[derive(Clone, Debug, PartialEq)]
+struct Discovery {
+    addr: SocketAddr,
+    mac: [u8; 6],
+    firmware: u8,
+    board: Board,
+    receivers: usize,
+    busy: bool,
+}
+
+/// HPSDR RX streamer
+pub struct RxStreamer {
+    radio: Arc<Radio>,
+    channels: Vec<usize>,
+    receivers: usize,
+    active: bool,
+    packet: Vec<u8>,
+    /// Samples of the last packet per receiver.
+    samples: Vec<Vec<Complex32>>,
+    // unread samples of the last packet
+    start: usize,
+    next_sequence: Option<u32>,
+    position: u64,
+    /// Packets were dropped before the samples of the last read.
+    overflow: bool,
+    /// TX samples due to keep the command and control registers flowing.
+    credit: f64,
+    canceller: StreamCanceller,
+    events: StreamEvents,
+    scale: f32,
+}
+
+/// HPSDR TX streamer
+pub struct TxStreamer {
+    radio: Arc<Radio>,
+    active: bool,
+    buffer: Vec<Complex32>,
+    /// Start of the host clock, pacing the stream, and the samples sent since.
+    clock: Option<(Instant, u64)>,
+    canceller: StreamCanceller,
+    scale: f32,
+}
+
+impl Hpsdr {
+    /// Discover radios with a broadcast or at the given `addr`.
+    ///
+    /// Radios in use by another host are skipped.
+    pub fn probe(args: &Args) -> Result<Vec<Args>, Error> {
+        Ok(find(args)?
+            .into_iter()
+            .filter(|d| {
+                if d.busy {
+                    log::debug!("hpsdr: radio at {} is in use", d.addr);
+                }
+                !d.busy
+            })
+            .map(|d| d.args())
+            .collect())
+    }
+
+    /// Connect to a radio.
+    ///
+    /// - `addr`: address of the radio, skipping the discovery broadcast.
+    /// - `mac`: MAC address of the radio, to pick one of several radios on the network.
+    pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        let discovery = find(&args)?.into_iter().next().ok_or(Error::NotFound)?;
+        if discovery.busy {
+            return Err(Error::Misc(format!(
+                "hpsdr: radio at {} is in use by another host",
+                discovery.addr
+            )));
+        }
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.connect(discovery.addr)?;
+        let settings = Settings {
+            board: discovery.board,
+            rate: 0,
+            receivers: 1,
+            rx_frequency: vec![0; discovery.receivers],
+            tx_frequency: 0,
+            gain: 0,
+            drive: DRIVE_RANGE.0,
+            mox: false,
+        };
+        Ok(Self {
+            radio: Arc::new(Radio {
+                socket,
+                discovery,
+                state: Mutex::new(State {
+                    settings,
+                    pending: VecDeque::new(),
+                    cycle: 0,
+                    sequence: 0,
+                    running: false,
+                    rx: false,
+                    tx: false,
+                }),
+            }),
+        })
+    }
+
+    fn check(&self, direction: Direction, channel: usize) -> Result<(), Error> {
+        let channels = self.num_channels(direction)?;
+        if channel < channels {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        self.radio.state.lock().unwrap().settings.clone()
+    }
+
+    fn gain_element_name(&self, direction: Direction) -> &'static str {
+        match (direction, self.radio.discovery.board) {
+            (Tx, _) => "DRIVE",
+            (Rx, Board::HermesLite) => "LNA",
+            (Rx, _) => "ATT",
+        }
+    }
+
+    /// Highest frequency of the direct sampling receivers, i.e., half of the ADC rate.
+    fn max_frequency(&self) -> f64 {
+        match self.radio.discovery.board {
+            Board::HermesLite => 38.4e6,
+            _ => 61.44e6,
+        }
+    }
+}
+
+/// Resolve `host[:port]`, defaulting to the port of the protocol.
+fn resolve(addr: &str) -> Result<SocketAddr, Error> {
+    let resolved = match addr.to_socket_addrs() {
+        Ok(mut a) => a.next(),
+        Err(_) => (addr, PORT).to_socket_addrs()?.next(),
+    };
+    resolved.ok_or(Error::NotFound)
+}
+
+/// Discover the radios matching the `addr` and `mac` arguments.
+fn find(args: &Args) -> Result<Vec<Discovery>, Error> {
+    let radios = match args.get::<String>("addr") {
+        Ok(addr) => discover(resolve(&addr)?, true)?,
+        Err(_) => discover((Ipv4Addr::BROADCAST, PORT).into(), false)?,
+    };
+    Ok(match args.get::<String>("mac") {
+        Ok(mac) => radios
+            .into_iter()
+            .filter(|d| d.mac_string().eq_ignore_ascii_case(&mac))
+            .collect(),
+        Err(_) => radios,
+    })
+}
+
+/// Send a discovery request and collect the replies. A `unicast` request returns the first
+/// reply.
+fn discover(target: SocketAddr, unicast: bool) -> Result<Vec<Discovery>, Error> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    let mut request = [0; 63];
+    request[..3].copy_from_slice(&[0xef, 0xfe, DISCOVER]);
+    if let Err(e) = socket.send_to(&request, target) {
+        if unicast {
+            return Err(e.into());
+        }
+        // no network with a broadcast route, so there is nothing to discover
+        log::debug!("hpsdr: discovery broadcast failed: {e}");
+        return Ok(Vec::new());
+    }
+
+    let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+    let mut radios: Vec<Discovery> = Vec::new();
+    let mut buf = [0; 1500];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        match socket.recv_from(&mut buf) {
+            Ok((n, from)) => {
+                let Some(d) = Discovery::parse(&buf[..n], from) else {
+                    continue;
+                };
+                if !radios.iter().any(|r| r.mac == d.mac) {
+                    radios.push(d);
+                }
+                if unicast {
+                    break;
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(radios)
+}
+
+impl Board {
+    fn from_id(id: u8) -> Self {
+        match id {
+            0 => Board::Metis,
+            1 => Board::Hermes,
+            2 => Board::Griffin,
+            4 => Board::Angelia,
+            5 => Board::Orion,
+            6 => Board::HermesLite,
+            10 => Board::OrionMk2,
+            id => Board::Unknown(id),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Board::Metis => "metis".to_string(),
+            Board::Hermes => "hermes".to_string(),
+            Board::Griffin => "griffin".to_string(),
+            Board::Angelia => "angelia".to_string(),
+            Board::Orion => "orion".to_string(),
+            Board::HermesLite => "hermes-lite".to_string(),
+            Board::OrionMk2 => "orion2".to_string(),
+            Board::Unknown(id) => format!("unknown-{id}"),
+        }
+    }
+}
+
+impl Discovery {
+    fn parse(p: &[u8], from: SocketAddr) -> Option<Self> {
+        if p.len() < 11 || p[..2] != [0xef, 0xfe] || !matches!(p[2], DISCOVER | DISCOVER_BUSY) {
+            return None;
+        }
+        let board = Board::from_id(p[10]);
+        let receivers = match board {
+            // the Hermes-Lite reports the receivers of its gateware
+            Board::HermesLite if p.len() > 19 && p[19] > 0 => (p[19] as usize).min(7),
+            Board::Angelia | Board::Orion | Board::OrionMk2 => 7,
+            _ => 4,
+        };
+        Some(Self {
+            addr: from,
+            mac: p[3..9].try_into().ok()?,
+            firmware: p[9],
+            board,
+            receivers,
+            busy: p[2] == DISCOVER_BUSY,
+        })
+    }
+
+    fn mac_string(&self) -> String {
+        self.mac
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    fn args(&self) -> Args {
+        let mut args = Args::new();
+        args.set("driver", "hpsdr");
+        args.set("addr", self.addr.to_string());
+        args.set("mac", self.mac_string());
+        args.set("board", self.board.name());
+        args.set("firmware", self.firmware.to_string());
+        args
+    }
+}
+
+impl Settings {
+    /// Control bytes C0 to C4 of a register.
+    fn register(&self, address: u8) -> [u8; 5] {
+        let mut c = [address | self.mox as u8, 0, 0, 0, 0];
+        match address {
+            reg::CONFIG => {
+                c[1] = self.rate as u8;
+                if self.board == Board::Metis {
+                    // clocks and microphone from Mercury and Penelope
+                    c[1] |= 0xf8;
+                }
+                // duplex, so that RX and TX frequencies are independent
+                c[4] = 0x04 | ((self.receivers - 1) as u8) << 3;
+            }
+            reg::TX_FREQUENCY => c[1..].copy_from_slice(&self.tx_frequency.to_be_bytes()),
+            reg::DRIVE => c[1] = drive_level(self.drive),
+            reg::ATTENUATOR => {
+                c[4] = if self.board == Board::HermesLite {
+                    0x40 | (self.gain + 12) as u8
+                } else {
+                    0x20 | (-self.gain) as u8
+                }
+            }
+            a => {
+                let receiver = ((a - reg::RX_FREQUENCY) / 2) as usize;
+                c[1..].copy_from_slice(&self.rx_frequency[receiver].to_be_bytes());
+            }
+        }
+        c
+    }
+
+    /// Registers in use, which are sent in turn.
+    fn addresses(&self) -> Vec<u8> {
+        let mut a = vec![reg::CONFIG, reg::TX_FREQUENCY];
+        a.extend((0..self.receivers as u8).map(|r| reg::RX_FREQUENCY + 2 * r));
+        a.extend([reg::DRIVE, reg::ATTENUATOR]);
+        a
+    }
+}
+
+/// Level of the TX DAC for a drive in dB below full scale.
+fn drive_level(drive: f64) -> u8 {
+    (255.0 * 10f64.powf(drive / 20.0)).round().clamp(0.0, 255.0) as u8
+}
+
+impl State {
+    fn next_register(&mut self) -> u8 {
+        if let Some(a) = self.pending.pop_front() {
+            return a;
+        }
+        let addresses = self.settings.addresses();
+        self.cycle = (self.cycle + 1) % addresses.len();
+        addresses[self.cycle]
+    }
+
+    /// Data packet for the radio with the next registers and up to [`TX_SAMPLES`] samples.
+    fn packet(&mut self, samples: &[Complex32], scale: f32) -> Vec<u8> {
+        let mut p = vec![0; PACKET];
+        p[..4].copy_from_slice(&[0xef, 0xfe, DATA, EP2]);
+        p[4..8].copy_from_slice(&self.sequence.to_be_bytes());
+        self.sequence = self.sequence.wrapping_add(1);
+        for (f, frame) in p[8..].chunks_exact_mut(FRAME).enumerate() {
+            frame[..3].copy_from_slice(&SYNC);
+            let address = self.next_register();
+            frame[3..8].copy_from_slice(&self.settings.register(address));
+            let samples = samples.iter().skip(f * TX_SAMPLES / 2).take(TX_SAMPLES / 2);
+            for (s, out) in samples.zip(frame[8..].chunks_exact_mut(8)) {
+                // left and right audio, followed by I and Q
+                let i = (s.re * scale * 32767.0).clamp(-32768.0, 32767.0) as i16;
+                let q = (s.im * scale * 32767.0).clamp(-32768.0, 32767.0) as i16;
+                out[4..6].copy_from_slice(&i.to_be_bytes());
+                out[6..8].copy_from_slice(&q.to_be_bytes());
+            }
+        }
+        p
+    }
+}
+
+/// Samples per frame of an RX packet with the given number of receivers.
+fn rx_samples_per_frame(receivers: usize) -> usize {
+    // 24 bit I and Q per receiver and 16 bit of microphone samples
+    504 / (6 * receivers + 2)
+}
+
+/// Parse an RX packet into the samples of each receiver, returning its sequence number.
+fn parse_rx(p: &[u8], scale: f32, samples: &mut [Vec<Complex32>]) -> Option<u32> {
+    if p.len() != PACKET || p[..4] != [0xef, 0xfe, DATA, EP6] {
+        return None;
+    }
+    let receivers = samples.len();
+    let per_frame = rx_samples_per_frame(receivers);
+    let scale = scale / (1 << 23) as f32;
+    let sample = |b: &[u8]| (i32::from_be_bytes([b[0], b[1], b[2], 0]) >> 8) as f32 * scale;
+    for s in samples.iter_mut() {
+        s.clear();
+    }
+    for frame in p[8..].chunks_exact(FRAME) {
+        if frame[..3] != SYNC {
+            return None;
+        }
+        for block in frame[8..].chunks_exact(6 * receivers + 2).take(per_frame) {
+            for (r, s) in samples.iter_mut().enumerate() {
+                let iq = &block[6 * r..6 * r + 6];
+                s.push(Complex32::new(sample(&iq[..3]), sample(&iq[3..])));
+            }
+        }
+    }
+    Some(u32::from_be_bytes(p[4..8].try_into().ok()?))
+}
+
+impl Radio {
+    fn start_stop(&self, start: bool) -> Result<(), Error> {
+        let mut p = [0; 64];
+        p[..4].copy_from_slice(&[0xef, 0xfe, START_STOP, start as u8]);
+        self.socket.send(&p)?;
+        Ok(())
+    }
+
+    /// Send the pending registers, unless the TX stream carries them with its samples.
+    fn flush(&self, state: &mut State) -> Result<(), Error> {
+        if state.tx && state.running {
+            return Ok(());
+        }
+        while !state.pending.is_empty() {
+            let p = state.packet(&[], 1.0);
+            self.socket.send(&p)?;
+        }
+        Ok(())
+    }
+
+    /// Change settings and send the affected registers.
+    fn configure(
+        &self,
+        addresses: impl IntoIterator<Item = u8>,
+        f: impl FnOnce(&mut Settings),
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state.settings);
+        for a in addresses {
+            if !state.pending.contains(&a) {
+                state.pending.push_back(a);
+            }
+        }
+        self.flush(&mut state)
+    }
+
+    /// Start or stop the radio, depending on the active streams.
+    fn update(&self, state: &mut State) -> Result<(), Error> {
+        let streaming = state.rx || state.tx;
+        if streaming && !state.running {
+            // the radio needs all registers before it starts
+            state.pending = state.settings.addresses().into();
+            self.flush(state)?;
+            self.start_stop(true)?;
+            state.running = true;
+        } else if !streaming && state.running {
+            self.start_stop(false)?;
+            state.running = false;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Radio {
+    fn drop(&mut self) {
+        if self.state.get_mut().unwrap().running {
+            if let Err(e) = self.start_stop(false) {
+                log::warn!("hpsdr: failed to stop the radio: {e}");
+            }
+        }
+    }
+}
+
+impl DeviceTrait for Hpsdr {
+    type RxStreamer = RxStreamer;
+    type TxStreamer = TxStreamer;
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn driver(&self) -> Driver {
+        Driver::Hpsdr
+    }
+
+    fn id(&self) -> Result<String, Error> {
+        Ok(self.radio.discovery.mac_string())
+    }
+
+    fn info(&self) -> Result<Args, Error> {
+        Ok(self.radio.discovery.args())
+    }
+
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(vec![ReferenceDoc::new(
+            "Hermes-Lite 2 protocol",
+            "https://github.com/softerhardware/Hermes-Lite2/wiki/Protocol",
+        )])
+    }
+
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        match direction {
+            Rx => Ok(self.radio.discovery.receivers),
+            Tx => Ok(1),
+        }
+    }
+
+    fn full_duplex(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        Ok(true)
+    }
+
+    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+        if channels.is_empty() || channels.iter().any(|c| self.check(Rx, *c).is_err()) {
+            return Err(Error::ValueError);
+        }
+        let receivers = channels.iter().max().unwrap() + 1;
+        Ok(RxStreamer {
+            radio: self.radio.clone(),
+            channels: channels.to_vec(),
+            receivers,
+            active: false,
+            packet: vec![0; PACKET],
+            samples: vec![Vec::new(); receivers],
+            start: 0,
+            next_sequence: None,
+            position: 0,
+            overflow: false,
+            credit: 0.0,
+            canceller: StreamCanceller::new(),
+            events: StreamEvents::new(),
+            scale: 1.0,
+        })
+    }
+
+    fn tx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
+        if channels != [0] {
+            return Err(Error::ValueError);
+        }
+        Ok(TxStreamer {
+            radio: self.radio.clone(),
+            active: false,
+            buffer: Vec::with_capacity(TX_SAMPLES),
+            clock: None,
+            canceller: StreamCanceller::new(),
+            scale: 1.0,
+        })
+    }
+
+    fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.antenna(direction, channel).map(|a| vec![a])
+    }
+
+    fn antenna(&self, direction: Direction, channel: usize) -> Result<String, Error> {
+        self.check(direction, channel)?;
+        match direction {
+            Rx => Ok("RX".to_string()),
+            Tx => Ok("TX".to_string()),
+        }
+    }
+
+    fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
+        if self.antenna(direction, channel)? == name {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_elements(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.check(direction, channel)?;
+        Ok(vec![self.gain_element_name(direction).to_string()])
+    }
+
+    fn supports_agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        Ok(false)
+    }
+
+    fn enable_agc(&self, direction: Direction, channel: usize, _agc: bool) -> Result<(), Error> {
+        self.check(direction, channel)?;
+        Err(Error::NotSupported)
+    }
+
+    fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        Err(Error::NotSupported)
+    }
+
+    fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
+        self.set_gain_element(direction, channel, self.gain_element_name(direction), gain)
+            .map(drop)
+    }
+
+    fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
+        self.gain_element(direction, channel, self.gain_element_name(direction))
+    }
+
+    fn gain_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.gain_element_range(direction, channel, self.gain_element_name(direction))
+    }
+
+    fn set_gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        gain: f64,
+    ) -> Result<f64, Error> {
+        let range = self.gain_element_range(direction, channel, name)?;
+        if !range.contains(gain) {
+            return Err(Error::OutOfRange(range, gain));
+        }
+        match direction {
+            Rx => self
+                .radio
+                .configure([reg::ATTENUATOR], |s| s.gain = gain.round() as i32)?,
+            Tx => self.radio.configure([reg::DRIVE], |s| s.drive = gain)?,
+        }
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
+    }
+
+    fn gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Option<f64>, Error> {
+        self.gain_element_range(direction, channel, name)?;
+        let settings = self.settings();
+        match direction {
+            Rx => Ok(Some(settings.gain as f64)),
+            Tx => Ok(Some(settings.drive)),
+        }
+    }
+
+    fn gain_element_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        if name != self.gain_element_name(direction) {
+            return Err(Error::ValueError);
+        }
+        let item = match name {
+            "LNA" => RangeItem::Step(-12.0, 48.0, 1.0),
+            "ATT" => RangeItem::Step(-31.0, 0.0, 1.0),
+            _ => RangeItem::Interval(DRIVE_RANGE.0, DRIVE_RANGE.1),
+        };
+        Ok(Range::new(vec![item]))
+    }
+
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        self.gain_element_range(direction, channel, name)?;
+        Ok(match name {
+            "LNA" => GainElementInfo::new(
+                name,
+                "LNA",
+                "dB",
+                "Gain of the AD9866 front end, shared by all receivers.",
+            ),
+            "ATT" => GainElementInfo::new(
+                name,
+                "Step attenuator",
+                "dB",
+                "Attenuator in front of the ADC, shared by all receivers. The gain is the \
+                 negative attenuation.",
+            ),
+            _ => GainElementInfo::new(
+                name,
+                "Drive level",
+                "dB",
+                "Amplitude of the TX DAC output relative to full scale.",
+            ),
+        })
+    }
+
+    fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.component_frequency_range(direction, channel, "NCO")
+    }
+
+    fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.component_frequency(direction, channel, "NCO")
+    }
+
+    fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        _args: Args,
+    ) -> Result<(), Error> {
+        self.set_component_frequency(direction, channel, "NCO", frequency)
+    }
+
+    fn frequency_components(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Vec<String>, Error> {
+        self.check(direction, channel)?;
+        Ok(vec!["NCO".to_string()])
+    }
+
+    fn component_frequency_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        if name != "NCO" {
+            return Err(Error::ValueError);
+        }
+        Ok(Range::new(vec![RangeItem::Interval(
+            0.0,
+            self.max_frequency(),
+        )]))
+    }
+
+    fn component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        self.component_frequency_range(direction, channel, name)?;
+        let settings = self.settings();
+        match direction {
+            Rx => Ok(settings.rx_frequency[channel] as f64),
+            Tx => Ok(settings.tx_frequency as f64),
+        }
+    }
+
+    fn set_component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        frequency: f64,
+    ) -> Result<(), Error> {
+        let range = self.component_frequency_range(direction, channel, name)?;
+        if !range.contains(frequency) {
+            return Err(Error::OutOfRange(range, frequency));
+        }
+        let hz = frequency.round() as u32;
+        match direction {
+            Rx => self
+                .radio
+                .configure([reg::RX_FREQUENCY + 2 * channel as u8], |s| {
+                    s.rx_frequency[channel] = hz
+                }),
+            Tx => self
+                .radio
+                .configure([reg::TX_FREQUENCY], |s| s.tx_frequency = hz),
+        }
+    }
+
+    fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.check(direction, channel)?;
+        match direction {
+            Rx => Ok(RATES[self.settings().rate]),
+            Tx => Ok(TX_RATE),
+        }
+    }
+
+    /// The receivers share the sample rate.
+    fn set_sample_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    ) -> Result<(), Error> {
+        let range = self.get_sample_rate_range(direction, channel)?;
+        if !range.contains(rate) {
+            return Err(Error::OutOfRange(range, rate));
+        }
+        match direction {
+            Rx => {
+                let index = RATES.iter().position(|r| *r == rate).unwrap();
+                self.radio.configure([reg::CONFIG], |s| s.rate = index)
+            }
+            Tx => Ok(()),
+        }
+    }
+
+    fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        match direction {
+            Rx => Ok(Range::new(
+                RATES.iter().map(|r| RangeItem::Value(*r)).collect(),
+            )),
+            Tx => Ok(Range::new(vec![RangeItem::Value(TX_RATE)])),
+        }
+    }
+
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        // the decimation filters pass the whole band
+        self.sample_rate(direction, channel)
+    }
+
+    fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
+        if self.bandwidth(direction, channel)? == bw {
+            Ok(())
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
+    fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        Ok(Range::new(vec![RangeItem::Value(
+            self.bandwidth(direction, channel)?,
+        )]))
+    }
+
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+impl crate::RxInterface for Hpsdr {}
+
+impl crate::TxInterface for Hpsdr {}
+
+impl RxStreamer {
+    /// Receive the next packet, returning `false` on timeout.
+    fn receive(&mut self, deadline: Instant) -> Result<bool, Error> {
+        let socket = &self.radio.socket;
+        loop {
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(false);
+            }
+            socket.set_read_timeout(Some(left.min(POLL_INTERVAL)))?;
+            let n = match socket.recv(&mut self.packet) {
+                Ok(n) => n,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let Some(sequence) = parse_rx(&self.packet[..n], self.scale, &mut self.samples) else {
+                continue;
+            };
+            let per_packet = 2 * rx_samples_per_frame(self.receivers) as u64;
+            if let Some(next) = self.next_sequence {
+                let gap = sequence.wrapping_sub(next) as i32;
+                if gap < 0 {
+                    // late or duplicate packet
+                    self.events.send(StreamEvent::LatePacket);
+                    continue;
+                }
+                if gap > 0 {
+                    log::debug!("hpsdr: {gap} packets dropped");
+                    self.events.send(StreamEvent::Overflow);
+                    self.overflow = true;
+                    self.position += gap as u64 * per_packet;
+                }
+            }
+            self.next_sequence = Some(sequence.wrapping_add(1));
+            self.start = 0;
+            self.keep_alive(per_packet)?;
+            return Ok(true);
+        }
+    }
+
+    /// Send packets at the TX rate while no TX stream does, to deliver the registers.
+    fn keep_alive(&mut self, received: u64) -> Result<(), Error> {
+        let mut state = self.radio.state.lock().unwrap();
+        if state.tx {
+            self.credit = 0.0;
+            return Ok(());
+        }
+        self.credit += received as f64 * TX_RATE / RATES[state.settings.rate];
+        while self.credit >= TX_SAMPLES as f64 {
+            let p = state.packet(&[], 1.0);
+            self.radio.socket.send(&p)?;
+            self.credit -= TX_SAMPLES as f64;
+        }
+        Ok(())
+    }
+
+    /// Discard packets waiting in the socket.
+    fn drain(&mut self) -> Result<(), Error> {
+        let socket = &self.radio.socket;
+        socket.set_read_timeout(Some(Duration::from_millis(1)))?;
+        while socket.recv(&mut self.packet).is_ok() {}
+        Ok(())
+    }
+}
+
+impl Drop for RxStreamer {
+    fn drop(&mut self) {
+        if self.active {
+            if let Err(e) = crate::RxStreamer::deactivate(self) {
+                log::warn!("failed to stop HPSDR RX stream on drop: {e}");
+            }
+        }
+    }
+}
+
+impl crate::RxStreamer for RxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(2 * rx_samples_per_frame(self.receivers))
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        {
+            let mut state = self.radio.state.lock().unwrap();
+            if state.rx {
+                return Err(Error::Busy(Rx));
+            }
+            state.rx = true;
+            state.settings.receivers = self.receivers;
+            state.pending.push_back(reg::CONFIG);
+            let result = self
+                .radio
+                .flush(&mut state)
+                .and_then(|_| self.radio.update(&mut state));
+            if result.is_err() {
+                state.rx = false;
+                return result;
+            }
+        }
+        self.canceller.reset();
+        self.samples.iter_mut().for_each(|s| s.clear());
+        self.start = 0;
+        self.next_sequence = None;
+        self.position = 0;
+        self.credit = 0.0;
+        self.active = true;
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        self.active = false;
+        let mut state = self.radio.state.lock().unwrap();
+        state.rx = false;
+        self.radio.update(&mut state)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        if buffers.iter().all(|b| b.is_empty()) {
+            return Ok(0);
+        }
+        self.overflow = false;
+        if self.start == self.samples[0].len() {
+            let deadline = Instant::now() + Duration::from_micros(timeout_us.max(0) as u64);
+            if !self.receive(deadline)? {
+                return Err(Error::Timeout);
+            }
+        }
+        let n = buffers[0].len().min(self.samples[0].len() - self.start);
+        for (b, c) in buffers.iter_mut().zip(&self.channels) {
+            b[..n].copy_from_slice(&self.samples[*c][self.start..self.start + n]);
+        }
+        self.start += n;
+        self.position += n as u64;
+        Ok(n)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let n = self.read(buffers, timeout_us)?;
+        let mut flags = RxFlags::empty();
+        if self.overflow {
+            flags.insert(RxFlags::OVERFLOW);
+        }
+        if self.start < self.samples[0].len() {
+            flags.insert(RxFlags::MORE_FRAGMENTS);
+        }
+        Ok((
+            n,
+            RxMetadata {
+                sample_position: Some(self.position - n as u64),
+                flags,
+                ..RxMetadata::default()
+            },
+        ))
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        Ok(BufferConfig {
+            mtu: 2 * rx_samples_per_frame(self.receivers),
+            granularity: 1,
+        })
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.events.subscribe())
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        self.position += (self.samples[0].len() - self.start) as u64;
+        self.start = self.samples[0].len();
+        // the sequence numbers count the dropped packets
+        self.drain()
+    }
+}
+
+impl TxStreamer {
+    /// Wait until the radio has room for the next packet, returning `false` if that is after
+    /// the `deadline`.
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<bool, Error> {
+        let now = Instant::now();
+        let (start, sent) = self.clock.get_or_insert((now, 0));
+        if *start + Duration::from_secs_f64(*sent as f64 / TX_RATE) < now {
+            // underrun, the radio played all samples
+            *start = now;
+            *sent = 0;
+        }
+        let due = *start + Duration::from_secs_f64(sent.saturating_sub(TX_LEAD) as f64 / TX_RATE);
+        loop {
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let now = Instant::now();
+            if now >= due {
+                return Ok(true);
+            }
+            if deadline.is_some_and(|d| due > d) {
+                return Ok(false);
+            }
+            std::thread::sleep((due - now).min(POLL_INTERVAL));
+        }
+    }
+
+    /// Send the buffered samples, padded to a full packet.
+    fn send(&mut self) -> Result<(), Error> {
+        self.buffer.resize(TX_SAMPLES, Complex32::new(0.0, 0.0));
+        let p = self
+            .radio
+            .state
+            .lock()
+            .unwrap()
+            .packet(&self.buffer, self.scale);
+        self.radio.socket.send(&p)?;
+        self.buffer.clear();
+        if let Some((_, sent)) = self.clock.as_mut() {
+            *sent += TX_SAMPLES as u64;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TxStreamer {
+    fn drop(&mut self) {
+        if self.active {
+            if let Err(e) = crate::TxStreamer::deactivate(self) {
+                log::warn!("failed to stop HPSDR TX stream on drop: {e}");
+            }
+        }
+    }
+}
+
+impl crate::TxStreamer for TxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(TX_SAMPLES)
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        let mut state = self.radio.state.lock().unwrap();
+        if state.tx {
+            return Err(Error::Busy(Tx));
+        }
+        state.settings.mox = true;
+        state.tx = true;
+        if let Err(e) = self.radio.update(&mut state) {
+            state.settings.mox = false;
+            state.tx = false;
+            return Err(e);
+        }
+        self.canceller.reset();
+        self.buffer.clear();
+        self.clock = None;
+        self.active = true;
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        if !self.buffer.is_empty() {
+            self.wait(None)?;
+            self.send()?;
+        }
+        self.active = false;
+        let mut state = self.radio.state.lock().unwrap();
+        state.tx = false;
+        state.settings.mox = false;
+        state.pending.push_back(reg::CONFIG);
+        self.radio.flush(&mut state)?;
+        self.radio.update(&mut state)
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if !self.active {
+            return Err(Error::Inactive);
+        }
+        if at_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        let deadline = Instant::now() + Duration::from_micros(timeout_us.max(0) as u64);
+        let input = buffers[0];
+        let mut n = 0;
+        loop {
+            if self.buffer.len() == TX_SAMPLES {
+                if !self.wait(Some(deadline))? {
+                    break;
+                }
+                self.send()?;
+            }
+            if n == input.len() {
+                break;
+            }
+            let take = (TX_SAMPLES - self.buffer.len()).min(input.len() - n);
+            self.buffer.extend_from_slice(&input[n..n + take]);
+            n += take;
+        }
+        if n == input.len() && end_burst && !self.buffer.is_empty() {
+            self.wait(None)?;
+            self.send()?;
+        }
+        if n == 0 && !input.is_empty() {
+            Err(Error::Timeout)
+        } else {
+            Ok(n)
+        }
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let mut n = 0;
+        while n < buffers[0].len() {
+            let buf = &buffers[0][n..];
+            match self.write(&[buf], None, end_burst, timeout_us)? {
+                0 => return Err(Error::Timeout),
+                k => n += k,
+            }
+        }
+        Ok(())
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RxStreamer as _;
+
+    /// RX packet with the given samples of each receiver.
+    fn rx_packet(sequence: u32, receivers: usize, sample: impl Fn(usize, usize) -> i32) -> Vec<u8> {
+        let mut p = vec![0; PACKET];
+        p[..4].copy_from_slice(&[0xef, 0xfe, DATA, EP6]);
+        p[4..8].copy_from_slice(&sequence.to_be_bytes());
+        let per_frame = rx_samples_per_frame(receivers);
+        for (f, frame) in p[8..].chunks_exact_mut(FRAME).enumerate() {
+            frame[..3].copy_from_slice(&SYNC);
+            for (i, block) in frame[8..]
+                .chunks_exact_mut(6 * receivers + 2)
+                .take(per_frame)
+                .enumerate()
+            {
+                for r in 0..receivers {
+                    let v = sample(r, f * per_frame + i).to_be_bytes();
+                    block[6 * r..6 * r + 3].copy_from_slice(&v[1..]);
+                    let v = (-sample(r, f * per_frame + i)).to_be_bytes();
+                    block[6 * r + 3..6 * r + 6].copy_from_slice(&v[1..]);
+                }
+            }
+        }
+        p
+    }
+
+    #[test]
+    fn registers_and_packets() {
+        let mut settings = Settings {
+            board: Board::Hermes,
+            rate: 2,
+            receivers: 2,
+            rx_frequency: vec![7_074_000, 14_074_000, 0, 0],
+            tx_frequency: 7_074_000,
+            gain: -20,
+            drive: 0.0,
+            mox: false,
+        };
+        assert_eq!(settings.register(reg::CONFIG), [0x00, 0x02, 0, 0, 0x0c]);
+        assert_eq!(
+            settings.register(reg::RX_FREQUENCY + 2),
+            [0x06, 0x00, 0xd6, 0xc0, 0x90]
+        );
+        assert_eq!(settings.register(reg::ATTENUATOR)[4], 0x20 | 20);
+        assert_eq!(settings.register(reg::DRIVE)[1], 255);
+        settings.board = Board::HermesLite;
+        settings.gain = -4;
+        settings.mox = true;
+        assert_eq!(
+            settings.register(reg::ATTENUATOR),
+            [0x15, 0, 0, 0, 0x40 | 8]
+        );
+        assert_eq!(settings.addresses().len(), 6);
+
+        let mut samples = vec![Vec::new(); 2];
+        let p = rx_packet(7, 2, |r, i| (r as i32 + 1) * i as i32 * 1000);
+        assert_eq!(parse_rx(&p, 1.0, &mut samples), Some(7));
+        assert_eq!(samples[1].len(), 72);
+        let v = 2.0 * 40.0 * 1000.0 / (1 << 23) as f32;
+        assert_eq!(samples[1][40], Complex32::new(v, -v));
+        assert_eq!(parse_rx(&p[..100], 1.0, &mut samples), None);
+
+        assert_eq!(
+            Discovery::parse(
+                &[0xef, 0xfe, 3, 0, 0x1c, 0xc0, 1, 2, 3, 33, 1],
+                "10.0.0.2:1024".parse().unwrap()
+            )
+            .map(|d| (d.board, d.busy, d.mac_string())),
+            Some((Board::Hermes, true, "00:1c:c0:01:02:03".to_string()))
+        );
+    }
+
+    #[test]
+    fn stream_from_radio() {
+        let radio = UdpSocket::bind("127.0.0.1:0").unwrap();
+        radio
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let addr = radio.local_addr().unwrap();
+        let fake = std::thread::spawn(move || {
+            let mut buf = [0; 2048];
+            let (_, host) = radio.recv_from(&mut buf).unwrap();
+            assert_eq!(buf[..3], [0xef, 0xfe, DISCOVER]);
+            let mut reply = [0; 60];
+            reply[..3].copy_from_slice(&[0xef, 0xfe, DISCOVER]);
+            reply[3..9].copy_from_slice(&[0, 0x1c, 0xc0, 0xa2, 0x13, 0xdd]);
+            reply[9] = 72;
+            reply[10] = 6;
+            reply[19] = 4;
+            radio.send_to(&reply, host).unwrap();
+
+            // collect the registers until the radio is started
+            let mut registers = Vec::new();
+            let host = loop {
+                let (n, host) = radio.recv_from(&mut buf).unwrap();
+                if buf[2] == START_STOP && buf[3] == 1 {
+                    break host;
+                }
+                assert_eq!(n, PACKET);
+                for frame in buf[8..n].chunks_exact(FRAME) {
+                    registers.push(<[u8; 5]>::try_from(&frame[3..8]).unwrap());
+                }
+            };
+            // the second packet is lost
+            for sequence in [0, 2] {
+                let p = rx_packet(sequence, 2, |r, i| (r as i32 + 1) * i as i32 * 1000);
+                radio.send_to(&p, host).unwrap();
+            }
+            registers
+        });
+
+        let dev = Hpsdr::open(format!("addr={addr}").as_str()).unwrap();
+        assert_eq!(
+            dev.info().unwrap().get::<String>("board").unwrap(),
+            "hermes-lite"
+        );
+        assert_eq!(dev.num_channels(Rx).unwrap(), 4);
+        dev.set_frequency(Rx, 1, 14.074e6, Args::new()).unwrap();
+        dev.set_sample_rate(Rx, 1, 96000.0).unwrap();
+        assert_eq!(dev.sample_rate(Rx, 0).unwrap(), 96000.0);
+        assert!(dev.set_sample_rate(Rx, 0, 100000.0).is_err());
+
+        let mut rx = dev.rx_streamer(&[1], Args::new()).unwrap();
+        let events = rx.stream_events().unwrap();
+        rx.activate().unwrap();
+        let registers = fake.join().unwrap();
+        assert!(registers.contains(&[0x06, 0x00, 0xd6, 0xc0, 0x90]));
+        assert_eq!(registers.last(), Some(&[0x14, 0, 0, 0, 0x40 | 12]));
+        assert!(registers.contains(&[0x00, 0x01, 0, 0, 0x0c]));
+
+        let mut samples = [Complex32::new(0.0, 0.0); 100];
+        assert_eq!(rx.read(&mut [&mut samples], 1_000_000).unwrap(), 72);
+        let v = 2.0 * 10.0 * 1000.0 / (1 << 23) as f32;
+        assert_eq!(samples[10], Complex32::new(v, -v));
+        // one packet was lost between the two
+        let (n, meta) = rx
+            .read_with_metadata(&mut [&mut samples[..50]], 1_000_000)
+            .unwrap();
+        assert_eq!(n, 50);
+        assert_eq!(meta.sample_position, Some(144));
+        assert!(meta
+            .flags
+            .contains(RxFlags::OVERFLOW | RxFlags::MORE_FRAGMENTS));
+        assert_eq!(rx.sample_position().unwrap(), 194);
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Overflow));
+    }
+}
@@ -0,0 +1,1122 @@
+//! ka9q-radio multicast client
+use num_complex::Complex32;
+use std::any::Any;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Direction::*;
+use crate::Driver;
+use crate::Error;
+use crate::GainElementInfo;
+use crate::Range;
+use crate::RangeItem;
+use crate::ReferenceDoc;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
+
+/// Packet types of the status and control protocol.
+const STATUS: u8 = 0;
+const CMD: u8 = 1;
+
+/// Tags of the status and control protocol, see `status.h` of ka9q-radio.
+mod tag {
+    pub const EOL: u8 = 0;
+    pub const COMMAND_TAG: u8 = 1;
+    pub const DESCRIPTION: u8 = 4;
+    pub const INPUT_SAMPRATE: u8 = 10;
+    pub const OUTPUT_DATA_DEST_SOCKET: u8 = 17;
+    pub const OUTPUT_SSRC: u8 = 18;
+    pub const OUTPUT_SAMPRATE: u8 = 20;
+    pub const RADIO_FREQUENCY: u8 = 33;
+    pub const LOW_EDGE: u8 = 39;
+    pub const HIGH_EDGE: u8 = 40;
+    pub const DEMOD_TYPE: u8 = 48;
+    pub const OUTPUT_CHANNELS: u8 = 49;
+    pub const AGC_ENABLE: u8 = 62;
+    pub const GAIN: u8 = 68;
+}
+
+/// Default ports of the status and control group and of the data group.
+const STATUS_PORT: u16 = 5006;
+const DATA_PORT: u16 = 5004;
+/// Largest RTP packet.
+const MAX_PACKET: usize = 65536;
+/// Interval, in which blocking socket calls check for cancellation and shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time to wait for the status of the channel, e.g., for its data group.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+/// Gain range of the channel. radiod accepts any gain, the range only guards against typos.
+const GAIN_RANGE: (f64, f64) = (-100.0, 100.0);
+
+/// Client for a channel of a ka9q-radio receiver (`radiod`).
+///
+/// radiod publishes the status of its channels on a status multicast group and the samples of
+/// each channel as an RTP stream on a data multicast group. Channels are identified by their
+/// RTP SSRC. Settings are changed with command packets to the status group, which radiod
+/// acknowledges with a status packet.
+#[derive(Clone)]
+pub struct Ka9q {
+    control: Arc<Control>,
+}
+
+/// Commands and status of a channel, shared with the status thread and the streamer.
+struct Control {
+    socket: UdpSocket,
+    status_addr: SocketAddr,
+    ssrc: u32,
+    data: Option<SocketAddr>,
+    interface: Option<IpAddr>,
+    encoding: Encoding,
+    tag: AtomicU32,
+    status: Mutex<Status>,
+    updated: Condvar,
+}
+
+/// Last reported (or commanded) settings of the channel.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Status {
+    description: Option<String>,
+    input_rate: Option<f64>,
+    sample_rate: Option<f64>,
+    frequency: Option<f64>,
+    low_edge: Option<f64>,
+    high_edge: Option<f64>,
+    gain: Option<f64>,
+    agc: Option<bool>,
+    channels: Option<u64>,
+    data: Option<SocketAddr>,
+}
+
+/// Sample format of the RTP payload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    S16Be,
+    S16Le,
+    F32Le,
+}
+
+/// ka9q-radio RX streamer
+pub struct RxStreamer {
+    control: Arc<Control>,
+    socket: Option<UdpSocket>,
+    packet: Vec<u8>,
+    samples: Vec<Complex32>,
+    // unread samples of the last packet
+    start: usize,
+    next_timestamp: Option<u32>,
+    position: u64,
+    canceller: StreamCanceller,
+    events: StreamEvents,
+    scale: f32,
+}
+
+/// ka9q-radio TX dummy streamer
+pub struct TxDummy;
+
+impl Ka9q {
+    /// Get the channel given by the arguments.
+    ///
+    /// radiod channels cannot be discovered without listening to the network, so a device is
+    /// only returned, if a `status` group is given.
+    pub fn probe(args: &Args) -> Result<Vec<Args>, Error> {
+        let Ok(status) = args.get::<String>("status") else {
+            return Ok(Vec::new());
+        };
+        let mut a = Args::new();
+        a.set("driver", "ka9q");
+        a.set("status", status);
+        for key in ["ssrc", "data", "interface", "encoding"] {
+            if let Ok(v) = args.get::<String>(key) {
+                a.set(key, v);
+            }
+        }
+        Ok(vec![a])
+    }
+
+    /// Connect to a channel of radiod.
+    ///
+    /// - `status`: status and control group, e.g., `hf-status.local` or `239.1.2.3:5006`.
+    /// - `ssrc`: RTP SSRC of the channel. radiod creates a channel for an unused SSRC, once its
+    ///   frequency is set.
+    /// - `data`: data group of the channel. Defaults to the group reported by radiod.
+    /// - `interface`: address of the local interface to join the groups on.
+    /// - `encoding`: sample format of the stream, `s16be` (default), `s16le`, or `f32le`.
+    ///
+    /// The channel is put into linear (IQ) mode with two output channels.
+    pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        let status_addr = resolve(&args.get::<String>("status")?, STATUS_PORT)?;
+        let ssrc = args.get::<u32>("ssrc").or(Err(Error::ValueError))?;
+        let data = match args.get::<String>("data") {
+            Ok(d) => Some(resolve(&d, DATA_PORT)?),
+            Err(_) => None,
+        };
+        let interface = match args.get::<String>("interface") {
+            Ok(i) => Some(i.parse().or(Err(Error::ValueError))?),
+            Err(_) => None,
+        };
+        let encoding = match args.get::<String>("encoding").as_deref() {
+            Ok("s16be") | Err(Error::NotFound) => Encoding::S16Be,
+            Ok("s16le") => Encoding::S16Le,
+            Ok("f32le") | Ok("f32") => Encoding::F32Le,
+            _ => return Err(Error::ValueError),
+        };
+
+        let local: IpAddr = match status_addr {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let socket = UdpSocket::bind((local, 0))?;
+        let listener = if status_addr.ip().is_multicast() {
+            join(status_addr, interface)?
+        } else {
+            // unicast radiod (or a test) replies to the sender
+            socket.try_clone()?
+        };
+        listener.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let control = Arc::new(Control {
+            socket,
+            status_addr,
+            ssrc,
+            data,
+            interface,
+            encoding,
+            tag: AtomicU32::new(std::process::id()),
+            status: Mutex::new(Status::default()),
+            updated: Condvar::new(),
+        });
+        let weak = Arc::downgrade(&control);
+        std::thread::Builder::new()
+            .name(format!("ka9q-status-{ssrc}"))
+            .spawn(move || listen(listener, weak))?;
+        control.command(&[
+            (tag::DEMOD_TYPE, Value::Int(0)),
+            (tag::OUTPUT_CHANNELS, Value::Int(2)),
+        ])?;
+        Ok(Self { control })
+    }
+
+    fn check(&self, direction: Direction, channel: usize) -> Result<(), Error> {
+        match (direction, channel) {
+            (Rx, 0) => Ok(()),
+            (Rx, _) => Err(Error::ValueError),
+            (Tx, _) => Err(Error::NotSupported),
+        }
+    }
+
+    fn status(&self) -> Status {
+        self.control.status.lock().unwrap().clone()
+    }
+}
+
+/// Resolve `host[:port]`, e.g., an mDNS name of a radiod group.
+fn resolve(addr: &str, port: u16) -> Result<SocketAddr, Error> {
+    let resolved = match addr.to_socket_addrs() {
+        Ok(mut a) => a.next(),
+        Err(_) => (addr, port).to_socket_addrs()?.next(),
+    };
+    resolved.ok_or(Error::NotFound)
+}
+
+/// Bind a socket to a group and join it.
+fn join(group: SocketAddr, interface: Option<IpAddr>) -> Result<UdpSocket, Error> {
+    if !group.ip().is_multicast() {
+        return Ok(UdpSocket::bind(group)?);
+    }
+    // binding to the group filters other traffic to the port; Windows only binds to interfaces
+    let bind: IpAddr = match (cfg!(windows), group.ip()) {
+        (true, IpAddr::V4(_)) => Ipv4Addr::UNSPECIFIED.into(),
+        (true, IpAddr::V6(_)) => Ipv6Addr::UNSPECIFIED.into(),
+        (false, ip) => ip,
+    };
+    let socket = UdpSocket::bind((bind, group.port()))?;
+    match group.ip() {
+        IpAddr::V4(ip) => {
+            let interface = match interface {
+                Some(IpAddr::V4(i)) => i,
+                Some(IpAddr::V6(_)) => return Err(Error::ValueError),
+                None => Ipv4Addr::UNSPECIFIED,
+            };
+            socket.join_multicast_v4(&ip, &interface)?;
+        }
+        IpAddr::V6(ip) => socket.join_multicast_v6(&ip, 0)?,
+    }
+    Ok(socket)
+}
+
+/// Update the status of the channel from status packets, until the device is dropped.
+fn listen(socket: UdpSocket, control: Weak<Control>) {
+    let mut buf = vec![0; MAX_PACKET];
+    loop {
+        let result = socket.recv(&mut buf);
+        let Some(control) = control.upgrade() else {
+            return;
+        };
+        match result {
+            Ok(n) if n > 0 && buf[0] == STATUS => match decode(&buf[1..n]) {
+                Ok(tlvs) => control.update(&tlvs),
+                Err(e) => log::debug!("ka9q: invalid status packet: {e}"),
+            },
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => {
+                log::warn!("ka9q: status socket failed: {e}");
+                return;
+            }
+        }
+    }
+}
+
+impl Control {
+    /// Send a command for the channel.
+    fn command(&self, settings: &[(u8, Value)]) -> Result<(), Error> {
+        let tag = self.tag.fetch_add(1, Ordering::Relaxed);
+        let mut packet = vec![CMD];
+        encode(&mut packet, tag::COMMAND_TAG, Value::Int(tag as u64));
+        encode(&mut packet, tag::OUTPUT_SSRC, Value::Int(self.ssrc as u64));
+        for (t, v) in settings {
+            encode(&mut packet, *t, *v);
+        }
+        packet.push(tag::EOL);
+        self.socket.send_to(&packet, self.status_addr)?;
+        // assume the command is applied, until radiod reports otherwise
+        let mut status = self.status.lock().unwrap();
+        for (t, v) in settings {
+            status.apply(*t, &v.to_bytes());
+        }
+        Ok(())
+    }
+
+    fn update(&self, tlvs: &[(u8, &[u8])]) {
+        let ssrc = tlvs.iter().find(|(t, _)| *t == tag::OUTPUT_SSRC);
+        if ssrc.map(|(_, v)| int(v)) != Some(self.ssrc as u64) {
+            return;
+        }
+        let mut status = self.status.lock().unwrap();
+        for (t, v) in tlvs {
+            status.apply(*t, v);
+        }
+        self.updated.notify_all();
+    }
+
+    /// Data group of the channel, waiting for radiod to report it.
+    fn data(&self) -> Result<SocketAddr, Error> {
+        if let Some(data) = self.data {
+            return Ok(data);
+        }
+        self.command(&[])?;
+        let status = self.status.lock().unwrap();
+        let (status, _) = self
+            .updated
+            .wait_timeout_while(status, STATUS_TIMEOUT, |s| s.data.is_none())
+            .unwrap();
+        status.data.ok_or(Error::Timeout)
+    }
+}
+
+impl Status {
+    fn apply(&mut self, t: u8, v: &[u8]) {
+        match t {
+            tag::DESCRIPTION => self.description = Some(String::from_utf8_lossy(v).into_owned()),
+            tag::INPUT_SAMPRATE => self.input_rate = Some(int(v) as f64),
+            tag::OUTPUT_SAMPRATE => self.sample_rate = Some(int(v) as f64),
+            tag::RADIO_FREQUENCY => self.frequency = Some(float(v)),
+            tag::LOW_EDGE => self.low_edge = Some(float(v)),
+            tag::HIGH_EDGE => self.high_edge = Some(float(v)),
+            tag::GAIN => self.gain = Some(float(v)),
+            tag::AGC_ENABLE => self.agc = Some(int(v) != 0),
+            tag::OUTPUT_CHANNELS => self.channels = Some(int(v)),
+            tag::OUTPUT_DATA_DEST_SOCKET => self.data = socket_addr(v),
+            _ => {}
+        }
+    }
+}
+
+/// Value of a setting, encoded big-endian without leading zero bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Int(u64),
+    Float(f32),
+    Double(f64),
+}
+
+impl Value {
+    fn to_bytes(self) -> Vec<u8> {
+        let bytes = match self {
+            Value::Int(i) => i.to_be_bytes().to_vec(),
+            Value::Float(f) => f.to_bits().to_be_bytes().to_vec(),
+            Value::Double(d) => d.to_bits().to_be_bytes().to_vec(),
+        };
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        bytes[zeros..].to_vec()
+    }
+}
+
+fn encode(packet: &mut Vec<u8>, t: u8, value: Value) {
+    let bytes = value.to_bytes();
+    packet.push(t);
+    // values are at most 8 bytes, so the length always fits into the short form
+    packet.push(bytes.len() as u8);
+    packet.extend_from_slice(&bytes);
+}
+
+/// Split the payload of a status or command packet into tags and values.
+fn decode(mut p: &[u8]) -> Result<Vec<(u8, &[u8])>, Error> {
+    let invalid = || Error::Misc("ka9q: truncated status packet".to_string());
+    let mut tlvs = Vec::new();
+    while let Some((&t, rest)) = p.split_first() {
+        if t == tag::EOL {
+            break;
+        }
+        let (&len, mut rest) = rest.split_first().ok_or_else(invalid)?;
+        let mut len = len as usize;
+        if len & 0x80 != 0 {
+            // long form, the low bits give the number of length bytes
+            let n = len & 0x7f;
+            if rest.len() < n || n > 4 {
+                return Err(invalid());
+            }
+            len = rest[..n].iter().fold(0, |l, b| (l << 8) | *b as usize);
+            rest = &rest[n..];
+        }
+        if rest.len() < len {
+            return Err(invalid());
+        }
+        tlvs.push((t, &rest[..len]));
+        p = &rest[len..];
+    }
+    Ok(tlvs)
+}
+
+fn int(v: &[u8]) -> u64 {
+    v.iter().take(8).fold(0, |i, b| (i << 8) | *b as u64)
+}
+
+/// Float or double, told apart by the length like radiod does.
+fn float(v: &[u8]) -> f64 {
+    if v.len() <= 4 {
+        f32::from_bits(int(v) as u32) as f64
+    } else {
+        f64::from_bits(int(v))
+    }
+}
+
+/// IPv4 or IPv6 address followed by the port.
+fn socket_addr(v: &[u8]) -> Option<SocketAddr> {
+    match v.len() {
+        6 => {
+            let ip: [u8; 4] = v[..4].try_into().ok()?;
+            Some(SocketAddr::new(ip.into(), u16::from_be_bytes([v[4], v[5]])))
+        }
+        18 => {
+            let ip: [u8; 16] = v[..16].try_into().ok()?;
+            Some(SocketAddr::new(
+                ip.into(),
+                u16::from_be_bytes([v[16], v[17]]),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Header fields of an RTP packet and the offset of its payload.
+struct RtpHeader {
+    timestamp: u32,
+    ssrc: u32,
+    payload: std::ops::Range<usize>,
+}
+
+fn parse_rtp(p: &[u8]) -> Option<RtpHeader> {
+    if p.len() < 12 || p[0] >> 6 != 2 {
+        return None;
+    }
+    let mut start = 12 + 4 * (p[0] & 0x0f) as usize;
+    if p[0] & 0x10 != 0 {
+        // header extension
+        let len = u16::from_be_bytes(p.get(start + 2..start + 4)?.try_into().ok()?);
+        start += 4 + 4 * len as usize;
+    }
+    let mut end = p.len();
+    if p[0] & 0x20 != 0 {
+        end = end.checked_sub(*p.last()? as usize)?;
+    }
+    if start > end {
+        return None;
+    }
+    Some(RtpHeader {
+        timestamp: u32::from_be_bytes(p[4..8].try_into().ok()?),
+        ssrc: u32::from_be_bytes(p[8..12].try_into().ok()?),
+        payload: start..end,
+    })
+}
+
+impl Encoding {
+    fn decode(self, payload: &[u8], scale: f32, out: &mut Vec<Complex32>) {
+        out.clear();
+        match self {
+            Encoding::S16Be | Encoding::S16Le => {
+                let scale = scale / 32768.0;
+                let sample = |b: &[u8]| {
+                    let b = [b[0], b[1]];
+                    let v = if self == Encoding::S16Be {
+                        i16::from_be_bytes(b)
+                    } else {
+                        i16::from_le_bytes(b)
+                    };
+                    v as f32 * scale
+                };
+                out.extend(
+                    payload
+                        .chunks_exact(4)
+                        .map(|c| Complex32::new(sample(&c[..2]), sample(&c[2..]))),
+                );
+            }
+            Encoding::F32Le => {
+                let sample = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) * scale;
+                out.extend(
+                    payload
+                        .chunks_exact(8)
+                        .map(|c| Complex32::new(sample(&c[..4]), sample(&c[4..]))),
+                );
+            }
+        }
+    }
+}
+
+impl DeviceTrait for Ka9q {
+    type RxStreamer = RxStreamer;
+    type TxStreamer = TxDummy;
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn driver(&self) -> Driver {
+        Driver::Ka9q
+    }
+
+    fn id(&self) -> Result<String, Error> {
+        Ok(format!(
+            "{}/{}",
+            self.control.status_addr, self.control.ssrc
+        ))
+    }
+
+    fn info(&self) -> Result<Args, Error> {
+        let mut args: Args = format!(
+            "driver=ka9q, status={}, ssrc={}",
+            self.control.status_addr, self.control.ssrc
+        )
+        .try_into()?;
+        if let Some(d) = self.status().description {
+            args.set("description", d);
+        }
+        Ok(args)
+    }
+
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(vec![ReferenceDoc::new(
+            "ka9q-radio",
+            "https://github.com/ka9q/ka9q-radio",
+        )])
+    }
+
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        match direction {
+            Rx => Ok(1),
+            Tx => Ok(0),
+        }
+    }
+
+    fn full_duplex(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        Ok(false)
+    }
+
+    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+        if channels != [0] {
+            return Err(Error::ValueError);
+        }
+        Ok(RxStreamer {
+            control: self.control.clone(),
+            socket: None,
+            packet: vec![0; MAX_PACKET],
+            samples: Vec::new(),
+            start: 0,
+            next_timestamp: None,
+            position: 0,
+            canceller: StreamCanceller::new(),
+            events: StreamEvents::new(),
+            scale: 1.0,
+        })
+    }
+
+    fn tx_streamer(&self, _channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.antenna(direction, channel).map(|a| vec![a])
+    }
+
+    fn antenna(&self, direction: Direction, channel: usize) -> Result<String, Error> {
+        self.check(direction, channel)?;
+        Ok("RX".to_string())
+    }
+
+    fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
+        self.check(direction, channel)?;
+        if name == "RX" {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_elements(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.check(direction, channel)?;
+        Ok(vec!["BASEBAND".to_string()])
+    }
+
+    fn supports_agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        Ok(true)
+    }
+
+    fn enable_agc(&self, direction: Direction, channel: usize, agc: bool) -> Result<(), Error> {
+        self.check(direction, channel)?;
+        self.control
+            .command(&[(tag::AGC_ENABLE, Value::Int(agc as u64))])
+    }
+
+    fn agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        self.check(direction, channel)?;
+        self.status().agc.ok_or(Error::NotFound)
+    }
+
+    fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
+        self.set_gain_element(direction, channel, "BASEBAND", gain)
+            .map(drop)
+    }
+
+    fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
+        self.gain_element(direction, channel, "BASEBAND")
+    }
+
+    fn gain_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.gain_element_range(direction, channel, "BASEBAND")
+    }
+
+    fn set_gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        gain: f64,
+    ) -> Result<f64, Error> {
+        let range = self.gain_element_range(direction, channel, name)?;
+        if !range.contains(gain) {
+            return Err(Error::OutOfRange(range, gain));
+        }
+        self.control.command(&[
+            (tag::AGC_ENABLE, Value::Int(0)),
+            (tag::GAIN, Value::Float(gain as f32)),
+        ])?;
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
+    }
+
+    fn gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Option<f64>, Error> {
+        self.gain_element_range(direction, channel, name)?;
+        let status = self.status();
+        if status.agc == Some(true) {
+            Ok(None)
+        } else {
+            status.gain.map(Some).ok_or(Error::NotFound)
+        }
+    }
+
+    fn gain_element_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        if name == "BASEBAND" {
+            Ok(Range::new(vec![RangeItem::Interval(
+                GAIN_RANGE.0,
+                GAIN_RANGE.1,
+            )]))
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        self.gain_element_range(direction, channel, name)?;
+        Ok(GainElementInfo::new(
+            name,
+            "Channel gain",
+            "dB",
+            "Digital gain of the linear demodulator of the channel. The gain of the front end is \
+             shared by all channels and configured in radiod.",
+        ))
+    }
+
+    fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.component_frequency_range(direction, channel, "TUNER")
+    }
+
+    fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.component_frequency(direction, channel, "TUNER")
+    }
+
+    fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        _args: Args,
+    ) -> Result<(), Error> {
+        self.set_component_frequency(direction, channel, "TUNER", frequency)
+    }
+
+    fn frequency_components(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Vec<String>, Error> {
+        self.check(direction, channel)?;
+        Ok(vec!["TUNER".to_string()])
+    }
+
+    fn component_frequency_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        self.component_frequency(direction, channel, name)?;
+        // the range depends on the front end of radiod, which is not reported
+        Err(Error::NotSupported)
+    }
+
+    fn component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        self.check(direction, channel)?;
+        if name != "TUNER" {
+            return Err(Error::ValueError);
+        }
+        self.status().frequency.ok_or(Error::NotFound)
+    }
+
+    fn set_component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        frequency: f64,
+    ) -> Result<(), Error> {
+        self.check(direction, channel)?;
+        if name != "TUNER" || !frequency.is_finite() || frequency < 0.0 {
+            return Err(Error::ValueError);
+        }
+        self.control
+            .command(&[(tag::RADIO_FREQUENCY, Value::Double(frequency))])
+    }
+
+    fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.check(direction, channel)?;
+        self.status().sample_rate.ok_or(Error::NotFound)
+    }
+
+    fn set_sample_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    ) -> Result<(), Error> {
+        let range = self.get_sample_rate_range(direction, channel)?;
+        if !range.contains(rate) {
+            return Err(Error::OutOfRange(range, rate));
+        }
+        self.control
+            .command(&[(tag::OUTPUT_SAMPRATE, Value::Int(rate.round() as u64))])
+    }
+
+    fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        let max = self.status().input_rate.unwrap_or(u32::MAX as f64);
+        Ok(Range::new(vec![RangeItem::Interval(1.0, max)]))
+    }
+
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        self.check(direction, channel)?;
+        match self.status() {
+            Status {
+                low_edge: Some(low),
+                high_edge: Some(high),
+                ..
+            } => Ok(high - low),
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
+        self.check(direction, channel)?;
+        if !bw.is_finite() || bw <= 0.0 {
+            return Err(Error::ValueError);
+        }
+        // symmetric filter around the center frequency
+        self.control.command(&[
+            (tag::LOW_EDGE, Value::Float((-bw / 2.0) as f32)),
+            (tag::HIGH_EDGE, Value::Float((bw / 2.0) as f32)),
+        ])
+    }
+
+    fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        self.check(direction, channel)?;
+        let max = self.status().sample_rate.unwrap_or(u32::MAX as f64);
+        Ok(Range::new(vec![RangeItem::Interval(0.0, max)]))
+    }
+
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+impl crate::RxInterface for Ka9q {}
+
+impl RxStreamer {
+    /// Receive the next packet of the channel, returning `false` on timeout.
+    fn receive(&mut self, deadline: Instant) -> Result<bool, Error> {
+        let socket = self.socket.as_ref().ok_or(Error::Inactive)?;
+        loop {
+            if self.canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(false);
+            }
+            socket.set_read_timeout(Some(left.min(POLL_INTERVAL)))?;
+            let n = match socket.recv(&mut self.packet) {
+                Ok(n) => n,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let Some(rtp) = parse_rtp(&self.packet[..n]) else {
+                continue;
+            };
+            if rtp.ssrc != self.control.ssrc {
+                continue;
+            }
+            if let Some(next) = self.next_timestamp {
+                let gap = rtp.timestamp.wrapping_sub(next) as i32;
+                if gap < 0 {
+                    // late or duplicate packet
+                    self.events.send(StreamEvent::LatePacket);
+                    continue;
+                }
+                if gap > 0 {
+                    log::debug!("ka9q: {gap} samples dropped");
+                    self.events.send(StreamEvent::Overflow);
+                    self.position += gap as u64;
+                }
+            }
+            self.control
+                .encoding
+                .decode(&self.packet[rtp.payload], self.scale, &mut self.samples);
+            self.start = 0;
+            self.next_timestamp = Some(rtp.timestamp.wrapping_add(self.samples.len() as u32));
+            return Ok(true);
+        }
+    }
+}
+
+impl crate::RxStreamer for RxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(MAX_PACKET / 4)
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        self.canceller.reset();
+        let data = self.control.data()?;
+        let socket = join(data, self.control.interface)?;
+        self.socket = Some(socket);
+        self.samples.clear();
+        self.start = 0;
+        self.next_timestamp = None;
+        self.position = 0;
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        // dropping the socket leaves the group
+        self.socket = None;
+        Ok(())
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        if buffers[0].is_empty() {
+            return Ok(0);
+        }
+        if self.start == self.samples.len() {
+            let deadline = Instant::now() + Duration::from_micros(timeout_us.max(0) as u64);
+            if !self.receive(deadline)? {
+                return Err(Error::Timeout);
+            }
+        }
+        let n = buffers[0].len().min(self.samples.len() - self.start);
+        buffers[0][..n].copy_from_slice(&self.samples[self.start..self.start + n]);
+        self.start += n;
+        self.position += n as u64;
+        Ok(n)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        Ok(BufferConfig {
+            mtu: MAX_PACKET / 4,
+            granularity: 1,
+        })
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.events.subscribe())
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        let socket = self.socket.as_ref().ok_or(Error::Inactive)?;
+        self.position += (self.samples.len() - self.start) as u64;
+        self.start = self.samples.len();
+        // drain the socket buffer, the timestamps count the dropped samples
+        socket.set_nonblocking(true)?;
+        while socket.recv(&mut self.packet).is_ok() {}
+        socket.set_nonblocking(false)?;
+        Ok(())
+    }
+}
+
+impl crate::TxStreamer for TxDummy {
+    fn mtu(&self) -> Result<usize, Error> {
+        Err(Error::NotSupported)
+    }
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+    fn write(
+        &mut self,
+        _buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        _end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<usize, Error> {
+        Err(Error::NotSupported)
+    }
+    fn write_all(
+        &mut self,
+        _buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        _end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RxStreamer as _;
+
+    #[test]
+    fn tlv() {
+        let mut packet = Vec::new();
+        encode(&mut packet, tag::OUTPUT_SSRC, Value::Int(1234));
+        encode(&mut packet, tag::RADIO_FREQUENCY, Value::Double(14.074e6));
+        encode(&mut packet, tag::GAIN, Value::Float(-6.0));
+        encode(&mut packet, tag::AGC_ENABLE, Value::Int(0));
+        packet.push(tag::EOL);
+        let tlvs = decode(&packet).unwrap();
+        assert_eq!(tlvs[0], (tag::OUTPUT_SSRC, &[0x04, 0xd2][..]));
+        assert_eq!(float(tlvs[1].1), 14.074e6);
+        assert_eq!(float(tlvs[2].1), -6.0);
+        assert_eq!(tlvs[3], (tag::AGC_ENABLE, &[][..]));
+
+        // long form of the length
+        let long = [tag::DESCRIPTION, 0x81, 3, b'a', b'b', b'c'];
+        assert_eq!(decode(&long).unwrap(), [(tag::DESCRIPTION, &b"abc"[..])]);
+        assert!(decode(&[tag::GAIN, 4, 0]).is_err());
+        assert_eq!(
+            socket_addr(&[239, 1, 2, 3, 0x13, 0x8c]),
+            Some("239.1.2.3:5004".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn stream_from_radiod() {
+        // radiod replying to the sender of unicast commands, streaming to a unicast data socket
+        let radiod = UdpSocket::bind("127.0.0.1:0").unwrap();
+        radiod
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let data = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let args = format!(
+            "driver=ka9q, status={}, ssrc=1234, data={data}",
+            radiod.local_addr().unwrap()
+        );
+        let dev = Ka9q::open(args.as_str()).unwrap();
+
+        let mut buf = vec![0; 1500];
+        let command = |buf: &mut Vec<u8>| {
+            let (n, client) = radiod.recv_from(buf).unwrap();
+            assert_eq!(buf[0], CMD);
+            (
+                decode(&buf[1..n])
+                    .unwrap()
+                    .iter()
+                    .map(|(t, v)| (*t, int(v)))
+                    .collect::<Vec<_>>(),
+                client,
+            )
+        };
+        let (setup, _) = command(&mut buf);
+        assert!(setup.contains(&(tag::OUTPUT_SSRC, 1234)));
+        assert!(setup.contains(&(tag::OUTPUT_CHANNELS, 2)));
+
+        dev.set_frequency(Rx, 0, 7.074e6, Args::new()).unwrap();
+        let (tune, client) = command(&mut buf);
+        let frequency = tune
+            .iter()
+            .find(|(t, _)| *t == tag::RADIO_FREQUENCY)
+            .unwrap();
+        assert_eq!(f64::from_bits(frequency.1), 7.074e6);
+        assert_eq!(dev.frequency(Rx, 0).unwrap(), 7.074e6);
+
+        let mut status = vec![STATUS];
+        encode(&mut status, tag::OUTPUT_SSRC, Value::Int(1234));
+        encode(&mut status, tag::OUTPUT_SAMPRATE, Value::Int(12000));
+        status.push(tag::EOL);
+        radiod.send_to(&status, client).unwrap();
+        let start = Instant::now();
+        while dev.sample_rate(Rx, 0).is_err() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(dev.sample_rate(Rx, 0).unwrap(), 12000.0);
+
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let events = rx.stream_events().unwrap();
+        rx.activate().unwrap();
+        let rtp = |timestamp: u32, ssrc: u32| {
+            let mut p = vec![0x80, 10, 0, 0];
+            p.extend_from_slice(&timestamp.to_be_bytes());
+            p.extend_from_slice(&ssrc.to_be_bytes());
+            for i in 0..4i16 {
+                p.extend_from_slice(&(i * 8192).to_be_bytes());
+                p.extend_from_slice(&(-i * 8192).to_be_bytes());
+            }
+            p
+        };
+        radiod.send_to(&rtp(100, 1234), data).unwrap();
+        radiod.send_to(&rtp(104, 999), data).unwrap();
+        radiod.send_to(&rtp(110, 1234), data).unwrap();
+        radiod.send_to(&rtp(100, 1234), data).unwrap();
+
+        let mut samples = [Complex32::new(0.0, 0.0); 8];
+        assert_eq!(rx.read(&mut [&mut samples], 1_000_000).unwrap(), 4);
+        assert_eq!(samples[1], Complex32::new(0.25, -0.25));
+        assert_eq!(rx.read(&mut [&mut samples], 1_000_000).unwrap(), 4);
+        // six samples were lost between the packets
+        assert_eq!(rx.sample_position().unwrap(), 14);
+        assert!(matches!(
+            rx.read(&mut [&mut samples], 10_000),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Overflow));
+        assert_eq!(events.try_recv(), Ok(StreamEvent::LatePacket));
+    }
+}
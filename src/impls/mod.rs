@@ -1,4 +1,10 @@
 //! Hardware drivers, implementing the [`DeviceTrait`](crate::DeviceTrait).
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+use crate::Error;
+
 #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
 pub mod aaronia;
 #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
@@ -14,11 +20,37 @@ pub mod dummy;
 #[cfg(feature = "dummy")]
 pub use dummy::Dummy;
 
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "file")]
+pub use file::File;
+
+#[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+pub mod hpsdr;
+#[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
+pub use hpsdr::Hpsdr;
+
+#[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+pub mod ka9q;
+#[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
+pub use ka9q::Ka9q;
+
 #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
 pub mod rtlsdr;
 #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
 pub use rtlsdr::RtlSdr;
 
+#[cfg(all(
+    feature = "signalhound",
+    any(target_os = "linux", target_os = "windows")
+))]
+pub mod signalhound;
+#[cfg(all(
+    feature = "signalhound",
+    any(target_os = "linux", target_os = "windows")
+))]
+pub use signalhound::SignalHound;
+
 #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
 pub mod soapy;
 #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
@@ -27,4 +59,405 @@ pub use soapy::Soapy;
 #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
 pub mod hackrfone;
 #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+pub use hackrfone::FrequencyPlan;
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
 pub use hackrfone::HackRfOne;
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+pub use hackrfone::SignalPath;
+
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+mod usb_reader;
+
+/// Map the error of opening an enumerated USB device to an actionable one.
+///
+/// Windows lists USB devices even if no WinUSB driver is bound to them, but opening them fails
+/// with a not-supported or not-found error of the USB stack. On macOS, opening fails if the user
+/// did not grant access to the device or a sandboxed application lacks the USB entitlement.
+/// Other errors are passed through unchanged.
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+pub(crate) fn usb_open_error(device: String, e: Error) -> Error {
+    log::debug!("opening {device} failed: {e}");
+    match usb_failure(&e) {
+        Some(UsbFailure::NoDriver) if cfg!(target_os = "windows") => {
+            Error::UsbDriverMissing(device)
+        }
+        Some(UsbFailure::Access) if cfg!(target_os = "macos") => Error::PermissionDenied {
+            remedy: format!(
+                "allow access to {device} in System Settings > Privacy & Security; sandboxed apps need the com.apple.security.device.usb entitlement"
+            ),
+        },
+        _ => e,
+    }
+}
+
+/// Cause of a failure to open a USB device.
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+#[derive(Debug, PartialEq, Eq)]
+enum UsbFailure {
+    /// No usable driver is bound to the device.
+    NoDriver,
+    /// Access to the device was denied.
+    Access,
+}
+
+/// Classify the error kind of the USB stack in the error or its sources.
+///
+/// nusb, used by the HackRF driver, reports `std::io::Error`s; rusb, used by the RTL-SDR
+/// driver, has its own error type.
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+fn usb_failure(e: &Error) -> Option<UsbFailure> {
+    let mut cur: Option<&(dyn std::error::Error + 'static)> = Some(e);
+    while let Some(err) = cur {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                std::io::ErrorKind::PermissionDenied => return Some(UsbFailure::Access),
+                std::io::ErrorKind::Unsupported | std::io::ErrorKind::NotFound => {
+                    return Some(UsbFailure::NoDriver);
+                }
+                _ => {}
+            }
+        }
+        #[cfg(feature = "rtlsdr")]
+        match err.downcast_ref::<rusb::Error>() {
+            Some(rusb::Error::Access) => return Some(UsbFailure::Access),
+            Some(rusb::Error::NotSupported | rusb::Error::NotFound) => {
+                return Some(UsbFailure::NoDriver);
+            }
+            _ => {}
+        }
+        cur = err.source();
+    }
+    None
+}
+
+/// Scan for USB devices, waiting up to `usb_wait_ms` (from `args`) for one to show up.
+///
+/// On macOS, devices may only appear once the user confirmed a system prompt, i.e., some time
+/// after they were plugged in. The wait defaults to zero.
+#[cfg(all(
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+pub(crate) fn wait_for_usb<T>(
+    args: &crate::Args,
+    mut scan: impl FnMut() -> Result<Vec<T>, Error>,
+) -> Result<Vec<T>, Error> {
+    let wait = std::time::Duration::from_millis(args.get::<u64>("usb_wait_ms").unwrap_or(0));
+    let deadline = std::time::Instant::now() + wait;
+    loop {
+        let devs = scan()?;
+        if !devs.is_empty() || std::time::Instant::now() >= deadline {
+            return Ok(devs);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+/// USB transfer settings of nusb-based drivers.
+///
+/// The best values differ between hosts: a Raspberry Pi may need fewer or smaller transfers than
+/// a desktop, which in turn may need more of them to ride out scheduling hiccups. They are set
+/// with the arguments
+/// - `usb_xfers`: number of transfers queued,
+/// - `usb_xfer_size`: size of a transfer in bytes, a multiple of the USB packet size,
+/// - `usb_timeout_ms`: time without a completed transfer, after which a read fails with
+///   [`Error::Timeout`], even if it was asked to block.
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct UsbConfig {
+    pub transfers: usize,
+    pub transfer_size: usize,
+    pub timeout: Option<std::time::Duration>,
+}
+
+#[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+impl UsbConfig {
+    /// Override the settings with the ones in `args`.
+    ///
+    /// Fails with [`Error::ValueError`], if a setting is malformed, zero, or the transfer size is
+    /// not a multiple of `packet_size`.
+    pub(crate) fn with_args(self, args: &crate::Args, packet_size: usize) -> Result<Self, Error> {
+        fn get<T>(args: &crate::Args, key: &str) -> Result<Option<T>, Error>
+        where
+            T: std::str::FromStr,
+            T::Err: std::error::Error,
+        {
+            match args.get::<T>(key) {
+                Ok(v) => Ok(Some(v)),
+                Err(Error::NotFound) => Ok(None),
+                Err(_) => {
+                    log::warn!("invalid {key}");
+                    Err(Error::ValueError)
+                }
+            }
+        }
+        let config = Self {
+            transfers: get(args, "usb_xfers")?.unwrap_or(self.transfers),
+            transfer_size: get(args, "usb_xfer_size")?.unwrap_or(self.transfer_size),
+            timeout: get(args, "usb_timeout_ms")?
+                .map(std::time::Duration::from_millis)
+                .or(self.timeout),
+        };
+        if config.transfers == 0
+            || config.transfer_size == 0
+            || !config.transfer_size.is_multiple_of(packet_size)
+        {
+            log::warn!("invalid USB transfer settings: {config:?}");
+            return Err(Error::ValueError);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "rtlsdr", feature = "hackrfone"),
+    not(target_arch = "wasm32")
+))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usb_failures() {
+        let io = |kind: std::io::ErrorKind| Error::Io(kind.into());
+        assert_eq!(
+            usb_failure(&io(std::io::ErrorKind::Unsupported)),
+            Some(UsbFailure::NoDriver)
+        );
+        assert_eq!(
+            usb_failure(&io(std::io::ErrorKind::PermissionDenied)),
+            Some(UsbFailure::Access)
+        );
+        assert_eq!(usb_failure(&io(std::io::ErrorKind::TimedOut)), None);
+        assert_eq!(usb_failure(&Error::DeviceError), None);
+        // a timeout is passed through on all platforms
+        assert!(matches!(
+            usb_open_error("usb".to_string(), io(std::io::ErrorKind::TimedOut)),
+            Error::Io(_)
+        ));
+
+        #[cfg(feature = "rtlsdr")]
+        {
+            let rtl = |e| Error::RtlSdr(seify_rtlsdr::error::RtlsdrError::Usb(e));
+            assert_eq!(
+                usb_failure(&rtl(rusb::Error::NotSupported)),
+                Some(UsbFailure::NoDriver)
+            );
+            assert_eq!(
+                usb_failure(&rtl(rusb::Error::Access)),
+                Some(UsbFailure::Access)
+            );
+            assert_eq!(usb_failure(&rtl(rusb::Error::Busy)), None);
+        }
+    }
+
+    #[cfg(feature = "hackrfone")]
+    #[test]
+    fn usb_config() {
+        use crate::Args;
+
+        let defaults = UsbConfig {
+            transfers: 16,
+            transfer_size: 131072,
+            timeout: None,
+        };
+        let args: Args = "usb_xfers=4, usb_xfer_size=16384, usb_timeout_ms=500"
+            .try_into()
+            .unwrap();
+        let c = defaults.with_args(&args, 512).unwrap();
+        assert_eq!(c.transfers, 4);
+        assert_eq!(c.transfer_size, 16384);
+        assert_eq!(c.timeout, Some(std::time::Duration::from_millis(500)));
+        assert_eq!(defaults.with_args(&Args::new(), 512).unwrap(), defaults);
+
+        for invalid in ["usb_xfers=0", "usb_xfer_size=1000", "usb_timeout_ms=soon"] {
+            let args: Args = invalid.try_into().unwrap();
+            assert!(defaults.with_args(&args, 512).is_err(), "{invalid}");
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "dummy", feature = "file")))]
+mod conformance {
+    //! Call every [`DeviceTrait`](crate::DeviceTrait) method on the drivers that can be
+    //! instantiated without hardware, checking that none of them panics.
+    use std::panic::catch_unwind;
+    use std::panic::AssertUnwindSafe;
+
+    use crate::Args;
+    use crate::DeviceTrait;
+    use crate::Direction;
+    use crate::RxStreamer;
+    use crate::TxStreamer;
+
+    fn no_panic<R>(what: &str, f: impl FnOnce() -> R) {
+        if catch_unwind(AssertUnwindSafe(f)).is_err() {
+            panic!("{what} panicked");
+        }
+    }
+
+    fn check<D: DeviceTrait>(dev: &D) {
+        no_panic("driver", || dev.driver());
+        no_panic("id", || dev.id());
+        no_panic("info", || dev.info());
+        no_panic("reference_docs", || dev.reference_docs());
+        no_panic("extensions", || dev.extensions());
+        no_panic("driver_event_log", || dev.driver_event_log());
+        no_panic("list_loopback_modes", || dev.list_loopback_modes());
+        no_panic("set_loopback_mode", || dev.set_loopback_mode("X"));
+        no_panic("loopback_mode", || dev.loopback_mode());
+        no_panic("list_time_sources", || dev.list_time_sources());
+        no_panic("time_source", || dev.time_source());
+        no_panic("set_time_source", || dev.set_time_source("X"));
+        no_panic("gps_locked", || dev.gps_locked());
+        no_panic("gps_location", || dev.gps_location());
+        no_panic("time_discipline", || dev.time_discipline());
+        no_panic("execute_at", || dev.execute_at(0, &[]));
+        no_panic("coherency_groups", || dev.coherency_groups());
+        no_panic("spectrum_streamer", || {
+            dev.spectrum_streamer(0, Args::new())
+        });
+        for dir in [Direction::Rx, Direction::Tx] {
+            no_panic("num_channels", || dev.num_channels(dir));
+            for ch in 0..3 {
+                let m = |name: &str| format!("{name}({dir:?}, {ch})");
+                no_panic(&m("full_duplex"), || dev.full_duplex(dir, ch));
+                no_panic(&m("spurs"), || dev.spurs(dir, ch));
+                no_panic(&m("antennas"), || dev.antennas(dir, ch));
+                no_panic(&m("antenna"), || dev.antenna(dir, ch));
+                no_panic(&m("set_antenna"), || dev.set_antenna(dir, ch, "X"));
+                no_panic(&m("supports_agc"), || dev.supports_agc(dir, ch));
+                no_panic(&m("enable_agc"), || dev.enable_agc(dir, ch, false));
+                no_panic(&m("agc"), || dev.agc(dir, ch));
+                no_panic(&m("supports_agc_element"), || {
+                    dev.supports_agc_element(dir, ch, "")
+                });
+                no_panic(&m("enable_agc_element"), || {
+                    dev.enable_agc_element(dir, ch, "", false)
+                });
+                no_panic(&m("agc_element"), || dev.agc_element(dir, ch, ""));
+                no_panic(&m("gain_elements"), || dev.gain_elements(dir, ch));
+                no_panic(&m("set_gain"), || dev.set_gain(dir, ch, 0.0));
+                no_panic(&m("gain"), || dev.gain(dir, ch));
+                no_panic(&m("gain_range"), || dev.gain_range(dir, ch));
+                for el in ["RF", "X"] {
+                    no_panic(&m("set_gain_element"), || {
+                        dev.set_gain_element(dir, ch, el, 0.0)
+                    });
+                    no_panic(&m("gain_element"), || dev.gain_element(dir, ch, el));
+                    no_panic(&m("gain_element_range"), || {
+                        dev.gain_element_range(dir, ch, el)
+                    });
+                    no_panic(&m("gain_element_info"), || {
+                        dev.gain_element_info(dir, ch, el)
+                    });
+                }
+                no_panic(&m("frequency_range"), || dev.frequency_range(dir, ch));
+                no_panic(&m("frequency"), || dev.frequency(dir, ch));
+                no_panic(&m("set_frequency"), || {
+                    dev.set_frequency(dir, ch, 100e6, Args::new())
+                });
+                no_panic(&m("frequency_components"), || {
+                    dev.frequency_components(dir, ch)
+                });
+                for c in ["freq", "X"] {
+                    no_panic(&m("component_frequency_range"), || {
+                        dev.component_frequency_range(dir, ch, c)
+                    });
+                    no_panic(&m("component_frequency"), || {
+                        dev.component_frequency(dir, ch, c)
+                    });
+                    no_panic(&m("set_component_frequency"), || {
+                        dev.set_component_frequency(dir, ch, c, 100e6)
+                    });
+                }
+                no_panic(&m("sample_rate"), || dev.sample_rate(dir, ch));
+                no_panic(&m("set_sample_rate"), || dev.set_sample_rate(dir, ch, 1e6));
+                no_panic(&m("get_sample_rate_range"), || {
+                    dev.get_sample_rate_range(dir, ch)
+                });
+                no_panic(&m("bandwidth"), || dev.bandwidth(dir, ch));
+                no_panic(&m("set_bandwidth"), || dev.set_bandwidth(dir, ch, 1e6));
+                no_panic(&m("get_bandwidth_range"), || {
+                    dev.get_bandwidth_range(dir, ch)
+                });
+                no_panic(&m("has_dc_offset_mode"), || dev.has_dc_offset_mode(dir, ch));
+                no_panic(&m("set_dc_offset_mode"), || {
+                    dev.set_dc_offset_mode(dir, ch, true)
+                });
+                no_panic(&m("dc_offset_mode"), || dev.dc_offset_mode(dir, ch));
+            }
+        }
+
+        for channels in [&[0][..], &[1], &[0, 1]] {
+            no_panic("rx_streamer", || {
+                if let Ok(mut rx) = dev.rx_streamer(channels, Args::new()) {
+                    let _ = rx.mtu();
+                    let _ = rx.canceller();
+                    if rx.activate().is_ok() {
+                        let mut buf = [num_complex::Complex32::new(0.0, 0.0); 16];
+                        let _ = rx.read(&mut [&mut buf], 1000);
+                        let _ = rx.deactivate();
+                    }
+                }
+            });
+            no_panic("tx_streamer", || {
+                if let Ok(mut tx) = dev.tx_streamer(channels, Args::new()) {
+                    let _ = tx.mtu();
+                    let _ = tx.canceller();
+                    if tx.activate().is_ok() {
+                        let buf = [num_complex::Complex32::new(0.0, 0.0); 16];
+                        let _ = tx.write(&[&buf], None, false, 1000);
+                        let _ = tx.write_all(&[&buf], None, true, 1000);
+                        let _ = tx.deactivate();
+                    }
+                }
+            });
+        }
+    }
+
+    #[cfg(feature = "dummy")]
+    /// Stream after the device was dropped.
+    fn outlives_device<D: DeviceTrait>(dev: D) {
+        let mut rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        drop(dev);
+        rx.activate().unwrap();
+        let mut buf = vec![num_complex::Complex32::new(0.0, 0.0); 64];
+        assert!(rx.read(&mut [&mut buf], 1_000_000).unwrap() > 0);
+        rx.deactivate().unwrap();
+    }
+
+    #[test]
+    fn dummy() {
+        let dev = super::Dummy::open(Args::new()).unwrap();
+        check(&dev);
+        check(&crate::AnyDevice::from(dev.clone()));
+        let generic = <crate::GenericDevice as crate::FromImpl>::from_impl(dev.clone());
+        check(&generic);
+        outlives_device(generic);
+        outlives_device(dev);
+    }
+
+    #[cfg(feature = "file")]
+    #[test]
+    fn file() {
+        let dev = super::File::from_bytes(vec![0u8; 64], "repeat=true").unwrap();
+        check(&dev);
+        let generic = <crate::GenericDevice as crate::FromImpl>::from_impl(dev.clone());
+        check(&generic);
+        outlives_device(generic);
+        outlives_device(dev);
+    }
+}
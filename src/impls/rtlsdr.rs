@@ -1,54 +1,157 @@
 //! RTL SDR
 use num_complex::Complex32;
 use seify_rtlsdr::enumerate;
+pub use seify_rtlsdr::DirectSampleMode;
 use seify_rtlsdr::RtlSdr as Sdr;
 use seify_rtlsdr::TunerGain;
 use std::any::Any;
+use std::ops::Deref;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+use super::usb_reader::UsbReader;
 
 use crate::Args;
+use crate::BufferConfig;
+use crate::CoherencyGroup;
 use crate::DeviceTrait;
 use crate::Direction;
 use crate::Direction::*;
 use crate::Driver;
 use crate::Error;
+use crate::GainElementInfo;
+use crate::Latency;
 use crate::Range;
 use crate::RangeItem;
+use crate::ReferenceDoc;
+use crate::Spur;
+use crate::StreamCanceller;
+use crate::StreamFormat;
 
+/// Size of the transfer buffer in bytes, for [`Latency::Balanced`].
 const MTU: usize = 4 * 16384;
+/// Number of transfers buffered between the reader thread and `read()`.
+const TRANSFERS: usize = 16;
+/// Time to wait for the reader thread to finish its pending transfer, when the stream is
+/// stopped.
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// RTL-SDR handle, shared by the device and its streamer.
+///
+/// Control calls lock `control` for the whole operation, so that paired calls, like tuning and
+/// resetting the buffer, are atomic. The streamer reads without the lock, so that control calls
+/// do not wait for a bulk transfer.
+struct Handle {
+    sdr: Sdr,
+    control: Mutex<()>,
+}
+
+// SAFETY: the driver crate does not mark its handle `Send` or `Sync`. All its methods take
+// `&self` and access the device through libusb, which is thread safe. The tuner state is only
+// changed by control calls, which are serialized by `control`; the streamer only does bulk reads.
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Handle {
+    /// Lock the control plane for one operation.
+    fn control(&self) -> ControlGuard<'_> {
+        ControlGuard {
+            sdr: &self.sdr,
+            _guard: self.control.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+/// Exclusive access to the control plane of the RTL-SDR, see [`RtlSdr::rtlsdr_device`].
+pub struct ControlGuard<'a> {
+    sdr: &'a Sdr,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl Deref for ControlGuard<'_> {
+    type Target = Sdr;
+
+    fn deref(&self) -> &Sdr {
+        self.sdr
+    }
+}
 
 /// Rusty RTL-SDR driver
 #[derive(Clone)]
 pub struct RtlSdr {
-    dev: Arc<Sdr>,
+    dev: Arc<Handle>,
     index: usize,
     i: Arc<Mutex<Inner>>,
 }
-unsafe impl Send for RtlSdr {}
-unsafe impl Sync for RtlSdr {}
 
 struct Inner {
     gain: TunerGain,
+    digital_agc: bool,
+    test_mode: bool,
 }
 
 /// Rusty RTL-SDR RX streamer
 pub struct RxStreamer {
-    dev: Arc<Sdr>,
-    buf: [u8; MTU],
+    dev: Arc<Handle>,
+    // size of a transfer in bytes
+    transfer: usize,
+    reader: Option<UsbReader>,
+    // reader thread that did not stop, as it is stuck in a transfer of a stalled device
+    stalled: Option<UsbReader>,
+    buf: Vec<u8>,
+    // start of the unread bytes of the last transfer
+    offset: usize,
+    position: u64,
+    canceller: StreamCanceller,
+    scale: f32,
+    // how late the last timed activation started
+    activation_error_ns: Option<i64>,
 }
 
-unsafe impl Send for RxStreamer {}
-
 impl RxStreamer {
-    fn new(dev: Arc<Sdr>) -> Self {
-        Self { dev, buf: [0; MTU] }
+    fn new(dev: Arc<Handle>, latency: Latency) -> Self {
+        let transfer = match latency {
+            Latency::Low => MTU / 4,
+            Latency::Balanced => MTU,
+            Latency::Throughput => MTU * 4,
+        };
+        Self {
+            dev,
+            transfer,
+            reader: None,
+            stalled: None,
+            buf: Vec::new(),
+            offset: 0,
+            position: 0,
+            canceller: StreamCanceller::new(),
+            scale: 1.0,
+            activation_error_ns: None,
+        }
+    }
+
+    /// Make samples available in the buffer, transferring them from the device if it is empty.
+    /// Returns the number of samples available, at most `len`.
+    fn fill(&mut self, len: usize, timeout_us: i64) -> Result<usize, Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        if len == 0 {
+            return Ok(0);
+        }
+        if self.offset == self.buf.len() {
+            let reader = self.reader.as_ref().ok_or(Error::Inactive)?;
+            let buf = reader.recv(&self.canceller, timeout_us, None)?;
+            reader.recycle(std::mem::replace(&mut self.buf, buf));
+            self.offset = 0;
+        }
+        Ok(len.min((self.buf.len() - self.offset) / 2))
     }
 }
 
 /// Rusty RTL-SDR TX dummy streamer
 pub struct TxDummy;
-unsafe impl Send for TxDummy {}
 
 impl RtlSdr {
     /// Get a list of detected RTL-SDR devices
@@ -66,28 +169,85 @@ impl RtlSdr {
     }
     /// Create an RTL-SDR device
     ///
-    /// At the moment, only an `index` argument is considered, which defines the index of the
-    /// devices in the list returned by the driver.
+    /// The `index` argument defines the index of the devices in the list returned by the driver.
+    /// With `usb_wait_ms`, opening waits for a device to show up. The crystal frequencies can be
+    /// corrected with `xtal_freq` and `tuner_xtal_freq` (in Hz) and direct sampling is enabled with
+    /// `direct_sampling=i` or `direct_sampling=q`. The transfer size of streams follows their
+    /// `latency` argument.
     pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
         let args = args.try_into().or(Err(Error::ValueError))?;
         let index = args.get::<usize>("index").unwrap_or(0);
-        let rtls = enumerate().or(Err(Error::DeviceError))?;
+        let rtls = super::wait_for_usb(&args, || enumerate().or(Err(Error::DeviceError)))?;
         if index >= rtls.len() {
             return Err(Error::NotFound);
         }
-        #[allow(clippy::arc_with_non_send_sync)]
-        let dev = Arc::new(Sdr::open(index)?);
+        let dev = Sdr::open(index)
+            .map_err(|e| super::usb_open_error(format!("rtlsdr (index {index})"), e.into()))?;
         dev.set_tuner_gain(TunerGain::Auto)?;
         dev.set_bias_tee(false)?;
+        dev.set_agc_mode(false)?;
+        if args.get::<u32>("xtal_freq").is_ok() || args.get::<u32>("tuner_xtal_freq").is_ok() {
+            let (rtl, tuner) = dev.get_xtal_freq()?;
+            dev.set_xtal_freq(
+                args.get("xtal_freq").unwrap_or(rtl),
+                args.get("tuner_xtal_freq").unwrap_or(tuner),
+            )?;
+        }
+        if let Ok(mode) = args.get::<String>("direct_sampling") {
+            dev.set_direct_sampling(parse_direct_sampling(&mode)?)?;
+        }
         let dev = RtlSdr {
-            dev,
+            dev: Arc::new(Handle {
+                sdr: dev,
+                control: Mutex::new(()),
+            }),
             index,
             i: Arc::new(Mutex::new(Inner {
                 gain: TunerGain::Auto,
+                digital_agc: false,
+                test_mode: false,
             })),
         };
         Ok(dev)
     }
+    /// Type of the tuner chip, e.g., `R820T` or `E4000`.
+    ///
+    /// Gain steps and frequency ranges depend on the tuner.
+    pub fn tuner(&self) -> String {
+        self.dev.control().get_tuner_type().to_string()
+    }
+    /// Crystal frequencies of the RTL2832 and the tuner in Hz.
+    pub fn xtal_freq(&self) -> Result<(u32, u32), Error> {
+        Ok(self.dev.control().get_xtal_freq()?)
+    }
+    /// Set the crystal frequencies of the RTL2832 and the tuner in Hz.
+    ///
+    /// This allows calibration software to correct crystal offsets more precisely than with a
+    /// ppm frequency correction.
+    pub fn set_xtal_freq(&self, rtl_freq: u32, tuner_freq: u32) -> Result<(), Error> {
+        Ok(self.dev.control().set_xtal_freq(rtl_freq, tuner_freq)?)
+    }
+    /// Sample directly from the I or Q ADC, bypassing the tuner (e.g., for HF reception).
+    pub fn set_direct_sampling(&self, mode: DirectSampleMode) -> Result<(), Error> {
+        Ok(self.dev.control().set_direct_sampling(mode)?)
+    }
+    /// Underlying RTL-SDR handle, as an escape hatch for features that Seify does not expose.
+    ///
+    /// Control calls of the device are blocked while the guard is held; the streamer keeps
+    /// reading. Settings changed through the handle bypass Seify, e.g., the gain mode that Seify
+    /// tracks.
+    pub fn rtlsdr_device(&self) -> ControlGuard<'_> {
+        self.dev.control()
+    }
+}
+
+fn parse_direct_sampling(mode: &str) -> Result<DirectSampleMode, Error> {
+    match mode {
+        "off" | "0" => Ok(DirectSampleMode::Off),
+        "i" | "1" => Ok(DirectSampleMode::On),
+        "q" | "2" => Ok(DirectSampleMode::OnSwap),
+        _ => Err(Error::ValueError),
+    }
 }
 
 impl DeviceTrait for RtlSdr {
@@ -111,7 +271,20 @@ impl DeviceTrait for RtlSdr {
     }
 
     fn info(&self) -> Result<Args, Error> {
-        format!("driver=rtlsdr, index={}", self.index).try_into()
+        let mut args: Args = format!("driver=rtlsdr, index={}", self.index).try_into()?;
+        args.set("tuner", self.tuner());
+        if let Ok((rtl, tuner)) = self.xtal_freq() {
+            args.set("xtal_freq", rtl.to_string());
+            args.set("tuner_xtal_freq", tuner.to_string());
+        }
+        Ok(args)
+    }
+
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(vec![ReferenceDoc::new(
+            "Osmocom rtl-sdr",
+            "https://osmocom.org/projects/rtl-sdr/wiki",
+        )])
     }
 
     fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
@@ -125,11 +298,18 @@ impl DeviceTrait for RtlSdr {
         Ok(false)
     }
 
-    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        Ok(vec![CoherencyGroup::new(vec![(Rx, 0)], true)])
+    }
+
+    fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
         if channels != [0] {
             Err(Error::ValueError)
         } else {
-            Ok(RxStreamer::new(self.dev.clone()))
+            Ok(RxStreamer::new(
+                self.dev.clone(),
+                Latency::from_args(&args)?,
+            ))
         }
     }
 
@@ -182,15 +362,19 @@ impl DeviceTrait for RtlSdr {
     }
 
     fn enable_agc(&self, direction: Direction, channel: usize, agc: bool) -> Result<(), Error> {
-        let gains = self.dev.get_tuner_gains().or(Err(Error::DeviceError))?;
+        let gains = self
+            .dev
+            .control()
+            .get_tuner_gains()
+            .or(Err(Error::DeviceError))?;
         if matches!(direction, Rx) && channel == 0 {
             let mut inner = self.i.lock().unwrap();
             if agc {
                 inner.gain = TunerGain::Auto;
-                Ok(self.dev.set_tuner_gain(inner.gain.clone())?)
+                Ok(self.dev.control().set_tuner_gain(inner.gain.clone())?)
             } else {
                 inner.gain = TunerGain::Manual(gains[gains.len() / 2]);
-                Ok(self.dev.set_tuner_gain(inner.gain.clone())?)
+                Ok(self.dev.control().set_tuner_gain(inner.gain.clone())?)
             }
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
@@ -210,8 +394,57 @@ impl DeviceTrait for RtlSdr {
         }
     }
 
+    fn supports_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<bool, Error> {
+        if matches!(direction, Rx) && channel == 0 && matches!(name, "TUNER" | "DIGITAL") {
+            Ok(true)
+        } else if matches!(direction, Rx) {
+            Err(Error::ValueError)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
+    fn enable_agc_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        agc: bool,
+    ) -> Result<(), Error> {
+        if matches!(direction, Rx) && channel == 0 && name == "TUNER" {
+            self.enable_agc(direction, channel, agc)
+        } else if matches!(direction, Rx) && channel == 0 && name == "DIGITAL" {
+            let mut inner = self.i.lock().unwrap();
+            self.dev.control().set_agc_mode(agc)?;
+            inner.digital_agc = agc;
+            Ok(())
+        } else if matches!(direction, Rx) {
+            Err(Error::ValueError)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
+    fn agc_element(&self, direction: Direction, channel: usize, name: &str) -> Result<bool, Error> {
+        if matches!(direction, Rx) && channel == 0 && name == "TUNER" {
+            self.agc(direction, channel)
+        } else if matches!(direction, Rx) && channel == 0 && name == "DIGITAL" {
+            Ok(self.i.lock().unwrap().digital_agc)
+        } else if matches!(direction, Rx) {
+            Err(Error::ValueError)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
     fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
         self.set_gain_element(direction, channel, "TUNER", gain)
+            .map(drop)
     }
 
     fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
@@ -228,16 +461,21 @@ impl DeviceTrait for RtlSdr {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
+    ) -> Result<f64, Error> {
         let r = self.gain_range(direction, channel)?;
         if r.contains(gain) && name == "TUNER" {
             let mut inner = self.i.lock().unwrap();
             inner.gain = TunerGain::Manual((gain * 10.0) as i32);
-            Ok(self.dev.set_tuner_gain(inner.gain.clone())?)
+            self.dev.control().set_tuner_gain(inner.gain.clone())?;
         } else {
             log::warn!("Gain out of range");
-            Err(Error::OutOfRange(r, gain))
+            return Err(Error::OutOfRange(r, gain));
         }
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
     }
 
     fn gain_element(
@@ -250,7 +488,7 @@ impl DeviceTrait for RtlSdr {
             let inner = self.i.lock().unwrap();
             match inner.gain {
                 TunerGain::Auto => Ok(None),
-                TunerGain::Manual(i) => Ok(Some(i as f64)),
+                TunerGain::Manual(i) => Ok(Some(i as f64 / 10.0)),
             }
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
@@ -274,6 +512,27 @@ impl DeviceTrait for RtlSdr {
         }
     }
 
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        if matches!(direction, Rx) && channel == 0 && name == "TUNER" {
+            Ok(GainElementInfo::new(
+                name,
+                "Tuner gain",
+                "dB",
+                "Total gain of the tuner. librtlsdr takes it in tenths of a dB and rounds it to the \
+                 closest gain step of the tuner chip.",
+            ))
+        } else if matches!(direction, Rx) {
+            Err(Error::ValueError)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
     fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
         self.component_frequency_range(direction, channel, "TUNER")
     }
@@ -328,7 +587,7 @@ impl DeviceTrait for RtlSdr {
         name: &str,
     ) -> Result<f64, Error> {
         if matches!(direction, Rx) && channel == 0 && name == "TUNER" {
-            Ok(self.dev.get_center_freq() as f64)
+            Ok(self.dev.control().get_center_freq() as f64)
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
         } else {
@@ -350,8 +609,21 @@ impl DeviceTrait for RtlSdr {
                 .contains(frequency)
             && name == "TUNER"
         {
-            self.dev.set_center_freq(frequency as u32)?;
-            Ok(self.dev.reset_buffer()?)
+            // the driver takes whole Hz and reports them back, not the frequency of the PLL
+            let dev = self.dev.control();
+            dev.set_center_freq(frequency.round() as u32)?;
+            Ok(dev.reset_buffer()?)
+        } else if matches!(direction, Rx) {
+            Err(Error::ValueError)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
+    fn spurs(&self, direction: Direction, channel: usize) -> Result<Vec<Spur>, Error> {
+        if matches!(direction, Rx) && channel == 0 {
+            // DC offset of the RTL2832U and harmonics of the 28.8 MHz reference
+            Ok(vec![Spur::LoOffset(0.0), Spur::Harmonics(28.8e6)])
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
         } else {
@@ -361,7 +633,7 @@ impl DeviceTrait for RtlSdr {
 
     fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
         if matches!(direction, Rx) && channel == 0 {
-            Ok(self.dev.get_sample_rate() as f64)
+            Ok(self.dev.control().get_sample_rate() as f64)
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
         } else {
@@ -381,8 +653,9 @@ impl DeviceTrait for RtlSdr {
                 .get_sample_rate_range(direction, channel)?
                 .contains(rate)
         {
-            self.dev.set_tuner_bandwidth(rate as u32)?;
-            Ok(self.dev.set_sample_rate(rate as u32)?)
+            let dev = self.dev.control();
+            dev.set_tuner_bandwidth(rate as u32)?;
+            Ok(dev.set_sample_rate(rate as u32)?)
         } else if matches!(direction, Rx) {
             Err(Error::ValueError)
         } else {
@@ -408,7 +681,7 @@ impl DeviceTrait for RtlSdr {
     }
 
     fn set_bandwidth(&self, _direction: Direction, _channel: usize, bw: f64) -> Result<(), Error> {
-        Ok(self.dev.set_tuner_bandwidth(bw as _)?)
+        Ok(self.dev.control().set_tuner_bandwidth(bw as _)?)
     }
 
     fn get_bandwidth_range(&self, _direction: Direction, _channel: usize) -> Result<Range, Error> {
@@ -431,48 +704,164 @@ impl DeviceTrait for RtlSdr {
     fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
         Err(Error::NotSupported)
     }
+
+    fn list_loopback_modes(&self) -> Result<Vec<String>, Error> {
+        Ok(vec!["none".to_string(), "counter".to_string()])
+    }
+
+    fn set_loopback_mode(&self, mode: &str) -> Result<(), Error> {
+        let test_mode = match mode {
+            "none" => false,
+            "counter" => true,
+            _ => return Err(Error::ValueError),
+        };
+        let mut inner = self.i.lock().unwrap();
+        self.dev.control().set_testmode(test_mode)?;
+        inner.test_mode = test_mode;
+        Ok(())
+    }
+
+    fn loopback_mode(&self) -> Result<String, Error> {
+        if self.i.lock().unwrap().test_mode {
+            Ok("counter".to_string())
+        } else {
+            Ok("none".to_string())
+        }
+    }
 }
 
+impl crate::RxInterface for RtlSdr {}
+
 impl crate::RxStreamer for RxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
-        Ok(MTU)
+        Ok(self.transfer / 2)
     }
-    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        self.dev.reset_buffer().or(Err(Error::DeviceError))
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        // never start a second reader next to one that still owns the previous stream
+        if let Some(reader) = self.stalled.as_mut() {
+            if !reader.stop(Duration::ZERO) {
+                return Err(Error::Busy(Rx));
+            }
+            self.stalled = None;
+        }
+        self.deactivate_at(None)?;
+        self.canceller.reset();
+        self.position = 0;
+        self.offset = self.buf.len();
+        // the stream starts with the reset of the sample FIFO, timed on the host clock
+        let deadline = time_ns.map(crate::time::deadline);
+        if let Some(deadline) = deadline {
+            crate::time::wait_until(deadline);
+        }
+        self.dev
+            .control()
+            .reset_buffer()
+            .or(Err(Error::DeviceError))?;
+        let dev = self.dev.clone();
+        let transfer = self.transfer;
+        self.reader = Some(UsbReader::spawn("rtlsdr-rx", TRANSFERS, move |buf| {
+            buf.resize(transfer, 0);
+            // without the control lock, so that settings can change while streaming
+            let n = dev.sdr.read_sync(buf)?;
+            buf.truncate(n & !1);
+            Ok(())
+        })?);
+        self.activation_error_ns = deadline.map(crate::time::late_ns);
+        Ok(())
     }
     fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        // the device streams until it is closed, so the pending transfer completes
+        if let Some(mut reader) = self.reader.take() {
+            if !reader.stop(STOP_TIMEOUT) {
+                self.stalled = Some(reader);
+                return Err(Error::Timeout);
+            }
+        }
         Ok(())
     }
-    fn read(&mut self, buffers: &mut [&mut [Complex32]], _timeout_us: i64) -> Result<usize, Error> {
-        debug_assert_eq!(buffers.len(), 1);
-        // make len multiple of 256 to make u multiple of 512
-        let len = std::cmp::min(buffers[0].len(), MTU / 2);
-        let len = len & !0xff;
-        if len == 0 {
-            return Ok(0);
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        let n = self.fill(buffers[0].len(), timeout_us)?;
+        let iq = self.buf[self.offset..].chunks_exact(2);
+        let scale = self.scale / 128.0;
+        for (s, iq) in buffers[0][..n].iter_mut().zip(iq) {
+            *s = Complex32::new(
+                (iq[0] as f32 - 127.0) * scale,
+                (iq[1] as f32 - 127.0) * scale,
+            );
         }
-        let n = self.dev.read_sync(&mut self.buf[0..len * 2])?;
-        debug_assert_eq!(n % 2, 0);
+        self.offset += n * 2;
+        self.position += n as u64;
+        Ok(n)
+    }
+    fn native_format(&self) -> StreamFormat {
+        StreamFormat::Cu8
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if format != StreamFormat::Cu8 {
+            return crate::sample::read_converted(self, format, buffers, timeout_us);
+        }
+        // raw samples of the ADC, the output scale only applies to converted samples
+        let n = self.fill(buffers[0].len() / 2, timeout_us)?;
+        buffers[0][..n * 2].copy_from_slice(&self.buf[self.offset..self.offset + n * 2]);
+        self.offset += n * 2;
+        self.position += n as u64;
+        Ok(n)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        Ok(BufferConfig {
+            mtu: self.transfer / 2,
+            granularity: self.transfer / 2,
+        })
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
 
-        for i in 0..n / 2 {
-            buffers[0][i] = Complex32::new(
-                (self.buf[i * 2] as f32 - 127.0) / 128.0,
-                (self.buf[i * 2 + 1] as f32 - 127.0) / 128.0,
-            );
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
         }
-        Ok(n / 2)
+        self.scale = scale;
+        Ok(())
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.activation_error_ns.ok_or(Error::NotFound)
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        let reader = self.reader.as_ref().ok_or(Error::Inactive)?;
+        // samples dropped in the FIFO are not counted, only the ones of the queued transfers
+        let mut dropped = (self.buf.len() - self.offset) / 2;
+        self.offset = self.buf.len();
+        while let Some(buf) = reader.try_recv() {
+            let buf = buf?;
+            dropped += buf.len() / 2;
+            reader.recycle(buf);
+        }
+        self.position += dropped as u64;
+        self.dev
+            .control()
+            .reset_buffer()
+            .or(Err(Error::DeviceError))
     }
 }
 
 impl crate::TxStreamer for TxDummy {
     fn mtu(&self) -> Result<usize, Error> {
-        unreachable!()
+        Err(Error::NotSupported)
     }
     fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        unreachable!()
+        Err(Error::NotSupported)
     }
     fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
-        unreachable!()
+        Err(Error::NotSupported)
     }
     fn write(
         &mut self,
@@ -481,7 +870,7 @@ impl crate::TxStreamer for TxDummy {
         _end_burst: bool,
         _timeout_us: i64,
     ) -> Result<usize, Error> {
-        unreachable!()
+        Err(Error::NotSupported)
     }
     fn write_all(
         &mut self,
@@ -490,6 +879,6 @@ impl crate::TxStreamer for TxDummy {
         _end_burst: bool,
         _timeout_us: i64,
     ) -> Result<(), Error> {
-        unreachable!()
+        Err(Error::NotSupported)
     }
 }
@@ -0,0 +1,1180 @@
+//! Signal Hound BB60 and SM200 spectrum analyzers, using the vendor APIs
+//!
+//! The driver links against `bb_api` and `sm_api`, which are part of the Signal Hound SDK and
+//! have to be installed on the system.
+use num_complex::Complex32;
+use std::any::Any;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::Args;
+use crate::CoherencyGroup;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Direction::*;
+use crate::Driver;
+use crate::Error;
+use crate::GainElementInfo;
+use crate::Range;
+use crate::RangeItem;
+use crate::RxFlags;
+use crate::RxMetadata;
+use crate::Spectrum;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
+
+/// Maximum IQ bandwidth of the BB60 for the decimations `1, 2, 4, ..., 8192`.
+const BB_MAX_BANDWIDTH: [f64; 14] = [
+    27e6, 17.8e6, 8e6, 3.75e6, 2e6, 1e6, 500e3, 250e3, 140e3, 65e3, 30e3, 15e3, 8e3, 4e3,
+];
+/// Maximum IQ bandwidth of the SM200 for the decimations `1, 2, 4, ..., 4096`, i.e., 80 % of
+/// the sample rate for decimated streams.
+const SM_MAX_BANDWIDTH: [f64; 13] = [
+    41.5e6, 20e6, 10e6, 5e6, 2.5e6, 1.25e6, 625e3, 312.5e3, 156.25e3, 78.125e3, 39.0625e3,
+    19.53125e3, 9.765625e3,
+];
+/// Maximum number of samples per read.
+const MTU: usize = 16384;
+
+#[allow(non_snake_case)]
+mod bb {
+    use std::os::raw::c_char;
+    use std::os::raw::c_int;
+
+    pub const BB_MAX_DEVICES: usize = 8;
+    pub const BB_SWEEPING: u32 = 0x0;
+    pub const BB_STREAMING: u32 = 0x4;
+    pub const BB_STREAM_IQ: u32 = 0x0;
+    pub const BB_AUTO_GAIN: c_int = -1;
+    pub const BB_AUTO_ATTEN: c_int = -1;
+    pub const BB_AVERAGE: u32 = 0x1;
+    pub const BB_LOG_SCALE: u32 = 0x0;
+    pub const BB_RBW_SHAPE_FLATTOP: u32 = 0x1;
+    pub const BB_NO_SPUR_REJECT: u32 = 0x0;
+    pub const BB_DATA_TYPE_32FC: c_int = 0;
+
+    #[link(name = "bb_api")]
+    extern "C" {
+        pub fn bbGetSerialNumberList(serials: *mut c_int, count: *mut c_int) -> c_int;
+        pub fn bbOpenDeviceBySerialNumber(device: *mut c_int, serial: c_int) -> c_int;
+        pub fn bbCloseDevice(device: c_int) -> c_int;
+        pub fn bbConfigureRefLevel(device: c_int, ref_level: f64) -> c_int;
+        pub fn bbConfigureGainAtten(device: c_int, gain: c_int, atten: c_int) -> c_int;
+        pub fn bbConfigureCenterSpan(device: c_int, center: f64, span: f64) -> c_int;
+        pub fn bbConfigureSweepCoupling(
+            device: c_int,
+            rbw: f64,
+            vbw: f64,
+            sweep_time: f64,
+            rbw_shape: u32,
+            rejection: u32,
+        ) -> c_int;
+        pub fn bbConfigureAcquisition(device: c_int, detector: u32, scale: u32) -> c_int;
+        pub fn bbConfigureIQCenter(device: c_int, center: f64) -> c_int;
+        pub fn bbConfigureIQ(device: c_int, decimation: c_int, bandwidth: f64) -> c_int;
+        pub fn bbConfigureIQDataType(device: c_int, data_type: c_int) -> c_int;
+        pub fn bbInitiate(device: c_int, mode: u32, flag: u32) -> c_int;
+        pub fn bbAbort(device: c_int) -> c_int;
+        pub fn bbQueryTraceInfo(
+            device: c_int,
+            len: *mut u32,
+            bin_size: *mut f64,
+            start: *mut f64,
+        ) -> c_int;
+        pub fn bbFetchTrace_32f(
+            device: c_int,
+            len: c_int,
+            trace_min: *mut f32,
+            trace_max: *mut f32,
+        ) -> c_int;
+        pub fn bbGetIQUnpacked(
+            device: c_int,
+            iq: *mut f32,
+            len: c_int,
+            triggers: *mut u32,
+            trigger_count: c_int,
+            purge: c_int,
+            remaining: *mut c_int,
+            sample_loss: *mut c_int,
+            sec: *mut c_int,
+            nano: *mut c_int,
+        ) -> c_int;
+        pub fn bbGetErrorString(status: c_int) -> *const c_char;
+    }
+}
+
+#[allow(non_snake_case)]
+mod sm {
+    use std::os::raw::c_char;
+    use std::os::raw::c_int;
+
+    pub const SM_MAX_DEVICES: usize = 9;
+    pub const SM_AUTO_ATTEN: c_int = -1;
+    pub const SM_TRUE: c_int = 1;
+    pub const SM_MODE_IDLE: c_int = 0;
+    pub const SM_MODE_SWEEPING: c_int = 1;
+    pub const SM_MODE_IQ_STREAMING: c_int = 3;
+    pub const SM_DETECTOR_AVERAGE: c_int = 0;
+    pub const SM_VIDEO_LOG: c_int = 0;
+    pub const SM_SCALE_LOG: c_int = 0;
+    pub const SM_DATA_TYPE_32FC: c_int = 0;
+
+    #[link(name = "sm_api")]
+    extern "C" {
+        pub fn smGetDeviceList(serials: *mut c_int, count: *mut c_int) -> c_int;
+        pub fn smOpenDeviceBySerial(device: *mut c_int, serial: c_int) -> c_int;
+        pub fn smCloseDevice(device: c_int) -> c_int;
+        pub fn smSetRefLevel(device: c_int, ref_level: f64) -> c_int;
+        pub fn smSetAttenuator(device: c_int, atten: c_int) -> c_int;
+        pub fn smSetSweepSpan(device: c_int, center: f64, span: f64) -> c_int;
+        pub fn smSetSweepCoupling(device: c_int, rbw: f64, vbw: f64, sweep_time: f64) -> c_int;
+        pub fn smSetSweepDetector(device: c_int, detector: c_int, video_units: c_int) -> c_int;
+        pub fn smSetSweepScale(device: c_int, scale: c_int) -> c_int;
+        pub fn smSetIQCenterFreq(device: c_int, center: f64) -> c_int;
+        pub fn smSetIQSampleRate(device: c_int, decimation: c_int) -> c_int;
+        pub fn smSetIQBandwidth(device: c_int, software_filter: c_int, bandwidth: f64) -> c_int;
+        pub fn smSetIQDataType(device: c_int, data_type: c_int) -> c_int;
+        pub fn smConfigure(device: c_int, mode: c_int) -> c_int;
+        pub fn smAbort(device: c_int) -> c_int;
+        pub fn smGetSweepParameters(
+            device: c_int,
+            rbw: *mut f64,
+            vbw: *mut f64,
+            start: *mut f64,
+            bin_size: *mut f64,
+            len: *mut c_int,
+        ) -> c_int;
+        pub fn smGetSweep(
+            device: c_int,
+            sweep_min: *mut f32,
+            sweep_max: *mut f32,
+            ns_since_epoch: *mut i64,
+        ) -> c_int;
+        pub fn smGetIQ(
+            device: c_int,
+            iq: *mut f32,
+            len: c_int,
+            triggers: *mut f64,
+            trigger_count: c_int,
+            ns_since_epoch: *mut i64,
+            purge: c_int,
+            sample_loss: *mut c_int,
+            remaining: *mut c_int,
+        ) -> c_int;
+        pub fn smGetErrorString(status: c_int) -> *const c_char;
+    }
+}
+
+/// Analyzer family, each with its own vendor API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Model {
+    Bb60,
+    Sm200,
+}
+
+impl Model {
+    const ALL: [Model; 2] = [Model::Bb60, Model::Sm200];
+
+    fn name(self) -> &'static str {
+        match self {
+            Model::Bb60 => "bb60",
+            Model::Sm200 => "sm200",
+        }
+    }
+
+    /// Sample rate of the IQ stream without decimation.
+    fn base_rate(self) -> f64 {
+        match self {
+            Model::Bb60 => 40e6,
+            Model::Sm200 => 50e6,
+        }
+    }
+
+    /// Maximum IQ bandwidth, indexed by the log2 of the decimation.
+    fn max_bandwidth(self) -> &'static [f64] {
+        match self {
+            Model::Bb60 => &BB_MAX_BANDWIDTH,
+            Model::Sm200 => &SM_MAX_BANDWIDTH,
+        }
+    }
+
+    fn frequency_range(self) -> Range {
+        match self {
+            Model::Bb60 => Range::new(vec![RangeItem::Interval(9e3, 6e9)]),
+            Model::Sm200 => Range::new(vec![RangeItem::Interval(100e3, 20e9)]),
+        }
+    }
+
+    /// Serial numbers of the connected devices.
+    fn serials(self) -> Result<Vec<i32>, Error> {
+        let mut serials = [0; sm::SM_MAX_DEVICES];
+        let mut count = 0;
+        let max = match self {
+            Model::Bb60 => bb::BB_MAX_DEVICES,
+            Model::Sm200 => sm::SM_MAX_DEVICES,
+        };
+        // SAFETY: the APIs write up to their maximum number of devices, which fits the array
+        self.check(unsafe {
+            match self {
+                Model::Bb60 => bb::bbGetSerialNumberList(serials.as_mut_ptr(), &mut count),
+                Model::Sm200 => sm::smGetDeviceList(serials.as_mut_ptr(), &mut count),
+            }
+        })?;
+        Ok(serials[..(count.max(0) as usize).min(max)].to_vec())
+    }
+
+    /// Map a status of the vendor API to an error, logging warnings.
+    fn check(self, status: c_int) -> Result<(), Error> {
+        if status == 0 {
+            return Ok(());
+        }
+        let error_string: unsafe extern "C" fn(c_int) -> *const c_char = match self {
+            Model::Bb60 => bb::bbGetErrorString,
+            Model::Sm200 => sm::smGetErrorString,
+        };
+        // SAFETY: the APIs return a static string for every status
+        let msg = unsafe { CStr::from_ptr(error_string(status)) }.to_string_lossy();
+        if status < 0 {
+            Err(Error::Misc(format!("signalhound: {msg}")))
+        } else {
+            log::warn!("signalhound: {msg}");
+            Ok(())
+        }
+    }
+}
+
+/// Signal Hound BB60 and SM200 driver
+///
+/// The analyzer has a single receive channel, which either streams IQ samples or sweeps spectra
+/// through a [`SpectrumStreamer`](crate::SpectrumStreamer), but not both at the same time.
+#[derive(Clone)]
+pub struct SignalHound {
+    model: Model,
+    serial: i32,
+    i: Arc<Mutex<Inner>>,
+}
+
+/// Settings of a sweep, see [`DeviceTrait::spectrum_streamer`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Sweep {
+    center: f64,
+    span: f64,
+    rbw: f64,
+    vbw: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Idle,
+    Iq,
+    Sweep(Sweep),
+}
+
+struct Inner {
+    model: Model,
+    handle: c_int,
+    frequency: f64,
+    // index into the maximum bandwidths of the model, the decimation is 2^i
+    decimation: usize,
+    bandwidth: f64,
+    ref_level: f64,
+    mode: Mode,
+}
+
+/// Signal Hound RX streamer
+pub struct RxStreamer {
+    i: Arc<Mutex<Inner>>,
+    position: u64,
+    canceller: StreamCanceller,
+    events: StreamEvents,
+    scale: f32,
+    // discard the buffered samples with the next read
+    purge: bool,
+}
+
+/// Signal Hound spectrum streamer
+///
+/// Sweeps the span around the `frequency` argument (default: the RX frequency) with the `span`
+/// (default: the sample rate), `rbw` (default: span / 1000), and `vbw` (default: rbw) arguments.
+/// The bins are averaged power in dBm.
+pub struct SpectrumStreamer {
+    i: Arc<Mutex<Inner>>,
+    sweep: Sweep,
+    canceller: StreamCanceller,
+    trace_min: Vec<f32>,
+}
+
+/// Signal Hound TX dummy streamer
+pub struct TxDummy;
+
+impl Inner {
+    fn check(&self, status: c_int) -> Result<(), Error> {
+        self.model.check(status)
+    }
+
+    fn configure_level(&self) -> Result<(), Error> {
+        // SAFETY: the handle is open for the lifetime of `Inner`
+        unsafe {
+            match self.model {
+                Model::Bb60 => {
+                    self.check(bb::bbConfigureRefLevel(self.handle, self.ref_level))?;
+                    self.check(bb::bbConfigureGainAtten(
+                        self.handle,
+                        bb::BB_AUTO_GAIN,
+                        bb::BB_AUTO_ATTEN,
+                    ))
+                }
+                Model::Sm200 => {
+                    self.check(sm::smSetRefLevel(self.handle, self.ref_level))?;
+                    self.check(sm::smSetAttenuator(self.handle, sm::SM_AUTO_ATTEN))
+                }
+            }
+        }
+    }
+
+    fn start_iq(&mut self) -> Result<(), Error> {
+        self.configure_level()?;
+        let decimation = 1 << self.decimation;
+        // SAFETY: the handle is open for the lifetime of `Inner`
+        unsafe {
+            match self.model {
+                Model::Bb60 => {
+                    self.check(bb::bbConfigureIQCenter(self.handle, self.frequency))?;
+                    self.check(bb::bbConfigureIQ(self.handle, decimation, self.bandwidth))?;
+                    self.check(bb::bbConfigureIQDataType(
+                        self.handle,
+                        bb::BB_DATA_TYPE_32FC,
+                    ))?;
+                    self.check(bb::bbInitiate(
+                        self.handle,
+                        bb::BB_STREAMING,
+                        bb::BB_STREAM_IQ,
+                    ))?;
+                }
+                Model::Sm200 => {
+                    self.check(sm::smSetIQCenterFreq(self.handle, self.frequency))?;
+                    self.check(sm::smSetIQSampleRate(self.handle, decimation))?;
+                    self.check(sm::smSetIQBandwidth(
+                        self.handle,
+                        sm::SM_TRUE,
+                        self.bandwidth,
+                    ))?;
+                    self.check(sm::smSetIQDataType(self.handle, sm::SM_DATA_TYPE_32FC))?;
+                    self.check(sm::smConfigure(self.handle, sm::SM_MODE_IQ_STREAMING))?;
+                }
+            }
+        }
+        self.mode = Mode::Iq;
+        Ok(())
+    }
+
+    fn start_sweep(&mut self, sweep: Sweep) -> Result<(), Error> {
+        self.configure_level()?;
+        // SAFETY: the handle is open for the lifetime of `Inner`
+        unsafe {
+            match self.model {
+                Model::Bb60 => {
+                    self.check(bb::bbConfigureCenterSpan(
+                        self.handle,
+                        sweep.center,
+                        sweep.span,
+                    ))?;
+                    self.check(bb::bbConfigureSweepCoupling(
+                        self.handle,
+                        sweep.rbw,
+                        sweep.vbw,
+                        0.001,
+                        bb::BB_RBW_SHAPE_FLATTOP,
+                        bb::BB_NO_SPUR_REJECT,
+                    ))?;
+                    self.check(bb::bbConfigureAcquisition(
+                        self.handle,
+                        bb::BB_AVERAGE,
+                        bb::BB_LOG_SCALE,
+                    ))?;
+                    self.check(bb::bbInitiate(self.handle, bb::BB_SWEEPING, 0))?;
+                }
+                Model::Sm200 => {
+                    self.check(sm::smSetSweepSpan(self.handle, sweep.center, sweep.span))?;
+                    self.check(sm::smSetSweepCoupling(
+                        self.handle,
+                        sweep.rbw,
+                        sweep.vbw,
+                        0.001,
+                    ))?;
+                    self.check(sm::smSetSweepDetector(
+                        self.handle,
+                        sm::SM_DETECTOR_AVERAGE,
+                        sm::SM_VIDEO_LOG,
+                    ))?;
+                    self.check(sm::smSetSweepScale(self.handle, sm::SM_SCALE_LOG))?;
+                    self.check(sm::smConfigure(self.handle, sm::SM_MODE_SWEEPING))?;
+                }
+            }
+        }
+        self.mode = Mode::Sweep(sweep);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        self.mode = Mode::Idle;
+        // SAFETY: the handle is open for the lifetime of `Inner`
+        self.check(unsafe { self.abort() })
+    }
+
+    /// Stop the measurement, returning the status of the API.
+    ///
+    /// # Safety
+    ///
+    /// The handle has to be open.
+    unsafe fn abort(&self) -> c_int {
+        match self.model {
+            Model::Bb60 => bb::bbAbort(self.handle),
+            Model::Sm200 => {
+                sm::smAbort(self.handle);
+                sm::smConfigure(self.handle, sm::SM_MODE_IDLE)
+            }
+        }
+    }
+
+    /// Apply changed settings, which the API only picks up when a measurement is initiated.
+    fn restart(&mut self) -> Result<(), Error> {
+        match self.mode {
+            Mode::Idle => Ok(()),
+            Mode::Iq => self.start_iq(),
+            Mode::Sweep(s) => self.start_sweep(s),
+        }
+    }
+
+    fn sample_rate(&self) -> f64 {
+        self.model.base_rate() / (1 << self.decimation) as f64
+    }
+
+    /// Read IQ samples into `buf`, returning the host time of the first one and whether samples
+    /// were lost.
+    fn read_iq(&self, buf: &mut [Complex32], purge: bool) -> Result<(i64, bool), Error> {
+        let n = buf.len() as c_int;
+        let iq = buf.as_mut_ptr().cast();
+        // SAFETY: Complex32 is two f32, matching the interleaved 32fc format, and the buffer holds
+        // `n` samples
+        unsafe {
+            match self.model {
+                Model::Bb60 => {
+                    let (mut remaining, mut loss, mut sec, mut nano) = (0, 0, 0, 0);
+                    self.check(bb::bbGetIQUnpacked(
+                        self.handle,
+                        iq,
+                        n,
+                        std::ptr::null_mut(),
+                        0,
+                        purge as c_int,
+                        &mut remaining,
+                        &mut loss,
+                        &mut sec,
+                        &mut nano,
+                    ))?;
+                    Ok((sec as i64 * 1_000_000_000 + nano as i64, loss != 0))
+                }
+                Model::Sm200 => {
+                    let (mut ns, mut loss, mut remaining) = (0, 0, 0);
+                    self.check(sm::smGetIQ(
+                        self.handle,
+                        iq,
+                        n,
+                        std::ptr::null_mut(),
+                        0,
+                        &mut ns,
+                        purge as c_int,
+                        &mut loss,
+                        &mut remaining,
+                    ))?;
+                    Ok((ns, loss != 0))
+                }
+            }
+        }
+    }
+
+    /// Read the next sweep, keeping the minimum trace in `trace_min`.
+    fn read_sweep(&self, trace_min: &mut Vec<f32>) -> Result<Spectrum, Error> {
+        let (mut bin, mut start) = (0.0, 0.0);
+        // SAFETY: the pointers are valid for the calls
+        let len = unsafe {
+            match self.model {
+                Model::Bb60 => {
+                    let mut n = 0u32;
+                    self.check(bb::bbQueryTraceInfo(
+                        self.handle,
+                        &mut n,
+                        &mut bin,
+                        &mut start,
+                    ))?;
+                    n as usize
+                }
+                Model::Sm200 => {
+                    let (mut rbw, mut vbw, mut n) = (0.0, 0.0, 0);
+                    self.check(sm::smGetSweepParameters(
+                        self.handle,
+                        &mut rbw,
+                        &mut vbw,
+                        &mut start,
+                        &mut bin,
+                        &mut n,
+                    ))?;
+                    n.max(0) as usize
+                }
+            }
+        };
+        let mut bins = vec![0.0; len];
+        trace_min.resize(len, 0.0);
+        let mut time_ns = None;
+        // SAFETY: both traces hold `len` values
+        unsafe {
+            match self.model {
+                Model::Bb60 => self.check(bb::bbFetchTrace_32f(
+                    self.handle,
+                    len as c_int,
+                    trace_min.as_mut_ptr(),
+                    bins.as_mut_ptr(),
+                ))?,
+                Model::Sm200 => {
+                    let mut ns = 0;
+                    self.check(sm::smGetSweep(
+                        self.handle,
+                        trace_min.as_mut_ptr(),
+                        bins.as_mut_ptr(),
+                        &mut ns,
+                    ))?;
+                    time_ns = Some(ns);
+                }
+            }
+        }
+        Ok(Spectrum {
+            time_ns,
+            start_frequency: start,
+            stop_frequency: start + bin * (len.max(1) - 1) as f64,
+            unit: "dBm".to_string(),
+            bins,
+        })
+    }
+
+    fn busy() -> Error {
+        Error::Misc("signalhound: device streams IQ samples or sweeps, not both".to_string())
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open and not used after this
+        unsafe {
+            self.abort();
+            match self.model {
+                Model::Bb60 => bb::bbCloseDevice(self.handle),
+                Model::Sm200 => sm::smCloseDevice(self.handle),
+            };
+        }
+    }
+}
+
+impl SignalHound {
+    /// Get a list of detected BB60 and SM200 devices
+    ///
+    /// The returned [`Args`] specify the device by its `model` and `serial` number.
+    pub fn probe(_args: &Args) -> Result<Vec<Args>, Error> {
+        devices()?
+            .into_iter()
+            .map(|(m, s)| format!("driver=signalhound, model={}, serial={s}", m.name()).try_into())
+            .collect()
+    }
+
+    /// Open a BB60 or SM200 device
+    ///
+    /// Opens the device with the `model` (`bb60` or `sm200`) and `serial` arguments or the first
+    /// device. The RX frequency is set to 1 GHz, the sample rate to 40 MHz (BB60) or 50 MHz
+    /// (SM200) / `decimation` (default: 8), and the reference level to -20 dBm.
+    pub fn open<A: TryInto<Args>>(args: A) -> Result<Self, Error> {
+        let args: Args = args.try_into().or(Err(Error::ValueError))?;
+        let model = match args.get::<String>("model") {
+            Ok(m) => Some(
+                Model::ALL
+                    .into_iter()
+                    .find(|x| x.name() == m.to_lowercase())
+                    .ok_or(Error::ValueError)?,
+            ),
+            Err(_) => None,
+        };
+        let serial = args.get::<i32>("serial").ok();
+        let (model, serial) = devices()?
+            .into_iter()
+            .find(|(m, s)| model.is_none_or(|x| x == *m) && serial.is_none_or(|x| x == *s))
+            .ok_or(Error::NotFound)?;
+        let decimation = args.get::<u32>("decimation").unwrap_or(8);
+        let max = model.max_bandwidth().len() - 1;
+        if !decimation.is_power_of_two() || decimation as usize > 1 << max {
+            return Err(Error::ValueError);
+        }
+        let decimation = decimation.trailing_zeros() as usize;
+
+        let mut handle = 0;
+        // SAFETY: `handle` is a valid pointer for the call
+        model.check(unsafe {
+            match model {
+                Model::Bb60 => bb::bbOpenDeviceBySerialNumber(&mut handle, serial),
+                Model::Sm200 => sm::smOpenDeviceBySerial(&mut handle, serial),
+            }
+        })?;
+        let inner = Inner {
+            model,
+            handle,
+            frequency: 1e9,
+            decimation,
+            bandwidth: model.max_bandwidth()[decimation],
+            ref_level: -20.0,
+            mode: Mode::Idle,
+        };
+        Ok(Self {
+            model,
+            serial,
+            i: Arc::new(Mutex::new(inner)),
+        })
+    }
+}
+
+/// Model and serial number of the connected devices.
+fn devices() -> Result<Vec<(Model, i32)>, Error> {
+    let mut devices = Vec::new();
+    for model in Model::ALL {
+        devices.extend(model.serials()?.into_iter().map(|s| (model, s)));
+    }
+    Ok(devices)
+}
+
+/// Check that the channel is the RX channel.
+fn rx(direction: Direction, channel: usize) -> Result<(), Error> {
+    match (direction, channel) {
+        (Rx, 0) => Ok(()),
+        (Rx, _) => Err(Error::ValueError),
+        (Tx, _) => Err(Error::NotSupported),
+    }
+}
+
+impl DeviceTrait for SignalHound {
+    type RxStreamer = RxStreamer;
+    type TxStreamer = TxDummy;
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn driver(&self) -> Driver {
+        Driver::SignalHound
+    }
+
+    fn id(&self) -> Result<String, Error> {
+        Ok(format!("{}", self.serial))
+    }
+
+    fn info(&self) -> Result<Args, Error> {
+        format!(
+            "driver=signalhound, model={}, serial={}",
+            self.model.name(),
+            self.serial
+        )
+        .try_into()
+    }
+
+    fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
+        match direction {
+            Rx => Ok(1),
+            Tx => Ok(0),
+        }
+    }
+
+    fn full_duplex(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn coherency_groups(&self) -> Result<Vec<CoherencyGroup>, Error> {
+        Ok(vec![CoherencyGroup::new(vec![(Rx, 0)], true)])
+    }
+
+    fn rx_streamer(&self, channels: &[usize], _args: Args) -> Result<Self::RxStreamer, Error> {
+        if channels != [0] {
+            return Err(Error::ValueError);
+        }
+        Ok(RxStreamer {
+            i: self.i.clone(),
+            position: 0,
+            canceller: StreamCanceller::new(),
+            events: StreamEvents::new(),
+            scale: 1.0,
+            purge: false,
+        })
+    }
+
+    fn tx_streamer(&self, _channels: &[usize], _args: Args) -> Result<Self::TxStreamer, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn spectrum_streamer(
+        &self,
+        channel: usize,
+        args: Args,
+    ) -> Result<Box<dyn crate::SpectrumStreamer>, Error> {
+        rx(Rx, channel)?;
+        let (frequency, rate) = {
+            let inner = self.i.lock().unwrap();
+            (inner.frequency, inner.sample_rate())
+        };
+        let span = args.get::<f64>("span").unwrap_or(rate);
+        let rbw = args.get::<f64>("rbw").unwrap_or(span / 1000.0);
+        Ok(Box::new(SpectrumStreamer {
+            i: self.i.clone(),
+            sweep: Sweep {
+                center: args.get::<f64>("frequency").unwrap_or(frequency),
+                span,
+                rbw,
+                vbw: args.get::<f64>("vbw").unwrap_or(rbw),
+            },
+            canceller: StreamCanceller::new(),
+            trace_min: Vec::new(),
+        }))
+    }
+
+    fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        self.antenna(direction, channel).map(|a| vec![a])
+    }
+
+    fn antenna(&self, direction: Direction, channel: usize) -> Result<String, Error> {
+        rx(direction, channel)?;
+        Ok("RF".to_string())
+    }
+
+    fn set_antenna(&self, direction: Direction, channel: usize, name: &str) -> Result<(), Error> {
+        rx(direction, channel)?;
+        if name == "RF" {
+            Ok(())
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_elements(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
+        rx(direction, channel)?;
+        Ok(vec!["REF".to_string()])
+    }
+
+    fn supports_agc(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        rx(direction, channel)?;
+        Ok(false)
+    }
+
+    fn enable_agc(&self, _direction: Direction, _channel: usize, _agc: bool) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn agc(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_gain(&self, direction: Direction, channel: usize, gain: f64) -> Result<(), Error> {
+        let range = self.gain_range(direction, channel)?;
+        if !range.contains(gain) {
+            return Err(Error::OutOfRange(range, gain));
+        }
+        let mut inner = self.i.lock().unwrap();
+        inner.ref_level = -gain;
+        inner.restart()
+    }
+
+    fn gain(&self, direction: Direction, channel: usize) -> Result<Option<f64>, Error> {
+        rx(direction, channel)?;
+        Ok(Some(-self.i.lock().unwrap().ref_level))
+    }
+
+    fn gain_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        rx(direction, channel)?;
+        Ok(Range::new(vec![RangeItem::Interval(-20.0, 130.0)]))
+    }
+
+    fn set_gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        gain: f64,
+    ) -> Result<f64, Error> {
+        if name != "REF" {
+            return Err(Error::ValueError);
+        }
+        self.set_gain(direction, channel, gain)?;
+        Ok(self
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
+    }
+
+    fn gain_element(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Option<f64>, Error> {
+        if name == "REF" {
+            self.gain(direction, channel)
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_element_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        if name == "REF" {
+            self.gain_range(direction, channel)
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn gain_element_info(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<GainElementInfo, Error> {
+        rx(direction, channel)?;
+        match name {
+            "REF" => Ok(GainElementInfo::new(
+                name,
+                "Reference level",
+                "dB",
+                "A gain of g dB sets the reference level to -g dBm. The API picks the attenuator and amplifier for it.",
+            )),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn frequency_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        rx(direction, channel)?;
+        Ok(self.model.frequency_range())
+    }
+
+    fn frequency(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        rx(direction, channel)?;
+        Ok(self.i.lock().unwrap().frequency)
+    }
+
+    fn set_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+        _args: Args,
+    ) -> Result<(), Error> {
+        self.set_component_frequency(direction, channel, "RF", frequency)
+    }
+
+    fn frequency_components(
+        &self,
+        direction: Direction,
+        channel: usize,
+    ) -> Result<Vec<String>, Error> {
+        rx(direction, channel)?;
+        Ok(vec!["RF".to_string()])
+    }
+
+    fn component_frequency_range(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<Range, Error> {
+        if name == "RF" {
+            self.frequency_range(direction, channel)
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        if name == "RF" {
+            self.frequency(direction, channel)
+        } else {
+            Err(Error::ValueError)
+        }
+    }
+
+    fn set_component_frequency(
+        &self,
+        direction: Direction,
+        channel: usize,
+        name: &str,
+        frequency: f64,
+    ) -> Result<(), Error> {
+        let range = self.component_frequency_range(direction, channel, name)?;
+        if !range.contains(frequency) {
+            return Err(Error::OutOfRange(range, frequency));
+        }
+        let mut inner = self.i.lock().unwrap();
+        inner.frequency = frequency;
+        if inner.mode == Mode::Iq {
+            inner.start_iq()?;
+        }
+        Ok(())
+    }
+
+    fn sample_rate(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        rx(direction, channel)?;
+        Ok(self.i.lock().unwrap().sample_rate())
+    }
+
+    fn set_sample_rate(
+        &self,
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    ) -> Result<(), Error> {
+        let range = self.get_sample_rate_range(direction, channel)?;
+        let m = self.model;
+        let decimation = (0..m.max_bandwidth().len())
+            .find(|d| (m.base_rate() / (1 << d) as f64 - rate).abs() < 1.0)
+            .ok_or(Error::OutOfRange(range, rate))?;
+        let mut inner = self.i.lock().unwrap();
+        inner.decimation = decimation;
+        inner.bandwidth = m.max_bandwidth()[decimation];
+        if inner.mode == Mode::Iq {
+            inner.start_iq()?;
+        }
+        Ok(())
+    }
+
+    fn get_sample_rate_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        rx(direction, channel)?;
+        let m = self.model;
+        Ok(Range::new(
+            (0..m.max_bandwidth().len())
+                .map(|d| RangeItem::Value(m.base_rate() / (1 << d) as f64))
+                .collect(),
+        ))
+    }
+
+    fn bandwidth(&self, direction: Direction, channel: usize) -> Result<f64, Error> {
+        rx(direction, channel)?;
+        Ok(self.i.lock().unwrap().bandwidth)
+    }
+
+    fn set_bandwidth(&self, direction: Direction, channel: usize, bw: f64) -> Result<(), Error> {
+        let range = self.get_bandwidth_range(direction, channel)?;
+        if !range.contains(bw) {
+            return Err(Error::OutOfRange(range, bw));
+        }
+        let mut inner = self.i.lock().unwrap();
+        inner.bandwidth = bw;
+        if inner.mode == Mode::Iq {
+            inner.start_iq()?;
+        }
+        Ok(())
+    }
+
+    fn get_bandwidth_range(&self, direction: Direction, channel: usize) -> Result<Range, Error> {
+        rx(direction, channel)?;
+        let max = self.model.max_bandwidth()[self.i.lock().unwrap().decimation];
+        Ok(Range::new(vec![RangeItem::Interval(max / 100.0, max)]))
+    }
+
+    fn has_dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn set_dc_offset_mode(
+        &self,
+        _direction: Direction,
+        _channel: usize,
+        _automatic: bool,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn dc_offset_mode(&self, _direction: Direction, _channel: usize) -> Result<bool, Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+impl crate::RxInterface for SignalHound {}
+
+impl crate::RxStreamer for RxStreamer {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(MTU)
+    }
+
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        let mut inner = self.i.lock().unwrap();
+        if matches!(inner.mode, Mode::Sweep(_)) {
+            return Err(Inner::busy());
+        }
+        inner.start_iq()?;
+        self.position = 0;
+        self.canceller.reset();
+        Ok(())
+    }
+
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        let mut inner = self.i.lock().unwrap();
+        if inner.mode == Mode::Iq {
+            inner.stop()?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        self.read_with_metadata(buffers, timeout_us).map(|r| r.0)
+    }
+
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        _timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        // the API blocks until the samples are available, so cancellation is checked per call
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let inner = self.i.lock().unwrap();
+        if inner.mode != Mode::Iq {
+            return Err(Error::Inactive);
+        }
+        let n = buffers[0].len().min(MTU);
+        let (time_ns, loss) = inner.read_iq(&mut buffers[0][..n], self.purge)?;
+        drop(inner);
+        self.purge = false;
+        if self.scale != 1.0 {
+            buffers[0][..n].iter_mut().for_each(|s| *s *= self.scale);
+        }
+        let mut meta = RxMetadata {
+            time_ns: Some(time_ns),
+            host_time: true,
+            sample_position: Some(self.position),
+            ..RxMetadata::default()
+        };
+        if loss {
+            log::warn!("signalhound: samples lost");
+            meta.flags.insert(RxFlags::OVERFLOW);
+            self.events.send(StreamEvent::Overflow);
+        }
+        self.position += n as u64;
+        Ok((n, meta))
+    }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.events.subscribe())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.i.lock().unwrap().mode != Mode::Iq {
+            return Err(Error::Inactive);
+        }
+        // the API purges its buffer on request of a read
+        self.purge = true;
+        Ok(())
+    }
+}
+
+impl crate::SpectrumStreamer for SpectrumStreamer {
+    fn activate(&mut self) -> Result<(), Error> {
+        let mut inner = self.i.lock().unwrap();
+        if inner.mode == Mode::Iq {
+            return Err(Inner::busy());
+        }
+        inner.start_sweep(self.sweep)?;
+        self.canceller.reset();
+        Ok(())
+    }
+
+    fn deactivate(&mut self) -> Result<(), Error> {
+        let mut inner = self.i.lock().unwrap();
+        if matches!(inner.mode, Mode::Sweep(_)) {
+            inner.stop()?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, _timeout_us: i64) -> Result<Spectrum, Error> {
+        // the API blocks until the sweep is done, so cancellation is checked per call
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let inner = self.i.lock().unwrap();
+        if !matches!(inner.mode, Mode::Sweep(_)) {
+            return Err(Error::Inactive);
+        }
+        inner.read_sweep(&mut self.trace_min)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+}
+
+impl crate::TxStreamer for TxDummy {
+    fn mtu(&self) -> Result<usize, Error> {
+        Err(Error::NotSupported)
+    }
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+    fn write(
+        &mut self,
+        _buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        _end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<usize, Error> {
+        Err(Error::NotSupported)
+    }
+    fn write_all(
+        &mut self,
+        _buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        _end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+}
@@ -1,14 +1,31 @@
 //! Soapy SDR
 use num_complex::Complex32;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::Args;
+use crate::ChannelEnable;
 use crate::DeviceTrait;
 use crate::Direction;
 use crate::Driver;
 use crate::Error;
+use crate::EventKind;
+use crate::EventLog;
+use crate::Extensions;
 use crate::Range;
 use crate::RangeItem;
+use crate::ReferenceDoc;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamEvents;
+
+/// Maximum time a single Soapy stream call blocks before checking for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Soapy Device
 #[derive(Clone)]
@@ -16,16 +33,83 @@ pub struct Soapy {
     dev: soapysdr::Device,
     args: Args,
     index: usize,
+    events: EventLog,
+    /// Channels left out of the streams, see [`ChannelEnable`].
+    disabled: Arc<Mutex<HashSet<(Direction, usize)>>>,
 }
 
 /// Soapy RX Streamer
 pub struct RxStreamer {
     streamer: soapysdr::RxStream<Complex32>,
+    position: u64,
+    canceller: StreamCanceller,
+    events: EventLog,
+    stream_events: StreamEvents,
+    scale: f32,
 }
 
 /// Soapy TX Streamer
 pub struct TxStreamer {
     streamer: soapysdr::TxStream<Complex32>,
+    canceller: StreamCanceller,
+    events: EventLog,
+    stream_events: StreamEvents,
+    scale: f32,
+}
+
+impl TxStreamer {
+    /// Copy of the samples with the input scale applied, `None` if the scale is one.
+    fn scaled(&self, buffers: &[&[Complex32]]) -> Option<Vec<Vec<Complex32>>> {
+        (self.scale != 1.0).then(|| {
+            buffers
+                .iter()
+                .map(|b| b.iter().map(|s| s * self.scale).collect())
+                .collect()
+        })
+    }
+}
+
+/// Call a blocking stream operation in slices of at most [`CANCEL_POLL_INTERVAL`], checking
+/// for cancellation in between, until it succeeds or the overall timeout expires.
+///
+/// A negative `timeout_us` blocks until the operation succeeds or the stream is cancelled.
+fn with_cancellation<T>(
+    canceller: &StreamCanceller,
+    timeout_us: i64,
+    mut f: impl FnMut(i64) -> Result<T, soapysdr::Error>,
+) -> Result<T, Error> {
+    let deadline =
+        (timeout_us >= 0).then(|| Instant::now() + Duration::from_micros(timeout_us as u64));
+    loop {
+        if canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let slice = match deadline {
+            Some(d) => d
+                .saturating_duration_since(Instant::now())
+                .min(CANCEL_POLL_INTERVAL),
+            None => CANCEL_POLL_INTERVAL,
+        };
+        match f(slice.as_micros() as i64) {
+            Err(e)
+                if e.code == soapysdr::ErrorCode::Timeout
+                    && deadline.is_none_or(|d| Instant::now() < d) => {}
+            r => return Ok(r?),
+        }
+    }
+}
+
+/// Report stream status codes, like overflows (`O`), underflows (`U`), and late commands (`L`),
+/// to the event log of the device and the subscribers of the stream.
+fn report(events: &EventLog, stream_events: &StreamEvents, e: &soapysdr::Error) {
+    let (kind, event) = match e.code {
+        soapysdr::ErrorCode::Overflow => (EventKind::Overflow, StreamEvent::Overflow),
+        soapysdr::ErrorCode::Underflow => (EventKind::Underflow, StreamEvent::Underflow),
+        soapysdr::ErrorCode::TimeError => (EventKind::LateCommand, StreamEvent::TimeError),
+        _ => return,
+    };
+    events.push(kind, format!("soapy: {e}"));
+    stream_events.send(event);
 }
 
 /// Configures SoapySDR logging to route through the `log` crate.
@@ -81,8 +165,17 @@ impl Soapy {
             dev: soapysdr::Device::new(soapysdr::Args::try_from(args)?)?,
             args: orig_args,
             index,
+            events: EventLog::default(),
+            disabled: Arc::new(Mutex::new(HashSet::new())),
         })
     }
+    /// Underlying SoapySDR device, as an escape hatch for features that Seify does not expose.
+    ///
+    /// Settings changed through the handle bypass Seify, e.g., they are not logged as
+    /// [events](crate::Device::events) and do not restart streams.
+    pub fn soapy_device(&self) -> &soapysdr::Device {
+        &self.dev
+    }
 }
 
 impl DeviceTrait for Soapy {
@@ -109,6 +202,19 @@ impl DeviceTrait for Soapy {
         Ok(self.args.clone())
     }
 
+    fn reference_docs(&self) -> Result<Vec<ReferenceDoc>, Error> {
+        Ok(vec![ReferenceDoc::new(
+            "SoapySDR",
+            "https://github.com/pothosware/SoapySDR/wiki",
+        )])
+    }
+
+    fn extensions(&self) -> Extensions {
+        let mut e = Extensions::new();
+        e.insert::<dyn ChannelEnable>(Arc::new(self.clone()));
+        e
+    }
+
     fn num_channels(&self, direction: Direction) -> Result<usize, Error> {
         Ok(self.dev.num_channels(direction.into())?)
     }
@@ -117,22 +223,39 @@ impl DeviceTrait for Soapy {
         Ok(self.dev.full_duplex(direction.into(), channel)?)
     }
 
-    fn rx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::RxStreamer, Error> {
+    fn rx_streamer(&self, channels: &[usize], mut args: Args) -> Result<Self::RxStreamer, Error> {
+        // the latency hint is not a SoapySDR stream argument
+        args.remove("latency");
         Ok(RxStreamer {
             streamer: self
                 .dev
                 .rx_stream_args(channels, soapysdr::Args::try_from(args)?)?,
+            position: 0,
+            canceller: StreamCanceller::new(),
+            events: self.events.clone(),
+            stream_events: StreamEvents::new(),
+            scale: 1.0,
         })
     }
 
-    fn tx_streamer(&self, channels: &[usize], args: Args) -> Result<Self::TxStreamer, Error> {
+    fn tx_streamer(&self, channels: &[usize], mut args: Args) -> Result<Self::TxStreamer, Error> {
+        // the latency hint is not a SoapySDR stream argument
+        args.remove("latency");
         Ok(TxStreamer {
             streamer: self
                 .dev
                 .tx_stream_args(channels, soapysdr::Args::try_from(args)?)?,
+            canceller: StreamCanceller::new(),
+            events: self.events.clone(),
+            stream_events: StreamEvents::new(),
+            scale: 1.0,
         })
     }
 
+    fn driver_event_log(&self) -> Option<EventLog> {
+        Some(self.events.clone())
+    }
+
     fn antennas(&self, direction: Direction, channel: usize) -> Result<Vec<String>, Error> {
         Ok(self.dev.antennas(direction.into(), channel)?)
     }
@@ -184,10 +307,14 @@ impl DeviceTrait for Soapy {
         channel: usize,
         name: &str,
         gain: f64,
-    ) -> Result<(), Error> {
+    ) -> Result<f64, Error> {
+        self.dev
+            .set_gain_element(direction.into(), channel, name, gain)?;
         Ok(self
-            .dev
-            .set_gain_element(direction.into(), channel, name, gain)?)
+            .gain_element(direction, channel, name)
+            .ok()
+            .flatten()
+            .unwrap_or(gain))
     }
 
     fn gain_element(
@@ -339,14 +466,95 @@ impl DeviceTrait for Soapy {
     fn dc_offset_mode(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
         Ok(self.dev.dc_offset_mode(direction.into(), channel)?)
     }
+
+    fn list_time_sources(&self) -> Result<Vec<String>, Error> {
+        Ok(self.dev.list_time_sources()?)
+    }
+
+    fn time_source(&self) -> Result<String, Error> {
+        Ok(self.dev.get_time_source()?)
+    }
+
+    fn set_time_source(&self, name: &str) -> Result<(), Error> {
+        Ok(self.dev.set_time_source(name)?)
+    }
+
+    fn gps_locked(&self) -> Result<bool, Error> {
+        if !self.dev.list_sensors()?.iter().any(|s| s == "gps_locked") {
+            return Err(Error::NotSupported);
+        }
+        Ok(self
+            .dev
+            .read_sensor("gps_locked")?
+            .eq_ignore_ascii_case("true"))
+    }
+
+    fn gps_location(&self) -> Result<crate::Location, Error> {
+        // UHD-based modules report the NMEA sentences of the GPSDO as sensors
+        if !self.dev.list_sensors()?.iter().any(|s| s == "gps_gpgga") {
+            return Err(Error::NotSupported);
+        }
+        crate::Location::from_nmea_gga(&self.dev.read_sensor("gps_gpgga")?)
+    }
+
+    fn hardware_time(&self) -> Result<i64, Error> {
+        Ok(self.dev.get_hardware_time(None)?)
+    }
+
+    fn set_hardware_time(&self, time_ns: i64) -> Result<(), Error> {
+        Ok(self.dev.set_hardware_time(None, time_ns)?)
+    }
+
+    fn set_hardware_time_next_pps(&self, time_ns: i64) -> Result<(), Error> {
+        // UHD-based Soapy modules latch the time on the next PPS edge for the "PPS" time name
+        Ok(self.dev.set_hardware_time(Some("PPS"), time_ns)?)
+    }
+}
+
+impl ChannelEnable for Soapy {
+    fn channel_enabled(&self, direction: Direction, channel: usize) -> Result<bool, Error> {
+        if channel >= self.num_channels(direction)? {
+            return Err(Error::ValueError);
+        }
+        Ok(!self
+            .disabled
+            .lock()
+            .unwrap()
+            .contains(&(direction, channel)))
+    }
+
+    fn set_channel_enabled(
+        &self,
+        direction: Direction,
+        channel: usize,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        if channel >= self.num_channels(direction)? {
+            return Err(Error::ValueError);
+        }
+        // SoapySDR enables the channels passed to the stream setup, so disabled channels are
+        // left out of the streams
+        let mut disabled = self.disabled.lock().unwrap();
+        if enabled {
+            disabled.remove(&(direction, channel));
+        } else {
+            disabled.insert((direction, channel));
+        }
+        Ok(())
+    }
 }
 
+impl crate::RxInterface for Soapy {}
+impl crate::TxInterface for Soapy {}
+
 impl crate::RxStreamer for RxStreamer {
     fn mtu(&self) -> Result<usize, Error> {
         Ok(self.streamer.mtu()?)
     }
 
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.canceller.reset();
+        self.position = 0;
         Ok(self.streamer.activate(time_ns)?)
     }
 
@@ -359,7 +567,47 @@ impl crate::RxStreamer for RxStreamer {
         buffers: &mut [&mut [num_complex::Complex32]],
         timeout_us: i64,
     ) -> Result<usize, Error> {
-        Ok(self.streamer.read(buffers, timeout_us)?)
+        let streamer = &mut self.streamer;
+        let events = &self.events;
+        let stream_events = &self.stream_events;
+        let n = with_cancellation(&self.canceller, timeout_us, |t| {
+            streamer
+                .read(&mut *buffers, t)
+                .inspect_err(|e| report(events, stream_events, e))
+        })?;
+        if self.scale != 1.0 {
+            for b in buffers.iter_mut() {
+                b[..n].iter_mut().for_each(|s| *s *= self.scale);
+            }
+        }
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn sample_position(&self) -> Result<u64, Error> {
+        Ok(self.position)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.stream_events.subscribe())
+    }
+
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // SoapySDR has no flush; restarting the stream makes the drivers drop their buffers
+        self.streamer.deactivate(None)?;
+        Ok(self.streamer.activate(None)?)
     }
 }
 
@@ -369,6 +617,7 @@ impl crate::TxStreamer for TxStreamer {
     }
 
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.canceller.reset();
         Ok(self.streamer.activate(time_ns)?)
     }
 
@@ -383,7 +632,17 @@ impl crate::TxStreamer for TxStreamer {
         end_burst: bool,
         timeout_us: i64,
     ) -> Result<usize, Error> {
-        Ok(self.streamer.write(buffers, at_ns, end_burst, timeout_us)?)
+        let scaled = self.scaled(buffers);
+        let refs: Vec<&[Complex32]> = scaled.iter().flatten().map(|b| b.as_slice()).collect();
+        let buffers = if scaled.is_some() { &refs[..] } else { buffers };
+        let streamer = &mut self.streamer;
+        let events = &self.events;
+        let stream_events = &self.stream_events;
+        with_cancellation(&self.canceller, timeout_us, |t| {
+            streamer
+                .write(buffers, at_ns, end_burst, t)
+                .inspect_err(|e| report(events, stream_events, e))
+        })
     }
 
     fn write_all(
@@ -393,9 +652,32 @@ impl crate::TxStreamer for TxStreamer {
         end_burst: bool,
         timeout_us: i64,
     ) -> Result<(), Error> {
+        if self.canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let scaled = self.scaled(buffers);
+        let refs: Vec<&[Complex32]> = scaled.iter().flatten().map(|b| b.as_slice()).collect();
+        let buffers = if scaled.is_some() { &refs[..] } else { buffers };
         Ok(self
             .streamer
-            .write_all(buffers, at_ns, end_burst, timeout_us)?)
+            .write_all(buffers, at_ns, end_burst, timeout_us)
+            .inspect_err(|e| report(&self.events, &self.stream_events, e))?)
+    }
+
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Ok(self.stream_events.subscribe())
+    }
+
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        if !scale.is_finite() {
+            return Err(Error::ValueError);
+        }
+        self.scale = scale;
+        Ok(())
     }
 }
 
@@ -0,0 +1,203 @@
+//! Reader thread for USB drivers with blocking transfers.
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Error;
+use crate::StreamCanceller;
+
+/// Granularity at which blocked reads check for timeouts and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Reader thread that drains a USB stream.
+///
+/// The bulk reads of the driver crates block until a transfer completes, which never happens if
+/// the device stalls. Reading in a separate thread allows `read()` of the streamers to honor its
+/// timeout and to be cancelled.
+///
+/// Consumed buffers are handed back with [`recycle`](Self::recycle) and reused, so that the
+/// stream does not allocate per transfer.
+///
+/// The thread only sees a stop request after its pending transfer. It is therefore stopped and
+/// joined while the device is still streaming, before streaming is turned off.
+pub(crate) struct UsbReader {
+    name: &'static str,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    data: Receiver<Result<Vec<u8>, Error>>,
+    free: SyncSender<Vec<u8>>,
+}
+
+impl UsbReader {
+    /// Start a thread that calls `read` with an empty buffer to fill, queuing up to `transfers`
+    /// of them.
+    pub(crate) fn spawn(
+        name: &'static str,
+        transfers: usize,
+        mut read: impl FnMut(&mut Vec<u8>) -> Result<(), Error> + Send + 'static,
+    ) -> Result<Self, Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let (tx, data) = mpsc::sync_channel(transfers);
+        let (free, recycled) = mpsc::sync_channel::<Vec<u8>>(transfers);
+        let thread = std::thread::Builder::new().name(name.to_string()).spawn({
+            let running = running.clone();
+            move || {
+                while running.load(Ordering::Acquire) {
+                    let mut buf = recycled.try_recv().unwrap_or_default();
+                    buf.clear();
+                    let r = read(&mut buf).map(|_| buf);
+                    let failed = r.is_err();
+                    if tx.send(r).is_err() || failed {
+                        break;
+                    }
+                }
+            }
+        })?;
+        Ok(Self {
+            name,
+            running,
+            thread: Some(thread),
+            data,
+            free,
+        })
+    }
+
+    /// Wait for the next transfer.
+    ///
+    /// A negative `timeout_us` blocks until data arrives, the stream is cancelled, or `limit`
+    /// expires.
+    pub(crate) fn recv(
+        &self,
+        canceller: &StreamCanceller,
+        timeout_us: i64,
+        limit: Option<Duration>,
+    ) -> Result<Vec<u8>, Error> {
+        let timeout = (timeout_us >= 0).then(|| Duration::from_micros(timeout_us as u64));
+        let deadline = timeout
+            .into_iter()
+            .chain(limit)
+            .min()
+            .map(|t| Instant::now() + t);
+        loop {
+            if canceller.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let wait = match deadline {
+                Some(d) => d
+                    .saturating_duration_since(Instant::now())
+                    .min(POLL_INTERVAL),
+                None => POLL_INTERVAL,
+            };
+            match self.data.recv_timeout(wait) {
+                Ok(buf) => return buf,
+                Err(RecvTimeoutError::Timeout) => {
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return Err(Error::Timeout);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::Inactive),
+            }
+        }
+    }
+
+    /// Take a transfer that is already queued, without waiting.
+    pub(crate) fn try_recv(&self) -> Option<Result<Vec<u8>, Error>> {
+        self.data.try_recv().ok()
+    }
+
+    /// Hand a consumed buffer back for reuse; it is dropped, if the queue is full.
+    pub(crate) fn recycle(&self, buf: Vec<u8>) {
+        let _ = self.free.try_send(buf);
+    }
+
+    /// Stop the reader thread and join it.
+    ///
+    /// Returns `false`, if the thread did not return from its pending transfer within
+    /// `timeout`, i.e., the device stalled.
+    pub(crate) fn stop(&mut self, timeout: Duration) -> bool {
+        self.running.store(false, Ordering::Release);
+        let deadline = Instant::now() + timeout;
+        loop {
+            // drop the queued transfers, so that the thread is not blocked on a full queue
+            while self.data.try_recv().is_ok() {}
+            match &self.thread {
+                None => return true,
+                Some(t) if t.is_finished() => {
+                    if self.thread.take().unwrap().join().is_err() {
+                        log::warn!("{} thread panicked", self.name);
+                    }
+                    return true;
+                }
+                Some(_) if Instant::now() >= deadline => return false,
+                Some(_) => std::thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+impl Drop for UsbReader {
+    fn drop(&mut self) {
+        if !self.stop(Duration::ZERO) {
+            log::warn!("{} thread is stuck in a transfer, detaching it", self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_transfer() {
+        // the transfer blocks until the test releases it, like a stalled device
+        let (release, blocked) = mpsc::channel::<()>();
+        let (entered, transfers) = mpsc::channel();
+        let mut reader = UsbReader::spawn("usb-test", 2, move |buf| {
+            let _ = entered.send(());
+            blocked.recv().map_err(|_| Error::DeviceError)?;
+            buf.extend_from_slice(&[1, 2]);
+            Ok(())
+        })
+        .unwrap();
+        let canceller = StreamCanceller::new();
+
+        let start = Instant::now();
+        assert!(matches!(
+            reader.recv(&canceller, 20_000, None),
+            Err(Error::Timeout)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(matches!(
+            reader.recv(&canceller, -1, Some(Duration::from_millis(20))),
+            Err(Error::Timeout)
+        ));
+
+        let c = canceller.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            c.cancel();
+        });
+        assert!(matches!(
+            reader.recv(&canceller, -1, None),
+            Err(Error::Cancelled)
+        ));
+        canceller.reset();
+
+        release.send(()).unwrap();
+        assert_eq!(reader.recv(&canceller, -1, None).unwrap(), vec![1, 2]);
+
+        // the thread is stuck in the next transfer until it completes
+        transfers.recv().unwrap();
+        transfers.recv().unwrap();
+        assert!(!reader.stop(Duration::from_millis(20)));
+        release.send(()).unwrap();
+        assert!(reader.stop(Duration::from_secs(5)));
+    }
+}
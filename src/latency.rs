@@ -0,0 +1,298 @@
+use num_complex::Complex32;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Error;
+use crate::RxStreamer;
+use crate::TxStreamer;
+
+/// Length of the marker sequence.
+const MARKER_LEN: usize = 1023;
+/// Minimum normalized correlation to accept a detection.
+const MIN_PEAK: f32 = 0.5;
+/// Maximum time to wait for the marker.
+const DEADLINE: Duration = Duration::from_secs(5);
+const TIMEOUT_US: i64 = 100_000;
+
+/// End-to-end latency from TX to RX, measured with [`measure_loop_latency`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoopLatency {
+    /// Latency in samples.
+    pub samples: u64,
+    /// Sample rate of the measurement.
+    pub sample_rate: f64,
+    /// Normalized correlation of the detected marker, between 0 and 1.
+    pub peak: f32,
+}
+
+impl LoopLatency {
+    /// Latency in seconds.
+    pub fn seconds(&self) -> f64 {
+        self.samples as f64 / self.sample_rate
+    }
+}
+
+/// Marker that is transmitted: a BPSK-modulated maximum-length sequence, which has a sharp
+/// autocorrelation peak.
+fn marker() -> Vec<Complex32> {
+    // x^10 + x^7 + 1
+    let mut lfsr: u16 = 0x3ff;
+    (0..MARKER_LEN)
+        .map(|_| {
+            let bit = ((lfsr >> 9) ^ (lfsr >> 6)) & 1;
+            lfsr = ((lfsr << 1) | bit) & 0x3ff;
+            Complex32::new(if bit == 1 { 0.5 } else { -0.5 }, 0.0)
+        })
+        .collect()
+}
+
+/// Find the marker in `capture`, returning the offset and normalized correlation.
+fn detect(capture: &[Complex32], marker: &[Complex32]) -> Option<(usize, f32)> {
+    let marker_energy: f32 = marker.iter().map(|m| m.norm_sqr()).sum();
+    let mut best = None;
+    let mut window_energy: f32 = capture
+        .iter()
+        .take(marker.len())
+        .map(|s| s.norm_sqr())
+        .sum();
+    for lag in 0..=capture.len().checked_sub(marker.len())? {
+        if lag > 0 {
+            window_energy +=
+                capture[lag + marker.len() - 1].norm_sqr() - capture[lag - 1].norm_sqr();
+        }
+        let c: Complex32 = capture[lag..]
+            .iter()
+            .zip(marker)
+            .map(|(s, m)| s * m.conj())
+            .sum();
+        let peak = c.norm() / (marker_energy * window_energy.max(f32::MIN_POSITIVE)).sqrt();
+        if best.is_none_or(|(_, p)| peak > p) {
+            best = Some((lag, peak));
+        }
+    }
+    best
+}
+
+/// Measure the loop latency of a full-duplex channel.
+///
+/// A marker sequence is transmitted, while the RX stream of the same channel is captured for up
+/// to `max_latency` samples. The latency is the offset of the detected marker from the RX sample
+/// that was due when the marker was written. TX has to be connected to RX, e.g., with a cable
+/// and attenuator or an internal [loopback mode](Device::set_loopback_mode).
+///
+/// The result includes the host latency between the RX read and the TX write. Repeat the
+/// measurement to judge its variance. Store it with the device in its
+/// [`Calibration`](crate::Calibration).
+pub fn measure_loop_latency<
+    R: RxStreamer + 'static,
+    T: TxStreamer + 'static,
+    D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+>(
+    dev: &Device<D>,
+    channel: usize,
+    max_latency: usize,
+) -> Result<LoopLatency, Error> {
+    let sample_rate = dev.rate(Direction::Rx, channel)?.as_sps();
+    let mut rx = dev.rx_streamer(&[channel])?;
+    let mut tx = dev.tx_streamer(&[channel])?;
+    let marker = marker();
+    rx.activate()?;
+    tx.activate()?;
+    let r = capture(&mut rx, &mut tx, &marker, max_latency + marker.len());
+    let _ = tx.deactivate();
+    let _ = rx.deactivate();
+    let (offset, peak) = detect(&r?, &marker)
+        .filter(|(_, p)| *p >= MIN_PEAK)
+        .ok_or_else(|| Error::Misc("loop latency marker not detected".to_string()))?;
+    Ok(LoopLatency {
+        samples: offset as u64,
+        sample_rate,
+        peak,
+    })
+}
+
+/// Latency of an RX stream, measured with [`measure_rx_latency`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RxLatency {
+    /// Number of reads that were measured.
+    pub reads: usize,
+    /// Minimum latency in nanoseconds.
+    pub min_ns: i64,
+    /// Mean latency in nanoseconds.
+    pub mean_ns: i64,
+    /// Maximum latency in nanoseconds.
+    pub max_ns: i64,
+}
+
+/// Measure the latency of an active RX stream from the timestamps of its samples.
+///
+/// The stream is read `reads` times into `buffers`. The latency of a read is the time from its
+/// last sample to the return of the read on the host clock. This requires hardware timestamps in
+/// host time, e.g., after setting the [hardware time](Device::set_hardware_time) from the host
+/// clock. Host clock estimates, like the ones of [`HostTimestamps`](crate::HostTimestamps), are
+/// derived from the arrival of the samples and rejected with [`Error::NotSupported`].
+///
+/// The result includes buffering in the driver and the host. Compare it for different
+/// [latency hints](crate::Latency) to tune a stream.
+pub fn measure_rx_latency<S: RxStreamer + ?Sized>(
+    rx: &mut S,
+    buffers: &mut [&mut [Complex32]],
+    sample_rate: f64,
+    reads: usize,
+) -> Result<RxLatency, Error> {
+    if !sample_rate.is_finite() || sample_rate <= 0.0 || reads == 0 {
+        return Err(Error::ValueError);
+    }
+    let start = Instant::now();
+    let mut latencies = Vec::with_capacity(reads);
+    while latencies.len() < reads {
+        if start.elapsed() > DEADLINE {
+            return Err(Error::Timeout);
+        }
+        let (n, meta) = match rx.read_with_metadata(buffers, TIMEOUT_US) {
+            Ok(r) => r,
+            Err(Error::Timeout) => continue,
+            Err(e) => return Err(e),
+        };
+        let now = crate::time::host_time_ns()?;
+        if n == 0 {
+            continue;
+        }
+        let time_ns = match meta.time_ns {
+            Some(t) if !meta.host_time => t,
+            _ => return Err(Error::NotSupported),
+        };
+        let last_ns = time_ns + ((n - 1) as f64 / sample_rate * 1e9) as i64;
+        latencies.push(now - last_ns);
+    }
+    Ok(RxLatency {
+        reads,
+        min_ns: *latencies.iter().min().unwrap(),
+        mean_ns: latencies.iter().sum::<i64>() / reads as i64,
+        max_ns: *latencies.iter().max().unwrap(),
+    })
+}
+
+fn capture<R: RxStreamer, T: TxStreamer>(
+    rx: &mut R,
+    tx: &mut T,
+    marker: &[Complex32],
+    len: usize,
+) -> Result<Vec<Complex32>, Error> {
+    let mut buf = vec![Complex32::new(0.0, 0.0); rx.mtu()?.clamp(1, len)];
+    // drain samples that are already buffered, so that the capture starts at the TX write
+    loop {
+        match rx.read(&mut [&mut buf], 0) {
+            Ok(n) if n == buf.len() => {}
+            Ok(_) | Err(Error::Timeout) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    tx.write_all(&[marker], None, true, TIMEOUT_US)?;
+    let start = Instant::now();
+    let mut capture = Vec::with_capacity(len);
+    while capture.len() < len {
+        if start.elapsed() > DEADLINE {
+            return Err(Error::Timeout);
+        }
+        let n = buf.len().min(len - capture.len());
+        match rx.read(&mut [&mut buf[..n]], TIMEOUT_US) {
+            Ok(n) => capture.extend_from_slice(&buf[..n]),
+            Err(Error::Timeout) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(capture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_marker() {
+        let marker = marker();
+        let mut capture = vec![Complex32::new(0.01, 0.0); 3000];
+        for (i, m) in marker.iter().enumerate() {
+            // attenuated and phase shifted
+            capture[517 + i] += m * Complex32::from_polar(0.1, 1.0);
+        }
+        let (offset, peak) = detect(&capture, &marker).unwrap();
+        assert_eq!(offset, 517);
+        assert!(peak > 0.9);
+        assert!(detect(&capture[..100], &marker).is_none());
+    }
+
+    /// Stream with timestamps 10 ms in the past.
+    struct Delayed {
+        host_time: bool,
+    }
+
+    impl RxStreamer for Delayed {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(1000)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn read(
+            &mut self,
+            buffers: &mut [&mut [Complex32]],
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            Ok(buffers[0].len())
+        }
+        fn read_with_metadata(
+            &mut self,
+            buffers: &mut [&mut [Complex32]],
+            timeout_us: i64,
+        ) -> Result<(usize, crate::RxMetadata), Error> {
+            let n = self.read(buffers, timeout_us)?;
+            // 1 kS/s, the last sample was taken 10 ms ago
+            let time_ns = crate::time::host_time_ns()? - 10_000_000 - (n as i64 - 1) * 1_000_000;
+            Ok((
+                n,
+                crate::RxMetadata {
+                    time_ns: Some(time_ns),
+                    host_time: self.host_time,
+                    ..crate::RxMetadata::default()
+                },
+            ))
+        }
+    }
+
+    #[test]
+    fn rx_latency() {
+        let mut buf = [Complex32::new(0.0, 0.0); 100];
+        let mut rx = Delayed { host_time: false };
+        let l = measure_rx_latency(&mut rx, &mut [&mut buf], 1000.0, 5).unwrap();
+        assert_eq!(l.reads, 5);
+        assert!(l.min_ns >= 10_000_000 && l.max_ns < 20_000_000, "{l:?}");
+        assert!(l.min_ns <= l.mean_ns && l.mean_ns <= l.max_ns);
+
+        let mut rx = Delayed { host_time: true };
+        assert!(matches!(
+            measure_rx_latency(&mut rx, &mut [&mut buf], 1000.0, 5),
+            Err(Error::NotSupported)
+        ));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn dummy_loopback() {
+        // the dummy returns no samples, once the loopback queue is empty
+        let dev = Device::from_impl(crate::impls::Dummy::open(crate::Args::new()).unwrap());
+        dev.set_loopback_mode("internal").unwrap();
+        let latency = measure_loop_latency(&dev, 0, 0).unwrap();
+        assert_eq!(latency.samples, 0);
+        assert!(latency.peak > 0.99);
+    }
+}
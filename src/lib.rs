@@ -1,21 +1,260 @@
+#[cfg(all(
+    any(
+        feature = "control-http",
+        feature = "prometheus",
+        feature = "websocket"
+    ),
+    not(target_arch = "wasm32")
+))]
+mod access;
+#[cfg(all(
+    any(
+        feature = "control-http",
+        feature = "prometheus",
+        feature = "websocket"
+    ),
+    not(target_arch = "wasm32")
+))]
+pub use access::Access;
+#[cfg(all(
+    any(
+        feature = "control-http",
+        feature = "prometheus",
+        feature = "websocket"
+    ),
+    not(target_arch = "wasm32")
+))]
+pub use access::Permission;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod actor;
+#[cfg(not(target_arch = "wasm32"))]
+pub use actor::DeviceActor;
+#[cfg(not(target_arch = "wasm32"))]
+pub use actor::PendingCommand;
+
+mod anti_alias;
+
+mod any;
+pub use any::AnyDevice;
+pub use any::AnyRxStreamer;
+pub use any::AnyTxStreamer;
+
 mod args;
 pub use args::Args;
 
+mod capabilities;
+pub use capabilities::driver_matrix;
+pub use capabilities::CapabilityReport;
+pub use capabilities::ChannelReport;
+pub use capabilities::DriverCapabilities;
+pub use capabilities::NamedRange;
+
+mod channel;
+pub use channel::Channel;
+pub use channel::CoherencyGroup;
+
+#[cfg(all(feature = "control-http", not(target_arch = "wasm32")))]
+mod control;
+#[cfg(all(feature = "control-http", not(target_arch = "wasm32")))]
+pub use control::ControlServer;
+
+mod dc_block;
+pub use dc_block::DcBlocker;
+
 mod device;
 pub use device::Device;
 pub use device::DeviceTrait;
+pub use device::FromImpl;
 pub use device::GenericDevice;
+pub use device::RxInterface;
+pub use device::TxInterface;
+
+mod docs;
+pub use docs::GainElementInfo;
+pub use docs::ReferenceDoc;
+
+mod events;
+pub use events::Event;
+pub use events::EventKind;
+pub use events::EventLog;
+pub use events::EVENT_LOG_CAPACITY;
+
+mod extension;
+pub use extension::ChannelEnable;
+pub use extension::Extension;
+pub use extension::Extensions;
+pub use extension::SampleRateClock;
+pub use extension::Sensors;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod fanout;
+#[cfg(not(target_arch = "wasm32"))]
+pub use fanout::FanOut;
+#[cfg(not(target_arch = "wasm32"))]
+pub use fanout::Subscription;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod favorites;
+#[cfg(not(target_arch = "wasm32"))]
+pub use favorites::Calibration;
+#[cfg(not(target_arch = "wasm32"))]
+pub use favorites::Favorite;
+#[cfg(all(feature = "favorites", not(target_arch = "wasm32")))]
+pub use favorites::Favorites;
+#[cfg(not(target_arch = "wasm32"))]
+pub use favorites::Profile;
+
+mod format;
+pub use format::IqFormat;
+pub use format::WavHeader;
+
+#[cfg(all(
+    any(feature = "control-http", feature = "prometheus"),
+    not(target_arch = "wasm32")
+))]
+mod http;
 
 pub mod impls;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub use latency::measure_loop_latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub use latency::measure_rx_latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub use latency::LoopLatency;
+#[cfg(not(target_arch = "wasm32"))]
+pub use latency::RxLatency;
+
+mod location;
+pub use location::Location;
+
+mod power;
+pub use power::PowerState;
+
+#[cfg(all(feature = "prometheus", not(target_arch = "wasm32")))]
+mod prometheus;
+#[cfg(all(feature = "prometheus", not(target_arch = "wasm32")))]
+pub use prometheus::Metered;
+#[cfg(all(feature = "prometheus", not(target_arch = "wasm32")))]
+pub use prometheus::PrometheusExporter;
+
 mod range;
 pub use range::Range;
 pub use range::RangeItem;
 
+mod recorder;
+pub use recorder::Compression;
+pub use recorder::Recorder;
+
+mod restart;
+
+mod sample;
+pub use sample::CustomFormat;
+pub use sample::Sample;
+pub use sample::StreamFormat;
+pub use sample::TypedRxStreamer;
+pub use sample::TypedTxStreamer;
+
+mod self_test;
+pub use self_test::SelfTestCheck;
+pub use self_test::SelfTestReport;
+
+#[cfg(feature = "signals")]
+mod signal;
+#[cfg(feature = "signals")]
+pub use signal::SignalGenerator;
+
+mod shared;
+pub use shared::ConfinedHandle;
+pub use shared::SharedGuard;
+pub use shared::SharedHandle;
+
+mod slew;
+
+mod spectrum;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+pub use spectrum::FftSpectrum;
+pub use spectrum::Spectrum;
+pub use spectrum::SpectrumStreamer;
+
+mod spurs;
+pub use spurs::plan_lo_offset;
+pub use spurs::Spur;
+
+mod state;
+pub use state::Change;
+pub use state::ChannelSnapshot;
+pub use state::DeviceState;
+pub use state::SettingValue;
+
 mod streamer;
+#[cfg(not(target_arch = "wasm32"))]
+pub use streamer::AsyncAdapter;
+pub use streamer::AsyncRxStreamer;
+pub use streamer::AsyncTxStreamer;
+pub use streamer::BlockingAdapter;
+pub use streamer::BufferConfig;
+pub use streamer::BufferLayout;
+pub use streamer::Latency;
+pub use streamer::RxFlags;
+pub use streamer::RxMetadata;
 pub use streamer::RxStreamer;
+pub use streamer::StreamCanceller;
+pub use streamer::StreamCtl;
+pub use streamer::StreamEvent;
+pub use streamer::StreamEvents;
 pub use streamer::TxStreamer;
 
+mod sweep;
+pub use sweep::Sweep;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tdd;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tdd::SlotInfo;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tdd::TddDevice;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tdd::TddRx;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tdd::TddSlot;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tdd::TddTx;
+
+mod time;
+pub use time::TimeDiscipline;
+
+mod timed;
+pub use timed::TimedCommand;
+#[cfg(not(target_arch = "wasm32"))]
+pub use timed::TimedCommands;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod timestamps;
+#[cfg(not(target_arch = "wasm32"))]
+pub use timestamps::HostTimestamps;
+
+mod units;
+pub use units::Frequency;
+pub use units::SampleRate;
+
+mod validate;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod watchdog;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watchdog::Watchdog;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watchdog::WatchdogAction;
+
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+mod websocket;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub use websocket::IqWebSocketServer;
+
 use serde::{Deserialize, Serialize};
 
 use std::str::FromStr;
@@ -32,14 +271,26 @@ pub enum Error {
     ValueError,
     #[error("Not Found")]
     NotFound,
-    #[error("corresponding feature not enabled")]
-    FeatureNotEnabled,
+    #[error("corresponding feature not enabled (cargo feature `{0}`)")]
+    FeatureNotEnabled(&'static str),
     #[error("Not Supported")]
     NotSupported,
     #[error("Overflow")]
     Overflow,
     #[error("Inactive")]
     Inactive,
+    #[error("Timeout")]
+    Timeout,
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Busy ({0:?} stream active)")]
+    Busy(Direction),
+    #[error("USB device {0} found but could not be opened; on Windows, install the WinUSB driver for it with Zadig (https://zadig.akeo.ie)")]
+    UsbDriverMissing(String),
+    #[error("Permission denied ({remedy})")]
+    PermissionDenied { remedy: String },
+    #[error("Unauthorized (missing or invalid access token)")]
+    Unauthorized,
     #[error("Json ({0})")]
     Json(#[from] serde_json::Error),
     #[error("Misc")]
@@ -68,17 +319,106 @@ impl From<ureq::Error> for Error {
 }
 
 /// Supported hardware drivers.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+///
+/// All drivers can be parsed, but only the ones enabled through their cargo
+/// [feature](Self::feature) can be used, see [`available_drivers`](Self::available_drivers).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum Driver {
     Aaronia,
     AaroniaHttp,
     Dummy,
+    File,
     HackRf,
+    Hpsdr,
+    Ka9q,
     RtlSdr,
+    SignalHound,
     Soapy,
 }
 
+impl Driver {
+    /// All drivers, also the ones that are not enabled.
+    pub const ALL: [Driver; 10] = [
+        Driver::Aaronia,
+        Driver::AaroniaHttp,
+        Driver::Dummy,
+        Driver::File,
+        Driver::HackRf,
+        Driver::Hpsdr,
+        Driver::Ka9q,
+        Driver::RtlSdr,
+        Driver::SignalHound,
+        Driver::Soapy,
+    ];
+
+    /// Cargo feature that enables the driver.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Driver::Aaronia => "aaronia",
+            Driver::AaroniaHttp => "aaronia_http",
+            Driver::Dummy => "dummy",
+            Driver::File => "file",
+            Driver::HackRf => "hackrfone",
+            Driver::Hpsdr => "hpsdr",
+            Driver::Ka9q => "ka9q",
+            Driver::RtlSdr => "rtlsdr",
+            Driver::SignalHound => "signalhound",
+            Driver::Soapy => "soapy",
+        }
+    }
+
+    /// Is the driver compiled in, i.e., is its feature enabled and the target platform
+    /// supported?
+    pub fn is_available(&self) -> bool {
+        match self {
+            Driver::Aaronia => cfg!(all(
+                feature = "aaronia",
+                any(target_os = "linux", target_os = "windows")
+            )),
+            Driver::AaroniaHttp => cfg!(all(feature = "aaronia_http", not(target_arch = "wasm32"))),
+            Driver::Dummy => cfg!(feature = "dummy"),
+            Driver::File => cfg!(feature = "file"),
+            Driver::HackRf => cfg!(all(feature = "hackrfone", not(target_arch = "wasm32"))),
+            Driver::Hpsdr => cfg!(all(feature = "hpsdr", not(target_arch = "wasm32"))),
+            Driver::Ka9q => cfg!(all(feature = "ka9q", not(target_arch = "wasm32"))),
+            Driver::RtlSdr => cfg!(all(feature = "rtlsdr", not(target_arch = "wasm32"))),
+            Driver::SignalHound => cfg!(all(
+                feature = "signalhound",
+                any(target_os = "linux", target_os = "windows")
+            )),
+            Driver::Soapy => cfg!(all(feature = "soapy", not(target_arch = "wasm32"))),
+        }
+    }
+
+    /// Drivers that are compiled in.
+    ///
+    /// ```
+    /// use seify::Driver;
+    ///
+    /// for driver in Driver::ALL.into_iter().filter(|d| !d.is_available()) {
+    ///     println!("{driver:?}: build with `--features {}`", driver.feature());
+    /// }
+    /// assert!(Driver::available_drivers().iter().all(|d| d.is_available()));
+    /// ```
+    pub fn available_drivers() -> Vec<Driver> {
+        Driver::ALL
+            .into_iter()
+            .filter(Driver::is_available)
+            .collect()
+    }
+
+    /// Fail with [`Error::FeatureNotEnabled`], naming the feature, if the driver is not compiled
+    /// in.
+    pub(crate) fn ensure_available(&self) -> Result<(), Error> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(Error::FeatureNotEnabled(self.feature()))
+        }
+    }
+}
+
 impl FromStr for Driver {
     type Err = Error;
 
@@ -93,21 +433,33 @@ impl FromStr for Driver {
         if s == "rtlsdr" || s == "rtl-sdr" || s == "rtl" {
             return Ok(Driver::RtlSdr);
         }
+        if s == "signalhound" || s == "signal_hound" || s == "bb60" || s == "sm200" {
+            return Ok(Driver::SignalHound);
+        }
         if s == "soapy" || s == "soapysdr" {
             return Ok(Driver::Soapy);
         }
         if s == "hackrf" || s == "hackrfone" {
             return Ok(Driver::HackRf);
         }
+        if s == "ka9q" || s == "ka9q-radio" || s == "ka9q_radio" {
+            return Ok(Driver::Ka9q);
+        }
+        if s == "hpsdr" || s == "openhpsdr" || s == "hermes" {
+            return Ok(Driver::Hpsdr);
+        }
         if s == "dummy" || s == "Dummy" {
             return Ok(Driver::Dummy);
         }
+        if s == "file" {
+            return Ok(Driver::File);
+        }
         Err(Error::ValueError)
     }
 }
 
 /// Direction (Rx/TX)
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Rx,
     Tx,
@@ -126,96 +478,139 @@ pub fn enumerate() -> Result<Vec<Args>, Error> {
 
 /// Enumerate devices with given [`Args`].
 ///
+/// Drivers that fail to probe are logged and skipped. Only if no device was found, the error of
+/// the first failing driver is returned. Use [`enumerate_report`] to inspect all failures.
+///
 /// ## Returns
 ///
 /// A vector or [`Args`] that provide information about the device and can be used to identify it
 /// uniquely, i.e., passing the [`Args`] to [`Device::from_args`](crate::Device::from_args) will
 /// open this particular device.
 pub fn enumerate_with_args<A: TryInto<Args>>(a: A) -> Result<Vec<Args>, Error> {
+    let mut report = enumerate_report(a)?;
+    if report.devices.is_empty() && !report.failures.is_empty() {
+        return Err(report.failures.remove(0).1);
+    }
+    Ok(report.devices)
+}
+
+/// Devices found during enumeration and the drivers that failed to probe.
+#[derive(Debug, Default)]
+pub struct EnumerationReport {
+    /// [`Args`] of the devices found, see [`enumerate`].
+    pub devices: Vec<Args>,
+    /// Drivers whose probe failed, together with the error.
+    pub failures: Vec<(Driver, Error)>,
+}
+
+impl EnumerationReport {
+    #[cfg(any(
+        all(feature = "aaronia", any(target_os = "linux", target_os = "windows")),
+        all(feature = "aaronia_http", not(target_arch = "wasm32")),
+        all(feature = "rtlsdr", not(target_arch = "wasm32")),
+        all(
+            feature = "signalhound",
+            any(target_os = "linux", target_os = "windows")
+        ),
+        all(feature = "soapy", not(target_arch = "wasm32")),
+        all(feature = "hackrfone", not(target_arch = "wasm32")),
+        all(feature = "hpsdr", not(target_arch = "wasm32")),
+        all(feature = "ka9q", not(target_arch = "wasm32")),
+        feature = "dummy",
+        feature = "file"
+    ))]
+    fn add(&mut self, driver: Driver, result: Result<Vec<Args>, Error>) {
+        match result {
+            Ok(mut devs) => self.devices.append(&mut devs),
+            Err(e) => {
+                log::warn!("probing {driver:?} failed: {e}");
+                self.failures.push((driver, e));
+            }
+        }
+    }
+}
+
+/// Enumerate devices with given [`Args`], collecting per-driver failures.
+///
+/// Unlike [`enumerate_with_args`], a failing driver does not hide the devices found by other
+/// drivers. An error is only returned for invalid arguments or if the requested driver is not
+/// enabled.
+pub fn enumerate_report<A: TryInto<Args>>(a: A) -> Result<EnumerationReport, Error> {
     let args: Args = a.try_into().or(Err(Error::ValueError))?;
-    let mut devs = Vec::new();
+    // without any driver enabled, the report stays empty
+    #[allow(unused_mut)]
+    let mut report = EnumerationReport::default();
     let driver = match args.get::<String>("driver") {
         Ok(s) => Some(s.parse::<Driver>()?),
         Err(_) => None,
     };
+    if let Some(d) = driver {
+        d.ensure_available()?;
+    }
 
     #[cfg(all(feature = "aaronia", any(target_os = "linux", target_os = "windows")))]
     {
         if driver.is_none() || matches!(driver, Some(Driver::Aaronia)) {
-            devs.append(&mut impls::Aaronia::probe(&args)?)
+            report.add(Driver::Aaronia, impls::Aaronia::probe(&args))
         }
     }
-    #[cfg(not(all(feature = "aaronia", any(target_os = "linux", target_os = "windows"))))]
-    {
-        if matches!(driver, Some(Driver::Aaronia)) {
-            return Err(Error::FeatureNotEnabled);
-        }
-    }
-
     #[cfg(all(feature = "aaronia_http", not(target_arch = "wasm32")))]
     {
         if driver.is_none() || matches!(driver, Some(Driver::AaroniaHttp)) {
-            devs.append(&mut impls::AaroniaHttp::probe(&args)?)
+            report.add(Driver::AaroniaHttp, impls::AaroniaHttp::probe(&args))
         }
     }
-    #[cfg(not(all(feature = "aaronia_http", not(target_arch = "wasm32"))))]
-    {
-        if matches!(driver, Some(Driver::AaroniaHttp)) {
-            return Err(Error::FeatureNotEnabled);
-        }
-    }
-
     #[cfg(all(feature = "rtlsdr", not(target_arch = "wasm32")))]
     {
         if driver.is_none() || matches!(driver, Some(Driver::RtlSdr)) {
-            devs.append(&mut impls::RtlSdr::probe(&args)?)
+            report.add(Driver::RtlSdr, impls::RtlSdr::probe(&args))
         }
     }
-    #[cfg(not(all(feature = "rtlsdr", not(target_arch = "wasm32"))))]
+    #[cfg(all(
+        feature = "signalhound",
+        any(target_os = "linux", target_os = "windows")
+    ))]
     {
-        if matches!(driver, Some(Driver::RtlSdr)) {
-            return Err(Error::FeatureNotEnabled);
+        if driver.is_none() || matches!(driver, Some(Driver::SignalHound)) {
+            report.add(Driver::SignalHound, impls::SignalHound::probe(&args))
         }
     }
-
     #[cfg(all(feature = "soapy", not(target_arch = "wasm32")))]
     {
         if driver.is_none() || matches!(driver, Some(Driver::Soapy)) {
-            devs.append(&mut impls::Soapy::probe(&args)?)
+            report.add(Driver::Soapy, impls::Soapy::probe(&args))
         }
     }
-    #[cfg(not(all(feature = "soapy", not(target_arch = "wasm32"))))]
+    #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
     {
-        if matches!(driver, Some(Driver::Soapy)) {
-            return Err(Error::FeatureNotEnabled);
+        if driver.is_none() || matches!(driver, Some(Driver::HackRf)) {
+            report.add(Driver::HackRf, impls::HackRfOne::probe(&args))
         }
     }
-
-    #[cfg(all(feature = "hackrfone", not(target_arch = "wasm32")))]
+    #[cfg(all(feature = "hpsdr", not(target_arch = "wasm32")))]
     {
-        if driver.is_none() || matches!(driver, Some(Driver::HackRf)) {
-            devs.append(&mut impls::HackRfOne::probe(&args)?)
+        if driver.is_none() || matches!(driver, Some(Driver::Hpsdr)) {
+            report.add(Driver::Hpsdr, impls::Hpsdr::probe(&args))
         }
     }
-    #[cfg(not(all(feature = "hackrfone", not(target_arch = "wasm32"))))]
+    #[cfg(all(feature = "ka9q", not(target_arch = "wasm32")))]
     {
-        if matches!(driver, Some(Driver::HackRf)) {
-            return Err(Error::FeatureNotEnabled);
+        if driver.is_none() || matches!(driver, Some(Driver::Ka9q)) {
+            report.add(Driver::Ka9q, impls::Ka9q::probe(&args))
         }
     }
     #[cfg(feature = "dummy")]
     {
         if driver.is_none() || matches!(driver, Some(Driver::Dummy)) {
-            devs.append(&mut impls::Dummy::probe(&args)?)
+            report.add(Driver::Dummy, impls::Dummy::probe(&args))
         }
     }
-    #[cfg(not(feature = "dummy"))]
+    #[cfg(feature = "file")]
     {
-        if matches!(driver, Some(Driver::Dummy)) {
-            return Err(Error::FeatureNotEnabled);
+        if driver.is_none() || matches!(driver, Some(Driver::File)) {
+            report.add(Driver::File, impls::File::probe(&args))
         }
     }
 
-    let _ = &mut devs;
-    Ok(devs)
+    Ok(report)
 }
@@ -0,0 +1,193 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::Args;
+use crate::Error;
+
+/// Position and orientation of the antenna, attached to recordings.
+///
+/// Altitude is in meters above the WGS84 ellipsoid, as in GeoJSON and SigMF.
+///
+/// ```
+/// use seify::Location;
+///
+/// let loc = Location::new(48.137, 11.575).unwrap().altitude(520.0).azimuth(90.0);
+/// assert_eq!(loc.altitude, Some(520.0));
+/// assert!(Location::new(91.0, 0.0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    /// Latitude in degrees, positive north.
+    pub latitude: f64,
+    /// Longitude in degrees, positive east.
+    pub longitude: f64,
+    /// Altitude in meters.
+    pub altitude: Option<f64>,
+    /// Azimuth of the antenna in degrees, clockwise from true north.
+    pub azimuth: Option<f64>,
+}
+
+impl Location {
+    /// Create a location from latitude and longitude in degrees.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, Error> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(Error::ValueError);
+        }
+        Ok(Self {
+            latitude,
+            longitude,
+            altitude: None,
+            azimuth: None,
+        })
+    }
+    /// Set the altitude in meters.
+    pub fn altitude(mut self, altitude: f64) -> Self {
+        self.altitude = Some(altitude);
+        self
+    }
+    /// Set the azimuth of the antenna in degrees, normalized to `[0, 360)`.
+    pub fn azimuth(mut self, azimuth: f64) -> Self {
+        self.azimuth = Some(azimuth.rem_euclid(360.0));
+        self
+    }
+
+    /// Read the location from the `lat`, `lon`, `alt`, and `azimuth` arguments.
+    ///
+    /// Returns `None`, if neither `lat` nor `lon` is set.
+    pub fn from_args(args: &Args) -> Result<Option<Self>, Error> {
+        let optional = |key| match args.get::<f64>(key) {
+            Ok(v) => Ok(Some(v)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        };
+        let mut loc = match (optional("lat")?, optional("lon")?) {
+            (Some(lat), Some(lon)) => Self::new(lat, lon)?,
+            (None, None) => return Ok(None),
+            _ => return Err(Error::ValueError),
+        };
+        if let Some(alt) = optional("alt")? {
+            loc = loc.altitude(alt);
+        }
+        if let Some(azimuth) = optional("azimuth")? {
+            loc = loc.azimuth(azimuth);
+        }
+        Ok(Some(loc))
+    }
+
+    /// Parse a `GGA` NMEA sentence, as reported by GPS receivers.
+    ///
+    /// Fails with [`Error::NotFound`] if the receiver has no fix.
+    pub fn from_nmea_gga(sentence: &str) -> Result<Self, Error> {
+        let sentence = sentence.trim().split('*').next().unwrap_or_default();
+        let f: Vec<&str> = sentence.split(',').collect();
+        if f.len() < 12 || !f[0].ends_with("GGA") {
+            return Err(Error::ValueError);
+        }
+        if f[6].is_empty() || f[6] == "0" {
+            return Err(Error::NotFound);
+        }
+        // ddmm.mmmm and dddmm.mmmm
+        fn degrees(v: &str, hemisphere: &str, negative: &str) -> Result<f64, Error> {
+            let v: f64 = v.parse().or(Err(Error::ValueError))?;
+            let d = (v / 100.0).trunc() + (v % 100.0) / 60.0;
+            Ok(if hemisphere == negative { -d } else { d })
+        }
+        let mut loc = Self::new(degrees(f[2], f[3], "S")?, degrees(f[4], f[5], "W")?)?;
+        // altitude above mean sea level plus the height of the geoid above the ellipsoid
+        if let Ok(msl) = f[9].parse::<f64>() {
+            loc = loc.altitude(msl + f[11].parse::<f64>().unwrap_or(0.0));
+        }
+        Ok(loc)
+    }
+
+    /// Query the current position from a [gpsd](https://gpsd.io) daemon at `addr`, e.g.,
+    /// `localhost:2947`.
+    ///
+    /// Waits up to `timeout` for a report with a fix. Requires the `gpsd` feature.
+    #[cfg(all(feature = "gpsd", not(target_arch = "wasm32")))]
+    pub fn from_gpsd(addr: &str, timeout: Duration) -> Result<Self, Error> {
+        use std::io::BufRead;
+        use std::io::Write;
+        use std::net::TcpStream;
+        use std::net::ToSocketAddrs;
+
+        let deadline = std::time::Instant::now() + timeout;
+        let addr = addr.to_socket_addrs()?.next().ok_or(Error::NotFound)?;
+        let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")?;
+        for line in std::io::BufReader::new(stream).lines() {
+            if std::time::Instant::now() > deadline {
+                break;
+            }
+            let report: serde_json::Value = match serde_json::from_str(&line?) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            // mode 2 is a 2D fix, mode 3 a 3D fix
+            if report["class"] != "TPV" || report["mode"].as_u64().unwrap_or(0) < 2 {
+                continue;
+            }
+            let (Some(lat), Some(lon)) = (report["lat"].as_f64(), report["lon"].as_f64()) else {
+                continue;
+            };
+            let mut loc = Self::new(lat, lon)?;
+            if let Some(alt) = report["altHAE"].as_f64() {
+                loc = loc.altitude(alt);
+            }
+            return Ok(loc);
+        }
+        Err(Error::NotFound)
+    }
+
+    /// Query the current position from a [gpsd](https://gpsd.io) daemon at `addr`, e.g.,
+    /// `localhost:2947`.
+    ///
+    /// Waits up to `timeout` for a report with a fix. Requires the `gpsd` feature.
+    #[cfg(not(all(feature = "gpsd", not(target_arch = "wasm32"))))]
+    pub fn from_gpsd(_addr: &str, _timeout: Duration) -> Result<Self, Error> {
+        Err(Error::FeatureNotEnabled("gpsd"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let args: Args = "lat=-33.5,lon=151.25,azimuth=-90".try_into().unwrap();
+        let loc = Location::from_args(&args).unwrap().unwrap();
+        assert_eq!((loc.latitude, loc.longitude), (-33.5, 151.25));
+        assert_eq!(loc.azimuth, Some(270.0));
+        assert_eq!(Location::from_args(&Args::new()).unwrap(), None);
+        assert!(Location::from_args(&"lat=10".try_into().unwrap()).is_err());
+
+        let gga = "$GPGGA,123519,4807.038,N,01131.000,W,1,08,0.9,545.4,M,46.9,M,,*47";
+        let loc = Location::from_nmea_gga(gga).unwrap();
+        assert!((loc.latitude - 48.1173).abs() < 1e-4);
+        assert!((loc.longitude + 11.516_667).abs() < 1e-4);
+        assert!((loc.altitude.unwrap() - 592.3).abs() < 1e-6);
+        let no_fix = "$GPGGA,123519,,,,,0,00,,,M,,M,,*66";
+        assert!(matches!(
+            Location::from_nmea_gga(no_fix),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn device_location() {
+        let dev = crate::Device::from_args("driver=dummy,lat=52.5,lon=13.4").unwrap();
+        assert_eq!(dev.location().unwrap().unwrap().latitude, 52.5);
+        dev.set_location(None);
+        assert_eq!(dev.location().unwrap(), None);
+        if !cfg!(feature = "gpsd") {
+            assert!(matches!(
+                crate::Device::from_args("driver=dummy,gpsd=localhost:2947"),
+                Err(Error::FeatureNotEnabled("gpsd"))
+            ));
+        }
+    }
+}
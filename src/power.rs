@@ -0,0 +1,17 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Power state of a device, set with [`DeviceTrait::set_power_state`](crate::DeviceTrait::set_power_state).
+///
+/// Battery-powered setups can idle the radio between captures. Streams can only be activated in
+/// [`PowerState::On`], and drivers refuse to leave it while a stream is active.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerState {
+    /// Fully operational.
+    #[default]
+    On,
+    /// RF front end powered down, but the device keeps its configuration and wakes up quickly.
+    Standby,
+    /// Powered down as far as the hardware allows.
+    Off,
+}
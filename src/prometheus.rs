@@ -0,0 +1,360 @@
+use num_complex::Complex32;
+use std::fmt::Write as _;
+use std::net::ToSocketAddrs;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use crate::http::Response;
+use crate::Access;
+use crate::BufferConfig;
+use crate::Device;
+use crate::Direction;
+use crate::Error;
+use crate::EventKind;
+use crate::GenericDevice;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+
+const EVENT_KINDS: [(EventKind, &str); 9] = [
+    (EventKind::Retune, "retune"),
+    (EventKind::SampleRate, "sample_rate"),
+    (EventKind::Gain, "gain"),
+    (EventKind::Agc, "agc"),
+    (EventKind::Overflow, "overflow"),
+    (EventKind::Underflow, "underflow"),
+    (EventKind::LateCommand, "late_command"),
+    (EventKind::Reconnect, "reconnect"),
+    (EventKind::Other, "other"),
+];
+
+/// Exports device and stream metrics in the Prometheus text format.
+///
+/// Devices are polled for their frequency, sample rate, and gain, when the metrics are
+/// [rendered](Self::render). Their [event log](Device::events) is exported as counters. RX streams
+/// are monitored by wrapping them with [`meter`](Self::meter), which counts samples and overflows
+/// and reports the achieved sample rate.
+///
+/// Clones share the registered devices and streams, so metrics can be [served](Self::serve) while
+/// devices are added.
+#[derive(Clone, Default)]
+pub struct PrometheusExporter {
+    registry: Arc<Mutex<Registry>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    devices: Vec<(String, Device<GenericDevice>)>,
+    streams: Vec<(String, Arc<StreamCounters>)>,
+}
+
+#[derive(Default)]
+struct StreamCounters {
+    samples: AtomicU64,
+    overflows: AtomicU64,
+    /// Time of the activation and samples received since.
+    active: Mutex<Option<(Instant, u64)>>,
+}
+
+impl PrometheusExporter {
+    /// Create an exporter without devices or streams.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Export the metrics of a device, labeled with `name`.
+    pub fn add_device(&self, name: impl Into<String>, device: Device<GenericDevice>) {
+        self.registry
+            .lock()
+            .unwrap()
+            .devices
+            .push((name.into(), device));
+    }
+    /// Wrap an RX streamer to export its metrics, labeled with `name`.
+    pub fn meter<S: RxStreamer>(&self, name: impl Into<String>, inner: S) -> Metered<S> {
+        let counters = Arc::new(StreamCounters::default());
+        self.registry
+            .lock()
+            .unwrap()
+            .streams
+            .push((name.into(), counters.clone()));
+        Metered { inner, counters }
+    }
+
+    /// Render the metrics.
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().unwrap();
+        let mut frequency = Vec::new();
+        let mut sample_rate = Vec::new();
+        let mut gain = Vec::new();
+        let mut events = Vec::new();
+        for (name, dev) in &registry.devices {
+            for direction in [Direction::Rx, Direction::Tx] {
+                let dir = match direction {
+                    Direction::Rx => "rx",
+                    Direction::Tx => "tx",
+                };
+                for channel in 0..dev.num_channels(direction).unwrap_or(0) {
+                    let labels = format!(
+                        "device=\"{}\",direction=\"{dir}\",channel=\"{channel}\"",
+                        escape(name)
+                    );
+                    if let Ok(f) = dev.center_frequency(direction, channel) {
+                        frequency.push((labels.clone(), f.as_hz()));
+                    }
+                    if let Ok(r) = dev.rate(direction, channel) {
+                        sample_rate.push((labels.clone(), r.as_sps()));
+                    }
+                    if let Ok(Some(g)) = dev.gain(direction, channel) {
+                        gain.push((labels, g));
+                    }
+                }
+            }
+            let log = dev.event_log();
+            for (kind, label) in EVENT_KINDS {
+                let labels = format!("device=\"{}\",kind=\"{label}\"", escape(name));
+                events.push((labels, log.total(kind) as f64));
+            }
+        }
+        let mut samples = Vec::new();
+        let mut overflows = Vec::new();
+        let mut achieved = Vec::new();
+        for (name, c) in &registry.streams {
+            let labels = format!("stream=\"{}\"", escape(name));
+            samples.push((labels.clone(), c.samples.load(Ordering::Relaxed) as f64));
+            overflows.push((labels.clone(), c.overflows.load(Ordering::Relaxed) as f64));
+            if let Some((start, n)) = *c.active.lock().unwrap() {
+                let secs = start.elapsed().as_secs_f64();
+                if secs > 0.0 {
+                    achieved.push((labels, n as f64 / secs));
+                }
+            }
+        }
+        drop(registry);
+
+        let mut out = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, values: Vec<(String, f64)>| {
+            let _ = writeln!(out, "# HELP seify_{name} {help}");
+            let _ = writeln!(out, "# TYPE seify_{name} {kind}");
+            for (labels, v) in values {
+                let _ = writeln!(out, "seify_{name}{{{labels}}} {v}");
+            }
+        };
+        family(
+            "frequency_hz",
+            "gauge",
+            "Center frequency of the channel.",
+            frequency,
+        );
+        family(
+            "sample_rate_hz",
+            "gauge",
+            "Configured sample rate of the channel.",
+            sample_rate,
+        );
+        family("gain_db", "gauge", "Overall gain of the channel.", gain);
+        family(
+            "events_total",
+            "counter",
+            "Events in the device event log.",
+            events,
+        );
+        family(
+            "stream_samples_total",
+            "counter",
+            "Samples received by the stream.",
+            samples,
+        );
+        family(
+            "stream_overflows_total",
+            "counter",
+            "Overflows reported by the stream.",
+            overflows,
+        );
+        family(
+            "stream_sample_rate_hz",
+            "gauge",
+            "Achieved sample rate since the stream was activated.",
+            achieved,
+        );
+        out
+    }
+
+    /// Serve the metrics over HTTP at `/metrics` from a background thread.
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> Result<JoinHandle<()>, Error> {
+        self.serve_with_access(addr, Access::new())
+    }
+
+    /// Serve the metrics, requiring an access token of any [permission](crate::Permission)
+    /// and using TLS, if configured.
+    pub fn serve_with_access<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        access: Access,
+    ) -> Result<JoinHandle<()>, Error> {
+        let exporter = self.clone();
+        crate::http::serve(addr, access, move |request, _| {
+            let (path, _) = request.path.split_once('?').unwrap_or((&request.path, ""));
+            match path {
+                "/metrics" | "/" if request.method == "GET" => {
+                    Response::new("200 OK", "text/plain; version=0.0.4", exporter.render())
+                }
+                _ => Response::new("404 Not Found", "text/plain", ""),
+            }
+        })
+    }
+}
+
+/// Escape a Prometheus label value.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// RX streamer adapter that counts samples and overflows for a [`PrometheusExporter`].
+pub struct Metered<S: RxStreamer> {
+    inner: S,
+    counters: Arc<StreamCounters>,
+}
+
+impl<S: RxStreamer> Metered<S> {
+    /// Get a reference to the wrapped streamer.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    /// Remove the adapter and get the wrapped streamer.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn observe<T>(&self, r: Result<T, Error>, samples: impl Fn(&T) -> usize) -> Result<T, Error> {
+        match &r {
+            Ok(t) => {
+                let n = samples(t) as u64;
+                self.counters.samples.fetch_add(n, Ordering::Relaxed);
+                if let Some((_, total)) = self.counters.active.lock().unwrap().as_mut() {
+                    *total += n;
+                }
+            }
+            Err(Error::Overflow) => {
+                self.counters.overflows.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => {}
+        }
+        r
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for Metered<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)?;
+        *self.counters.active.lock().unwrap() = Some((Instant::now(), 0));
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        *self.counters.active.lock().unwrap() = None;
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        let r = self.inner.read(buffers, timeout_us);
+        self.observe(r, |n| *n)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let r = self.inner.read_with_metadata(buffers, timeout_us);
+        self.observe(r, |(n, _)| *n)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let r = self.inner.read_format(format, buffers, timeout_us);
+        self.observe(r, |n| *n)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::Frequency;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+
+    #[test]
+    fn render_and_serve() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        dev.tune(Direction::Rx, 0, Frequency::hz(100e6)).unwrap();
+        let exporter = PrometheusExporter::new();
+        exporter.add_device("dummy \"0\"", dev.clone());
+        let rx = dev.rx_streamer(&[0]).unwrap();
+        let mut rx = exporter.meter("rx", rx);
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 128];
+        rx.read(&mut [&mut buf], 1000).unwrap();
+
+        let text = exporter.render();
+        assert!(text.contains(
+            "seify_frequency_hz{device=\"dummy \\\"0\\\"\",direction=\"rx\",channel=\"0\"} 100000000"
+        ));
+        assert!(text.contains("seify_events_total{device=\"dummy \\\"0\\\"\",kind=\"retune\"} 1"));
+        assert!(text.contains("seify_stream_samples_total{stream=\"rx\"} 128"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        exporter.serve(addr).unwrap();
+        let mut s = TcpStream::connect(addr).unwrap();
+        s.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        s.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("seify_stream_overflows_total{stream=\"rx\"} 0"));
+    }
+}
@@ -0,0 +1,423 @@
+use num_complex::Complex32;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::Error;
+use crate::IqFormat;
+use crate::Location;
+
+/// Compression of a recording.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Write raw samples.
+    #[default]
+    None,
+    /// Compress the samples with zstd, requires the `zstd` feature.
+    ///
+    /// Every `chunk` samples are compressed as an independent frame, and
+    /// [`Recorder::finish`] appends a seek table in the
+    /// [zstd seekable format](https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md),
+    /// so tools can decompress from any chunk. The decompressed data is the raw recording, with
+    /// the fixed scaling of the [`IqFormat`].
+    Zstd {
+        /// Compression level, 1 to 22. Low levels keep up with higher sample rates.
+        level: i32,
+        /// Samples per frame.
+        chunk: usize,
+    },
+}
+
+/// Writes received samples to a recording, with an optional pre-trigger history.
+///
+/// Samples are passed to the recorder with [`push`](Self::push). While idle, the recorder keeps
+/// the last [`pre_trigger`](Self::pre_trigger) seconds in a ring buffer. When the recording is
+/// [triggered](Self::trigger) manually or a sample exceeds the [threshold](Self::threshold), the
+/// history is written first, so the recording includes the signal before the trigger.
+///
+/// ```
+/// # use seify::{IqFormat, Recorder};
+/// # use num_complex::Complex32;
+/// # use std::time::Duration;
+/// let mut rec = Recorder::new(Vec::new(), IqFormat::Cf32, 1000.0)
+///     .pre_trigger(Duration::from_millis(10))
+///     .threshold(0.5);
+/// rec.push(&[Complex32::new(0.1, 0.0); 100]).unwrap();
+/// assert!(!rec.is_recording());
+/// rec.push(&[Complex32::new(0.9, 0.0)]).unwrap();
+/// assert!(rec.is_recording());
+/// // 10 samples of history and the trigger sample
+/// assert_eq!(rec.into_inner().len(), 11 * IqFormat::Cf32.sample_size());
+/// ```
+pub struct Recorder<W: Write> {
+    writer: W,
+    format: IqFormat,
+    sample_rate: f64,
+    history: VecDeque<Complex32>,
+    history_len: usize,
+    post_trigger: Option<u64>,
+    threshold: Option<f32>,
+    /// Samples left to record, `Some(None)` to record until stopped.
+    remaining: Option<Option<u64>>,
+    buf: Vec<u8>,
+    compression: Compression,
+    /// Encoded samples of the current zstd frame.
+    pending: Vec<u8>,
+    /// Compressed and decompressed size of the written zstd frames.
+    frames: Vec<(u32, u32)>,
+    location: Option<Location>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Create an idle recorder that writes samples in the given format.
+    pub fn new(writer: W, format: IqFormat, sample_rate: f64) -> Self {
+        Self {
+            writer,
+            format,
+            sample_rate,
+            history: VecDeque::new(),
+            history_len: 0,
+            post_trigger: None,
+            threshold: None,
+            remaining: None,
+            buf: Vec::new(),
+            compression: Compression::None,
+            pending: Vec::new(),
+            frames: Vec::new(),
+            location: None,
+        }
+    }
+    /// Include `duration` of samples before the trigger.
+    pub fn pre_trigger(mut self, duration: Duration) -> Self {
+        self.history_len = self.samples(duration) as usize;
+        self.history = VecDeque::with_capacity(self.history_len);
+        self
+    }
+    /// Stop recording `duration` after the trigger. By default, recording continues until
+    /// [`stop`](Self::stop) is called.
+    pub fn post_trigger(mut self, duration: Duration) -> Self {
+        self.post_trigger = Some(self.samples(duration));
+        self
+    }
+    /// Trigger the recording, when the magnitude of a sample reaches `level`.
+    pub fn threshold(mut self, level: f32) -> Self {
+        self.threshold = Some(level);
+        self
+    }
+
+    /// Compress the recording.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Attach the location of the receiver, e.g., from [`Device::location`](crate::Device::location),
+    /// to the [SigMF metadata](Self::sigmf_meta).
+    pub fn location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// [SigMF](https://sigmf.org) metadata of the recording, to be stored as `.sigmf-meta` file
+    /// next to the samples.
+    ///
+    /// The location is stored as `core:geolocation` and the azimuth of the antenna as
+    /// `antenna:azimuth_angle` of the capture. Compressed recordings cannot be described in
+    /// SigMF and fail with [`Error::NotSupported`].
+    pub fn sigmf_meta(&self) -> Result<serde_json::Value, Error> {
+        if self.compression != Compression::None {
+            return Err(Error::NotSupported);
+        }
+        let datatype = match self.format {
+            IqFormat::Cf32 => "cf32_le",
+            IqFormat::Cs16 => "ci16_le",
+            IqFormat::Cs8 => "ci8",
+            IqFormat::Cu8 => "cu8",
+        };
+        let mut global = serde_json::json!({
+            "core:datatype": datatype,
+            "core:sample_rate": self.sample_rate,
+            "core:version": "1.0.0",
+        });
+        let mut capture = serde_json::json!({ "core:sample_start": 0 });
+        if let Some(loc) = self.location {
+            let mut coordinates = vec![loc.longitude, loc.latitude];
+            coordinates.extend(loc.altitude);
+            global["core:geolocation"] = serde_json::json!({
+                "type": "Point",
+                "coordinates": coordinates,
+            });
+            if let Some(azimuth) = loc.azimuth {
+                global["core:extensions"] = serde_json::json!([
+                    { "name": "antenna", "version": "1.0.0", "optional": true }
+                ]);
+                capture["antenna:azimuth_angle"] = azimuth.into();
+            }
+        }
+        Ok(serde_json::json!({
+            "global": global,
+            "captures": [capture],
+            "annotations": [],
+        }))
+    }
+
+    fn samples(&self, duration: Duration) -> u64 {
+        (duration.as_secs_f64() * self.sample_rate).round() as u64
+    }
+
+    /// Returns true, if the recorder is writing samples.
+    pub fn is_recording(&self) -> bool {
+        self.remaining.is_some()
+    }
+
+    /// Start recording, writing the pre-trigger history first.
+    ///
+    /// Triggering while recording restarts the post-trigger period.
+    pub fn trigger(&mut self) -> Result<(), Error> {
+        let history: Vec<Complex32> = self.history.drain(..).collect();
+        self.remaining = Some(self.post_trigger);
+        self.write(&history)
+    }
+
+    /// Stop recording and flush the writer. The recorder is re-armed and collects history again.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.remaining = None;
+        self.write_frame()?;
+        Ok(self.writer.flush()?)
+    }
+
+    /// Stop recording, complete the file, and return the writer.
+    ///
+    /// For zstd compression, this writes the seek table.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.stop()?;
+        if let Compression::Zstd { .. } = self.compression {
+            let n = u32::try_from(self.frames.len()).map_err(|_| Error::Overflow)?;
+            let mut t = Vec::with_capacity(self.frames.len() * 8 + 17);
+            t.extend_from_slice(&ZSTD_SKIPPABLE_MAGIC.to_le_bytes());
+            t.extend_from_slice(&(n * 8 + 9).to_le_bytes());
+            for (compressed, decompressed) in &self.frames {
+                t.extend_from_slice(&compressed.to_le_bytes());
+                t.extend_from_slice(&decompressed.to_le_bytes());
+            }
+            t.extend_from_slice(&n.to_le_bytes());
+            // descriptor without checksums
+            t.push(0);
+            t.extend_from_slice(&ZSTD_SEEKABLE_MAGIC.to_le_bytes());
+            self.writer.write_all(&t)?;
+            self.writer.flush()?;
+        }
+        Ok(self.writer)
+    }
+
+    /// Pass received samples to the recorder.
+    pub fn push(&mut self, mut samples: &[Complex32]) -> Result<(), Error> {
+        while !samples.is_empty() {
+            match self.remaining {
+                Some(remaining) => {
+                    let n = match remaining {
+                        Some(r) => (r as usize).min(samples.len()),
+                        None => samples.len(),
+                    };
+                    self.write(&samples[..n])?;
+                    samples = &samples[n..];
+                    if let Some(r) = remaining {
+                        if r == n as u64 {
+                            self.stop()?;
+                        } else {
+                            self.remaining = Some(Some(r - n as u64));
+                        }
+                    }
+                }
+                None => {
+                    let trigger = self
+                        .threshold
+                        .and_then(|t| samples.iter().position(|s| s.norm() >= t));
+                    let n = trigger.unwrap_or(samples.len());
+                    self.remember(&samples[..n]);
+                    samples = &samples[n..];
+                    if trigger.is_some() {
+                        self.trigger()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add samples to the pre-trigger ring buffer.
+    fn remember(&mut self, samples: &[Complex32]) {
+        let samples = &samples[samples.len().saturating_sub(self.history_len)..];
+        let overflow = (self.history.len() + samples.len()).saturating_sub(self.history_len);
+        self.history.drain(..overflow);
+        self.history.extend(samples);
+    }
+
+    fn write(&mut self, samples: &[Complex32]) -> Result<(), Error> {
+        match self.compression {
+            Compression::None => {
+                self.buf.clear();
+                self.format.encode(samples, &mut self.buf);
+                self.writer.write_all(&self.buf)?;
+            }
+            Compression::Zstd { chunk, .. } => {
+                let chunk = chunk.max(1);
+                for s in samples.chunks(chunk) {
+                    let space = chunk - self.pending.len() / self.format.sample_size();
+                    let n = space.min(s.len());
+                    self.format.encode(&s[..n], &mut self.pending);
+                    if n == space {
+                        self.write_frame()?;
+                    }
+                    self.format.encode(&s[n..], &mut self.pending);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compress the pending samples into a zstd frame.
+    fn write_frame(&mut self) -> Result<(), Error> {
+        let Compression::Zstd { level, .. } = self.compression else {
+            return Ok(());
+        };
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let frame = compress(&self.pending, level)?;
+        let compressed = u32::try_from(frame.len()).map_err(|_| Error::Overflow)?;
+        let decompressed = u32::try_from(self.pending.len()).map_err(|_| Error::Overflow)?;
+        self.writer.write_all(&frame)?;
+        self.frames.push((compressed, decompressed));
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Get a reference to the writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consume the recorder and return the writer, without completing the file.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+const ZSTD_SKIPPABLE_MAGIC: u32 = 0x184d2a5e;
+const ZSTD_SEEKABLE_MAGIC: u32 = 0x8f92eab1;
+
+#[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+pub(crate) fn compress(data: &[u8], level: i32) -> Result<Vec<u8>, Error> {
+    Ok(zstd::bulk::compress(data, level)?)
+}
+
+#[cfg(not(all(feature = "zstd", not(target_arch = "wasm32"))))]
+pub(crate) fn compress(_data: &[u8], _level: i32) -> Result<Vec<u8>, Error> {
+    Err(Error::FeatureNotEnabled("zstd"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(start: usize, n: usize) -> Vec<Complex32> {
+        (start..start + n)
+            .map(|i| Complex32::new(i as f32, 0.0))
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<f32> {
+        let mut out = vec![Complex32::new(0.0, 0.0); bytes.len() / 8];
+        IqFormat::Cf32.decode(bytes, &mut out);
+        out.iter().map(|s| s.re).collect()
+    }
+
+    #[test]
+    fn manual_trigger_includes_history() {
+        let mut rec = Recorder::new(Vec::new(), IqFormat::Cf32, 10.0)
+            .pre_trigger(Duration::from_millis(300))
+            .post_trigger(Duration::from_millis(200));
+        rec.push(&ramp(0, 5)).unwrap();
+        rec.push(&ramp(5, 2)).unwrap();
+        rec.trigger().unwrap();
+        rec.push(&ramp(7, 5)).unwrap();
+        assert!(!rec.is_recording());
+        assert_eq!(decode(rec.get_ref()), [4.0, 5.0, 6.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn threshold_trigger() {
+        let mut rec = Recorder::new(Vec::new(), IqFormat::Cf32, 1.0)
+            .pre_trigger(Duration::from_secs(2))
+            .post_trigger(Duration::from_secs(3))
+            .threshold(10.0);
+        let mut burst = ramp(0, 13);
+        burst.extend(ramp(0, 5));
+        rec.push(&burst).unwrap();
+        assert_eq!(decode(rec.get_ref()), [8.0, 9.0, 10.0, 11.0, 12.0]);
+        assert!(!rec.is_recording());
+
+        // re-armed with fresh history
+        rec.push(&ramp(1, 3)).unwrap();
+        rec.push(&ramp(30, 1)).unwrap();
+        rec.stop().unwrap();
+        assert_eq!(decode(&rec.into_inner()[40..]), [2.0, 3.0, 30.0]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_chunks() {
+        let mut rec =
+            Recorder::new(Vec::new(), IqFormat::Cs16, 1.0).compression(Compression::Zstd {
+                level: 3,
+                chunk: 1000,
+            });
+        let samples: Vec<Complex32> = (0..2500)
+            .map(|i| Complex32::from_polar(0.5, i as f32 * 0.01))
+            .collect();
+        rec.trigger().unwrap();
+        rec.push(&samples[..700]).unwrap();
+        rec.push(&samples[700..]).unwrap();
+        assert_eq!(rec.frames.len(), 2);
+        let file = rec.finish().unwrap();
+
+        let mut raw = Vec::new();
+        IqFormat::Cs16.encode(&samples, &mut raw);
+        assert!(file.len() < raw.len());
+        assert_eq!(zstd::stream::decode_all(&file[..]).unwrap(), raw);
+        let footer = &file[file.len() - 9..];
+        assert_eq!(footer[0..4], 3u32.to_le_bytes());
+        assert_eq!(footer[5..9], ZSTD_SEEKABLE_MAGIC.to_le_bytes());
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_needs_feature() {
+        let mut rec = Recorder::new(Vec::new(), IqFormat::Cf32, 1.0)
+            .compression(Compression::Zstd { level: 3, chunk: 1 });
+        assert!(matches!(rec.trigger(), Ok(())));
+        assert!(matches!(
+            rec.push(&[Complex32::new(0.0, 0.0)]),
+            Err(Error::FeatureNotEnabled("zstd"))
+        ));
+    }
+
+    #[test]
+    fn sigmf_location() {
+        let loc = Location::new(48.0, 11.5)
+            .unwrap()
+            .altitude(500.0)
+            .azimuth(45.0);
+        let rec = Recorder::new(Vec::new(), IqFormat::Cs8, 2e6).location(Some(loc));
+        let meta = rec.sigmf_meta().unwrap();
+        assert_eq!(meta["global"]["core:datatype"], "ci8");
+        assert_eq!(
+            meta["global"]["core:geolocation"]["coordinates"],
+            serde_json::json!([11.5, 48.0, 500.0])
+        );
+        assert_eq!(meta["captures"][0]["antenna:azimuth_angle"], 45.0);
+        let rec = rec.compression(Compression::Zstd { level: 1, chunk: 8 });
+        assert!(matches!(rec.sigmf_meta(), Err(Error::NotSupported)));
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+
+use num_complex::Complex32;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::BufferLayout;
+use crate::Direction;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+use crate::TxStreamer;
+
+/// Settings that some drivers only apply when a stream starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Change {
+    /// Sample rate, e.g., the RTL-SDR resets its buffer and the HackRF configures it in
+    /// `start_rx`.
+    SampleRate,
+    /// Frequency, bandwidth, or antenna.
+    Tuning,
+}
+
+/// Settings changes that restart the active streams of a [`GenericDevice`](crate::GenericDevice),
+/// set with the `restart` streamer argument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Policy {
+    /// Keep streaming, the driver applies changes on its own (default).
+    #[default]
+    Off,
+    /// Restart after sample rate changes.
+    SampleRate,
+    /// Restart after sample rate, frequency, bandwidth, or antenna changes.
+    All,
+}
+
+impl Policy {
+    /// Parse the `restart` streamer argument: `off` (default), `rate`, or `all`.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        match args.get::<String>("restart").as_deref() {
+            Err(Error::NotFound) | Ok("off") => Ok(Self::Off),
+            Ok("rate") => Ok(Self::SampleRate),
+            Ok("all") => Ok(Self::All),
+            _ => Err(Error::ValueError),
+        }
+    }
+
+    fn covers(self, change: Change) -> bool {
+        match self {
+            Self::Off => false,
+            Self::SampleRate => change == Change::SampleRate,
+            Self::All => true,
+        }
+    }
+}
+
+/// Number of settings changes per channel.
+pub(crate) type Changes = Arc<Mutex<HashMap<(Direction, usize, Change), u64>>>;
+
+/// Count a change of the settings of a channel.
+pub(crate) fn record(changes: &Changes, direction: Direction, channel: usize, change: Change) {
+    *changes
+        .lock()
+        .unwrap()
+        .entry((direction, channel, change))
+        .or_default() += 1;
+}
+
+/// Restarts a stream before the next read or write, if its channels were reconfigured while it
+/// was active.
+struct Restarter {
+    changes: Changes,
+    direction: Direction,
+    channels: Vec<usize>,
+    policy: Policy,
+    // changes seen at the last (re)activation, `None` while inactive
+    seen: Option<u64>,
+}
+
+impl Restarter {
+    fn new(changes: Changes, direction: Direction, channels: &[usize], policy: Policy) -> Self {
+        Self {
+            changes,
+            direction,
+            channels: channels.to_vec(),
+            policy,
+            seen: None,
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.changes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((d, c, change), _)| {
+                *d == self.direction && self.channels.contains(c) && self.policy.covers(*change)
+            })
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    fn activated(&mut self) {
+        self.seen = Some(self.count());
+    }
+
+    fn deactivated(&mut self) {
+        self.seen = None;
+    }
+
+    /// Check, if the stream has to be restarted.
+    fn stale(&mut self) -> bool {
+        let Some(seen) = self.seen else {
+            return false;
+        };
+        let count = self.count();
+        if count == seen {
+            return false;
+        }
+        log::debug!(
+            "restarting {:?} stream after settings change",
+            self.direction
+        );
+        self.seen = Some(count);
+        true
+    }
+}
+
+/// RX streamer of a [`GenericDevice`](crate::GenericDevice), restarted after settings changes.
+pub(crate) struct RestartingRx<S: RxStreamer> {
+    inner: S,
+    restarter: Restarter,
+}
+
+impl<S: RxStreamer> RestartingRx<S> {
+    pub fn new(inner: S, changes: Changes, channels: &[usize], policy: Policy) -> Self {
+        Self {
+            inner,
+            restarter: Restarter::new(changes, Direction::Rx, channels, policy),
+        }
+    }
+
+    fn restart_if_stale(&mut self) -> Result<(), Error> {
+        if self.restarter.stale() {
+            self.inner.deactivate()?;
+            self.inner.activate()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for RestartingRx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)?;
+        self.restarter.activated();
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.restarter.deactivated();
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner.read(buffers, timeout_us)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        self.restart_if_stale()?;
+        self.inner.read_with_metadata(buffers, timeout_us)
+    }
+    fn native_layout(&self) -> BufferLayout {
+        self.inner.native_layout()
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner.read_interleaved(buffer, channels, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner.read_format(format, buffers, timeout_us)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// TX streamer of a [`GenericDevice`](crate::GenericDevice), restarted after settings changes.
+pub(crate) struct RestartingTx<S: TxStreamer> {
+    inner: S,
+    restarter: Restarter,
+}
+
+impl<S: TxStreamer> RestartingTx<S> {
+    pub fn new(inner: S, changes: Changes, channels: &[usize], policy: Policy) -> Self {
+        Self {
+            inner,
+            restarter: Restarter::new(changes, Direction::Tx, channels, policy),
+        }
+    }
+
+    fn restart_if_stale(&mut self) -> Result<(), Error> {
+        if self.restarter.stale() {
+            self.inner.deactivate()?;
+            self.inner.activate()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: TxStreamer> TxStreamer for RestartingTx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)?;
+        self.restarter.activated();
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.restarter.deactivated();
+        self.inner.deactivate_at(time_ns)
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner.write(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        self.restart_if_stale()?;
+        self.inner.write_all(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner
+            .write_segments(segments, at_ns, end_burst, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.restart_if_stale()?;
+        self.inner
+            .write_format(format, buffers, at_ns, end_burst, timeout_us)
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_input_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        self.inner.flush(timeout_us)
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        self.inner.queued_samples()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frequency;
+    use crate::SampleRate;
+
+    #[test]
+    fn policy() {
+        let args = |s: &str| Args::from(s).unwrap();
+        assert_eq!(Policy::from_args(&Args::new()).unwrap(), Policy::Off);
+        assert_eq!(
+            Policy::from_args(&args("restart=rate")).unwrap(),
+            Policy::SampleRate
+        );
+        assert_eq!(
+            Policy::from_args(&args("restart=all")).unwrap(),
+            Policy::All
+        );
+        assert!(Policy::from_args(&args("restart=1")).is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn restart_after_rate_change() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        let args = Args::from("restart=rate").unwrap();
+        let mut rx = dev.rx_streamer_with_args(&[0], args).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 64];
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        assert_eq!(rx.sample_position().unwrap(), 64);
+
+        // the dummy driver resets its position on activation
+        dev.tune(Direction::Rx, 0, Frequency::hz(100e6)).unwrap();
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        assert_eq!(rx.sample_position().unwrap(), 128);
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(2e6))
+            .unwrap();
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        assert_eq!(rx.sample_position().unwrap(), 64);
+
+        rx.deactivate().unwrap();
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        rx.activate().unwrap();
+        rx.read(&mut [&mut buf], 1000).unwrap();
+        assert_eq!(rx.sample_position().unwrap(), 64);
+    }
+}
@@ -0,0 +1,589 @@
+use num_complex::Complex;
+use num_complex::Complex32;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::str::FromStr;
+
+use crate::Error;
+use crate::RxStreamer;
+use crate::TxStreamer;
+
+/// Sample format of a stream.
+///
+/// Samples are interleaved I and Q values in the byte order of the host. Integer formats are
+/// scaled, so that the full scale of the converter corresponds to ±1.0 of [`Complex32`]
+/// samples (see [full scale](RxStreamer#full-scale)). Formats of drivers that do not fit one of
+/// these are described with a [`CustomFormat`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StreamFormat {
+    /// Complex 32-bit float, i.e., [`Complex32`].
+    Cf32,
+    /// Complex 64-bit float.
+    Cf64,
+    /// Complex signed 16-bit integer.
+    Cs16,
+    /// Complex signed 12-bit integer, packed into three bytes per sample like SoapySDR's `CS12`.
+    Cs12,
+    /// Complex signed 8-bit integer, e.g., of HackRFs.
+    Cs8,
+    /// Complex unsigned 8-bit integer with an offset of 127, e.g., of RTL-SDRs.
+    Cu8,
+    /// Format with its own conversion, e.g., the 12-bit samples in 16-bit integers of a driver.
+    Custom(CustomFormat),
+}
+
+/// Sample format defined outside of Seify, with the conversion from and to [`Complex32`].
+///
+/// Drivers return it as their [native format](RxStreamer::native_format) to pass samples
+/// through without a copy to [`Sample`] types that use the same format. Other sample types are
+/// converted with [`encode`](Self::encode) and [`decode`](Self::decode). Formats are
+/// identified by their name.
+#[derive(Clone, Copy, Debug)]
+pub struct CustomFormat {
+    /// Name of the format, e.g., `SC16Q11`.
+    pub name: &'static str,
+    /// Size of a complex sample in bytes.
+    pub sample_size: usize,
+    /// Convert `Complex32` samples to this format.
+    pub encode: fn(&[Complex32], &mut [u8]),
+    /// Convert samples of this format to `Complex32`.
+    pub decode: fn(&[u8], &mut [Complex32]),
+}
+
+impl PartialEq for CustomFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomFormat {}
+
+impl Hash for CustomFormat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl StreamFormat {
+    /// Size of a complex sample in bytes.
+    pub fn sample_size(&self) -> usize {
+        match self {
+            StreamFormat::Cf64 => 16,
+            StreamFormat::Cf32 => 8,
+            StreamFormat::Cs16 => 4,
+            StreamFormat::Cs12 => 3,
+            StreamFormat::Cs8 | StreamFormat::Cu8 => 2,
+            StreamFormat::Custom(c) => c.sample_size,
+        }
+    }
+
+    /// Convert `Complex32` samples to this format.
+    ///
+    /// `bytes` has to hold [`sample_size`](Self::sample_size) bytes per sample.
+    pub fn encode(&self, samples: &[Complex32], bytes: &mut [u8]) {
+        debug_assert_eq!(bytes.len(), samples.len() * self.sample_size());
+        let chunks = bytes.chunks_exact_mut(self.sample_size());
+        let int = |v: f32, scale: f32| (v * scale).round().clamp(-scale, scale - 1.0);
+        match self {
+            StreamFormat::Cf64 => {
+                for (b, s) in chunks.zip(samples) {
+                    b[0..8].copy_from_slice(&(s.re as f64).to_ne_bytes());
+                    b[8..16].copy_from_slice(&(s.im as f64).to_ne_bytes());
+                }
+            }
+            StreamFormat::Cf32 => {
+                for (b, s) in chunks.zip(samples) {
+                    b[0..4].copy_from_slice(&s.re.to_ne_bytes());
+                    b[4..8].copy_from_slice(&s.im.to_ne_bytes());
+                }
+            }
+            StreamFormat::Cs16 => {
+                for (b, s) in chunks.zip(samples) {
+                    b[0..2].copy_from_slice(&(int(s.re, 32768.0) as i16).to_ne_bytes());
+                    b[2..4].copy_from_slice(&(int(s.im, 32768.0) as i16).to_ne_bytes());
+                }
+            }
+            StreamFormat::Cs12 => {
+                for (b, s) in chunks.zip(samples) {
+                    let i = int(s.re, 2048.0) as i16 as u16;
+                    let q = int(s.im, 2048.0) as i16 as u16;
+                    b[0] = i as u8;
+                    b[1] = ((i >> 8) & 0x0f) as u8 | ((q & 0x0f) << 4) as u8;
+                    b[2] = (q >> 4) as u8;
+                }
+            }
+            StreamFormat::Cs8 => {
+                for (b, s) in chunks.zip(samples) {
+                    b[0] = int(s.re, 128.0) as i8 as u8;
+                    b[1] = int(s.im, 128.0) as i8 as u8;
+                }
+            }
+            StreamFormat::Cu8 => {
+                for (b, s) in chunks.zip(samples) {
+                    b[0] = (s.re * 128.0 + 127.0).round().clamp(0.0, 255.0) as u8;
+                    b[1] = (s.im * 128.0 + 127.0).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            StreamFormat::Custom(c) => (c.encode)(samples, bytes),
+        }
+    }
+
+    /// Convert samples of this format to `Complex32`.
+    ///
+    /// `bytes` has to hold [`sample_size`](Self::sample_size) bytes per sample.
+    pub fn decode(&self, bytes: &[u8], samples: &mut [Complex32]) {
+        debug_assert_eq!(bytes.len(), samples.len() * self.sample_size());
+        let chunks = bytes.chunks_exact(self.sample_size());
+        match self {
+            StreamFormat::Cf64 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        f64::from_ne_bytes(b[0..8].try_into().unwrap()) as f32,
+                        f64::from_ne_bytes(b[8..16].try_into().unwrap()) as f32,
+                    );
+                }
+            }
+            StreamFormat::Cf32 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        f32::from_ne_bytes(b[0..4].try_into().unwrap()),
+                        f32::from_ne_bytes(b[4..8].try_into().unwrap()),
+                    );
+                }
+            }
+            StreamFormat::Cs16 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        i16::from_ne_bytes([b[0], b[1]]) as f32 / 32768.0,
+                        i16::from_ne_bytes([b[2], b[3]]) as f32 / 32768.0,
+                    );
+                }
+            }
+            StreamFormat::Cs12 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    // sign-extend the 12-bit values through the top bits of an i16
+                    let i = (((b[1] as u16 & 0x0f) << 12) | ((b[0] as u16) << 4)) as i16 >> 4;
+                    let q = (((b[2] as u16) << 8) | (b[1] as u16 & 0xf0)) as i16 >> 4;
+                    *s = Complex32::new(i as f32 / 2048.0, q as f32 / 2048.0);
+                }
+            }
+            StreamFormat::Cs8 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    *s = Complex32::new(b[0] as i8 as f32 / 128.0, b[1] as i8 as f32 / 128.0);
+                }
+            }
+            StreamFormat::Cu8 => {
+                for (s, b) in samples.iter_mut().zip(chunks) {
+                    *s = Complex32::new(
+                        (b[0] as f32 - 127.0) / 128.0,
+                        (b[1] as f32 - 127.0) / 128.0,
+                    );
+                }
+            }
+            StreamFormat::Custom(c) => (c.decode)(bytes, samples),
+        }
+    }
+}
+
+impl fmt::Display for StreamFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            StreamFormat::Cf32 => "CF32",
+            StreamFormat::Cf64 => "CF64",
+            StreamFormat::Cs16 => "CS16",
+            StreamFormat::Cs12 => "CS12",
+            StreamFormat::Cs8 => "CS8",
+            StreamFormat::Cu8 => "CU8",
+            StreamFormat::Custom(c) => c.name,
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for StreamFormat {
+    type Err = Error;
+
+    /// Parse the SoapySDR name of the format, e.g., `CS16`. Custom formats cannot be parsed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "CF32" => Ok(StreamFormat::Cf32),
+            "CF64" => Ok(StreamFormat::Cf64),
+            "CS16" => Ok(StreamFormat::Cs16),
+            "CS12" => Ok(StreamFormat::Cs12),
+            "CS8" => Ok(StreamFormat::Cs8),
+            "CU8" => Ok(StreamFormat::Cu8),
+            _ => Err(Error::ValueError),
+        }
+    }
+}
+
+/// Sample type of [typed streamers](TypedRxStreamer), stored in its [`StreamFormat`].
+///
+/// Implemented for `Complex<f32>`, `Complex<f64>`, `Complex<i16>`, `Complex<i8>`, and
+/// `Complex<u8>`. Packed 12-bit samples have no sample type and are read as bytes with
+/// [`RxStreamer::read_format`]. Other types are added with a [`CustomFormat`], e.g., for the
+/// native format of a driver.
+///
+/// # Safety
+///
+/// Typed streamers pass the samples to the drivers as bytes. The type has to have the memory
+/// layout of [`FORMAT`](Self::FORMAT), i.e., [`sample_size`](StreamFormat::sample_size) bytes
+/// without padding, and every bit pattern has to be a valid value.
+pub unsafe trait Sample: Copy + Send + 'static {
+    /// Format of the samples in memory.
+    const FORMAT: StreamFormat;
+
+    /// Convert the sample to `Complex32`, by default with [`StreamFormat::decode`].
+    fn to_complex32(self) -> Complex32 {
+        let mut s = [Complex32::new(0.0, 0.0)];
+        Self::FORMAT.decode(as_bytes(&[self]), &mut s);
+        s[0]
+    }
+
+    /// Convert a `Complex32` sample, by default with [`StreamFormat::encode`].
+    fn from_complex32(sample: Complex32) -> Self {
+        // SAFETY: all bit patterns, including zeros, are valid samples
+        let mut s = unsafe { std::mem::MaybeUninit::<Self>::zeroed().assume_init() };
+        Self::FORMAT.encode(&[sample], as_bytes_mut(std::slice::from_mut(&mut s)));
+        s
+    }
+}
+
+macro_rules! sample {
+    ($t:ty, $format:ident) => {
+        // SAFETY: `Complex` is a `repr(C)` pair of integers or floats
+        unsafe impl Sample for Complex<$t> {
+            const FORMAT: StreamFormat = StreamFormat::$format;
+        }
+    };
+}
+
+sample!(f32, Cf32);
+sample!(f64, Cf64);
+sample!(i16, Cs16);
+sample!(i8, Cs8);
+sample!(u8, Cu8);
+
+fn as_bytes<T: Sample>(samples: &[T]) -> &[u8] {
+    // SAFETY: samples have no padding, see the safety section of `Sample`
+    unsafe { std::slice::from_raw_parts(samples.as_ptr().cast(), std::mem::size_of_val(samples)) }
+}
+
+fn as_bytes_mut<T: Sample>(samples: &mut [T]) -> &mut [u8] {
+    // SAFETY: samples have no padding and all bit patterns are valid, see the safety section of
+    // `Sample`
+    unsafe {
+        std::slice::from_raw_parts_mut(samples.as_mut_ptr().cast(), std::mem::size_of_val(samples))
+    }
+}
+
+/// Read `Complex32` samples and convert them to `format`, the default of
+/// [`RxStreamer::read_format`].
+pub(crate) fn read_converted<S: RxStreamer + ?Sized>(
+    streamer: &mut S,
+    format: StreamFormat,
+    buffers: &mut [&mut [u8]],
+    timeout_us: i64,
+) -> Result<usize, Error> {
+    let size = format.sample_size();
+    let len = buffers.first().map_or(0, |b| b.len() / size);
+    let mut scratch = vec![vec![Complex32::new(0.0, 0.0); len]; buffers.len()];
+    let mut refs: Vec<&mut [Complex32]> = scratch.iter_mut().map(|s| s.as_mut_slice()).collect();
+    let n = streamer.read(&mut refs, timeout_us)?;
+    for (b, s) in buffers.iter_mut().zip(&scratch) {
+        format.encode(&s[..n], &mut b[..n * size]);
+    }
+    Ok(n)
+}
+
+/// Convert samples in `format` to `Complex32` and write them, the default of
+/// [`TxStreamer::write_format`].
+pub(crate) fn write_converted<S: TxStreamer + ?Sized>(
+    streamer: &mut S,
+    format: StreamFormat,
+    buffers: &[&[u8]],
+    at_ns: Option<i64>,
+    end_burst: bool,
+    timeout_us: i64,
+) -> Result<usize, Error> {
+    let size = format.sample_size();
+    let scratch: Vec<Vec<Complex32>> = buffers
+        .iter()
+        .map(|b| {
+            let mut s = vec![Complex32::new(0.0, 0.0); b.len() / size];
+            format.decode(&b[..s.len() * size], &mut s);
+            s
+        })
+        .collect();
+    let refs: Vec<&[Complex32]> = scratch.iter().map(|s| s.as_slice()).collect();
+    streamer.write(&refs, at_ns, end_burst, timeout_us)
+}
+
+/// RX streamer reading samples of type `T`, created with
+/// [`Device::rx_streamer_typed`](crate::Device::rx_streamer_typed).
+///
+/// Samples in the [native format](RxStreamer::native_format) of the driver are passed through
+/// without conversion, e.g., `Complex<u8>` of RTL-SDRs. The streamer dereferences to the wrapped
+/// one, e.g., to [`activate`](RxStreamer::activate) it.
+pub struct TypedRxStreamer<S, T> {
+    inner: S,
+    sample: PhantomData<fn() -> T>,
+}
+
+impl<S: RxStreamer, T: Sample> TypedRxStreamer<S, T> {
+    /// Wrap a streamer.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            sample: PhantomData,
+        }
+    }
+    /// Get the wrapped streamer back.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+    /// Read samples into the buffers, one per channel, like [`RxStreamer::read`].
+    pub fn read(&mut self, buffers: &mut [&mut [T]], timeout_us: i64) -> Result<usize, Error> {
+        let mut bytes: Vec<&mut [u8]> = buffers.iter_mut().map(|b| as_bytes_mut(b)).collect();
+        self.inner.read_format(T::FORMAT, &mut bytes, timeout_us)
+    }
+}
+
+impl<S, T> Deref for TypedRxStreamer<S, T> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S, T> DerefMut for TypedRxStreamer<S, T> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+/// TX streamer writing samples of type `T`, created with
+/// [`Device::tx_streamer_typed`](crate::Device::tx_streamer_typed).
+///
+/// Like [`TypedRxStreamer`], samples in the native format of the driver are passed through.
+pub struct TypedTxStreamer<S, T> {
+    inner: S,
+    sample: PhantomData<fn() -> T>,
+}
+
+impl<S: TxStreamer, T: Sample> TypedTxStreamer<S, T> {
+    /// Wrap a streamer.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            sample: PhantomData,
+        }
+    }
+    /// Get the wrapped streamer back.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+    /// Write samples from the buffers, one per channel, like [`TxStreamer::write`].
+    pub fn write(
+        &mut self,
+        buffers: &[&[T]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let bytes: Vec<&[u8]> = buffers.iter().map(|b| as_bytes(b)).collect();
+        self.inner
+            .write_format(T::FORMAT, &bytes, at_ns, end_burst, timeout_us)
+    }
+    /// Write all samples, like [`TxStreamer::write_all`].
+    ///
+    /// Fails with `Err(Error::Timeout)`, if a write accepts no samples.
+    pub fn write_all(
+        &mut self,
+        buffers: &[&[T]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let len = buffers.first().map_or(0, |b| b.len());
+        let mut done = 0;
+        while done < len {
+            let rest: Vec<&[T]> = buffers.iter().map(|b| &b[done..]).collect();
+            let at_ns = if done == 0 { at_ns } else { None };
+            match self.write(&rest, at_ns, end_burst, timeout_us)? {
+                0 => return Err(Error::Timeout),
+                n => done += n,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S, T> Deref for TypedTxStreamer<S, T> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S, T> DerefMut for TypedTxStreamer<S, T> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let samples = [
+            Complex32::new(0.5, -0.25),
+            Complex32::new(-1.0, 0.0),
+            Complex32::new(0.125, 0.75),
+        ];
+        for format in [
+            StreamFormat::Cf32,
+            StreamFormat::Cf64,
+            StreamFormat::Cs16,
+            StreamFormat::Cs12,
+            StreamFormat::Cs8,
+            StreamFormat::Cu8,
+        ] {
+            let mut bytes = vec![0; samples.len() * format.sample_size()];
+            format.encode(&samples, &mut bytes);
+            let mut decoded = [Complex32::new(9.0, 9.0); 3];
+            format.decode(&bytes, &mut decoded);
+            for (d, s) in decoded.iter().zip(&samples) {
+                assert!((d - s).norm() < 1e-2, "{format}: {d} != {s}");
+            }
+            assert_eq!(format.to_string().parse::<StreamFormat>().unwrap(), format);
+        }
+        // full scale clips instead of wrapping around
+        let mut bytes = [0; 2];
+        StreamFormat::Cs8.encode(&[Complex32::new(1.0, -1.0)], &mut bytes);
+        assert_eq!(bytes, [127, 0x80]);
+    }
+
+    /// 12-bit samples in 16-bit integers, like SC16Q11 of bladeRFs.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct ComplexI12 {
+        i: i16,
+        q: i16,
+    }
+
+    const SC16Q11: CustomFormat = CustomFormat {
+        name: "SC16Q11",
+        sample_size: 4,
+        encode: |samples, bytes| {
+            let int = |v: f32| ((v * 2048.0).round().clamp(-2048.0, 2047.0) as i16).to_ne_bytes();
+            for (b, s) in bytes.chunks_exact_mut(4).zip(samples) {
+                b[0..2].copy_from_slice(&int(s.re));
+                b[2..4].copy_from_slice(&int(s.im));
+            }
+        },
+        decode: |bytes, samples| {
+            for (s, b) in samples.iter_mut().zip(bytes.chunks_exact(4)) {
+                *s = Complex32::new(
+                    i16::from_ne_bytes([b[0], b[1]]) as f32 / 2048.0,
+                    i16::from_ne_bytes([b[2], b[3]]) as f32 / 2048.0,
+                );
+            }
+        },
+    };
+
+    // SAFETY: `repr(C)` pair of `i16`
+    unsafe impl Sample for ComplexI12 {
+        const FORMAT: StreamFormat = StreamFormat::Custom(SC16Q11);
+    }
+
+    #[test]
+    fn custom_format() {
+        let s = ComplexI12::from_complex32(Complex32::new(0.5, -1.0));
+        assert_eq!(s, ComplexI12 { i: 1024, q: -2048 });
+        assert_eq!(s.to_complex32(), Complex32::new(0.5, -1.0));
+        assert_eq!(
+            Complex::<u8>::from_complex32(Complex32::new(0.0, 1.0)),
+            Complex::new(127, 255)
+        );
+        assert_eq!(ComplexI12::FORMAT.sample_size(), 4);
+        assert_eq!(ComplexI12::FORMAT.to_string(), "SC16Q11");
+        assert_ne!(ComplexI12::FORMAT, StreamFormat::Cs16);
+    }
+
+    /// Accepts no samples, like a stream whose buffers stay full.
+    struct Stalled;
+
+    impl TxStreamer for Stalled {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(1024)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn write(
+            &mut self,
+            _buffers: &[&[Complex32]],
+            _at_ns: Option<i64>,
+            _end_burst: bool,
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            Ok(0)
+        }
+        fn write_all(
+            &mut self,
+            _buffers: &[&[Complex32]],
+            _at_ns: Option<i64>,
+            _end_burst: bool,
+            _timeout_us: i64,
+        ) -> Result<(), Error> {
+            Err(Error::NotSupported)
+        }
+    }
+
+    #[test]
+    fn write_all_stalled() {
+        let mut tx = TypedTxStreamer::<_, Complex<i8>>::new(Stalled);
+        let samples = [Complex::new(1i8, 1); 4];
+        assert!(matches!(
+            tx.write_all(&[&samples], None, false, 1000),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn typed_loopback() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let mut rx = dev.rx_streamer_typed::<Complex<u8>>(&[0]).unwrap();
+        let mut tx = dev.tx_streamer_typed::<Complex<i8>>(&[0]).unwrap();
+        assert_eq!(rx.native_format(), StreamFormat::Cf32);
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+        let samples = [Complex::new(64i8, -128), Complex::new(0, 127)];
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        let mut buf = [Complex::new(0u8, 0); 2];
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 2);
+        assert_eq!(buf, [Complex::new(191, 0), Complex::new(127, 254)]);
+
+        let mut rx = TypedRxStreamer::<_, ComplexI12>::new(rx.into_inner());
+        let mut tx = TypedTxStreamer::<_, ComplexI12>::new(tx.into_inner());
+        let samples = [ComplexI12 { i: 1024, q: -2048 }];
+        tx.write_all(&[&samples], None, false, 1000).unwrap();
+        let mut buf = [ComplexI12 { i: 0, q: 0 }];
+        assert_eq!(rx.read(&mut [&mut buf], 1000).unwrap(), 1);
+        assert_eq!(buf, samples);
+    }
+}
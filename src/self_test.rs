@@ -0,0 +1,208 @@
+use num_complex::Complex32;
+
+use crate::Args;
+use crate::DeviceTrait;
+use crate::Direction::Rx;
+use crate::Direction::Tx;
+use crate::Error;
+use crate::RxStreamer;
+use crate::TxStreamer;
+
+/// Number of samples read by the streaming check.
+const STREAM_SAMPLES: usize = 1 << 16;
+/// Timeout for a single read or write of the self-test.
+const TIMEOUT_US: i64 = 200_000;
+
+/// Result of [`DeviceTrait::self_test`].
+#[derive(Debug)]
+pub struct SelfTestReport {
+    /// Checks that were run, in order.
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// A single check of a [`SelfTestReport`].
+#[derive(Debug)]
+pub struct SelfTestCheck {
+    /// Name of the check, e.g., `rx_stream`.
+    pub name: String,
+    /// Details on success or the reason of the failure.
+    ///
+    /// Checks that the device cannot run fail with [`Error::NotSupported`].
+    pub result: Result<String, Error>,
+}
+
+impl SelfTestReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self { checks: Vec::new() }
+    }
+    /// Add the result of a check.
+    pub fn add<S: Into<String>>(&mut self, name: S, result: Result<String, Error>) {
+        let name = name.into();
+        match &result {
+            Ok(d) => log::debug!("self-test {name}: {d}"),
+            Err(Error::NotSupported) => log::debug!("self-test {name}: skipped"),
+            Err(e) => log::warn!("self-test {name} failed: {e}"),
+        }
+        self.checks.push(SelfTestCheck { name, result });
+    }
+    /// Returns true, if no check failed.
+    ///
+    /// Checks that are not supported by the device do not count as failures.
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+    /// Checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks
+            .iter()
+            .filter(|c| matches!(c.result, Err(ref e) if !matches!(e, Error::NotSupported)))
+    }
+}
+
+impl Default for SelfTestReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generic health check, used by drivers that do not implement their own.
+///
+/// Reads back the RX (or, for TX-only devices, TX) frequency, streams a few samples, and runs an
+/// internal loopback test, if the device has an `internal` loopback mode.
+pub(crate) fn run<D: DeviceTrait + ?Sized>(dev: &D) -> SelfTestReport {
+    let mut report = SelfTestReport::new();
+    report.add("control", control(dev));
+    report.add("rx_stream", rx_stream(dev));
+    report.add("loopback", loopback(dev));
+    report
+}
+
+fn has_rx(dev: &(impl DeviceTrait + ?Sized)) -> Result<(), Error> {
+    match dev.num_channels(Rx)? {
+        0 => Err(Error::NotSupported),
+        _ => Ok(()),
+    }
+}
+
+fn control(dev: &(impl DeviceTrait + ?Sized)) -> Result<String, Error> {
+    // TX-only devices are checked on their TX channel
+    let direction = match has_rx(dev) {
+        Ok(()) => Rx,
+        Err(Error::NotSupported) if dev.num_channels(Tx)? > 0 => Tx,
+        Err(e) => return Err(e),
+    };
+    let f = dev.frequency(direction, 0)?;
+    dev.set_frequency(direction, 0, f, Args::new())?;
+    let readback = dev.frequency(direction, 0)?;
+    if (readback - f).abs() > 1.0 {
+        return Err(Error::Misc(format!(
+            "frequency readback mismatch (set {f}, got {readback})"
+        )));
+    }
+    Ok(format!("frequency readback {readback} Hz"))
+}
+
+fn rx_stream(dev: &(impl DeviceTrait + ?Sized)) -> Result<String, Error> {
+    has_rx(dev)?;
+    let mut rx = dev.rx_streamer(&[0], Args::new())?;
+    let mut buf = vec![Complex32::new(0.0, 0.0); rx.mtu()?.clamp(1, STREAM_SAMPLES)];
+    #[cfg(not(target_arch = "wasm32"))]
+    let start = std::time::Instant::now();
+    rx.activate()?;
+    let mut n = 0;
+    while n < STREAM_SAMPLES {
+        match rx.read(&mut [&mut buf], TIMEOUT_US) {
+            Ok(0) => break,
+            Ok(r) => n += r,
+            Err(e) => {
+                let _ = rx.deactivate();
+                return Err(e);
+            }
+        }
+    }
+    rx.deactivate()?;
+    if n == 0 {
+        return Err(Error::Misc("no samples received".to_string()));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    return Ok(format!(
+        "received {n} samples ({:.0} samples/s)",
+        n as f64 / start.elapsed().as_secs_f64()
+    ));
+    #[cfg(target_arch = "wasm32")]
+    return Ok(format!("received {n} samples"));
+}
+
+fn loopback(dev: &(impl DeviceTrait + ?Sized)) -> Result<String, Error> {
+    has_rx(dev)?;
+    if dev.num_channels(Tx)? == 0 || !dev.list_loopback_modes()?.iter().any(|m| m == "internal") {
+        return Err(Error::NotSupported);
+    }
+    let previous = dev.loopback_mode()?;
+    dev.set_loopback_mode("internal")?;
+    let r = loopback_roundtrip(dev);
+    dev.set_loopback_mode(&previous)?;
+    r
+}
+
+fn loopback_roundtrip(dev: &(impl DeviceTrait + ?Sized)) -> Result<String, Error> {
+    let pattern: Vec<Complex32> = (0..256)
+        .map(|i| Complex32::from_polar(0.5, i as f32 * 0.1))
+        .collect();
+    let mut rx = dev.rx_streamer(&[0], Args::new())?;
+    let mut tx = dev.tx_streamer(&[0], Args::new())?;
+    rx.activate()?;
+    tx.activate()?;
+    tx.write_all(&[&pattern], None, true, TIMEOUT_US)?;
+
+    let mut received = Vec::new();
+    let mut buf = vec![Complex32::new(0.0, 0.0); pattern.len()];
+    while received.len() < pattern.len() {
+        match rx.read(&mut [&mut buf], TIMEOUT_US)? {
+            0 => break,
+            n => received.extend_from_slice(&buf[..n]),
+        }
+    }
+    tx.deactivate()?;
+    rx.deactivate()?;
+
+    if received.len() < pattern.len() {
+        return Err(Error::Misc(format!(
+            "received {} of {} loopback samples",
+            received.len(),
+            pattern.len()
+        )));
+    }
+    let err = pattern
+        .iter()
+        .zip(received.iter())
+        .map(|(a, b)| (a - b).norm())
+        .fold(0.0f32, f32::max);
+    if err > 1e-3 {
+        return Err(Error::Misc(format!("loopback samples differ by {err}")));
+    }
+    Ok(format!("{} samples looped back", pattern.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_skip_unsupported() {
+        let mut report = SelfTestReport::new();
+        report.add("a", Ok("fine".to_string()));
+        report.add("b", Err(Error::NotSupported));
+        assert!(report.passed());
+        report.add("c", Err(Error::Timeout));
+        assert!(!report.passed());
+        assert_eq!(
+            report
+                .failures()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            ["c"]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use num_complex::Complex32;
+use std::fmt;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::BufferConfig;
+use crate::BufferLayout;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+use crate::TxStreamer;
+
+/// Shared, thread-safe handle for objects that are not `Sync`.
+///
+/// Seify requires devices to be `Send + Sync` (to be used as [`GenericDevice`](crate::GenericDevice))
+/// and streamers to be `Send`. Driver handles, however, are often not thread safe. Wrapping them
+/// in a `SharedHandle` serializes access through a mutex, which makes the handle `Send + Sync` and
+/// allows sharing it between a device and its streamers.
+///
+/// The adapter also implements [`RxStreamer`] and [`TxStreamer`], if the wrapped object does,
+/// which provides `Sync` streamers that can be shared between threads.
+///
+/// Handles that are not even `Send`, e.g., of C libraries with raw pointers, are kept on their
+/// own thread with a [`ConfinedHandle`].
+pub struct SharedHandle<T>(Arc<Mutex<T>>);
+
+impl<T: Send> SharedHandle<T> {
+    /// Wrap a value.
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+}
+
+impl<T> SharedHandle<T> {
+    /// Lock the handle for exclusive access.
+    ///
+    /// A lock that was poisoned by a panicking thread is recovered, since driver handles have no
+    /// invariants that Seify could restore.
+    pub fn lock(&self) -> SharedGuard<'_, T> {
+        SharedGuard(self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl<T> Clone for SharedHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.try_lock() {
+            Ok(g) => f.debug_tuple("SharedHandle").field(&*g).finish(),
+            Err(_) => f.debug_tuple("SharedHandle").field(&"<locked>").finish(),
+        }
+    }
+}
+
+/// Exclusive access to the value of a [`SharedHandle`].
+pub struct SharedGuard<'a, T>(MutexGuard<'a, T>);
+
+impl<T> Deref for SharedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for SharedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// A call to run on the thread of a [`ConfinedHandle`].
+type Call<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+/// Handle to a value that lives on its own thread.
+///
+/// For driver handles that are not `Send`, which a [`SharedHandle`] cannot wrap. The value is
+/// created on a worker thread and never leaves it. [`call`](Self::call) sends a closure to the
+/// thread and waits for its result, so calls are serialized. The thread exits when the last
+/// handle is dropped.
+pub struct ConfinedHandle<T> {
+    calls: mpsc::Sender<Call<T>>,
+}
+
+impl<T: 'static> ConfinedHandle<T> {
+    /// Create the value with `open` on a new thread with the given name.
+    pub fn spawn(
+        name: &str,
+        open: impl FnOnce() -> Result<T, Error> + Send + 'static,
+    ) -> Result<Self, Error> {
+        let (calls, rx) = mpsc::channel::<Call<T>>();
+        let (opened, result) = mpsc::sync_channel(1);
+        std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let mut value = match open() {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let _ = opened.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = opened.send(Ok(()));
+                for call in rx {
+                    call(&mut value);
+                }
+            })?;
+        // a panic in `open` drops the sender
+        result.recv().map_err(|_| Error::DeviceError)??;
+        Ok(Self { calls })
+    }
+
+    /// Run `f` with the value on its thread and return its result.
+    ///
+    /// Fails with [`Error::DeviceError`], if the thread is gone, since an earlier call panicked.
+    pub fn call<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut T) -> Result<R, Error> + Send + 'static,
+    ) -> Result<R, Error> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        self.calls
+            .send(Box::new(move |value| {
+                let _ = tx.send(f(value));
+            }))
+            .map_err(|_| Error::DeviceError)?;
+        rx.recv().map_err(|_| Error::DeviceError)?
+    }
+}
+
+impl<T> Clone for ConfinedHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            calls: self.calls.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ConfinedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfinedHandle").finish_non_exhaustive()
+    }
+}
+
+impl<T: RxStreamer> RxStreamer for SharedHandle<T> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.lock().mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.lock().activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.lock().deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        self.lock().read(buffers, timeout_us)
+    }
+    fn try_read(&mut self, buffers: &mut [&mut [Complex32]]) -> Result<usize, Error> {
+        self.lock().try_read(buffers)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.lock().poll_ready(cx)
+    }
+    fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffers: &mut [&mut [Complex32]],
+    ) -> Poll<Result<usize, Error>> {
+        self.lock().poll_read(cx, buffers)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.lock().sample_position()
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        self.lock().read_with_metadata(buffers, timeout_us)
+    }
+    fn native_layout(&self) -> BufferLayout {
+        self.lock().native_layout()
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.lock().read_interleaved(buffer, channels, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.lock().native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.lock().read_format(format, buffers, timeout_us)
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.lock().buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.lock().set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.lock().canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.lock().stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.lock().activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.lock().flush()
+    }
+}
+
+impl<T: TxStreamer> TxStreamer for SharedHandle<T> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.lock().mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.lock().activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.lock().deactivate_at(time_ns)
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.lock().write(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        self.lock().write_all(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.lock()
+            .write_segments(segments, at_ns, end_burst, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.lock().native_format()
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.lock()
+            .write_format(format, buffers, at_ns, end_burst, timeout_us)
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.lock().set_input_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.lock().canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.lock().stream_events()
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        self.lock().flush(timeout_us)
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        self.lock().queued_samples()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.lock().activation_error_ns()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn bounds() {
+        assert_send_sync::<SharedHandle<std::cell::Cell<u8>>>();
+        assert_send_sync::<ConfinedHandle<std::rc::Rc<u8>>>();
+        assert_send_sync::<SharedHandle<Box<dyn RxStreamer>>>();
+        assert_send_sync::<crate::GenericDevice>();
+    }
+
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let h = SharedHandle::new(1u8);
+        let h2 = h.clone();
+        let _ = std::thread::spawn(move || {
+            let _g = h2.lock();
+            panic!("poison");
+        })
+        .join();
+        *h.lock() += 1;
+        assert_eq!(*h.lock(), 2);
+    }
+
+    #[test]
+    fn confined() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let h = ConfinedHandle::spawn("confined", || Ok(Rc::new(Cell::new(1u8)))).unwrap();
+        let h2 = h.clone();
+        std::thread::spawn(move || {
+            h2.call(|v| {
+                v.set(v.get() + 1);
+                Ok(())
+            })
+            .unwrap()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(h.call(|v| Ok(v.get())).unwrap(), 2);
+        assert!(matches!(
+            h.call(|_| Err::<(), _>(Error::ValueError)),
+            Err(Error::ValueError)
+        ));
+        assert!(matches!(
+            ConfinedHandle::<u8>::spawn("confined", || Err(Error::NotFound)),
+            Err(Error::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+use num_complex::Complex32;
+use std::f64::consts::TAU;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Modulation {
+    Cw,
+    Fm { tone: f64, deviation: f64 },
+    Am { tone: f64, depth: f64 },
+}
+
+/// Generator for continuous test signals, e.g., for hardware bring-up.
+///
+/// The signal is a carrier at a baseband offset in Hz, relative to the center frequency of the
+/// device, that is unmodulated (CW) or modulated with a sine tone in FM or AM. Phase is
+/// continuous across calls to [`fill`](Self::fill).
+///
+/// ```
+/// # use seify::SignalGenerator;
+/// let mut fm = SignalGenerator::fm(1e6, 100e3, 1e3, 5e3).unwrap();
+/// let block = fm.generate(1024);
+/// assert!(block.iter().all(|s| (s.norm() - 0.7).abs() < 1e-5));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignalGenerator {
+    sample_rate: f64,
+    offset: f64,
+    amplitude: f32,
+    modulation: Modulation,
+    carrier_phase: f64,
+    tone_phase: f64,
+}
+
+impl SignalGenerator {
+    fn new(sample_rate: f64, offset: f64, modulation: Modulation) -> Result<Self, Error> {
+        let nyquist = sample_rate / 2.0;
+        let (tone, deviation) = match modulation {
+            Modulation::Cw => (0.0, 0.0),
+            Modulation::Fm { tone, deviation } => (tone, deviation),
+            Modulation::Am { tone, depth } => {
+                if !(0.0..=1.0).contains(&depth) {
+                    return Err(Error::ValueError);
+                }
+                // the sidebands are `tone` Hz from the carrier
+                (tone, tone)
+            }
+        };
+        let valid = sample_rate.is_finite()
+            && sample_rate > 0.0
+            && tone >= 0.0
+            && deviation >= 0.0
+            && offset.abs() + deviation <= nyquist;
+        if !valid {
+            return Err(Error::ValueError);
+        }
+        Ok(Self {
+            sample_rate,
+            offset,
+            amplitude: 0.7,
+            modulation,
+            carrier_phase: 0.0,
+            tone_phase: 0.0,
+        })
+    }
+
+    /// Unmodulated carrier at `offset` Hz.
+    pub fn cw(sample_rate: f64, offset: f64) -> Result<Self, Error> {
+        Self::new(sample_rate, offset, Modulation::Cw)
+    }
+    /// Carrier at `offset` Hz, frequency modulated with a `tone` Hz sine and a peak `deviation` in
+    /// Hz.
+    pub fn fm(sample_rate: f64, offset: f64, tone: f64, deviation: f64) -> Result<Self, Error> {
+        Self::new(sample_rate, offset, Modulation::Fm { tone, deviation })
+    }
+    /// Carrier at `offset` Hz, amplitude modulated with a `tone` Hz sine and a modulation `depth`
+    /// between 0 and 1.
+    pub fn am(sample_rate: f64, offset: f64, tone: f64, depth: f64) -> Result<Self, Error> {
+        Self::new(sample_rate, offset, Modulation::Am { tone, depth })
+    }
+    /// Set the peak amplitude, between 0 and 1. Defaults to 0.7.
+    pub fn amplitude(mut self, amplitude: f32) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&amplitude) {
+            return Err(Error::ValueError);
+        }
+        self.amplitude = amplitude;
+        Ok(self)
+    }
+
+    /// Fill `buf` with the next samples of the signal.
+    pub fn fill(&mut self, buf: &mut [Complex32]) {
+        let fs = self.sample_rate;
+        for s in buf {
+            let (frequency, envelope) = match self.modulation {
+                Modulation::Cw => (self.offset, 1.0),
+                Modulation::Fm { tone, deviation } => {
+                    self.tone_phase = (self.tone_phase + TAU * tone / fs) % TAU;
+                    (self.offset + deviation * self.tone_phase.sin(), 1.0)
+                }
+                Modulation::Am { tone, depth } => {
+                    self.tone_phase = (self.tone_phase + TAU * tone / fs) % TAU;
+                    (
+                        self.offset,
+                        (1.0 + depth * self.tone_phase.sin()) / (1.0 + depth),
+                    )
+                }
+            };
+            *s = Complex32::from_polar(self.amplitude * envelope as f32, self.carrier_phase as f32);
+            self.carrier_phase = (self.carrier_phase + TAU * frequency / fs) % TAU;
+        }
+    }
+
+    /// Generate the next `n` samples of the signal.
+    pub fn generate(&mut self, n: usize) -> Vec<Complex32> {
+        let mut buf = vec![Complex32::new(0.0, 0.0); n];
+        self.fill(&mut buf);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequency(a: Complex32, b: Complex32, sample_rate: f64) -> f64 {
+        (b * a.conj()).arg() as f64 * sample_rate / TAU
+    }
+
+    #[test]
+    fn carriers() {
+        let fs = 1000.0;
+        let mut cw = SignalGenerator::cw(fs, -100.0).unwrap();
+        let v = cw.generate(10);
+        let w = cw.generate(10);
+        assert!((frequency(v[9], w[0], fs) + 100.0).abs() < 1e-2);
+
+        let mut fm = SignalGenerator::fm(fs, 0.0, 10.0, 50.0).unwrap();
+        let v = fm.generate(100);
+        let f: Vec<f64> = v.windows(2).map(|w| frequency(w[0], w[1], fs)).collect();
+        let max = f.iter().cloned().fold(f64::MIN, f64::max);
+        assert!((max - 50.0).abs() < 1.0);
+
+        let mut am = SignalGenerator::am(fs, 0.0, 10.0, 0.5)
+            .unwrap()
+            .amplitude(1.0)
+            .unwrap();
+        let v = am.generate(100);
+        let max = v.iter().map(|s| s.norm()).fold(0.0, f32::max);
+        let min = v.iter().map(|s| s.norm()).fold(1.0, f32::min);
+        assert!((max - 1.0).abs() < 1e-3);
+        assert!((min - 1.0 / 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(SignalGenerator::cw(1000.0, 600.0).is_err());
+        assert!(SignalGenerator::fm(1000.0, 400.0, 10.0, 200.0).is_err());
+        assert!(SignalGenerator::am(1000.0, 0.0, 10.0, 1.5).is_err());
+        assert!(SignalGenerator::cw(1000.0, 0.0)
+            .unwrap()
+            .amplitude(2.0)
+            .is_err());
+    }
+}
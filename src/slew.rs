@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use crate::Args;
+use crate::Error;
+
+/// Slew-rate limits of the gain and the frequency of a [`GenericDevice`](crate::GenericDevice),
+/// set with device arguments.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct SlewLimits {
+    /// Maximum gain change per call in dB.
+    gain_step: Option<f64>,
+    /// Maximum frequency change per step of a retune in Hz.
+    frequency_step: Option<f64>,
+    /// Time to wait after each intermediate step of a retune.
+    dwell: Duration,
+}
+
+impl SlewLimits {
+    /// Parse the `max_gain_step_db_per_call`, `max_retune_step_hz`, and `retune_dwell_us`
+    /// arguments.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        let step = |key| match args.get::<f64>(key) {
+            Ok(s) if s > 0.0 && s.is_finite() => Ok(Some(s)),
+            Err(Error::NotFound) => Ok(None),
+            _ => Err(Error::ValueError),
+        };
+        let dwell = match args.get::<u64>("retune_dwell_us") {
+            Ok(us) => Duration::from_micros(us),
+            Err(Error::NotFound) => Duration::ZERO,
+            Err(_) => return Err(Error::ValueError),
+        };
+        Ok(Self {
+            gain_step: step("max_gain_step_db_per_call")?,
+            frequency_step: step("max_retune_step_hz")?,
+            dwell,
+        })
+    }
+
+    /// Gain to set for a `target`, at most one step away from the `current` gain.
+    ///
+    /// Without a limit or the current gain, the target is set directly.
+    pub fn gain(&self, current: Option<f64>, target: f64) -> f64 {
+        match (self.gain_step, current) {
+            (Some(step), Some(current)) => {
+                let gain = target.clamp(current - step, current + step);
+                if gain != target {
+                    log::debug!("gain limited to {gain} dB on the way to {target} dB");
+                }
+                gain
+            }
+            _ => target,
+        }
+    }
+
+    /// Intermediate frequencies of a retune from the `current` frequency to the `target`,
+    /// excluding the target.
+    ///
+    /// The steps are computed lazily, as small limits and large retunes take many of them.
+    pub fn ramp(&self, current: Option<f64>, target: f64) -> impl Iterator<Item = f64> {
+        let (current, steps) = match (self.frequency_step, current) {
+            (Some(step), Some(current)) => {
+                (current, ((target - current).abs() / step).ceil() as usize)
+            }
+            _ => (target, 0),
+        };
+        (1..steps).map(move |i| current + (target - current) * i as f64 / steps as f64)
+    }
+
+    /// Wait after an intermediate step of a retune.
+    pub fn dwell(&self) {
+        if !self.dwell.is_zero() {
+            std::thread::sleep(self.dwell);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let args = Args::from("max_gain_step_db_per_call=3, max_retune_step_hz=1e6").unwrap();
+        let limits = SlewLimits::from_args(&args).unwrap();
+        assert_eq!(limits.gain(Some(10.0), 20.0), 13.0);
+        assert_eq!(limits.gain(Some(10.0), 8.0), 8.0);
+        assert_eq!(limits.gain(None, 20.0), 20.0);
+        let ramp = |current, target| limits.ramp(current, target).collect::<Vec<_>>();
+        assert_eq!(ramp(Some(100e6), 103e6), [101e6, 102e6]);
+        assert_eq!(ramp(Some(100e6), 97e6), [99e6, 98e6]);
+        assert!(ramp(Some(100e6), 100.5e6).is_empty());
+        assert!(ramp(None, 100e6).is_empty());
+        // steps are not allocated up front
+        let tiny = SlewLimits::from_args(&Args::from("max_retune_step_hz=1e-3").unwrap()).unwrap();
+        assert_eq!(tiny.ramp(Some(0.0), 6e9).nth(1), Some(2e-3));
+
+        let open = SlewLimits::from_args(&Args::new()).unwrap();
+        assert_eq!(open.gain(Some(0.0), 40.0), 40.0);
+        assert_eq!(open.ramp(Some(0.0), 1e9).count(), 0);
+        assert!(SlewLimits::from_args(&Args::from("max_retune_step_hz=-1").unwrap()).is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn limited_device() {
+        let dev = crate::Device::from_args("driver=dummy, max_gain_step_db_per_call=5").unwrap();
+        let rx = crate::Direction::Rx;
+        dev.set_gain(rx, 0, 0.0).unwrap();
+        // the setter returns the limited gain
+        assert_eq!(dev.set_gain(rx, 0, 12.0).unwrap(), 5.0);
+        assert_eq!(dev.gain(rx, 0).unwrap(), Some(5.0));
+        dev.set_gain(rx, 0, 12.0).unwrap();
+        dev.set_gain(rx, 0, 12.0).unwrap();
+        assert_eq!(dev.gain(rx, 0).unwrap(), Some(12.0));
+
+        // so does the element setter
+        assert_eq!(dev.set_gain_element(rx, 0, "RF", 20.0).unwrap(), 17.0);
+        assert_eq!(dev.gain_element(rx, 0, "RF").unwrap(), Some(17.0));
+        assert_eq!(dev.set_gain_element(rx, 0, "RF", 19.0).unwrap(), 19.0);
+    }
+}
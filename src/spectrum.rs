@@ -0,0 +1,261 @@
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use num_complex::Complex32;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use rustfft::Fft;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use rustfft::FftPlanner;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use std::collections::VecDeque;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use std::sync::Arc;
+
+use crate::Error;
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+use crate::RxStreamer;
+use crate::StreamCanceller;
+
+/// Power spectrum of a [`SpectrumStreamer`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectrum {
+    /// Time of the first sample of the measurement in nanoseconds, if known.
+    pub time_ns: Option<i64>,
+    /// Frequency of the first bin in Hz.
+    pub start_frequency: f64,
+    /// Frequency of the last bin in Hz.
+    pub stop_frequency: f64,
+    /// Unit of the bins, e.g., `dBm` for calibrated analyzers or `dBFS`.
+    pub unit: String,
+    /// Power of each bin, from the start to the stop frequency.
+    pub bins: Vec<f32>,
+}
+
+/// Receive power spectra from a [Device](crate::Device).
+///
+/// Spectrum analyzers stream spectra natively, which needs much less bandwidth than IQ samples.
+/// For other devices, [`FftSpectrum`] computes them from an [`RxStreamer`](crate::RxStreamer).
+/// [`Device::spectrum_streamer`](crate::Device::spectrum_streamer) picks either.
+pub trait SpectrumStreamer: Send {
+    /// Start the stream.
+    fn activate(&mut self) -> Result<(), Error>;
+
+    /// Stop the stream.
+    fn deactivate(&mut self) -> Result<(), Error>;
+
+    /// Read the next spectrum, waiting up to `timeout_us`, if the driver supports timeouts.
+    fn read(&mut self, timeout_us: i64) -> Result<Spectrum, Error>;
+
+    /// Get a handle to cancel blocking [`read`](SpectrumStreamer::read) calls from another
+    /// thread.
+    ///
+    /// Returns `Err(Error::NotSupported)` if the driver cannot interrupt a blocked read.
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Err(Error::NotSupported)
+    }
+}
+
+#[doc(hidden)]
+impl SpectrumStreamer for Box<dyn SpectrumStreamer> {
+    fn activate(&mut self) -> Result<(), Error> {
+        self.as_mut().activate()
+    }
+    fn deactivate(&mut self) -> Result<(), Error> {
+        self.as_mut().deactivate()
+    }
+    fn read(&mut self, timeout_us: i64) -> Result<Spectrum, Error> {
+        self.as_mut().read(timeout_us)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.as_ref().canceller()
+    }
+}
+
+/// Computes power spectra of consecutive, non-overlapping frames.
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+pub(crate) struct Periodogram {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    frame: Vec<Complex32>,
+}
+
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+impl Periodogram {
+    pub fn new(size: usize) -> Self {
+        let window = (0..size)
+            .map(|i| {
+                let x = std::f32::consts::PI * i as f32 / size as f32;
+                x.sin().powi(2)
+            })
+            .collect();
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(size),
+            window,
+            frame: Vec::with_capacity(size),
+        }
+    }
+
+    /// Add samples and return the spectra of the completed frames.
+    pub fn process(&mut self, mut input: &[Complex32]) -> Vec<Vec<f32>> {
+        let size = self.window.len();
+        let mut out = Vec::new();
+        while !input.is_empty() {
+            let n = (size - self.frame.len()).min(input.len());
+            self.frame.extend_from_slice(&input[..n]);
+            input = &input[n..];
+            if self.frame.len() == size {
+                for (s, w) in self.frame.iter_mut().zip(&self.window) {
+                    *s *= w;
+                }
+                self.fft.process(&mut self.frame);
+                let scale = 1.0 / (size * size) as f32;
+                let mut power: Vec<f32> = self
+                    .frame
+                    .iter()
+                    .map(|s| 10.0 * (s.norm_sqr() * scale + 1e-20).log10())
+                    .collect();
+                power.rotate_left(size / 2);
+                out.push(power);
+                self.frame.clear();
+            }
+        }
+        out
+    }
+}
+
+/// [`SpectrumStreamer`] that computes spectra from the samples of an [`RxStreamer`] in software.
+///
+/// Spectra of `size` bins in `dBFS` are computed from consecutive, non-overlapping frames with a
+/// Hann window. Requires the `spectrum` feature.
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+pub struct FftSpectrum<S: RxStreamer> {
+    inner: S,
+    periodogram: Periodogram,
+    frequency: f64,
+    sample_rate: f64,
+    buf: Vec<Complex32>,
+    spectra: VecDeque<Spectrum>,
+}
+
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+impl<S: RxStreamer> FftSpectrum<S> {
+    /// Compute spectra of `size` bins, for a stream tuned to `frequency` with `sample_rate`.
+    pub fn new(inner: S, size: usize, frequency: f64, sample_rate: f64) -> Result<Self, Error> {
+        if size == 0 || sample_rate <= 0.0 {
+            return Err(Error::ValueError);
+        }
+        Ok(Self {
+            inner,
+            periodogram: Periodogram::new(size),
+            frequency,
+            sample_rate,
+            buf: vec![Complex32::new(0.0, 0.0); size],
+            spectra: VecDeque::new(),
+        })
+    }
+    /// Get a reference to the wrapped streamer.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    /// Get the wrapped streamer.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(all(feature = "spectrum", not(target_arch = "wasm32")))]
+impl<S: RxStreamer> SpectrumStreamer for FftSpectrum<S> {
+    fn activate(&mut self) -> Result<(), Error> {
+        self.periodogram.frame.clear();
+        self.spectra.clear();
+        self.inner.activate()
+    }
+    fn deactivate(&mut self) -> Result<(), Error> {
+        self.inner.deactivate()
+    }
+    fn read(&mut self, timeout_us: i64) -> Result<Spectrum, Error> {
+        let size = self.buf.len();
+        let bin = self.sample_rate / size as f64;
+        while self.spectra.is_empty() {
+            let (n, meta) = self
+                .inner
+                .read_with_metadata(&mut [&mut self.buf], timeout_us)?;
+            if n == 0 {
+                return Err(Error::Timeout);
+            }
+            // samples of the first frame that were read before this call
+            let carried = self.periodogram.frame.len() as f64;
+            for (k, bins) in self
+                .periodogram
+                .process(&self.buf[..n])
+                .into_iter()
+                .enumerate()
+            {
+                let offset = (k * size) as f64 - carried;
+                self.spectra.push_back(Spectrum {
+                    time_ns: meta
+                        .time_ns
+                        .map(|t| t + (offset / self.sample_rate * 1e9) as i64),
+                    start_frequency: self.frequency - self.sample_rate / 2.0,
+                    stop_frequency: self.frequency + self.sample_rate / 2.0 - bin,
+                    unit: "dBFS".to_string(),
+                    bins,
+                });
+            }
+        }
+        Ok(self.spectra.pop_front().unwrap())
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+}
+
+#[cfg(all(test, feature = "spectrum", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::Frequency;
+    use crate::SampleRate;
+
+    #[test]
+    fn spectrum_of_tone() {
+        let mut s = Periodogram::new(64);
+        let tone: Vec<Complex32> = (0..96)
+            .map(|i| Complex32::from_polar(1.0, std::f32::consts::TAU * 8.0 * i as f32 / 64.0))
+            .collect();
+        let frames = s.process(&tone);
+        assert_eq!(frames.len(), 1);
+        let peak = (0..64)
+            .max_by(|a, b| frames[0][*a].total_cmp(&frames[0][*b]))
+            .unwrap();
+        assert_eq!(peak, 32 + 8);
+        assert_eq!(s.frame.len(), 32);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn software_fallback() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        dev.tune(crate::Direction::Rx, 0, Frequency::hz(100e6))
+            .unwrap();
+        dev.set_rate(crate::Direction::Rx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        let mut tx = dev.tx_streamer(&[0]).unwrap();
+        tx.activate().unwrap();
+        let tone: Vec<Complex32> = (0..256)
+            .map(|i| Complex32::from_polar(1.0, std::f32::consts::TAU * 16.0 * i as f32 / 64.0))
+            .collect();
+        tx.write_all(&[&tone], None, false, 0).unwrap();
+
+        let args = crate::Args::from("size=64").unwrap();
+        let mut s = dev.spectrum_streamer(0, args).unwrap();
+        s.activate().unwrap();
+        let spectrum = s.read(0).unwrap();
+        assert_eq!(spectrum.bins.len(), 64);
+        assert_eq!(spectrum.start_frequency, 99.5e6);
+        assert_eq!(spectrum.unit, "dBFS");
+        // a quarter of the sample rate above the center
+        let peak = (0..64)
+            .max_by(|a, b| spectrum.bins[*a].total_cmp(&spectrum.bins[*b]))
+            .unwrap();
+        assert_eq!(peak, 32 + 16);
+    }
+}
@@ -0,0 +1,534 @@
+use num_complex::Complex32;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::Args;
+use crate::BufferConfig;
+use crate::BufferLayout;
+use crate::Direction;
+use crate::Error;
+use crate::Range;
+use crate::RangeItem;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+use crate::TxStreamer;
+
+/// Known spur (birdie) of a device, see [`DeviceTrait::spurs`](crate::DeviceTrait::spurs).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Spur {
+    /// Spur at a fixed offset in Hz from the LO, e.g., the DC spike and LO leakage of zero-IF
+    /// receivers. Moves with the LO, so it can be tuned out of the passband.
+    LoOffset(f64),
+    /// Birdies at the harmonics of a clock with the given frequency in Hz. They are at fixed RF
+    /// frequencies and cannot be avoided by moving the LO.
+    Harmonics(f64),
+}
+
+impl Spur {
+    /// Number of spurs in `[start, stop]`, with the LO tuned to `lo`.
+    fn count(&self, lo: f64, start: f64, stop: f64) -> usize {
+        match *self {
+            Spur::LoOffset(offset) => usize::from((start..=stop).contains(&(lo + offset))),
+            Spur::Harmonics(clock) if clock > 0.0 => {
+                let first = (start / clock).ceil().max(1.0);
+                let last = (stop / clock).floor();
+                (last - first + 1.0).max(0.0) as usize
+            }
+            Spur::Harmonics(_) => 0,
+        }
+    }
+}
+
+/// Plan the LO for receiving `passband` Hz around `frequency` with `sample_rate`, keeping
+/// `spurs` out of the passband.
+///
+/// Returns the offset of the LO from `frequency`. The passband has to stay within 80% of the
+/// sampled bandwidth, which limits the offset. Spurs that do not move with the LO are ignored,
+/// and the smallest offset wins if no LO is free of spurs. The LO has to be in `range`.
+///
+/// ```
+/// use seify::plan_lo_offset;
+/// use seify::Range;
+/// use seify::RangeItem;
+/// use seify::Spur;
+///
+/// let range = Range::new(vec![RangeItem::Interval(0.0, 2e9)]);
+/// let offset = plan_lo_offset(100e6, 2e6, 500e3, &[Spur::LoOffset(0.0)], &range).unwrap();
+/// assert!(offset.abs() > 250e3 && offset.abs() + 250e3 <= 800e3);
+/// ```
+pub fn plan_lo_offset(
+    frequency: f64,
+    sample_rate: f64,
+    passband: f64,
+    spurs: &[Spur],
+    range: &Range,
+) -> Result<f64, Error> {
+    let half = passband / 2.0;
+    let max = 0.4 * sample_rate - half;
+    if !(sample_rate > 0.0 && passband > 0.0 && max >= 0.0) {
+        return Err(Error::ValueError);
+    }
+    // keep spurs one step away from the band edges
+    let step = sample_rate / 64.0;
+    let (start, stop) = (frequency - half - step, frequency + half + step);
+    let score = |lo: f64| {
+        spurs
+            .iter()
+            .map(|s| s.count(lo, start, stop))
+            .sum::<usize>()
+    };
+
+    let mut best: Option<(usize, f64)> = None;
+    let candidates = (0..=(max / step) as usize)
+        .flat_map(|k| [k as f64 * step, -(k as f64) * step])
+        .filter(|o| range.contains(frequency + o));
+    for offset in candidates {
+        let s = score(frequency + offset);
+        if best.is_none_or(|(b, _)| s < b) {
+            best = Some((s, offset));
+        }
+        if s == 0 {
+            break;
+        }
+    }
+    best.map(|(_, o)| o)
+        .ok_or(Error::OutOfRange(range.clone(), frequency))
+}
+
+/// LOs that keep the band sampled at `sample_rate` within one converter path, if the band at
+/// `frequency` crosses a path boundary.
+///
+/// Devices, whose range is stitched from multiple converter paths, e.g., the low-pass, bypass,
+/// and high-pass paths of the HackRF, report one interval per path. Intervals that touch are
+/// paths of the same chain, so the LO has to stay half the sample rate away from their common
+/// boundaries. Returns `None`, if the band stays in the path of `frequency`.
+pub(crate) fn path_range(frequency: f64, sample_rate: f64, range: &Range) -> Option<Range> {
+    let paths: Vec<(f64, f64)> = range
+        .items
+        .iter()
+        .filter_map(|i| match *i {
+            RangeItem::Interval(start, stop) => Some((start, stop)),
+            _ => None,
+        })
+        .collect();
+    let below = |edge: f64| paths.iter().any(|p| p.1 == edge);
+    let above = |edge: f64| paths.iter().any(|p| p.0 == edge);
+    let half = sample_rate / 2.0;
+    // the upper path serves a common boundary
+    let (start, stop) = *paths
+        .iter()
+        .rev()
+        .find(|p| (p.0..=p.1).contains(&frequency))?;
+    if !(frequency - half < start && below(start) || frequency + half > stop && above(stop)) {
+        return None;
+    }
+    Some(Range::new(
+        paths
+            .iter()
+            .map(|&(start, stop)| {
+                let start = if below(start) { start + half } else { start };
+                let stop = if above(stop) { stop - half } else { stop };
+                RangeItem::Interval(start, stop)
+            })
+            .filter(|i| matches!(i, RangeItem::Interval(start, stop) if start <= stop))
+            .collect(),
+    ))
+}
+
+/// LO offset of a channel, compensated digitally in the streamers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Shift {
+    pub offset: f64,
+    pub rate: f64,
+    /// Passband requested with `spur_bw`, defaults to a quarter of the sample rate.
+    pub passband: Option<f64>,
+    /// Whether the LO avoids the spurs, or only stays in one converter path.
+    pub avoid_spurs: bool,
+}
+
+pub(crate) type Shifts = Arc<Mutex<HashMap<(Direction, usize), Shift>>>;
+
+/// Parse `avoid_spurs` and `spur_bw` from tuning arguments, removing them.
+pub(crate) fn take_args(args: &mut Args) -> Result<Option<Option<f64>>, Error> {
+    let passband = match args.get::<f64>("spur_bw") {
+        Ok(bw) => Some(bw),
+        Err(Error::NotFound) => None,
+        Err(e) => return Err(e),
+    };
+    let avoid = match args.get::<String>("avoid_spurs").as_deref() {
+        Ok("1" | "true") => true,
+        Ok("0" | "false") | Err(Error::NotFound) => false,
+        _ => return Err(Error::ValueError),
+    };
+    args.remove("avoid_spurs");
+    args.remove("spur_bw");
+    Ok(avoid.then_some(passband))
+}
+
+/// Mixes the samples of each channel with the LO offset of its [`Shift`].
+struct Mixer {
+    direction: Direction,
+    shifts: Shifts,
+    channels: Vec<usize>,
+    phases: Vec<f64>,
+}
+
+impl Mixer {
+    fn new(direction: Direction, shifts: Shifts, channels: &[usize]) -> Self {
+        Self {
+            direction,
+            shifts,
+            channels: channels.to_vec(),
+            phases: vec![0.0; channels.len()],
+        }
+    }
+
+    /// Phase increment per sample of each channel, zero if it is not shifted.
+    fn steps(&self) -> Vec<f64> {
+        let shifts = self.shifts.lock().unwrap();
+        // RX moves the signal from -offset to DC, TX from DC to -offset
+        let sign = match self.direction {
+            Direction::Rx => 1.0,
+            Direction::Tx => -1.0,
+        };
+        self.channels
+            .iter()
+            .map(|c| {
+                shifts
+                    .get(&(self.direction, *c))
+                    .map_or(0.0, |s| sign * TAU * s.offset / s.rate)
+            })
+            .collect()
+    }
+
+    fn mix(&self, steps: &[f64], buffers: &mut [&mut [Complex32]], n: usize) {
+        for ((b, phase), step) in buffers.iter_mut().zip(&self.phases).zip(steps) {
+            if *step != 0.0 {
+                for (i, s) in b[..n].iter_mut().enumerate() {
+                    *s *= Complex32::from_polar(1.0, (phase + step * i as f64) as f32);
+                }
+            }
+        }
+    }
+
+    fn mix_interleaved(&self, steps: &[f64], buffer: &mut [Complex32], n: usize) {
+        let channels = steps.len();
+        for (c, (phase, step)) in self.phases.iter().zip(steps).enumerate() {
+            if *step != 0.0 {
+                for i in 0..n {
+                    buffer[i * channels + c] *=
+                        Complex32::from_polar(1.0, (phase + step * i as f64) as f32);
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, steps: &[f64], n: usize) {
+        for (phase, step) in self.phases.iter_mut().zip(steps) {
+            *phase = (*phase + step * n as f64).rem_euclid(TAU);
+        }
+    }
+}
+
+/// RX streamer of a [`GenericDevice`](crate::GenericDevice), compensating LO offsets.
+pub(crate) struct ShiftedRx<S: RxStreamer> {
+    inner: S,
+    mixer: Mixer,
+}
+
+impl<S: RxStreamer> ShiftedRx<S> {
+    pub fn new(inner: S, shifts: Shifts, channels: &[usize]) -> Self {
+        Self {
+            inner,
+            mixer: Mixer::new(Direction::Rx, shifts, channels),
+        }
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for ShiftedRx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        let n = self.inner.read(buffers, timeout_us)?;
+        let steps = self.mixer.steps();
+        self.mixer.mix(&steps, buffers, n);
+        self.mixer.advance(&steps, n);
+        Ok(n)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let (n, meta) = self.inner.read_with_metadata(buffers, timeout_us)?;
+        let steps = self.mixer.steps();
+        self.mixer.mix(&steps, buffers, n);
+        self.mixer.advance(&steps, n);
+        Ok((n, meta))
+    }
+    fn native_layout(&self) -> BufferLayout {
+        self.inner.native_layout()
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let n = self.inner.read_interleaved(buffer, channels, timeout_us)?;
+        let steps = self.mixer.steps();
+        self.mixer.mix_interleaved(&steps, buffer, n);
+        self.mixer.advance(&steps, n);
+        Ok(n)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if self.mixer.steps().iter().all(|s| *s == 0.0) {
+            return self.inner.read_format(format, buffers, timeout_us);
+        }
+        crate::sample::read_converted(self, format, buffers, timeout_us)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// TX streamer of a [`GenericDevice`](crate::GenericDevice), compensating LO offsets.
+pub(crate) struct ShiftedTx<S: TxStreamer> {
+    inner: S,
+    mixer: Mixer,
+    scratch: Vec<Vec<Complex32>>,
+}
+
+impl<S: TxStreamer> ShiftedTx<S> {
+    pub fn new(inner: S, shifts: Shifts, channels: &[usize]) -> Self {
+        Self {
+            inner,
+            mixer: Mixer::new(Direction::Tx, shifts, channels),
+            scratch: vec![Vec::new(); channels.len()],
+        }
+    }
+}
+
+impl<S: TxStreamer> TxStreamer for ShiftedTx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let steps = self.mixer.steps();
+        if steps.iter().all(|s| *s == 0.0) {
+            return self.inner.write(buffers, at_ns, end_burst, timeout_us);
+        }
+        let n = buffers.first().map_or(0, |b| b.len());
+        for (s, b) in self.scratch.iter_mut().zip(buffers) {
+            s.clear();
+            s.extend_from_slice(b);
+        }
+        let mut shifted: Vec<&mut [Complex32]> =
+            self.scratch.iter_mut().map(|s| s.as_mut_slice()).collect();
+        self.mixer.mix(&steps, &mut shifted, n);
+        let shifted: Vec<&[Complex32]> = self.scratch.iter().map(|s| s.as_slice()).collect();
+        let written = self.inner.write(&shifted, at_ns, end_burst, timeout_us)?;
+        self.mixer.advance(&steps, written);
+        Ok(written)
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let len = buffers.first().map_or(0, |b| b.len());
+        let mut i = 0;
+        while i < len {
+            let rest: Vec<&[Complex32]> = buffers.iter().map(|b| &b[i..]).collect();
+            let at = if i == 0 { at_ns } else { None };
+            match self.write(&rest, at, end_burst, timeout_us)? {
+                0 => return Err(Error::Timeout),
+                n => i += n,
+            }
+        }
+        Ok(())
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if self.mixer.steps().iter().all(|s| *s == 0.0) {
+            return self
+                .inner
+                .write_format(format, buffers, at_ns, end_burst, timeout_us);
+        }
+        crate::sample::write_converted(self, format, buffers, at_ns, end_burst, timeout_us)
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_input_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        self.inner.flush(timeout_us)
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        self.inner.queued_samples()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frequency;
+    use crate::SampleRate;
+
+    #[test]
+    fn plan() {
+        let range = Range::new(vec![RangeItem::Interval(0.0, 2e9)]);
+        let dc = [Spur::LoOffset(0.0), Spur::Harmonics(28.8e6)];
+        // clean without moving the LO
+        assert_eq!(plan_lo_offset(100e6, 2e6, 500e3, &[], &range).unwrap(), 0.0);
+        let offset = plan_lo_offset(100e6, 2e6, 500e3, &dc, &range).unwrap();
+        assert!(offset.abs() > 250e3 + 2e6 / 64.0);
+        assert!(offset.abs() + 250e3 <= 800e3);
+        // the harmonic at 115.2 MHz stays in the passband, the DC spike moves out
+        let offset = plan_lo_offset(115.2e6, 2e6, 500e3, &dc, &range).unwrap();
+        assert_ne!(offset, 0.0);
+        // passband too wide to move the LO out of it
+        assert!(plan_lo_offset(100e6, 2e6, 2e6, &dc, &range).is_err());
+    }
+
+    #[test]
+    fn paths() {
+        let range = Range::new(vec![
+            RangeItem::Interval(0.0, 2.17e9),
+            RangeItem::Interval(2.17e9, 2.74e9),
+            RangeItem::Interval(2.74e9, 7.27e9),
+        ]);
+        assert!(path_range(2.1e9, 10e6, &range).is_none());
+        // the bottom of the range is no path boundary
+        assert!(path_range(1e6, 10e6, &range).is_none());
+
+        let path = path_range(2.166e9, 10e6, &range).unwrap();
+        assert!(!path.contains(2.166e9));
+        assert!(path.contains(2.165e9) && path.contains(2.175e9));
+        let offset = plan_lo_offset(2.166e9, 10e6, 2.5e6, &[], &path).unwrap();
+        assert_eq!(offset, -1.09375e6);
+        // the passband would leave the sampled band
+        assert!(plan_lo_offset(2.17e9, 10e6, 2.5e6, &[], &path).is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn avoid_spurs() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        let args = Args::from("avoid_spurs=1").unwrap();
+        dev.set_frequency_with_args(Direction::Rx, 0, 100e6, args)
+            .unwrap();
+        assert_eq!(
+            dev.center_frequency(Direction::Rx, 0).unwrap().as_hz(),
+            100e6
+        );
+        let lo = dev.component_frequency(Direction::Rx, 0, "freq").unwrap();
+        assert!((lo - 100e6).abs() > 125e3);
+
+        // a tone at the requested frequency ends up at DC
+        dev.set_rate(Direction::Tx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        dev.set_frequency_with_args(
+            Direction::Tx,
+            0,
+            100e6,
+            Args::from("avoid_spurs=1").unwrap(),
+        )
+        .unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let mut tx = dev.tx_streamer(&[0]).unwrap();
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        tx.activate().unwrap();
+        rx.activate().unwrap();
+        let one = vec![Complex32::new(1.0, 0.0); 256];
+        tx.write_all(&[&one], None, false, 0).unwrap();
+        let mut buf = vec![Complex32::new(0.0, 0.0); 256];
+        assert_eq!(rx.read(&mut [&mut buf], 0).unwrap(), 256);
+        assert!(buf.iter().all(|s| (s - one[0]).norm() < 1e-3));
+
+        dev.tune(Direction::Rx, 0, Frequency::hz(100e6)).unwrap();
+        assert_eq!(
+            dev.component_frequency(Direction::Rx, 0, "freq").unwrap(),
+            100e6
+        );
+    }
+}
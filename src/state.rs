@@ -0,0 +1,389 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Driver;
+use crate::Error;
+use crate::Frequency;
+use crate::RxStreamer;
+use crate::SampleRate;
+use crate::TxStreamer;
+
+/// Snapshot of the settings of a device, see [`Device::snapshot`].
+///
+/// Snapshots can be [restored](Self::restore) and [compared](Self::diff), e.g., before and after
+/// a restore or between two units of the same hardware. Settings the device does not report are
+/// `None` or empty. Like the [`CapabilityReport`](crate::CapabilityReport), a snapshot can be
+/// serialized, e.g., to JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceState {
+    /// Driver of the device.
+    pub driver: Driver,
+    /// Identifier of the device.
+    pub id: Option<String>,
+    /// Time source.
+    pub time_source: Option<String>,
+    /// RX channels.
+    pub rx: Vec<ChannelSnapshot>,
+    /// TX channels.
+    pub tx: Vec<ChannelSnapshot>,
+}
+
+/// Settings of a channel of a [`DeviceState`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSnapshot {
+    /// Center frequency in Hz.
+    pub frequency: Option<f64>,
+    /// Frequencies of the tunable components in Hz, by name.
+    pub components: BTreeMap<String, f64>,
+    /// Sample rate in samples per second.
+    pub sample_rate: Option<f64>,
+    /// Bandwidth in Hz.
+    pub bandwidth: Option<f64>,
+    /// Overall gain in dB.
+    pub gain: Option<f64>,
+    /// Gains of the gain elements in dB, by name.
+    pub gain_elements: BTreeMap<String, f64>,
+    /// Automatic gain control.
+    pub agc: Option<bool>,
+    /// Antenna.
+    pub antenna: Option<String>,
+}
+
+/// Difference of a setting between two [`DeviceState`]s.
+///
+/// Displayed as, e.g., `rx0 frequency: 100 MHz -> 101 MHz`, with `-` for a missing value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// Direction and channel, `None` for device settings.
+    pub channel: Option<(Direction, usize)>,
+    /// Setting, e.g., `frequency` or `gain/LNA` for a gain element.
+    pub setting: String,
+    /// Value in the first state.
+    pub old: Option<SettingValue>,
+    /// Value in the second state.
+    pub new: Option<SettingValue>,
+}
+
+/// Value of a setting of a [`Change`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    /// Frequency.
+    Frequency(Frequency),
+    /// Sample rate.
+    SampleRate(SampleRate),
+    /// Gain in dB.
+    Gain(f64),
+    /// Flag, e.g., AGC.
+    Bool(bool),
+    /// Name, e.g., of an antenna.
+    Text(String),
+}
+
+impl DeviceState {
+    /// Read the settings of a device.
+    ///
+    /// Fails only, if the channels of the device cannot be read. Other values are left out.
+    pub fn collect<
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    >(
+        dev: &Device<D>,
+    ) -> Result<Self, Error> {
+        let channels = |direction| -> Result<Vec<ChannelSnapshot>, Error> {
+            Ok((0..dev.num_channels(direction)?)
+                .map(|channel| ChannelSnapshot::collect(dev, direction, channel))
+                .collect())
+        };
+        Ok(Self {
+            driver: dev.driver(),
+            id: dev.id().ok(),
+            time_source: dev.time_source().ok(),
+            rx: channels(Direction::Rx)?,
+            tx: channels(Direction::Tx)?,
+        })
+    }
+
+    /// Apply the settings to a device.
+    ///
+    /// Settings are applied in the order of a [`Profile`](crate::Profile), i.e., the sample rate
+    /// first and the frequency last. Gain elements are restored instead of the overall gain,
+    /// unless the AGC is enabled. Settings the device cannot change are skipped, other errors are
+    /// returned.
+    pub fn restore<
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    >(
+        &self,
+        dev: &Device<D>,
+    ) -> Result<(), Error> {
+        fn skip<T>(r: Result<T, Error>) -> Result<(), Error> {
+            match r {
+                Err(Error::NotSupported) => Ok(()),
+                r => r.map(|_| ()),
+            }
+        }
+        if let Some(source) = &self.time_source {
+            skip(dev.set_time_source(source))?;
+        }
+        for (direction, channels) in [(Direction::Rx, &self.rx), (Direction::Tx, &self.tx)] {
+            if channels.len() > dev.num_channels(direction)? {
+                return Err(Error::NotFound);
+            }
+            for (c, s) in channels.iter().enumerate() {
+                if let Some(rate) = s.sample_rate {
+                    skip(dev.set_rate(direction, c, SampleRate::sps(rate)))?;
+                }
+                if let Some(bw) = s.bandwidth {
+                    skip(dev.set_bandwidth(direction, c, bw))?;
+                }
+                if let Some(antenna) = &s.antenna {
+                    skip(dev.set_antenna(direction, c, antenna))?;
+                }
+                if let Some(agc) = s.agc {
+                    skip(dev.enable_agc(direction, c, agc))?;
+                }
+                if s.agc != Some(true) {
+                    if s.gain_elements.is_empty() {
+                        if let Some(gain) = s.gain {
+                            skip(dev.set_gain(direction, c, gain))?;
+                        }
+                    }
+                    for (name, gain) in &s.gain_elements {
+                        skip(dev.set_gain_element(direction, c, name, *gain))?;
+                    }
+                }
+                if let Some(frequency) = s.frequency {
+                    skip(dev.tune(direction, c, Frequency::hz(frequency)))?;
+                }
+                if s.frequency.is_none() {
+                    for (name, frequency) in &s.components {
+                        skip(dev.set_component_frequency(direction, c, name, *frequency))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings that differ from `other`, with the values of `self` as `old` values.
+    ///
+    /// Channels that only exist in one of the states are listed with all their settings.
+    pub fn diff(&self, other: &Self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut push = |channel, setting: String, old, new| {
+            if old != new {
+                changes.push(Change {
+                    channel,
+                    setting,
+                    old,
+                    new,
+                });
+            }
+        };
+        let driver = |s: &Self| Some(SettingValue::Text(format!("{:?}", s.driver)));
+        push(None, "driver".to_string(), driver(self), driver(other));
+        let text = |v: &Option<String>| v.clone().map(SettingValue::Text);
+        push(None, "id".to_string(), text(&self.id), text(&other.id));
+        push(
+            None,
+            "time_source".to_string(),
+            text(&self.time_source),
+            text(&other.time_source),
+        );
+
+        for (direction, old, new) in [
+            (Direction::Rx, &self.rx, &other.rx),
+            (Direction::Tx, &self.tx, &other.tx),
+        ] {
+            let empty = ChannelSnapshot::default();
+            for c in 0..old.len().max(new.len()) {
+                let old = old.get(c).unwrap_or(&empty).values();
+                let mut new = new.get(c).unwrap_or(&empty).values();
+                for (setting, value) in old {
+                    let other = new.remove(&setting);
+                    push(Some((direction, c)), setting, Some(value), other);
+                }
+                for (setting, value) in new {
+                    push(Some((direction, c)), setting, None, Some(value));
+                }
+            }
+        }
+        changes
+    }
+
+    /// Pretty-print the [differences](Self::diff) to `other`, one aligned line per setting.
+    pub fn diff_report(&self, other: &Self) -> String {
+        let changes = self.diff(other);
+        if changes.is_empty() {
+            return "no changes\n".to_string();
+        }
+        let lines: Vec<[String; 3]> = changes
+            .iter()
+            .map(|c| [c.name(), display(c.old.as_ref()), display(c.new.as_ref())])
+            .collect();
+        let width = |i: usize| lines.iter().map(|l| l[i].len()).max().unwrap_or(0);
+        let (name, old) = (width(0), width(1));
+        lines
+            .iter()
+            .map(|[n, o, v]| format!("{n:name$}  {o:>old$} -> {v}\n"))
+            .collect()
+    }
+}
+
+impl ChannelSnapshot {
+    fn collect<
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    >(
+        dev: &Device<D>,
+        direction: Direction,
+        channel: usize,
+    ) -> Self {
+        let named = |names: Result<Vec<String>, Error>, value: &dyn Fn(&str) -> Option<f64>| {
+            names
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|name| value(&name).map(|v| (name, v)))
+                .collect()
+        };
+        Self {
+            frequency: dev
+                .center_frequency(direction, channel)
+                .map(Frequency::as_hz)
+                .ok(),
+            components: named(dev.frequency_components(direction, channel), &|name| {
+                dev.component_frequency(direction, channel, name).ok()
+            }),
+            sample_rate: dev.rate(direction, channel).map(SampleRate::as_sps).ok(),
+            bandwidth: dev.bandwidth(direction, channel).ok(),
+            gain: dev.gain(direction, channel).ok().flatten(),
+            gain_elements: named(dev.gain_elements(direction, channel), &|name| {
+                dev.gain_element(direction, channel, name).ok().flatten()
+            }),
+            agc: dev.agc(direction, channel).ok(),
+            antenna: dev.antenna(direction, channel).ok(),
+        }
+    }
+
+    /// Settings by name, as compared by [`DeviceState::diff`].
+    fn values(&self) -> BTreeMap<String, SettingValue> {
+        let mut values = BTreeMap::new();
+        let mut insert = |name: String, value: Option<SettingValue>| {
+            if let Some(value) = value {
+                values.insert(name, value);
+            }
+        };
+        let frequency = |f: f64| SettingValue::Frequency(Frequency::hz(f));
+        insert("frequency".to_string(), self.frequency.map(frequency));
+        for (name, f) in &self.components {
+            insert(format!("frequency/{name}"), Some(frequency(*f)));
+        }
+        insert(
+            "sample_rate".to_string(),
+            self.sample_rate
+                .map(|r| SettingValue::SampleRate(SampleRate::sps(r))),
+        );
+        insert("bandwidth".to_string(), self.bandwidth.map(frequency));
+        insert("gain".to_string(), self.gain.map(SettingValue::Gain));
+        for (name, g) in &self.gain_elements {
+            insert(format!("gain/{name}"), Some(SettingValue::Gain(*g)));
+        }
+        insert("agc".to_string(), self.agc.map(SettingValue::Bool));
+        insert(
+            "antenna".to_string(),
+            self.antenna.clone().map(SettingValue::Text),
+        );
+        values
+    }
+}
+
+impl Change {
+    /// Setting with its channel, e.g., `rx0 frequency`.
+    fn name(&self) -> String {
+        match self.channel {
+            Some((Direction::Rx, c)) => format!("rx{c} {}", self.setting),
+            Some((Direction::Tx, c)) => format!("tx{c} {}", self.setting),
+            None => self.setting.clone(),
+        }
+    }
+}
+
+fn display(value: Option<&SettingValue>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name(),
+            display(self.old.as_ref()),
+            display(self.new.as_ref())
+        )
+    }
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Frequency(v) => v.fmt(f),
+            Self::SampleRate(v) => v.fmt(f),
+            Self::Gain(v) => write!(f, "{v} dB"),
+            Self::Bool(v) => v.fmt(f),
+            Self::Text(v) => v.fmt(f),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_and_restore() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        dev.tune(Direction::Rx, 0, Frequency::hz(100e6)).unwrap();
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        let before = dev.snapshot().unwrap();
+        assert!(before.diff(&before).is_empty());
+        assert_eq!(before.diff_report(&before), "no changes\n");
+
+        dev.tune(Direction::Rx, 0, Frequency::hz(101e6)).unwrap();
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(2e6))
+            .unwrap();
+        let after = dev.snapshot().unwrap();
+        let changes = before.diff(&after);
+        let lines: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert!(lines.contains(&"rx0 frequency: 100 MHz -> 101 MHz".to_string()));
+        assert!(lines.contains(&"rx0 sample_rate: 1 MS/s -> 2 MS/s".to_string()));
+        assert!(changes
+            .iter()
+            .all(|c| c.channel == Some((Direction::Rx, 0))));
+        assert_eq!(
+            before.diff_report(&after),
+            "rx0 frequency       100 MHz -> 101 MHz\n\
+             rx0 frequency/freq  100 MHz -> 101 MHz\n\
+             rx0 sample_rate      1 MS/s -> 2 MS/s\n"
+        );
+
+        before.restore(&dev).unwrap();
+        assert!(dev.snapshot().unwrap().diff(&before).is_empty());
+
+        let mut missing = before.clone();
+        missing.rx.clear();
+        let change = &missing.diff(&before)[0];
+        assert_eq!(change.old, None);
+        assert_eq!(change.channel, Some((Direction::Rx, 0)));
+    }
+}
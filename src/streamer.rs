@@ -1,8 +1,307 @@
+#[cfg(not(target_arch = "wasm32"))]
+use futures::channel::oneshot;
 use num_complex::Complex32;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+use std::time::Duration;
+use std::time::Instant;
 
+use crate::Args;
 use crate::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::PendingCommand;
+use crate::StreamFormat;
+
+/// Handle to interrupt blocking stream operations from another thread.
+///
+/// Streamers are used through `&mut self`, so `deactivate()` cannot be called while another
+/// thread is blocked in `read()` or `write()`. Instead, a streamer hands out clones of its
+/// canceller through [`RxStreamer::canceller`] and [`TxStreamer::canceller`]. After
+/// [`cancel`](Self::cancel) is called, blocked and subsequent stream operations return
+/// [`Error::Cancelled`] until the streamer is activated again. The thread that owns the streamer
+/// can then deactivate it.
+///
+/// Drivers check the flag at least once per underlying transfer, i.e., a blocked operation
+/// returns at the latest when the current hardware transfer completes or times out.
+#[derive(Clone, Debug, Default)]
+pub struct StreamCanceller {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl StreamCanceller {
+    /// Create a new, non-cancelled handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cancel blocking operations of the associated streamer.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+    /// Returns true, if the stream was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+    /// Clear the cancellation flag, e.g., when the stream is re-activated.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+}
+
+/// Status event of a stream, see [`RxStreamer::stream_events`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StreamEvent {
+    /// Received samples were dropped, because they were not read in time.
+    Overflow,
+    /// The transmitter ran out of samples.
+    Underflow,
+    /// A packet arrived too late, e.g., after later ones, and was dropped.
+    LatePacket,
+    /// The connection to the device was lost.
+    Disconnected,
+    /// A timed activation or burst could not be executed at its time.
+    TimeError,
+}
+
+/// Sends [`StreamEvent`]s to the subscribers of a streamer.
+///
+/// Drivers keep one sender per streamer and hand out receivers in
+/// [`stream_events`](RxStreamer::stream_events). Clones share the subscribers. Events are only
+/// queued for receivers that still exist.
+#[derive(Clone, Debug, Default)]
+pub struct StreamEvents {
+    subscribers: Arc<Mutex<Vec<mpsc::Sender<StreamEvent>>>>,
+}
+
+impl StreamEvents {
+    /// Create a sender without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a subscriber.
+    pub fn subscribe(&self) -> mpsc::Receiver<StreamEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+    /// Send an event to all subscribers, removing the ones that were dropped.
+    pub fn send(&self, event: StreamEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| s.send(event).is_ok());
+    }
+}
+
+/// Metadata of a [`RxStreamer::read_with_metadata`] call.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RxMetadata {
+    /// Time of the first sample of the read in nanoseconds, if known.
+    pub time_ns: Option<i64>,
+    /// True, if `time_ns` is a coarse estimate from the host clock rather than a hardware
+    /// timestamp (see [`HostTimestamps`](crate::HostTimestamps)).
+    pub host_time: bool,
+    /// [Sample position](RxStreamer::sample_position) of the first sample of the read.
+    pub sample_position: Option<u64>,
+    /// Conditions the driver reported for the read.
+    pub flags: RxFlags,
+    /// True, if the last sample of the read ends a burst, e.g., of a TDD slot.
+    pub end_burst: bool,
+}
+
+/// Conditions of a read, reported in [`RxMetadata::flags`].
+///
+/// ```
+/// use seify::RxFlags;
+///
+/// let flags = RxFlags::OVERFLOW | RxFlags::MORE_FRAGMENTS;
+/// assert!(flags.contains(RxFlags::OVERFLOW));
+/// assert!(!RxFlags::default().contains(RxFlags::OVERFLOW));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RxFlags(u32);
+
+impl RxFlags {
+    /// Samples were dropped before the first sample of the read.
+    pub const OVERFLOW: Self = Self(1);
+    /// The packet of the driver continues with the next read.
+    pub const MORE_FRAGMENTS: Self = Self(2);
+
+    /// No flags.
+    pub fn empty() -> Self {
+        Self(0)
+    }
+    /// True, if no flag is set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    /// True, if all flags of `other` are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// Set the flags of `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl std::ops::BitOr for RxFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// One-shot options of a [`RxStreamer::read_with_ctl`] call.
+///
+/// ```
+/// use seify::StreamCtl;
+///
+/// // after a retune: drop the samples of the old frequency and return quickly
+/// let ctl = StreamCtl::new().skip_to_latest().first_packet();
+/// assert!(ctl.skip_to_latest && ctl.first_packet && !ctl.metadata);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamCtl {
+    /// Return after the first packet of the driver, i.e., at most [`mtu`](RxStreamer::mtu)
+    /// samples.
+    pub first_packet: bool,
+    /// Discard the samples that were buffered before the call, e.g., after a retune, so that the
+    /// read starts with fresh samples.
+    pub skip_to_latest: bool,
+    /// Return the [metadata](RxMetadata) of the read.
+    pub metadata: bool,
+}
+
+impl StreamCtl {
+    /// Options of a plain read.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Return after the first packet.
+    pub fn first_packet(mut self) -> Self {
+        self.first_packet = true;
+        self
+    }
+    /// Discard buffered samples before reading.
+    pub fn skip_to_latest(mut self) -> Self {
+        self.skip_to_latest = true;
+        self
+    }
+    /// Return the metadata of the read.
+    pub fn metadata(mut self) -> Self {
+        self.metadata = true;
+        self
+    }
+}
+
+/// Buffer sizes of a stream, see [`RxStreamer::buffer_config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferConfig {
+    /// Maximum number of samples per stream operation, see [`RxStreamer::mtu`].
+    pub mtu: usize,
+    /// Number of samples of the native transfers of the driver.
+    ///
+    /// Reads of any size are supported, but reads of multiples of the granularity avoid buffering
+    /// in the driver.
+    pub granularity: usize,
+}
+
+/// Memory layout of the samples of multi-channel streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferLayout {
+    /// One buffer per channel.
+    Planar,
+    /// A single buffer with the samples of all channels interleaved, i.e., the first sample of
+    /// each channel, then the second sample of each channel, and so on.
+    Interleaved,
+}
+
+/// Whether a stream should favor low latency or high throughput.
+///
+/// Drivers adjust the number and size of their buffers to the hint, which is passed to them as
+/// the `latency` streamer argument (`low`, `balanced`, or `throughput`). It is set for all
+/// streamers of a device with [`Device::set_latency_hint`](crate::Device::set_latency_hint).
+/// Buffer arguments of a driver, like `usb_xfer_size`, take precedence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Latency {
+    /// Few, small buffers, e.g., for full-duplex voice.
+    Low,
+    /// The defaults of the driver.
+    #[default]
+    Balanced,
+    /// Many, large buffers to avoid overflows, e.g., for recording.
+    Throughput,
+}
+
+impl Latency {
+    /// Get the hint from the `latency` argument, defaulting to [`Latency::Balanced`].
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        match args.get("latency") {
+            Err(Error::NotFound) => Ok(Self::default()),
+            r => r,
+        }
+    }
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Balanced => write!(f, "balanced"),
+            Self::Throughput => write!(f, "throughput"),
+        }
+    }
+}
+
+impl FromStr for Latency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "balanced" => Ok(Self::Balanced),
+            "throughput" => Ok(Self::Throughput),
+            _ => Err(Error::ValueError),
+        }
+    }
+}
 
 /// Receive samples from a [Device](crate::Device) through one or multiple channels.
+///
+/// Streamers are `Send`, i.e., they can be moved to a dedicated thread, but they are not required
+/// to be `Sync`. To share a streamer between threads, wrap it in a
+/// [`SharedHandle`](crate::SharedHandle); get its [`canceller`](Self::canceller) first, since a
+/// blocked read holds the lock.
+///
+/// Dropping an active streamer deactivates it, also when the stream is dropped while unwinding
+/// from a panic.
+///
+/// # Full scale
+///
+/// Drivers scale samples, so that I and Q values of ±1.0 correspond to the full scale of the ADC,
+/// e.g., `(x - 127) / 128` for the 8-bit unsigned samples of RTL-SDRs and `x / 128` for the
+/// 8-bit signed samples of HackRFs. For SoapySDR devices, the scaling is done by the module and
+/// depends on the device. Use [`set_output_scale`](Self::set_output_scale) to apply a
+/// calibration on top, e.g., to get consistent power measurements across drivers.
+///
+/// # Validation
+///
+/// Streamers of a [`GenericDevice`](crate::GenericDevice) check the channels and buffers, and
+/// return [`Error::ValueError`] where the methods below document a panic. Drivers used directly
+/// may panic.
 pub trait RxStreamer: Send {
     /// Get the stream's maximum transmission unit (MTU) in number of elements.
     ///
@@ -24,7 +323,8 @@ pub trait RxStreamer: Send {
     ///
     /// # Arguments:
     ///   * `time_ns` -- optional activation time in nanoseconds from the time the function is
-    ///     called.
+    ///     called. Drivers without hardware timing start on the host clock, see
+    ///     [`activation_error_ns`](Self::activation_error_ns).
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error>;
 
     /// Deactivate a stream.
@@ -51,6 +351,276 @@ pub trait RxStreamer: Send {
     ///  * If `buffers` is not the same length as the `channels` array passed to
     ///    [`Device::rx_streamer`](crate::Device::rx_streamer) that created the streamer.
     fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error>;
+
+    /// Read until the buffers are full.
+    ///
+    /// Calls [`read`](RxStreamer::read) until all samples are read, mirroring
+    /// [`TxStreamer::write_all`]. `timeout_us` applies to the whole call. Once it expired, a read
+    /// without samples ends the call with `Err(Error::Timeout)`, leaving the samples read so far
+    /// in the buffers.
+    ///
+    /// Fails with `Err(Error::ValueError)`, if the buffers are not the same length.
+    fn read_exact(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let len = buffers.first().map_or(0, |b| b.len());
+        if buffers.iter().any(|b| b.len() != len) {
+            return Err(Error::ValueError);
+        }
+        let deadline = Instant::now() + Duration::from_micros(timeout_us.max(0) as u64);
+        let mut done = 0;
+        while done < len {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let mut rest: Vec<&mut [Complex32]> =
+                buffers.iter_mut().map(|b| &mut b[done..]).collect();
+            let n = self.read(&mut rest, left.as_micros() as i64)?;
+            if n == 0 && left.is_zero() {
+                return Err(Error::Timeout);
+            }
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Read the samples that are available without blocking.
+    ///
+    /// Returns `Ok(0)`, if no samples are available. The default implementation calls
+    /// [`read`](RxStreamer::read) with a timeout of zero.
+    fn try_read(&mut self, buffers: &mut [&mut [Complex32]]) -> Result<usize, Error> {
+        match self.read(buffers, 0) {
+            Err(Error::Timeout) => Ok(0),
+            r => r,
+        }
+    }
+
+    /// Check if samples are available, registering the task of the context to be woken when
+    /// they are, otherwise.
+    ///
+    /// The default implementation cannot tell and returns `Poll::Ready`. Wrappers forward the
+    /// call to the streamer they wrap.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let _ = cx;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Read samples from an event loop without dedicating a blocking thread to the stream.
+    ///
+    /// Returns `Poll::Ready` with the result of a [`try_read`](RxStreamer::try_read) that returned
+    /// samples or failed. Otherwise, the task of the context is woken once samples are available
+    /// (see [`poll_ready`](RxStreamer::poll_ready)) or right away, if the driver cannot tell.
+    fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffers: &mut [&mut [Complex32]],
+    ) -> Poll<Result<usize, Error>> {
+        match self.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+        match self.try_read(buffers) {
+            Ok(0) if buffers.iter().any(|b| !b.is_empty()) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Number of samples produced by the stream since its activation.
+    ///
+    /// The position increases monotonically. It includes samples that were dropped, e.g., due to
+    /// an overflow, if the driver can tell how many samples were lost. Downstream code can use it
+    /// to reconstruct a contiguous timeline across dropped buffers.
+    ///
+    /// Returns `Err(Error::NotSupported)` if the driver does not track the position.
+    fn sample_position(&self) -> Result<u64, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Read samples like [`read`](RxStreamer::read) and return [metadata](RxMetadata) of the
+    /// first sample.
+    ///
+    /// The default implementation adds the sample position, if the driver tracks it. Drivers with
+    /// hardware timestamps report them in [`RxMetadata::time_ns`].
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let sample_position = self.sample_position().ok();
+        let n = self.read(buffers, timeout_us)?;
+        Ok((
+            n,
+            RxMetadata {
+                sample_position,
+                ..RxMetadata::default()
+            },
+        ))
+    }
+
+    /// Read samples with [one-shot options](StreamCtl).
+    ///
+    /// Returns the number of samples read and, if requested, the metadata of the first sample.
+    /// Fails with `Err(Error::NotSupported)`, if the stream cannot [`flush`](RxStreamer::flush).
+    fn read_with_ctl(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+        ctl: StreamCtl,
+    ) -> Result<(usize, Option<RxMetadata>), Error> {
+        if ctl.skip_to_latest {
+            self.flush()?;
+        }
+        if ctl.first_packet {
+            let mtu = self.mtu()?.max(1);
+            let mut limited: Vec<&mut [Complex32]> = buffers
+                .iter_mut()
+                .map(|b| {
+                    let n = b.len().min(mtu);
+                    &mut b[..n]
+                })
+                .collect();
+            let ctl = StreamCtl {
+                first_packet: false,
+                skip_to_latest: false,
+                ..ctl
+            };
+            return self.read_with_ctl(&mut limited, timeout_us, ctl);
+        }
+        if ctl.metadata {
+            let (n, meta) = self.read_with_metadata(buffers, timeout_us)?;
+            Ok((n, Some(meta)))
+        } else {
+            Ok((self.read(buffers, timeout_us)?, None))
+        }
+    }
+
+    /// Layout in which the driver produces multi-channel samples.
+    ///
+    /// Reads in the native layout avoid a copy. The default is [`BufferLayout::Planar`].
+    fn native_layout(&self) -> BufferLayout {
+        BufferLayout::Planar
+    }
+
+    /// Read samples of `channels` channels, interleaved in a single buffer.
+    ///
+    /// Returns the number of samples read per channel, which may be smaller than
+    /// `buffer.len() / channels`.
+    ///
+    /// The default implementation reads planar buffers and interleaves them, unless the stream
+    /// has a single channel. Drivers with a native [`BufferLayout::Interleaved`] override it.
+    ///
+    /// # Panics
+    ///  * If `channels` differs from the number of channels of the stream.
+    ///  * If the length of `buffer` is not a multiple of `channels`.
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        assert!(channels > 0 && buffer.len().is_multiple_of(channels));
+        if channels == 1 {
+            return self.read(&mut [buffer], timeout_us);
+        }
+        let len = buffer.len() / channels;
+        let mut planar = vec![Complex32::new(0.0, 0.0); buffer.len()];
+        let mut buffers: Vec<&mut [Complex32]> = planar.chunks_mut(len).collect();
+        let n = self.read(&mut buffers, timeout_us)?;
+        for (c, channel) in buffers.iter().enumerate() {
+            for (i, s) in channel[..n].iter().enumerate() {
+                buffer[i * channels + c] = *s;
+            }
+        }
+        Ok(n)
+    }
+
+    /// [Format](StreamFormat) in which the driver produces samples.
+    ///
+    /// Reads in the native format with [`read_format`](Self::read_format) avoid a conversion. The
+    /// default is [`StreamFormat::Cf32`].
+    fn native_format(&self) -> StreamFormat {
+        StreamFormat::Cf32
+    }
+
+    /// Read samples in `format` into byte buffers, one per channel.
+    ///
+    /// Returns the number of samples read per channel, which may be smaller than the length of
+    /// the buffers divided by the [sample size](StreamFormat::sample_size). The
+    /// [typed streamers](crate::TypedRxStreamer) use this method.
+    ///
+    /// The default implementation reads `Complex32` samples and converts them. Drivers override
+    /// it for their [native format](Self::native_format).
+    ///
+    /// # Panics
+    ///  * If `buffers` are not the same length as the `channels` array passed to [`Device::rx_streamer`](crate::Device::rx_streamer).
+    ///  * If the buffers in `buffers` are not the same length.
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        crate::sample::read_converted(self, format, buffers, timeout_us)
+    }
+
+    /// Get the [buffer sizes](BufferConfig) of the stream.
+    ///
+    /// The default implementation reports the [MTU](RxStreamer::mtu) and a granularity of one
+    /// sample.
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        Ok(BufferConfig {
+            mtu: self.mtu()?,
+            granularity: 1,
+        })
+    }
+
+    /// Multiply received samples by `scale`, on top of the [full-scale](RxStreamer#full-scale)
+    /// convention. Defaults to 1.
+    fn set_output_scale(&mut self, _scale: f32) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Get a handle to cancel blocking [`read`](RxStreamer::read) calls from another thread.
+    ///
+    /// Returns `Err(Error::NotSupported)` if the driver cannot interrupt a blocked read.
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Subscribe to [status events](StreamEvent) of the stream, like overflows.
+    ///
+    /// Drivers also report events through errors, like [`Error::Overflow`], or the
+    /// [metadata](RxMetadata) of reads. The receiver collects them from all operations, e.g., to
+    /// monitor a stream from another thread. Returns `Err(Error::NotSupported)` if the driver
+    /// does not report events.
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Discard the samples buffered in the driver, the OS, or the device.
+    ///
+    /// After a retune or a long pause, the next read starts with fresh samples. The stream stays
+    /// active and the [`sample_position`](RxStreamer::sample_position) keeps counting; discarded
+    /// samples are included, if the driver knows their number. Returns `Err(Error::NotSupported)`
+    /// if the driver cannot clear its buffers.
+    fn flush(&mut self) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Accuracy of the last timed activation: the time the stream started minus the requested
+    /// time in nanoseconds.
+    ///
+    /// Drivers without hardware timestamps start timed streams on the host clock, sleeping and
+    /// then spinning until the requested time. Returns `Err(Error::NotSupported)` if the driver
+    /// does not track the accuracy, and `Err(Error::NotFound)` if the stream was not activated at
+    /// a time.
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        Err(Error::NotSupported)
+    }
 }
 
 #[doc(hidden)]
@@ -67,9 +637,76 @@ impl RxStreamer for Box<dyn RxStreamer> {
     fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
         self.as_mut().read(buffers, timeout_us)
     }
+    fn try_read(&mut self, buffers: &mut [&mut [Complex32]]) -> Result<usize, Error> {
+        self.as_mut().try_read(buffers)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.as_mut().poll_ready(cx)
+    }
+    fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffers: &mut [&mut [Complex32]],
+    ) -> Poll<Result<usize, Error>> {
+        self.as_mut().poll_read(cx, buffers)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.as_ref().sample_position()
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        self.as_mut().read_with_metadata(buffers, timeout_us)
+    }
+    fn native_layout(&self) -> BufferLayout {
+        self.as_ref().native_layout()
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.as_mut().read_interleaved(buffer, channels, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.as_ref().native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.as_mut().read_format(format, buffers, timeout_us)
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.as_ref().buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.as_mut().set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.as_ref().canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.as_ref().stream_events()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.as_mut().flush()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.as_ref().activation_error_ns()
+    }
 }
 
 /// Transmit samples with a [Device](crate::Device) through one or multiple channels.
+///
+/// Like [`RxStreamer`], streamers are `Send` but not necessarily `Sync`. Dropping an active
+/// streamer deactivates it. Streamers of a [`GenericDevice`](crate::GenericDevice) check the
+/// buffers, see [validation](RxStreamer#validation).
 pub trait TxStreamer: Send {
     /// Get the stream's maximum transmission unit (MTU) in number of elements.
     ///
@@ -91,7 +728,8 @@ pub trait TxStreamer: Send {
     ///
     /// # Arguments:
     ///   * `time_ns` -- optional activation time in nanoseconds from the time the function is
-    ///     called.
+    ///     called. Drivers without hardware timing start on the host clock, see
+    ///     [`activation_error_ns`](Self::activation_error_ns).
     fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error>;
 
     /// Deactivate a stream.
@@ -156,6 +794,136 @@ pub trait TxStreamer: Send {
         end_burst: bool,
         timeout_us: i64,
     ) -> Result<(), Error>;
+
+    /// Write samples that are split into multiple segments per channel (scatter/gather).
+    ///
+    /// `segments` contains a list of slices for each channel, e.g., the two parts of a ring buffer
+    /// that wrapped around. The segments of a channel are transmitted back to back, without
+    /// concatenating them first. The channels may be split at different positions.
+    ///
+    /// `at_ns` applies to the first sample and `end_burst` to the last one. Like
+    /// [`write`](TxStreamer::write), it returns the number of samples written per channel, which
+    /// may be smaller than the total length of the segments.
+    ///
+    /// The default implementation calls [`write`](TxStreamer::write) for each run of samples that
+    /// is contiguous in all channels.
+    ///
+    /// # Panics
+    ///  * If `segments` are not the same length as the `channels` array passed to [`Device::tx_streamer`](crate::Device::tx_streamer).
+    ///  * If the total length of the segments differs between channels.
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let len = |channel: &[&[Complex32]]| channel.iter().map(|s| s.len()).sum::<usize>();
+        let total = segments.first().map_or(0, |c| len(c));
+        assert!(segments.iter().all(|c| len(c) == total));
+
+        // current segment and offset in it, per channel
+        let mut cursors = vec![(0, 0); segments.len()];
+        let mut buffers: Vec<&[Complex32]> = Vec::with_capacity(segments.len());
+        let mut written = 0;
+        loop {
+            buffers.clear();
+            for (channel, (segment, offset)) in segments.iter().zip(cursors.iter_mut()) {
+                while *segment < channel.len() && *offset == channel[*segment].len() {
+                    *segment += 1;
+                    *offset = 0;
+                }
+                buffers.push(channel.get(*segment).map_or(&[][..], |s| &s[*offset..]));
+            }
+            let n = buffers.iter().map(|b| b.len()).min().unwrap_or(0);
+            for b in buffers.iter_mut() {
+                *b = &b[..n];
+            }
+            let last = written + n == total;
+            let at = if written == 0 { at_ns } else { None };
+            let w = self.write(&buffers, at, end_burst && last, timeout_us)?;
+            for (_, offset) in cursors.iter_mut() {
+                *offset += w;
+            }
+            written += w;
+            if w < n || last || n == 0 {
+                return Ok(written);
+            }
+        }
+    }
+
+    /// [Format](StreamFormat) in which the driver consumes samples. The default is
+    /// [`StreamFormat::Cf32`].
+    fn native_format(&self) -> StreamFormat {
+        StreamFormat::Cf32
+    }
+
+    /// Write samples in `format` from byte buffers, one per channel.
+    ///
+    /// Like [`write`](TxStreamer::write), it returns the number of samples written per channel.
+    /// The default implementation converts the samples to `Complex32`. Drivers override it for
+    /// their [native format](Self::native_format).
+    ///
+    /// # Panics
+    ///  * If `buffers` are not the same length as the `channels` array passed to [`Device::tx_streamer`](crate::Device::tx_streamer).
+    ///  * If the buffers in `buffers` are not the same length.
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        crate::sample::write_converted(self, format, buffers, at_ns, end_burst, timeout_us)
+    }
+
+    /// Multiply samples by `scale`, before they are converted to the format of the DAC, where
+    /// ±1.0 is the full scale. Defaults to 1.
+    fn set_input_scale(&mut self, _scale: f32) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Get a handle to cancel blocking [`write`](TxStreamer::write) calls from another thread.
+    ///
+    /// Returns `Err(Error::NotSupported)` if the driver cannot interrupt a blocked write.
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Subscribe to [status events](StreamEvent) of the stream, like underflows, see
+    /// [`RxStreamer::stream_events`].
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Block until all written samples have been transmitted by the device.
+    ///
+    /// A negative `timeout_us` waits until the queue is empty or the stream is cancelled. Returns
+    /// `Err(Error::Timeout)` if samples are still queued after the timeout, and
+    /// `Err(Error::NotSupported)` if the driver cannot tell when samples have left the device.
+    fn flush(&mut self, _timeout_us: i64) -> Result<(), Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Number of samples per channel that were written but not yet transmitted, i.e., how
+    /// backed-up the TX queue of the driver and the device is.
+    ///
+    /// Returns `Err(Error::NotSupported)` if the driver cannot track the queue.
+    fn queued_samples(&self) -> Result<usize, Error> {
+        Err(Error::NotSupported)
+    }
+
+    /// Accuracy of the last timed activation: the time the stream started minus the requested
+    /// time in nanoseconds.
+    ///
+    /// Drivers without hardware timestamps start timed streams on the host clock, sleeping and
+    /// then spinning until the requested time. Returns `Err(Error::NotSupported)` if the driver
+    /// does not track the accuracy, and `Err(Error::NotFound)` if the stream was not activated at
+    /// a time.
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        Err(Error::NotSupported)
+    }
 }
 
 #[doc(hidden)]
@@ -188,4 +956,655 @@ impl TxStreamer for Box<dyn TxStreamer> {
         self.as_mut()
             .write_all(buffers, at_ns, end_burst, timeout_us)
     }
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.as_mut()
+            .write_segments(segments, at_ns, end_burst, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.as_ref().native_format()
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        self.as_mut()
+            .write_format(format, buffers, at_ns, end_burst, timeout_us)
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.as_mut().set_input_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.as_ref().canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.as_ref().stream_events()
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        self.as_mut().flush(timeout_us)
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        self.as_ref().queued_samples()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.as_ref().activation_error_ns()
+    }
+}
+
+/// Receive samples asynchronously.
+///
+/// Drivers built on async USB or network stacks implement it to read without blocking a thread.
+/// Timeouts are left to the executor, e.g., by racing a read against a timer. Futures may be
+/// dropped before they complete; the samples of a cancelled read are lost.
+///
+/// [`BlockingAdapter`] turns an async streamer into an [`RxStreamer`], and [`AsyncAdapter`] runs a
+/// blocking streamer on a worker thread.
+pub trait AsyncRxStreamer: Send {
+    /// Get the stream's maximum transmission unit (MTU) in number of elements.
+    fn mtu(&self) -> Result<usize, Error>;
+
+    /// Activate the stream.
+    fn activate(&mut self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Deactivate the stream.
+    fn deactivate(&mut self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Read samples like [`RxStreamer::read`], completing once samples are available.
+    fn read(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+    ) -> impl Future<Output = Result<usize, Error>> + Send;
+}
+
+/// Transmit samples asynchronously.
+///
+/// The counterpart of [`AsyncRxStreamer`].
+pub trait AsyncTxStreamer: Send {
+    /// Get the stream's maximum transmission unit (MTU) in number of elements.
+    fn mtu(&self) -> Result<usize, Error>;
+
+    /// Activate the stream.
+    fn activate(&mut self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Deactivate the stream.
+    fn deactivate(&mut self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Write samples like [`TxStreamer::write`], completing once some samples were accepted.
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        end_burst: bool,
+    ) -> impl Future<Output = Result<usize, Error>> + Send;
+
+    /// Write all samples, ending the burst after the last one, if `end_burst` is set.
+    ///
+    /// Fails with `Err(Error::Timeout)`, if a write accepts no samples.
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        end_burst: bool,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        async move {
+            let mut n = 0;
+            while n < buffers[0].len() {
+                let rest: Vec<&[Complex32]> = buffers.iter().map(|b| &b[n..]).collect();
+                match self.write(&rest, end_burst).await? {
+                    0 => return Err(Error::Timeout),
+                    k => n += k,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Interval, in which the adapters check for cancellation and abandoned operations.
+const ADAPTER_POLL: Duration = Duration::from_millis(100);
+
+#[cfg(not(target_arch = "wasm32"))]
+type StreamJob<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// Async streamer, running a blocking [`RxStreamer`] or [`TxStreamer`] on a worker thread.
+///
+/// Samples are copied between the caller's buffers and buffers of the worker. The worker and the
+/// streamer are dropped, once the adapter is dropped.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use num_complex::Complex32;
+/// use seify::AsyncAdapter;
+/// use seify::AsyncRxStreamer;
+/// use seify::Device;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let mut rx = AsyncAdapter::new(dev.rx_streamer(&[0]).unwrap());
+/// let mut samples = [Complex32::new(0.0, 0.0); 1024];
+/// futures::executor::block_on(async {
+///     rx.activate().await.unwrap();
+///     let n = rx.read(&mut [&mut samples]).await.unwrap();
+///     assert!(n > 0);
+/// });
+/// # }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AsyncAdapter<S> {
+    queue: mpsc::Sender<StreamJob<S>>,
+    buffers: Vec<Vec<Complex32>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S: Send + 'static> AsyncAdapter<S> {
+    /// Move the streamer to a new worker thread.
+    pub fn new(streamer: S) -> Self {
+        let (queue, jobs) = mpsc::channel::<StreamJob<S>>();
+        std::thread::spawn(move || {
+            let mut streamer = streamer;
+            for job in jobs {
+                job(&mut streamer);
+            }
+        });
+        Self {
+            queue,
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Run `f` on the worker, retrying it while it times out and the result is still awaited.
+    fn submit<O, F>(&self, mut f: F) -> PendingCommand<O>
+    where
+        O: Send + 'static,
+        F: FnMut(&mut S) -> Result<O, Error> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.queue.send(Box::new(move |s| loop {
+            if tx.is_canceled() {
+                return;
+            }
+            match f(s) {
+                Err(Error::Timeout) => continue,
+                r => {
+                    let _ = tx.send(r);
+                    return;
+                }
+            }
+        }));
+        PendingCommand::new(rx)
+    }
+
+    /// Take the worker buffers, sized like `lens`.
+    fn take_buffers(&mut self, lens: impl Iterator<Item = usize>) -> Vec<Vec<Complex32>> {
+        let mut buffers = std::mem::take(&mut self.buffers);
+        let lens: Vec<usize> = lens.collect();
+        buffers.resize(lens.len(), Vec::new());
+        for (b, len) in buffers.iter_mut().zip(lens) {
+            b.resize(len, Complex32::new(0.0, 0.0));
+        }
+        buffers
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S: RxStreamer + 'static> AsyncRxStreamer for AsyncAdapter<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.submit(|s| s.mtu()).wait()
+    }
+    fn activate(&mut self) -> impl Future<Output = Result<(), Error>> + Send {
+        self.submit(|s| s.activate())
+    }
+    fn deactivate(&mut self) -> impl Future<Output = Result<(), Error>> + Send {
+        self.submit(|s| s.deactivate())
+    }
+    fn read(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+    ) -> impl Future<Output = Result<usize, Error>> + Send {
+        let mut owned = Some(self.take_buffers(buffers.iter().map(|b| b.len())));
+        let pending = self.submit(move |s| {
+            let mut worker = owned.take().unwrap();
+            let mut slices: Vec<&mut [Complex32]> =
+                worker.iter_mut().map(|b| b.as_mut_slice()).collect();
+            match s.read(&mut slices, ADAPTER_POLL.as_micros() as i64) {
+                Err(Error::Timeout) => {
+                    owned = Some(worker);
+                    Err(Error::Timeout)
+                }
+                r => Ok((worker, r)),
+            }
+        });
+        async move {
+            let (worker, result) = pending.await?;
+            if let Ok(n) = result {
+                for (b, w) in buffers.iter_mut().zip(&worker) {
+                    b[..n].copy_from_slice(&w[..n]);
+                }
+            }
+            self.buffers = worker;
+            result
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S: TxStreamer + 'static> AsyncTxStreamer for AsyncAdapter<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.submit(|s| s.mtu()).wait()
+    }
+    fn activate(&mut self) -> impl Future<Output = Result<(), Error>> + Send {
+        self.submit(|s| s.activate())
+    }
+    fn deactivate(&mut self) -> impl Future<Output = Result<(), Error>> + Send {
+        self.submit(|s| s.deactivate())
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        end_burst: bool,
+    ) -> impl Future<Output = Result<usize, Error>> + Send {
+        let mut worker = self.take_buffers(buffers.iter().map(|b| b.len()));
+        for (w, b) in worker.iter_mut().zip(buffers) {
+            w.copy_from_slice(b);
+        }
+        let mut owned = Some(worker);
+        let pending = self.submit(move |s| {
+            let worker = owned.take().unwrap();
+            let slices: Vec<&[Complex32]> = worker.iter().map(|b| b.as_slice()).collect();
+            match s.write(&slices, None, end_burst, ADAPTER_POLL.as_micros() as i64) {
+                Err(Error::Timeout) => {
+                    owned = Some(worker);
+                    Err(Error::Timeout)
+                }
+                r => Ok((worker, r)),
+            }
+        });
+        async move {
+            let (worker, result) = pending.await?;
+            self.buffers = worker;
+            result
+        }
+    }
+}
+
+/// Blocking [`RxStreamer`] or [`TxStreamer`], driving an async streamer on the calling thread.
+///
+/// Operations poll the future until it completes, the timeout passes, or the stream is
+/// [cancelled](StreamCanceller). A timed out future is dropped. The futures of the driver must
+/// not depend on a specific runtime.
+pub struct BlockingAdapter<S> {
+    streamer: S,
+    canceller: StreamCanceller,
+}
+
+impl<S> BlockingAdapter<S> {
+    /// Wrap an async streamer.
+    pub fn new(streamer: S) -> Self {
+        Self {
+            streamer,
+            canceller: StreamCanceller::new(),
+        }
+    }
+
+    /// Get the async streamer back.
+    pub fn into_inner(self) -> S {
+        self.streamer
+    }
+}
+
+/// Wakes the thread blocked in [`block_on`].
+struct Unpark(std::thread::Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` on the current thread until it completes, `timeout_us` passes, or `canceller`
+/// is cancelled.
+fn block_on<F: Future>(
+    future: F,
+    timeout_us: i64,
+    canceller: &StreamCanceller,
+) -> Result<F::Output, Error> {
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    let deadline = Instant::now() + Duration::from_micros(timeout_us.max(0) as u64);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Ok(output);
+        }
+        if canceller.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(Error::Timeout);
+        }
+        std::thread::park_timeout(left.min(ADAPTER_POLL));
+    }
+}
+
+impl<S: AsyncRxStreamer> RxStreamer for BlockingAdapter<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.streamer.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        self.canceller.reset();
+        futures::executor::block_on(self.streamer.activate())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        futures::executor::block_on(self.streamer.deactivate())
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        block_on(self.streamer.read(buffers), timeout_us, &self.canceller)?
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+}
+
+impl<S: AsyncTxStreamer> TxStreamer for BlockingAdapter<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.streamer.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        self.canceller.reset();
+        futures::executor::block_on(self.streamer.activate())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        if time_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        futures::executor::block_on(self.streamer.deactivate())
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if at_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        block_on(
+            self.streamer.write(buffers, end_burst),
+            timeout_us,
+            &self.canceller,
+        )?
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        if at_ns.is_some() {
+            return Err(Error::NotSupported);
+        }
+        let mut n = 0;
+        while n < buffers[0].len() {
+            let rest: Vec<&[Complex32]> = buffers.iter().map(|b| &b[n..]).collect();
+            match self.write(&rest, None, end_burst, timeout_us)? {
+                0 => return Err(Error::Timeout),
+                k => n += k,
+            }
+        }
+        Ok(())
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Ok(self.canceller.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the writes and accepts at most `limit` samples per call.
+    struct Recorder {
+        writes: Vec<(Vec<Vec<Complex32>>, Option<i64>, bool)>,
+        limit: usize,
+    }
+
+    impl TxStreamer for Recorder {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(self.limit)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn write(
+            &mut self,
+            buffers: &[&[Complex32]],
+            at_ns: Option<i64>,
+            end_burst: bool,
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            let n = buffers[0].len().min(self.limit);
+            let samples = buffers.iter().map(|b| b[..n].to_vec()).collect();
+            self.writes.push((samples, at_ns, end_burst));
+            Ok(n)
+        }
+        fn write_all(
+            &mut self,
+            _buffers: &[&[Complex32]],
+            _at_ns: Option<i64>,
+            _end_burst: bool,
+            _timeout_us: i64,
+        ) -> Result<(), Error> {
+            Err(Error::NotSupported)
+        }
+    }
+
+    /// Produces `channel * 100 + index` on each channel and reads at most `limit` samples.
+    struct Counter {
+        limit: usize,
+    }
+
+    impl RxStreamer for Counter {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(self.limit)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn read(
+            &mut self,
+            buffers: &mut [&mut [Complex32]],
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            let n = buffers[0].len().min(self.limit);
+            for (c, b) in buffers.iter_mut().enumerate() {
+                for (i, s) in b[..n].iter_mut().enumerate() {
+                    *s = Complex32::new((c * 100 + i) as f32, 0.0);
+                }
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn read_interleaved() {
+        let mut rx = Counter { limit: 3 };
+        let mut buf = vec![Complex32::new(-1.0, 0.0); 8];
+        assert_eq!(rx.read_interleaved(&mut buf, 2, 0).unwrap(), 3);
+        let re: Vec<f32> = buf.iter().map(|s| s.re).collect();
+        assert_eq!(re, [0.0, 100.0, 1.0, 101.0, 2.0, 102.0, -1.0, -1.0]);
+        assert_eq!(rx.read_interleaved(&mut buf[..2], 1, 0).unwrap(), 2);
+        assert_eq!(buf[1].re, 1.0);
+    }
+
+    #[test]
+    fn read_exact() {
+        let mut rx = Counter { limit: 3 };
+        let mut a = vec![Complex32::new(-1.0, 0.0); 8];
+        let mut b = vec![Complex32::new(-1.0, 0.0); 8];
+        rx.read_exact(&mut [&mut a, &mut b], 0).unwrap();
+        let re: Vec<f32> = b.iter().map(|s| s.re).collect();
+        assert_eq!(re, [100.0, 101.0, 102.0, 100.0, 101.0, 102.0, 100.0, 101.0]);
+        assert!(matches!(
+            rx.read_exact(&mut [&mut a, &mut b[..4]], 0),
+            Err(Error::ValueError)
+        ));
+
+        let mut rx = Counter { limit: 0 };
+        let start = Instant::now();
+        assert!(matches!(
+            rx.read_exact(&mut [&mut a], 10_000),
+            Err(Error::Timeout)
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn read_with_ctl() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        let mtu = rx.mtu().unwrap();
+        let mut buf = vec![Complex32::new(0.0, 0.0); 3 * mtu];
+        let (n, meta) = rx
+            .read_with_ctl(&mut [&mut buf], 0, StreamCtl::new())
+            .unwrap();
+        assert_eq!((n, meta), (3 * mtu, None));
+        let ctl = StreamCtl::new().first_packet().metadata();
+        let (n, meta) = rx.read_with_ctl(&mut [&mut buf], 0, ctl).unwrap();
+        assert_eq!(n, mtu);
+        assert!(meta.is_some());
+    }
+
+    #[test]
+    fn write_segments() {
+        let s: Vec<Complex32> = (0..6).map(|i| Complex32::new(i as f32, 0.0)).collect();
+        let mut tx = Recorder {
+            writes: Vec::new(),
+            limit: 100,
+        };
+        let n = tx
+            .write_segments(
+                &[&[&s[..2], &s[2..6]], &[&s[..5], &[], &s[5..]]],
+                Some(7),
+                true,
+                0,
+            )
+            .unwrap();
+        assert_eq!(n, 6);
+        let lens: Vec<usize> = tx.writes.iter().map(|w| w.0[0].len()).collect();
+        assert_eq!(lens, [2, 3, 1]);
+        assert_eq!(tx.writes[1].0[1], &s[2..5]);
+        assert_eq!(tx.writes[0].1, Some(7));
+        assert!(tx.writes[1..].iter().all(|w| w.1.is_none()));
+        assert_eq!(
+            tx.writes.iter().map(|w| w.2).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+
+        // stops at a short write
+        tx.limit = 1;
+        tx.writes.clear();
+        assert_eq!(
+            tx.write_segments(&[&[&s[..2], &s[2..]]], None, true, 0)
+                .unwrap(),
+            1
+        );
+        assert_eq!(tx.writes.len(), 1);
+    }
+
+    /// Never produces samples.
+    struct Silent;
+
+    impl AsyncRxStreamer for Silent {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(1)
+        }
+        async fn activate(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+        async fn deactivate(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+        async fn read(&mut self, _buffers: &mut [&mut [Complex32]]) -> Result<usize, Error> {
+            futures::future::pending().await
+        }
+    }
+
+    #[test]
+    fn async_adapters() {
+        // blocking -> async -> blocking
+        let mut rx = BlockingAdapter::new(AsyncAdapter::new(Counter { limit: 3 }));
+        assert_eq!(RxStreamer::mtu(&rx).unwrap(), 3);
+        rx.activate().unwrap();
+        let mut a = [Complex32::new(0.0, 0.0); 4];
+        let mut b = [Complex32::new(0.0, 0.0); 4];
+        assert_eq!(rx.read(&mut [&mut a, &mut b], 1_000_000).unwrap(), 3);
+        assert_eq!(b[2].re, 102.0);
+        assert_eq!(a[3].re, 0.0);
+
+        let mut tx = AsyncAdapter::new(Recorder {
+            writes: Vec::new(),
+            limit: 3,
+        });
+        let s = [Complex32::new(1.0, 0.0); 8];
+        futures::executor::block_on(async {
+            assert_eq!(tx.write(&[&s], false).await.unwrap(), 3);
+            tx.write_all(&[&s], true).await.unwrap();
+        });
+
+        // a stream that accepts no samples ends write_all instead of spinning
+        let mut stalled = BlockingAdapter::new(AsyncAdapter::new(Recorder {
+            writes: Vec::new(),
+            limit: 0,
+        }));
+        assert!(matches!(
+            stalled.write_all(&[&s], None, false, 1000),
+            Err(Error::Timeout)
+        ));
+        let mut stalled = AsyncAdapter::new(Recorder {
+            writes: Vec::new(),
+            limit: 0,
+        });
+        assert!(matches!(
+            futures::executor::block_on(stalled.write_all(&[&s], true)),
+            Err(Error::Timeout)
+        ));
+
+        let mut silent = BlockingAdapter::new(Silent);
+        assert!(matches!(
+            silent.read(&mut [&mut a], 1000),
+            Err(Error::Timeout)
+        ));
+        silent.canceller().unwrap().cancel();
+        assert!(matches!(
+            silent.read(&mut [&mut a], 1_000_000),
+            Err(Error::Cancelled)
+        ));
+    }
 }
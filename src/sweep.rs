@@ -0,0 +1,203 @@
+use num_complex::Complex32;
+use std::f64::consts::TAU;
+
+use crate::Error;
+use crate::TxStreamer;
+
+/// Segment of a [`Sweep`].
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Chirp {
+        start: f64,
+        stop: f64,
+        duration: f64,
+        amplitude: f32,
+    },
+    Samples(Vec<Complex32>),
+}
+
+/// Generator for swept tones and user-defined waveform segments.
+///
+/// A sweep is a sequence of tones, linear chirps, and custom sample segments, defined at the
+/// sample rate of the TX stream. Frequencies are baseband offsets in Hz, relative to the center
+/// frequency of the device. Phase is continuous across tone and chirp segments.
+///
+/// ```
+/// # use seify::Sweep;
+/// let sweep = Sweep::new(1e6)
+///     .tone(-100e3, 0.001, 0.5)
+///     .chirp(-400e3, 400e3, 0.01, 0.8);
+/// assert_eq!(sweep.generate().unwrap().len(), 11000);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    sample_rate: f64,
+    segments: Vec<Segment>,
+}
+
+impl Sweep {
+    /// Create an empty sweep for a stream with the given sample rate.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            segments: Vec::new(),
+        }
+    }
+    /// Append a tone at `frequency` for `duration` seconds.
+    pub fn tone(self, frequency: f64, duration: f64, amplitude: f32) -> Self {
+        self.chirp(frequency, frequency, duration, amplitude)
+    }
+    /// Append a linear sweep from `start` to `stop` Hz over `duration` seconds.
+    pub fn chirp(mut self, start: f64, stop: f64, duration: f64, amplitude: f32) -> Self {
+        self.segments.push(Segment::Chirp {
+            start,
+            stop,
+            duration,
+            amplitude,
+        });
+        self
+    }
+    /// Append silence for `duration` seconds.
+    pub fn pause(self, duration: f64) -> Self {
+        self.tone(0.0, duration, 0.0)
+    }
+    /// Append user-defined samples.
+    pub fn samples(mut self, samples: Vec<Complex32>) -> Self {
+        self.segments.push(Segment::Samples(samples));
+        self
+    }
+    /// Total duration of the sweep in seconds.
+    pub fn duration(&self) -> f64 {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Chirp { duration, .. } => self.len(*duration) as f64 / self.sample_rate,
+                Segment::Samples(s) => s.len() as f64 / self.sample_rate,
+            })
+            .sum()
+    }
+
+    fn len(&self, duration: f64) -> usize {
+        (duration * self.sample_rate).round() as usize
+    }
+
+    /// Generate the samples of the sweep.
+    ///
+    /// Returns `Err(Error::ValueError)` if a frequency is outside the Nyquist band, an amplitude is
+    /// outside `[0, 1]`, or a duration is negative.
+    pub fn generate(&self) -> Result<Vec<Complex32>, Error> {
+        if !self.sample_rate.is_finite() || self.sample_rate <= 0.0 {
+            return Err(Error::ValueError);
+        }
+        let nyquist = self.sample_rate / 2.0;
+        let mut out = Vec::new();
+        let mut phase = 0.0f64;
+        for s in &self.segments {
+            match s {
+                Segment::Chirp {
+                    start,
+                    stop,
+                    duration,
+                    amplitude,
+                } => {
+                    if !(-nyquist..=nyquist).contains(start)
+                        || !(-nyquist..=nyquist).contains(stop)
+                        || !(0.0..=1.0).contains(amplitude)
+                        || duration.is_nan()
+                        || *duration < 0.0
+                    {
+                        return Err(Error::ValueError);
+                    }
+                    let n = self.len(*duration);
+                    out.reserve(n);
+                    for k in 0..n {
+                        let f = start + (stop - start) * k as f64 / n as f64;
+                        out.push(Complex32::from_polar(*amplitude, phase as f32));
+                        phase = (phase + TAU * f / self.sample_rate) % TAU;
+                    }
+                }
+                Segment::Samples(s) => out.extend_from_slice(s),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Transmit the sweep as one burst.
+    ///
+    /// The stream has to be activated. With `at_ns`, the burst starts at the given hardware time,
+    /// if the driver supports timed writes.
+    pub fn transmit<T: TxStreamer + ?Sized>(
+        &self,
+        tx: &mut T,
+        at_ns: Option<i64>,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        let samples = self.generate()?;
+        let mtu = tx.mtu()?.max(1);
+        let chunks = samples.len().div_ceil(mtu);
+        for (i, chunk) in samples.chunks(mtu).enumerate() {
+            let at = if i == 0 { at_ns } else { None };
+            tx.write_all(&[chunk], at, i + 1 == chunks, timeout_us)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequency(a: Complex32, b: Complex32, sample_rate: f64) -> f64 {
+        (b * a.conj()).arg() as f64 * sample_rate / TAU
+    }
+
+    #[test]
+    fn tone_and_chirp() {
+        let fs = 1000.0;
+        let s = Sweep::new(fs)
+            .tone(100.0, 0.1, 0.5)
+            .pause(0.05)
+            .chirp(-200.0, 200.0, 0.2, 1.0)
+            .samples(vec![Complex32::new(0.1, 0.2); 3]);
+        let v = s.generate().unwrap();
+        assert_eq!(v.len(), 100 + 50 + 200 + 3);
+        assert!((s.duration() - 0.353).abs() < 1e-9);
+        assert!((v[0].norm() - 0.5).abs() < 1e-6);
+        assert!((frequency(v[10], v[11], fs) - 100.0).abs() < 1e-2);
+        assert_eq!(v[120], Complex32::new(0.0, 0.0));
+        assert!((frequency(v[150], v[151], fs) + 200.0).abs() < 1e-2);
+        assert!((frequency(v[348], v[349], fs) - 196.0).abs() < 1e-2);
+        assert_eq!(v[352], Complex32::new(0.1, 0.2));
+    }
+
+    #[test]
+    fn invalid_segments() {
+        assert!(Sweep::new(1000.0).tone(600.0, 1.0, 1.0).generate().is_err());
+        assert!(Sweep::new(1000.0).tone(0.0, 1.0, 1.5).generate().is_err());
+        assert!(Sweep::new(1000.0).tone(0.0, -1.0, 1.0).generate().is_err());
+        assert!(Sweep::new(0.0).generate().is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn transmit_through_loopback() {
+        use crate::DeviceTrait;
+        use crate::RxStreamer;
+
+        let dev = crate::impls::Dummy::open(crate::Args::new()).unwrap();
+        dev.set_loopback_mode("internal").unwrap();
+        let mut rx = dev.rx_streamer(&[0], crate::Args::new()).unwrap();
+        let mut tx = dev.tx_streamer(&[0], crate::Args::new()).unwrap();
+        rx.activate().unwrap();
+        tx.activate().unwrap();
+
+        let sweep = Sweep::new(1e6).chirp(-1e5, 1e5, 0.004, 0.5);
+        sweep.transmit(&mut tx, None, 1000).unwrap();
+        let mut buf = vec![Complex32::new(0.0, 0.0); 8000];
+        let mut n = 0;
+        while n < 4000 {
+            n += rx.read(&mut [&mut buf[n..]], 1000).unwrap();
+        }
+        assert_eq!(&buf[..4000], &sweep.generate().unwrap()[..]);
+    }
+}
@@ -0,0 +1,475 @@
+use num_complex::Complex32;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::TrySendError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Device;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::TxStreamer;
+
+/// Timeout of the stream operations of the scheduler thread, which bounds the time to stop it.
+const TIMEOUT_US: i64 = 100_000;
+/// Number of bursts queued per direction.
+const DEPTH: usize = 4;
+
+/// Slot of a [`TddDevice`] schedule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TddSlot {
+    /// Direction of the slot.
+    pub direction: Direction,
+    /// Length of the slot, including the guard time after a switch.
+    pub duration: Duration,
+}
+
+impl TddSlot {
+    /// Receive for `duration`.
+    pub fn rx(duration: Duration) -> Self {
+        Self {
+            direction: Direction::Rx,
+            duration,
+        }
+    }
+    /// Transmit for `duration`.
+    pub fn tx(duration: Duration) -> Self {
+        Self {
+            direction: Direction::Tx,
+            duration,
+        }
+    }
+}
+
+/// Position of a burst in the schedule of a [`TddDevice`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotInfo {
+    /// Number of the slot since the start, counting the slots of both directions.
+    pub number: u64,
+    /// Index of the slot in the schedule.
+    pub index: usize,
+}
+
+struct Burst {
+    slot: SlotInfo,
+    time_ns: Option<i64>,
+    samples: Vec<Complex32>,
+}
+
+/// Time-division duplex on a half-duplex device, e.g., a HackRF.
+///
+/// A scheduler thread repeats a schedule of [slots](TddSlot), switching the transceiver between
+/// RX and TX by (de)activating the streamers of the device. After a switch, the first `guard` of
+/// the slot is left for the hardware to settle: received samples are discarded and transmission
+/// starts late.
+///
+/// Samples are handed over through a pair of streamers. [`TddRx`] returns the bursts of the RX
+/// slots, where each read stays within one slot, see [`TddRx::slot`]. Bursts written to
+/// [`TddTx`] (ended with `end_burst`) go out in the next TX slots, one burst per slot. If no burst
+/// is queued, the TX slot stays silent.
+///
+/// Slots are timed on the host, so their boundaries are only as precise as the scheduling and the
+/// USB latency of the host.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use std::time::Duration;
+/// use seify::Device;
+/// use seify::RxStreamer;
+/// use seify::TddDevice;
+/// use seify::TddSlot;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let schedule = vec![
+///     TddSlot::rx(Duration::from_millis(10)),
+///     TddSlot::tx(Duration::from_millis(10)),
+/// ];
+/// let (tdd, mut rx, _tx) = TddDevice::start(&dev, 0, schedule, Duration::from_millis(1)).unwrap();
+/// let mut buf = vec![num_complex::Complex32::new(0.0, 0.0); 1024];
+/// rx.read(&mut [&mut buf], 1_000_000).unwrap();
+/// assert_eq!(rx.slot().unwrap().index, 0);
+/// tdd.stop().unwrap();
+/// # }
+/// ```
+pub struct TddDevice {
+    stop: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<Result<(), Error>>>>,
+}
+
+/// RX streamer of a [`TddDevice`].
+///
+/// The scheduler runs independently of it, so activation is a no-op. Bursts that are not read
+/// before the next ones arrive are dropped.
+pub struct TddRx {
+    bursts: Receiver<Burst>,
+    current: Option<(Burst, usize)>,
+    slot: Option<SlotInfo>,
+    rate: f64,
+    mtu: usize,
+}
+
+/// TX streamer of a [`TddDevice`].
+pub struct TddTx {
+    bursts: SyncSender<Vec<Complex32>>,
+    pending: Vec<Complex32>,
+    capacity: usize,
+}
+
+impl TddDevice {
+    /// Start switching `channel` of the device along `schedule`, which repeats until stopped.
+    ///
+    /// The sample rates of both directions have to be set before.
+    pub fn start<R, T, D>(
+        dev: &Device<D>,
+        channel: usize,
+        schedule: Vec<TddSlot>,
+        guard: Duration,
+    ) -> Result<(Self, TddRx, TddTx), Error>
+    where
+        R: RxStreamer + 'static,
+        T: TxStreamer + 'static,
+        D: DeviceTrait<RxStreamer = R, TxStreamer = T> + Clone + 'static,
+    {
+        if schedule.is_empty() || schedule.iter().any(|s| s.duration <= guard) {
+            return Err(Error::ValueError);
+        }
+        let rx_rate = dev.rate(Direction::Rx, channel)?.as_sps();
+        let tx_rate = dev.rate(Direction::Tx, channel)?.as_sps();
+        let samples = |direction: Direction, rate: f64| {
+            schedule
+                .iter()
+                .filter(|s| s.direction == direction)
+                .map(|s| ((s.duration - guard).as_secs_f64() * rate) as usize)
+                .max()
+                .unwrap_or(0)
+        };
+        let (rx_bursts, bursts) = std::sync::mpsc::sync_channel(DEPTH);
+        let rx = TddRx {
+            bursts,
+            current: None,
+            slot: None,
+            rate: rx_rate,
+            mtu: samples(Direction::Rx, rx_rate),
+        };
+        let (bursts, tx_bursts) = std::sync::mpsc::sync_channel(DEPTH);
+        let tx = TddTx {
+            bursts,
+            pending: Vec::new(),
+            capacity: samples(Direction::Tx, tx_rate),
+        };
+        let mut scheduler = Scheduler {
+            rx: dev.rx_streamer(&[channel])?,
+            tx: dev.tx_streamer(&[channel])?,
+            rx_rate,
+            schedule,
+            guard,
+            stop: Arc::new(AtomicBool::new(false)),
+            rx_bursts,
+            tx_bursts,
+            active: None,
+        };
+
+        let stop = scheduler.stop.clone();
+        let thread = std::thread::spawn(move || {
+            let r = scheduler.run();
+            if let Err(e) = &r {
+                log::warn!("TDD scheduler failed: {e}");
+            }
+            r.and(scheduler.idle())
+        });
+        let tdd = Self {
+            stop,
+            thread: Mutex::new(Some(thread)),
+        };
+        Ok((tdd, rx, tx))
+    }
+
+    /// Stop the schedule and deactivate the streamers.
+    ///
+    /// Returns the error that ended the schedule, if it failed.
+    pub fn stop(&self) -> Result<(), Error> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.thread.lock().unwrap().take() {
+            Some(t) => t
+                .join()
+                .unwrap_or_else(|_| Err(Error::Misc("TDD scheduler panicked".to_string()))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TddDevice {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+struct Scheduler<R, T> {
+    rx: R,
+    tx: T,
+    rx_rate: f64,
+    schedule: Vec<TddSlot>,
+    guard: Duration,
+    stop: Arc<AtomicBool>,
+    rx_bursts: SyncSender<Burst>,
+    tx_bursts: Receiver<Vec<Complex32>>,
+    active: Option<Direction>,
+}
+
+impl<R: RxStreamer, T: TxStreamer> Scheduler<R, T> {
+    fn run(&mut self) -> Result<(), Error> {
+        let mut start = Instant::now();
+        let mut number = 0;
+        while !self.stop.load(Ordering::Relaxed) {
+            for index in 0..self.schedule.len() {
+                if self.stop.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                let slot = self.schedule[index];
+                let end = start + slot.duration;
+                let settle = if self.active == Some(slot.direction) {
+                    Duration::ZERO
+                } else {
+                    self.idle()?;
+                    match slot.direction {
+                        Direction::Rx => self.rx.activate()?,
+                        Direction::Tx => self.tx.activate()?,
+                    }
+                    self.active = Some(slot.direction);
+                    self.guard
+                };
+                let info = SlotInfo { number, index };
+                match slot.direction {
+                    Direction::Rx => self.receive(info, slot.duration - settle, settle, end)?,
+                    Direction::Tx => {
+                        crate::time::wait_until(start + settle);
+                        self.transmit()?;
+                    }
+                }
+                crate::time::wait_until(end);
+                start = end;
+                number += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deactivate the active streamer.
+    fn idle(&mut self) -> Result<(), Error> {
+        match self.active.take() {
+            Some(Direction::Rx) => self.rx.deactivate(),
+            Some(Direction::Tx) => self.tx.deactivate(),
+            None => Ok(()),
+        }
+    }
+
+    fn receive(
+        &mut self,
+        slot: SlotInfo,
+        length: Duration,
+        settle: Duration,
+        end: Instant,
+    ) -> Result<(), Error> {
+        let mut discard =
+            vec![Complex32::new(0.0, 0.0); (settle.as_secs_f64() * self.rx_rate) as usize];
+        self.read_until(&mut discard, end)?;
+        let mut samples =
+            vec![Complex32::new(0.0, 0.0); (length.as_secs_f64() * self.rx_rate) as usize];
+        let time_ns = crate::time::host_time_ns().ok();
+        let n = self.read_until(&mut samples, end)?;
+        samples.truncate(n);
+        let burst = Burst {
+            slot,
+            time_ns,
+            samples,
+        };
+        if let Err(TrySendError::Full(_)) = self.rx_bursts.try_send(burst) {
+            log::warn!("TDD: RX burst dropped");
+        }
+        Ok(())
+    }
+
+    /// Fill the buffer, unless the stream stalls after the end of the slot.
+    fn read_until(&mut self, buf: &mut [Complex32], end: Instant) -> Result<usize, Error> {
+        let mut n = 0;
+        while n < buf.len() && !self.stop.load(Ordering::Relaxed) {
+            match self.rx.read(&mut [&mut buf[n..]], TIMEOUT_US) {
+                Ok(0) | Err(Error::Timeout) if Instant::now() >= end => break,
+                Ok(i) => n += i,
+                Err(Error::Timeout) => {}
+                Err(Error::Overflow) => log::warn!("TDD: RX overflow"),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+
+    fn transmit(&mut self) -> Result<(), Error> {
+        match self.tx_bursts.try_recv() {
+            Ok(burst) => self.tx.write_all(&[&burst], None, true, TIMEOUT_US),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl TddRx {
+    /// Slot of the samples of the last read.
+    pub fn slot(&self) -> Option<SlotInfo> {
+        self.slot
+    }
+}
+
+impl RxStreamer for TddRx {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(self.mtu)
+    }
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Ok(())
+    }
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Ok(())
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        self.read_with_metadata(buffers, timeout_us).map(|r| r.0)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        if self.current.is_none() {
+            let timeout = Duration::from_micros(timeout_us.max(0) as u64);
+            let burst = self.bursts.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => Error::Timeout,
+                RecvTimeoutError::Disconnected => Error::Inactive,
+            })?;
+            self.current = Some((burst, 0));
+        }
+        let (burst, pos) = self.current.as_mut().unwrap();
+        let n = buffers[0].len().min(burst.samples.len() - *pos);
+        buffers[0][..n].copy_from_slice(&burst.samples[*pos..*pos + n]);
+        let meta = RxMetadata {
+            time_ns: burst
+                .time_ns
+                .map(|t| t + (*pos as f64 / self.rate * 1e9) as i64),
+            host_time: true,
+            sample_position: None,
+            end_burst: *pos + n == burst.samples.len(),
+            ..RxMetadata::default()
+        };
+        *pos += n;
+        self.slot = Some(burst.slot);
+        if *pos == burst.samples.len() {
+            self.current = None;
+        }
+        Ok((n, meta))
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        Err(Error::NotSupported)
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.current = None;
+        while self.bursts.try_recv().is_ok() {}
+        Ok(())
+    }
+}
+
+impl TxStreamer for TddTx {
+    fn mtu(&self) -> Result<usize, Error> {
+        Ok(self.capacity)
+    }
+    fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Ok(())
+    }
+    fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Append to the current burst, which is queued for the next TX slot with `end_burst`.
+    ///
+    /// Fails with [`Error::ValueError`], if the burst does not fit into a TX slot.
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        _at_ns: Option<i64>,
+        end_burst: bool,
+        _timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if self.pending.len() + buffers[0].len() > self.capacity {
+            self.pending.clear();
+            return Err(Error::ValueError);
+        }
+        self.pending.extend_from_slice(buffers[0]);
+        if end_burst {
+            let burst = std::mem::take(&mut self.pending);
+            self.bursts.send(burst).or(Err(Error::Inactive))?;
+        }
+        Ok(buffers[0].len())
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        self.write(buffers, at_ns, end_burst, timeout_us)
+            .map(|_| ())
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::SampleRate;
+
+    #[test]
+    fn slots() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        dev.set_rate(Direction::Rx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        dev.set_rate(Direction::Tx, 0, SampleRate::sps(1e6))
+            .unwrap();
+        let schedule = vec![
+            TddSlot::rx(Duration::from_millis(10)),
+            TddSlot::tx(Duration::from_millis(10)),
+        ];
+        let (tdd, mut rx, mut tx) =
+            TddDevice::start(&dev, 0, schedule, Duration::from_millis(2)).unwrap();
+        assert_eq!(rx.mtu().unwrap(), 8000);
+
+        let mut buf = vec![Complex32::new(0.0, 0.0); 10_000];
+        let n = rx.read(&mut [&mut buf], 1_000_000).unwrap();
+        assert_eq!(n, 8000);
+        let first = rx.slot().unwrap();
+        assert_eq!(first.index, 0);
+        let (n, meta) = rx
+            .read_with_metadata(&mut [&mut buf[..5000]], 1_000_000)
+            .unwrap();
+        assert_eq!(n, 5000);
+        assert!(!meta.end_burst);
+        let (n, meta) = rx.read_with_metadata(&mut [&mut buf], 1_000_000).unwrap();
+        assert_eq!(n, 3000);
+        assert!(meta.end_burst);
+        // the TX slot lies in between
+        assert_eq!(rx.slot().unwrap().number, first.number + 2);
+
+        let burst = vec![Complex32::new(1.0, 0.0); 4000];
+        tx.write_all(&[&burst], None, true, 0).unwrap();
+        assert!(matches!(
+            tx.write_all(&[&buf], None, true, 0),
+            Err(Error::ValueError)
+        ));
+        tdd.stop().unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::DeviceTrait;
+use crate::Error;
+
+/// Status of the time discipline of a device, e.g., a GPSDO-disciplined clock.
+///
+/// Returned by [`DeviceTrait::time_discipline`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeDiscipline {
+    /// Currently used time source, e.g., `internal`, `external`, or `gpsdo`.
+    pub time_source: Option<String>,
+    /// GPS lock of the on-board GPSDO, if the device has one.
+    pub gps_locked: Option<bool>,
+    /// Hardware time in nanoseconds.
+    pub hardware_time_ns: i64,
+    /// Hardware time minus host time (since the UNIX epoch) in nanoseconds.
+    ///
+    /// For a device that was set to UNIX time on a PPS edge, this is the offset of the host clock
+    /// against the PPS. Comparing it over time gives the drift between the two clocks.
+    pub host_offset_ns: i64,
+}
+
+/// Host time since the UNIX epoch in nanoseconds.
+pub(crate) fn host_time_ns() -> Result<i64, Error> {
+    let t = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Error::Misc(format!("host clock before UNIX epoch: {e}")))?;
+    Ok(t.as_nanos() as i64)
+}
+
+/// Remaining time of a timed wait that is spent spinning, since sleeping is not precise enough.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+const SPIN: Duration = Duration::from_millis(2);
+
+/// Instant `time_ns` nanoseconds from now, as passed to `activate_at`.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) fn deadline(time_ns: i64) -> Instant {
+    Instant::now() + Duration::from_nanos(time_ns.max(0) as u64)
+}
+
+/// Wait until `deadline`, sleeping most of the time and spinning for the rest.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) fn wait_until(deadline: Instant) {
+    if let Some(sleep) = deadline
+        .checked_duration_since(Instant::now())
+        .and_then(|d| d.checked_sub(SPIN))
+    {
+        std::thread::sleep(sleep);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// Nanoseconds from `deadline` to now, i.e., how late an action at `deadline` was.
+#[cfg_attr(
+    not(any(feature = "dummy", feature = "hackrfone", feature = "rtlsdr")),
+    allow(dead_code)
+)]
+pub(crate) fn late_ns(deadline: Instant) -> i64 {
+    let now = Instant::now();
+    match now.checked_duration_since(deadline) {
+        Some(d) => d.as_nanos() as i64,
+        None => -((deadline - now).as_nanos() as i64),
+    }
+}
+
+/// Collect the time discipline status from the time-related methods of a device.
+pub(crate) fn status<D: DeviceTrait + ?Sized>(dev: &D) -> Result<TimeDiscipline, Error> {
+    let before = host_time_ns()?;
+    let hardware_time_ns = dev.hardware_time()?;
+    let after = host_time_ns()?;
+    Ok(TimeDiscipline {
+        time_source: dev.time_source().ok(),
+        gps_locked: dev.gps_locked().ok(),
+        hardware_time_ns,
+        host_offset_ns: hardware_time_ns - (before + (after - before) / 2),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_wait() {
+        let deadline = deadline(5_000_000);
+        wait_until(deadline);
+        let late = late_ns(deadline);
+        assert!((0..1_000_000).contains(&late), "{late}");
+        assert!(late_ns(Instant::now() + Duration::from_secs(1)) < 0);
+    }
+}
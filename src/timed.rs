@@ -0,0 +1,211 @@
+#[cfg(not(target_arch = "wasm32"))]
+use futures::channel::oneshot;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Device;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::DeviceTrait;
+use crate::Direction;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Frequency;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::PendingCommand;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::SampleRate;
+
+/// Control command of a [`TimedCommands`] batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimedCommand {
+    /// [`set_frequency`](crate::DeviceTrait::set_frequency) without arguments.
+    SetFrequency {
+        direction: Direction,
+        channel: usize,
+        frequency: f64,
+    },
+    /// [`set_gain`](crate::DeviceTrait::set_gain).
+    SetGain {
+        direction: Direction,
+        channel: usize,
+        gain: f64,
+    },
+    /// [`set_sample_rate`](crate::DeviceTrait::set_sample_rate).
+    SetSampleRate {
+        direction: Direction,
+        channel: usize,
+        rate: f64,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TimedCommand {
+    fn execute<D: DeviceTrait + Clone + 'static>(&self, dev: &Device<D>) -> Result<(), Error> {
+        // the applied values are only logged
+        match *self {
+            TimedCommand::SetFrequency {
+                direction,
+                channel,
+                frequency,
+            } => dev
+                .tune(direction, channel, Frequency::hz(frequency))
+                .map(|_| ()),
+            TimedCommand::SetGain {
+                direction,
+                channel,
+                gain,
+            } => dev.set_gain(direction, channel, gain).map(|_| ()),
+            TimedCommand::SetSampleRate {
+                direction,
+                channel,
+                rate,
+            } => dev
+                .set_rate(direction, channel, SampleRate::sps(rate))
+                .map(|_| ()),
+        }
+    }
+}
+
+/// Batch of control commands that take effect at the same time, created with [`Device::at`].
+///
+/// Drivers with a hardware command queue implement
+/// [`execute_at`](DeviceTrait::execute_at), so that, e.g., retunes of several channels hit the
+/// same sample and the phase relation between them is kept. For other drivers, a scheduler
+/// thread waits for the time on the host and executes the commands one after another, which is
+/// only as precise as the host scheduling.
+///
+/// The time is in nanoseconds of the [hardware time](Device::hardware_time) or, for devices
+/// without a hardware clock, of the host time since the UNIX epoch.
+///
+/// ```
+/// # #[cfg(feature = "dummy")]
+/// # {
+/// use seify::Device;
+/// use seify::Direction::Rx;
+/// use seify::Frequency;
+///
+/// let dev = Device::from_args("driver=dummy").unwrap();
+/// let now = dev.hardware_time().unwrap();
+/// dev.at(now + 10_000_000)
+///     .set_frequency(Rx, 0, 100e6)
+///     .set_gain(Rx, 0, 20.0)
+///     .submit()
+///     .wait()
+///     .unwrap();
+/// assert_eq!(dev.center_frequency(Rx, 0).unwrap(), Frequency::mhz(100.0));
+/// # }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub struct TimedCommands<D: DeviceTrait + Clone + 'static> {
+    dev: Device<D>,
+    time_ns: i64,
+    commands: Vec<TimedCommand>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<D: DeviceTrait + Clone + 'static> TimedCommands<D> {
+    pub(crate) fn new(dev: Device<D>, time_ns: i64) -> Self {
+        Self {
+            dev,
+            time_ns,
+            commands: Vec::new(),
+        }
+    }
+    /// Add a command to the batch.
+    pub fn command(mut self, command: TimedCommand) -> Self {
+        self.commands.push(command);
+        self
+    }
+    /// Set the center frequency.
+    pub fn set_frequency(self, direction: Direction, channel: usize, frequency: f64) -> Self {
+        self.command(TimedCommand::SetFrequency {
+            direction,
+            channel,
+            frequency,
+        })
+    }
+    /// Set the overall gain.
+    pub fn set_gain(self, direction: Direction, channel: usize, gain: f64) -> Self {
+        self.command(TimedCommand::SetGain {
+            direction,
+            channel,
+            gain,
+        })
+    }
+    /// Set the sample rate.
+    pub fn set_sample_rate(self, direction: Direction, channel: usize, rate: f64) -> Self {
+        self.command(TimedCommand::SetSampleRate {
+            direction,
+            channel,
+            rate,
+        })
+    }
+    /// Time of the batch in nanoseconds.
+    pub fn time_ns(&self) -> i64 {
+        self.time_ns
+    }
+    /// Commands of the batch.
+    pub fn commands(&self) -> &[TimedCommand] {
+        &self.commands
+    }
+
+    /// Queue the batch on the device or on a scheduler thread.
+    ///
+    /// The result resolves once the commands are queued in hardware or, if emulated, executed.
+    /// Emulated batches stop at the first failing command and times in the past execute
+    /// immediately.
+    pub fn submit(self) -> PendingCommand<()> {
+        let (tx, rx) = oneshot::channel();
+        match self.dev.execute_at(self.time_ns, &self.commands) {
+            Err(Error::NotSupported) => {}
+            r => {
+                let _ = tx.send(r);
+                return PendingCommand::new(rx);
+            }
+        }
+        let now = match self.dev.hardware_time() {
+            Err(Error::NotSupported) => crate::time::host_time_ns(),
+            r => r,
+        };
+        let now = match now {
+            Ok(now) => now,
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return PendingCommand::new(rx);
+            }
+        };
+        let deadline = crate::time::deadline(self.time_ns - now);
+        std::thread::spawn(move || {
+            crate::time::wait_until(deadline);
+            let r = self.commands.iter().try_for_each(|c| c.execute(&self.dev));
+            let _ = tx.send(r);
+        });
+        PendingCommand::new(rx)
+    }
+}
+
+#[cfg(all(test, feature = "dummy"))]
+mod tests {
+    use super::*;
+    use crate::Direction::Rx;
+    use crate::Direction::Tx;
+
+    #[test]
+    fn emulated() {
+        let dev = Device::from_args("driver=dummy").unwrap();
+        dev.tune(Rx, 0, Frequency::hz(100e6)).unwrap();
+        let now = dev.hardware_time().unwrap();
+        let pending = dev
+            .at(now + 50_000_000)
+            .set_frequency(Rx, 0, 200e6)
+            .set_frequency(Tx, 0, 200e6)
+            .submit();
+        assert_eq!(dev.center_frequency(Rx, 0).unwrap().as_hz(), 100e6);
+        pending.wait().unwrap();
+        assert_eq!(dev.center_frequency(Rx, 0).unwrap().as_hz(), 200e6);
+        assert_eq!(dev.center_frequency(Tx, 0).unwrap().as_hz(), 200e6);
+
+        let r = dev.at(0).set_gain(Rx, 7, 10.0).submit().wait();
+        assert!(r.is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use num_complex::Complex32;
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Instant;
+
+use crate::BufferConfig;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+
+/// RX streamer adapter that stamps reads with the host clock.
+///
+/// Many devices, like the RTL-SDR, do not provide hardware timestamps. This adapter estimates the
+/// time of the first sample of each read from the host clock and the
+/// [sample position](RxStreamer::sample_position), and reports it through
+/// [`RxStreamer::read_with_metadata`] with [`RxMetadata::host_time`] set. Timestamps of the
+/// wrapped streamer take precedence.
+///
+/// Samples cannot arrive before they were sampled, so the estimate is the earliest time that is
+/// consistent with all reads so far. Its accuracy is, therefore, limited by the minimum transfer
+/// latency and the drift between sample and host clock. Time is in nanoseconds since the UNIX
+/// epoch but advances with the monotonic host clock.
+pub struct HostTimestamps<S: RxStreamer> {
+    inner: S,
+    estimator: Estimator,
+    position: u64,
+    epoch_ns: i64,
+    epoch: Instant,
+}
+
+impl<S: RxStreamer> HostTimestamps<S> {
+    /// Stamp reads of a streamer with the given sample rate.
+    pub fn new(inner: S, sample_rate: f64) -> Result<Self, Error> {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(Error::ValueError);
+        }
+        Ok(Self {
+            inner,
+            estimator: Estimator::new(sample_rate),
+            position: 0,
+            epoch_ns: crate::time::host_time_ns()?,
+            epoch: Instant::now(),
+        })
+    }
+    /// Get a reference to the wrapped streamer.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    /// Remove the adapter and get the wrapped streamer.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn now_ns(&self) -> i64 {
+        self.epoch_ns + self.epoch.elapsed().as_nanos() as i64
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for HostTimestamps<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)?;
+        self.estimator.reset();
+        self.position = 0;
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        self.read_with_metadata(buffers, timeout_us).map(|(n, _)| n)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position().or(Ok(self.position))
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let (n, mut meta) = self.inner.read_with_metadata(buffers, timeout_us)?;
+        let position = meta.sample_position.unwrap_or(self.position);
+        self.position = position + n as u64;
+        meta.sample_position = Some(position);
+        if meta.time_ns.is_none() && n > 0 {
+            meta.time_ns = Some(self.estimator.stamp(self.now_ns(), position, n));
+            meta.host_time = true;
+        }
+        Ok((n, meta))
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// Estimates the time of sample position zero from the arrival times of reads.
+struct Estimator {
+    sample_rate: f64,
+    start_ns: Option<i64>,
+}
+
+impl Estimator {
+    fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            start_ns: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.start_ns = None;
+    }
+
+    fn duration_ns(&self, samples: u64) -> i64 {
+        (samples as f64 / self.sample_rate * 1e9) as i64
+    }
+
+    /// Time of the first sample of a read of `n` samples at `position` that completed at `now_ns`.
+    fn stamp(&mut self, now_ns: i64, position: u64, n: usize) -> i64 {
+        // the last sample of the read was taken at the latest now
+        let latest_start = now_ns - self.duration_ns(position + n as u64);
+        let start = match self.start_ns {
+            Some(s) => s.min(latest_start),
+            None => latest_start,
+        };
+        self.start_ns = Some(start);
+        start + self.duration_ns(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_follows_earliest_arrival() {
+        let mut e = Estimator::new(1000.0);
+        // 100 samples (100 ms) per read, arriving with 30 ms latency, then one with 10 ms
+        assert_eq!(e.stamp(1_130_000_000, 0, 100), 1_030_000_000);
+        assert_eq!(e.stamp(1_230_000_000, 100, 100), 1_130_000_000);
+        assert_eq!(e.stamp(1_310_000_000, 200, 100), 1_210_000_000);
+        // late reads don't move the estimate
+        assert_eq!(e.stamp(1_500_000_000, 300, 100), 1_310_000_000);
+        e.reset();
+        assert_eq!(e.stamp(2_100_000_000, 0, 100), 2_000_000_000);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn stamps_reads() {
+        use crate::DeviceTrait;
+        let dev = crate::impls::Dummy::open(crate::Args::new()).unwrap();
+        let rx = dev.rx_streamer(&[0], crate::Args::new()).unwrap();
+        let mut rx = HostTimestamps::new(rx, 1e6).unwrap();
+        rx.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 100];
+        let (_, first) = rx.read_with_metadata(&mut [&mut buf], 1000).unwrap();
+        let (_, second) = rx.read_with_metadata(&mut [&mut buf], 1000).unwrap();
+        assert!(first.host_time && second.host_time);
+        assert_eq!(first.sample_position, Some(0));
+        assert_eq!(second.sample_position, Some(100));
+        assert!(second.time_ns.unwrap() - first.time_ns.unwrap() <= 100_000);
+        assert!(HostTimestamps::new(rx.into_inner(), 0.0).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::ops::Add;
+use std::ops::Sub;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Frequency in Hz.
+///
+/// Constructors make the unit explicit, avoiding Hz/MHz mix-ups of bare `f64` values. It is
+/// displayed with a suitable unit and parsed from strings with an optional unit, like
+/// `"433.92 MHz"`.
+///
+/// ```
+/// use seify::Frequency;
+///
+/// let f = Frequency::mhz(433.92);
+/// assert_eq!(f.as_hz(), 433.92e6);
+/// assert_eq!(f.to_string(), "433.92 MHz");
+/// assert_eq!("433.92MHz".parse::<Frequency>().unwrap(), f);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Frequency(f64);
+
+/// Sample rate in samples per second.
+///
+/// Like [`Frequency`], it is displayed with a suitable unit, e.g., `2.4 MS/s`, and parsed from
+/// strings with an optional unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SampleRate(f64);
+
+impl Frequency {
+    /// Frequency in Hz.
+    pub const fn hz(hz: f64) -> Self {
+        Self(hz)
+    }
+    /// Frequency in kHz.
+    pub const fn khz(khz: f64) -> Self {
+        Self(khz * 1e3)
+    }
+    /// Frequency in MHz.
+    pub const fn mhz(mhz: f64) -> Self {
+        Self(mhz * 1e6)
+    }
+    /// Frequency in GHz.
+    pub const fn ghz(ghz: f64) -> Self {
+        Self(ghz * 1e9)
+    }
+    /// Value in Hz.
+    pub const fn as_hz(self) -> f64 {
+        self.0
+    }
+    /// Value in kHz.
+    pub const fn as_khz(self) -> f64 {
+        self.0 / 1e3
+    }
+    /// Value in MHz.
+    pub const fn as_mhz(self) -> f64 {
+        self.0 / 1e6
+    }
+}
+
+impl SampleRate {
+    /// Sample rate in samples per second.
+    pub const fn sps(sps: f64) -> Self {
+        Self(sps)
+    }
+    /// Sample rate in kilo samples per second.
+    pub const fn ksps(ksps: f64) -> Self {
+        Self(ksps * 1e3)
+    }
+    /// Sample rate in mega samples per second.
+    pub const fn msps(msps: f64) -> Self {
+        Self(msps * 1e6)
+    }
+    /// Value in samples per second.
+    pub const fn as_sps(self) -> f64 {
+        self.0
+    }
+    /// Value in mega samples per second.
+    pub const fn as_msps(self) -> f64 {
+        self.0 / 1e6
+    }
+}
+
+impl Add for Frequency {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Frequency {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+/// Write `value` with the largest unit prefix, for which it is at least one.
+fn format_si(f: &mut fmt::Formatter<'_>, value: f64, unit: &str) -> fmt::Result {
+    let (scale, prefix) = [(1e9, "G"), (1e6, "M"), (1e3, "k")]
+        .into_iter()
+        .find(|(s, _)| value.abs() >= *s)
+        .unwrap_or((1.0, ""));
+    match f.precision() {
+        Some(p) => write!(f, "{:.p$} {prefix}{unit}", value / scale),
+        None => write!(f, "{} {prefix}{unit}", value / scale),
+    }
+}
+
+/// Parse a number with an optional SI prefix and unit, case-insensitive, e.g., `2.4M`.
+fn parse_si(s: &str, units: &[&str]) -> Result<f64, Error> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let value: f64 = number.trim().parse().or(Err(Error::ValueError))?;
+    let suffix = suffix.to_lowercase();
+    let (scale, rest) = match suffix.chars().next() {
+        Some('g') => (1e9, &suffix[1..]),
+        Some('m') => (1e6, &suffix[1..]),
+        Some('k') => (1e3, &suffix[1..]),
+        _ => (1.0, &suffix[..]),
+    };
+    if rest.is_empty() || units.contains(&rest) {
+        Ok(value * scale)
+    } else {
+        Err(Error::ValueError)
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_si(f, self.0, "Hz")
+    }
+}
+
+impl fmt::Display for SampleRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_si(f, self.0, "S/s")
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_si(s, &["hz"]).map(Self)
+    }
+}
+
+impl FromStr for SampleRate {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_si(s, &["s/s", "sps", "hz"]).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Frequency::ghz(2.45).to_string(), "2.45 GHz");
+        assert_eq!(Frequency::khz(12.5).to_string(), "12.5 kHz");
+        assert_eq!(Frequency::hz(-500.0).to_string(), "-500 Hz");
+        assert_eq!(format!("{:.3}", Frequency::mhz(100.0)), "100.000 MHz");
+        assert_eq!(SampleRate::msps(2.4).to_string(), "2.4 MS/s");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("100e6".parse::<Frequency>().unwrap(), Frequency::mhz(100.0));
+        assert_eq!("1.5 GHz".parse::<Frequency>().unwrap(), Frequency::ghz(1.5));
+        assert_eq!("868m".parse::<Frequency>().unwrap(), Frequency::mhz(868.0));
+        assert_eq!(
+            "250 kS/s".parse::<SampleRate>().unwrap(),
+            SampleRate::ksps(250.0)
+        );
+        assert_eq!(
+            "2.4 Msps".parse::<SampleRate>().unwrap(),
+            SampleRate::msps(2.4)
+        );
+        assert!("12 parsecs".parse::<Frequency>().is_err());
+        assert!("MHz".parse::<Frequency>().is_err());
+    }
+}
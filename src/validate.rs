@@ -0,0 +1,288 @@
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+
+use num_complex::Complex32;
+
+use crate::BufferConfig;
+use crate::BufferLayout;
+use crate::ChannelEnable;
+use crate::DeviceTrait;
+use crate::Direction;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+use crate::TxStreamer;
+
+/// Check the channels of a new streamer: at least one, no duplicates, and all on the device.
+pub(crate) fn check_channels(channels: &[usize], available: usize) -> Result<(), Error> {
+    if channels.is_empty() {
+        log::warn!("streamer without channels");
+        return Err(Error::ValueError);
+    }
+    for (i, c) in channels.iter().enumerate() {
+        if *c >= available || channels[..i].contains(c) {
+            log::warn!("invalid streamer channels {channels:?} ({available} available)");
+            return Err(Error::ValueError);
+        }
+    }
+    Ok(())
+}
+
+/// Check that the channels of a streamer are not disabled, see
+/// [`ChannelEnable`](crate::ChannelEnable).
+pub(crate) fn check_enabled<D: DeviceTrait>(
+    dev: &D,
+    direction: Direction,
+    channels: &[usize],
+) -> Result<(), Error> {
+    let Some(enable) = dev.extensions().get::<dyn ChannelEnable>() else {
+        return Ok(());
+    };
+    for c in channels {
+        if enable.channel_enabled(direction, *c).ok() == Some(false) {
+            log::warn!("streamer channel {c} ({direction:?}) is disabled");
+            return Err(Error::Inactive);
+        }
+    }
+    Ok(())
+}
+
+/// Check that there is one buffer per channel and that all buffers have the same length.
+fn check_buffers(channels: usize, lens: impl ExactSizeIterator<Item = usize>) -> Result<(), Error> {
+    if lens.len() != channels {
+        return Err(Error::ValueError);
+    }
+    let mut lens = lens.peekable();
+    let first = lens.peek().copied();
+    if lens.any(|l| Some(l) != first) {
+        return Err(Error::ValueError);
+    }
+    Ok(())
+}
+
+/// RX streamer of a [`GenericDevice`](crate::GenericDevice), checking the buffers before they
+/// are passed to the driver.
+pub(crate) struct CheckedRx<S: RxStreamer> {
+    inner: S,
+    channels: usize,
+}
+
+impl<S: RxStreamer> CheckedRx<S> {
+    pub fn new(inner: S, channels: usize) -> Self {
+        Self { inner, channels }
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for CheckedRx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        check_buffers(self.channels, buffers.iter().map(|b| b.len()))?;
+        self.inner.read(buffers, timeout_us)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        check_buffers(self.channels, buffers.iter().map(|b| b.len()))?;
+        self.inner.read_with_metadata(buffers, timeout_us)
+    }
+    fn native_layout(&self) -> BufferLayout {
+        self.inner.native_layout()
+    }
+    fn read_interleaved(
+        &mut self,
+        buffer: &mut [Complex32],
+        channels: usize,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        if channels != self.channels || !buffer.len().is_multiple_of(channels) {
+            return Err(Error::ValueError);
+        }
+        self.inner.read_interleaved(buffer, channels, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        check_buffers(
+            self.channels,
+            buffers.iter().map(|b| b.len() / format.sample_size()),
+        )?;
+        self.inner.read_format(format, buffers, timeout_us)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// TX streamer of a [`GenericDevice`](crate::GenericDevice), checking the buffers before they
+/// are passed to the driver.
+pub(crate) struct CheckedTx<S: TxStreamer> {
+    inner: S,
+    channels: usize,
+}
+
+impl<S: TxStreamer> CheckedTx<S> {
+    pub fn new(inner: S, channels: usize) -> Self {
+        Self { inner, channels }
+    }
+}
+
+impl<S: TxStreamer> TxStreamer for CheckedTx<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn write(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        check_buffers(self.channels, buffers.iter().map(|b| b.len()))?;
+        self.inner.write(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_all(
+        &mut self,
+        buffers: &[&[Complex32]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<(), Error> {
+        check_buffers(self.channels, buffers.iter().map(|b| b.len()))?;
+        self.inner.write_all(buffers, at_ns, end_burst, timeout_us)
+    }
+    fn write_segments(
+        &mut self,
+        segments: &[&[&[Complex32]]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        check_buffers(
+            self.channels,
+            segments.iter().map(|c| c.iter().map(|s| s.len()).sum()),
+        )?;
+        self.inner
+            .write_segments(segments, at_ns, end_burst, timeout_us)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn write_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &[&[u8]],
+        at_ns: Option<i64>,
+        end_burst: bool,
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        check_buffers(
+            self.channels,
+            buffers.iter().map(|b| b.len() / format.sample_size()),
+        )?;
+        self.inner
+            .write_format(format, buffers, at_ns, end_burst, timeout_us)
+    }
+    fn set_input_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_input_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn flush(&mut self, timeout_us: i64) -> Result<(), Error> {
+        self.inner.flush(timeout_us)
+    }
+    fn queued_samples(&self) -> Result<usize, Error> {
+        self.inner.queued_samples()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels() {
+        assert!(check_channels(&[0], 1).is_ok());
+        assert!(check_channels(&[1, 0], 2).is_ok());
+        assert!(check_channels(&[], 2).is_err());
+        assert!(check_channels(&[1], 1).is_err());
+        assert!(check_channels(&[0, 0], 2).is_err());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn buffers() {
+        let dev = crate::Device::from_args("driver=dummy").unwrap();
+        assert!(matches!(dev.rx_streamer(&[1]), Err(Error::ValueError)));
+        let mut rx = dev.rx_streamer(&[0]).unwrap();
+        rx.activate().unwrap();
+        let mut a = [Complex32::new(0.0, 0.0); 16];
+        let mut b = [Complex32::new(0.0, 0.0); 16];
+        assert!(matches!(
+            rx.read(&mut [&mut a, &mut b], 0),
+            Err(Error::ValueError)
+        ));
+        assert!(matches!(rx.read(&mut [], 0), Err(Error::ValueError)));
+        assert!(rx.read(&mut [&mut a], 0).is_ok());
+
+        let mut tx = dev.tx_streamer(&[0]).unwrap();
+        tx.activate().unwrap();
+        assert!(matches!(
+            tx.write(&[&a, &b], None, false, 0),
+            Err(Error::ValueError)
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+use num_complex::Complex32;
+use std::sync::mpsc;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::BufferConfig;
+use crate::Error;
+use crate::RxMetadata;
+use crate::RxStreamer;
+use crate::StreamCanceller;
+use crate::StreamEvent;
+use crate::StreamFormat;
+
+/// Reaction of a [`Watchdog`] to a stalled stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// Keep waiting for samples and re-arm the watchdog.
+    Ignore,
+    /// Deactivate and re-activate the stream.
+    Restart,
+    /// Return [`Error::Timeout`] from the current read.
+    Fail,
+}
+
+type StallHandler = Box<dyn FnMut(Duration) -> WatchdogAction + Send>;
+
+/// RX streamer adapter that detects stalled streams.
+///
+/// USB devices can stop delivering samples without reporting an error, which leaves unattended
+/// receivers hanging. The watchdog monitors the sample flow of the wrapped streamer and, if no
+/// samples arrive for the configured period, calls a handler that decides how to recover. The
+/// default handler restarts the stream.
+///
+/// The watchdog does not spawn threads, it is evaluated in [`read`](RxStreamer::read). Reads must,
+/// therefore, use a finite timeout. To reset the device, capture it in the handler and return
+/// [`WatchdogAction::Ignore`] or [`WatchdogAction::Fail`].
+pub struct Watchdog<S: RxStreamer> {
+    inner: S,
+    period: Duration,
+    last_samples: Instant,
+    stalls: usize,
+    handler: StallHandler,
+}
+
+impl<S: RxStreamer> Watchdog<S> {
+    /// Monitor a streamer, considering it stalled if no samples arrive within `period`.
+    pub fn new(inner: S, period: Duration) -> Self {
+        Self {
+            inner,
+            period,
+            last_samples: Instant::now(),
+            stalls: 0,
+            handler: Box::new(|_| WatchdogAction::Restart),
+        }
+    }
+    /// Set the handler that is called with the time since the last samples, when the stream
+    /// stalls.
+    pub fn on_stall<F: FnMut(Duration) -> WatchdogAction + Send + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.handler = Box::new(handler);
+        self
+    }
+    /// Number of detected stalls.
+    pub fn stalls(&self) -> usize {
+        self.stalls
+    }
+    /// Get a reference to the wrapped streamer.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    /// Remove the watchdog and get the wrapped streamer.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn check(&mut self) -> Result<(), Error> {
+        let elapsed = self.last_samples.elapsed();
+        if elapsed < self.period {
+            return Ok(());
+        }
+        self.stalls += 1;
+        log::warn!("stream stalled, no samples for {elapsed:?}");
+        let action = (self.handler)(elapsed);
+        self.last_samples = Instant::now();
+        match action {
+            WatchdogAction::Ignore => Ok(()),
+            WatchdogAction::Restart => {
+                if let Err(e) = self.inner.deactivate() {
+                    log::warn!("deactivating stalled stream failed: {e}");
+                }
+                self.inner.activate()
+            }
+            WatchdogAction::Fail => Err(Error::Timeout),
+        }
+    }
+
+    /// Update the watchdog with the result of a read.
+    fn observe<T>(
+        &mut self,
+        r: Result<T, Error>,
+        samples: impl Fn(&T) -> usize,
+    ) -> Result<T, Error> {
+        match r {
+            Ok(t) if samples(&t) > 0 => {
+                self.last_samples = Instant::now();
+                Ok(t)
+            }
+            Ok(t) => {
+                self.check()?;
+                Ok(t)
+            }
+            Err(Error::Timeout) => {
+                self.check()?;
+                Err(Error::Timeout)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<S: RxStreamer> RxStreamer for Watchdog<S> {
+    fn mtu(&self) -> Result<usize, Error> {
+        self.inner.mtu()
+    }
+    fn activate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.activate_at(time_ns)?;
+        self.last_samples = Instant::now();
+        Ok(())
+    }
+    fn deactivate_at(&mut self, time_ns: Option<i64>) -> Result<(), Error> {
+        self.inner.deactivate_at(time_ns)
+    }
+    fn read(&mut self, buffers: &mut [&mut [Complex32]], timeout_us: i64) -> Result<usize, Error> {
+        let r = self.inner.read(buffers, timeout_us);
+        self.observe(r, |n| *n)
+    }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn read_with_metadata(
+        &mut self,
+        buffers: &mut [&mut [Complex32]],
+        timeout_us: i64,
+    ) -> Result<(usize, RxMetadata), Error> {
+        let r = self.inner.read_with_metadata(buffers, timeout_us);
+        self.observe(r, |(n, _)| *n)
+    }
+    fn native_format(&self) -> StreamFormat {
+        self.inner.native_format()
+    }
+    fn read_format(
+        &mut self,
+        format: StreamFormat,
+        buffers: &mut [&mut [u8]],
+        timeout_us: i64,
+    ) -> Result<usize, Error> {
+        let r = self.inner.read_format(format, buffers, timeout_us);
+        self.observe(r, |n| *n)
+    }
+    fn sample_position(&self) -> Result<u64, Error> {
+        self.inner.sample_position()
+    }
+    fn buffer_config(&self) -> Result<BufferConfig, Error> {
+        self.inner.buffer_config()
+    }
+    fn set_output_scale(&mut self, scale: f32) -> Result<(), Error> {
+        self.inner.set_output_scale(scale)
+    }
+    fn canceller(&self) -> Result<StreamCanceller, Error> {
+        self.inner.canceller()
+    }
+    fn stream_events(&self) -> Result<mpsc::Receiver<StreamEvent>, Error> {
+        self.inner.stream_events()
+    }
+    fn activation_error_ns(&self) -> Result<i64, Error> {
+        self.inner.activation_error_ns()
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    /// Streamer that delivers `samples` reads worth of samples and then stalls.
+    struct Stalling {
+        samples: usize,
+        activations: usize,
+    }
+
+    impl RxStreamer for Stalling {
+        fn mtu(&self) -> Result<usize, Error> {
+            Ok(16)
+        }
+        fn activate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            self.activations += 1;
+            Ok(())
+        }
+        fn deactivate_at(&mut self, _time_ns: Option<i64>) -> Result<(), Error> {
+            Ok(())
+        }
+        fn read(
+            &mut self,
+            buffers: &mut [&mut [Complex32]],
+            _timeout_us: i64,
+        ) -> Result<usize, Error> {
+            if self.samples > 0 {
+                self.samples -= 1;
+                Ok(buffers[0].len())
+            } else {
+                Err(Error::Timeout)
+            }
+        }
+    }
+
+    #[test]
+    fn restart_on_stall() {
+        let s = Stalling {
+            samples: 1,
+            activations: 0,
+        };
+        let mut w = Watchdog::new(s, Duration::ZERO);
+        w.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 16];
+        assert_eq!(w.read(&mut [&mut buf], 1000).unwrap(), 16);
+        assert!(matches!(w.read(&mut [&mut buf], 1000), Err(Error::Timeout)));
+        assert_eq!(w.stalls(), 1);
+        assert_eq!(w.inner().activations, 2);
+    }
+
+    #[test]
+    fn handler_decides() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = calls.clone();
+        let s = Stalling {
+            samples: 0,
+            activations: 0,
+        };
+        let mut w = Watchdog::new(s, Duration::ZERO).on_stall(move |_| {
+            c.fetch_add(1, Ordering::Relaxed);
+            WatchdogAction::Fail
+        });
+        w.activate().unwrap();
+        let mut buf = [Complex32::new(0.0, 0.0); 16];
+        assert!(matches!(w.read(&mut [&mut buf], 1000), Err(Error::Timeout)));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(w.into_inner().activations, 1);
+
+        let s = Stalling {
+            samples: 0,
+            activations: 0,
+        };
+        let mut w = Watchdog::new(s, Duration::from_secs(3600));
+        w.activate().unwrap();
+        assert!(matches!(w.read(&mut [&mut buf], 1000), Err(Error::Timeout)));
+        assert_eq!(w.stalls(), 0);
+    }
+}
@@ -0,0 +1,509 @@
+use num_complex::Complex32;
+use std::net::TcpListener;
+use std::net::ToSocketAddrs;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tungstenite::handshake::server::ErrorResponse;
+use tungstenite::handshake::server::Request;
+use tungstenite::handshake::server::Response;
+use tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tungstenite::http::HeaderValue;
+use tungstenite::Message;
+
+use crate::access::Slot;
+use crate::access::Stream;
+use crate::access::REQUEST_TIMEOUT;
+use crate::spectrum::Periodogram;
+use crate::Access;
+use crate::Error;
+use crate::FanOut;
+use crate::IqFormat;
+
+/// Largest supported FFT size.
+const MAX_FFT_SIZE: usize = 1 << 16;
+
+/// Compression level of zstd messages, low enough to keep up with high sample rates.
+const ZSTD_LEVEL: i32 = 3;
+
+/// WebSocket endpoint streaming IQ samples or spectra of an RX stream, e.g., for browser
+/// waterfalls.
+///
+/// Every client subscribes to a [`FanOut`] and selects the stream with the URL:
+///
+/// - `ws://<addr>/iq?decimation=<n>`: binary messages of interleaved little-endian `f32` IQ
+///   samples.
+/// - `ws://<addr>/fft?size=<n>&decimation=<n>`: binary messages of `size` little-endian `f32`
+///   power values in dB, from negative to positive frequencies.
+///
+/// Decimation averages `n` consecutive samples and defaults to 1. The FFT size has to be a power
+/// of two and defaults to 1024. If a client does not keep up, blocks are dropped for this client
+/// and it receives a text message `{"dropped": <total>}`.
+///
+/// To save bandwidth, e.g., over mobile links, IQ samples can be sent in a smaller
+/// [`IqFormat`] and binary messages compressed as independent zstd frames (cargo feature
+/// `zstd`), which pays off for low-entropy bands. Clients negotiate the encoding by offering
+/// WebSocket subprotocols `seify.<format>` or `seify.<format>+zstd`, e.g., `seify.cs8+zstd`, in
+/// order of preference. The server selects the first one it supports and rejects the request, if
+/// there is none. Without subprotocols, the query parameters `format=<format>` and
+/// `compression=zstd` select the encoding, which defaults to uncompressed `cf32`. Spectra are
+/// always `cf32`.
+///
+/// With [access tokens](Self::access), clients need a token of any
+/// [permission](crate::Permission), e.g., `ws://<addr>/iq?access_token=<token>`.
+pub struct IqWebSocketServer {
+    fanout: FanOut,
+    depth: usize,
+    access: Access,
+}
+
+/// Stream selected by a client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Iq,
+    Fft(usize),
+}
+
+/// Encoding of the binary messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Encoding {
+    format: IqFormat,
+    zstd: bool,
+}
+
+impl Encoding {
+    const RAW: Self = Self {
+        format: IqFormat::Cf32,
+        zstd: false,
+    };
+
+    /// Parse a subprotocol, e.g., `seify.cs8+zstd`.
+    fn from_protocol(protocol: &str) -> Option<Self> {
+        let encoding = protocol.trim().strip_prefix("seify.")?;
+        let (format, zstd) = match encoding.strip_suffix("+zstd") {
+            Some(format) => (format, true),
+            None => (encoding, false),
+        };
+        Some(Self {
+            format: format.parse().ok()?,
+            zstd,
+        })
+    }
+
+    fn protocol(&self) -> String {
+        let zstd = if self.zstd { "+zstd" } else { "" };
+        format!("seify.{}{zstd}", self.format)
+    }
+
+    /// Check that the server can produce the encoding for the mode.
+    fn check(&self, mode: Mode) -> Result<(), Error> {
+        if self.zstd && !cfg!(feature = "zstd") {
+            return Err(Error::FeatureNotEnabled("zstd"));
+        }
+        if matches!(mode, Mode::Fft(_)) && self.format != IqFormat::Cf32 {
+            return Err(Error::ValueError);
+        }
+        Ok(())
+    }
+
+    /// Select the first supported of the offered subprotocols.
+    fn negotiate(offers: &str, mode: Mode) -> Option<Self> {
+        offers
+            .split(',')
+            .filter_map(Self::from_protocol)
+            .find(|e| e.check(mode).is_ok())
+    }
+
+    fn encode(&self, samples: &[Complex32]) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        self.format.encode(samples, &mut bytes);
+        self.compress(bytes)
+    }
+
+    fn compress(&self, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if self.zstd {
+            crate::recorder::compress(&bytes, ZSTD_LEVEL)
+        } else {
+            Ok(bytes)
+        }
+    }
+}
+
+/// Stream and encoding requested by a client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Selection {
+    mode: Mode,
+    decimation: usize,
+    encoding: Encoding,
+}
+
+impl IqWebSocketServer {
+    /// Create a server for the stream of the fan-out.
+    pub fn new(fanout: FanOut) -> Self {
+        Self {
+            fanout,
+            depth: 16,
+            access: Access::new(),
+        }
+    }
+    /// Set the number of blocks that are queued per client.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the access tokens and TLS configuration. Defaults to an open server.
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Accept clients from a background thread.
+    ///
+    /// Each client is served by its own thread, up to a limit of concurrent clients. Clients have
+    /// to complete the handshake within a timeout.
+    pub fn serve<A: ToSocketAddrs>(self, addr: A) -> Result<JoinHandle<()>, Error> {
+        let listener = TcpListener::bind(addr)?;
+        let active = Arc::new(AtomicUsize::new(0));
+        Ok(std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let s = match stream {
+                    Ok(s) => s,
+                    Err(e) => {
+                        log::warn!("accepting websocket client failed: {e}");
+                        continue;
+                    }
+                };
+                let Some(slot) = Slot::take(&active) else {
+                    log::warn!("too many websocket clients, dropping connection");
+                    continue;
+                };
+                let fanout = self.fanout.clone();
+                let depth = self.depth;
+                let access = self.access.clone();
+                let r = std::thread::Builder::new()
+                    .name("seify-websocket".to_string())
+                    .spawn(move || {
+                        let _slot = slot;
+                        let r = access.accept(s).and_then(|mut s| {
+                            s.set_deadline(Some(Instant::now() + REQUEST_TIMEOUT));
+                            client(s, &access, fanout, depth)
+                        });
+                        if let Err(e) = r {
+                            log::debug!("websocket client closed: {e}");
+                        }
+                    });
+                if let Err(e) = r {
+                    log::warn!("spawning websocket thread failed: {e}");
+                }
+            }
+        }))
+    }
+}
+
+fn ws_err(e: impl std::fmt::Display) -> Error {
+    Error::Misc(format!("websocket: {e}"))
+}
+
+/// Parse the request path into the stream and the encoding of the query.
+fn parse(path: &str) -> Result<Selection, Error> {
+    let (route, query) = path.split_once('?').unwrap_or((path, ""));
+    let mut decimation = 1;
+    let mut size: usize = 1024;
+    let mut encoding = Encoding::RAW;
+    for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+        match key {
+            "decimation" => decimation = value.parse().or(Err(Error::ValueError))?,
+            "size" => size = value.parse().or(Err(Error::ValueError))?,
+            "format" => encoding.format = value.parse()?,
+            "compression" => match value {
+                "none" => encoding.zstd = false,
+                "zstd" => encoding.zstd = true,
+                _ => return Err(Error::ValueError),
+            },
+            "access_token" => {}
+            _ => return Err(Error::ValueError),
+        }
+    }
+    if decimation == 0 || !size.is_power_of_two() || size > MAX_FFT_SIZE {
+        return Err(Error::ValueError);
+    }
+    let mode = match route {
+        "/iq" => Mode::Iq,
+        "/fft" => Mode::Fft(size),
+        _ => return Err(Error::NotFound),
+    };
+    encoding.check(mode)?;
+    Ok(Selection {
+        mode,
+        decimation,
+        encoding,
+    })
+}
+
+// the error type of the handshake callback is defined by tungstenite
+#[allow(clippy::result_large_err)]
+fn client(stream: Stream, access: &Access, fanout: FanOut, depth: usize) -> Result<(), Error> {
+    let mut selected = Err(Error::NotFound);
+    let callback = |req: &Request, mut resp: Response| -> Result<Response, ErrorResponse> {
+        let authorization = req
+            .headers()
+            .get("authorization")
+            .and_then(|h| h.to_str().ok());
+        let query = req.uri().query().unwrap_or("");
+        let offers = req
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|h| h.to_str().ok());
+        selected = access
+            .authenticate(crate::access::token(authorization, query))
+            .and_then(|_| parse(&req.uri().to_string()))
+            .and_then(|mut s| {
+                if let Some(offers) = offers {
+                    s.encoding = Encoding::negotiate(offers, s.mode).ok_or(Error::NotSupported)?;
+                    let protocol = HeaderValue::from_str(&s.encoding.protocol()).map_err(ws_err)?;
+                    resp.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, protocol);
+                }
+                Ok(s)
+            });
+        match &selected {
+            Ok(_) => Ok(resp),
+            Err(e) => {
+                let mut r = ErrorResponse::new(Some(e.to_string()));
+                *r.status_mut() = match e {
+                    Error::Unauthorized => tungstenite::http::StatusCode::UNAUTHORIZED,
+                    Error::NotFound => tungstenite::http::StatusCode::NOT_FOUND,
+                    _ => tungstenite::http::StatusCode::BAD_REQUEST,
+                };
+                Err(r)
+            }
+        }
+    };
+    let mut ws = tungstenite::accept_hdr(stream, callback).map_err(ws_err)?;
+    ws.get_mut().set_deadline(None);
+    let Selection {
+        mode,
+        decimation,
+        encoding,
+    } = selected?;
+
+    let subscription = fanout.subscribe(depth);
+    let mut decimator = Decimator::new(decimation);
+    let mut spectrum = match mode {
+        Mode::Fft(size) => Some(Periodogram::new(size)),
+        Mode::Iq => None,
+    };
+    let mut dropped = 0;
+    let mut samples = Vec::new();
+    loop {
+        let block = subscription.recv()?;
+        if subscription.dropped() != dropped {
+            dropped = subscription.dropped();
+            let msg = format!("{{\"dropped\": {dropped}}}");
+            ws.send(Message::Text(msg)).map_err(ws_err)?;
+        }
+        samples.clear();
+        decimator.process(&block, &mut samples);
+        match spectrum.as_mut() {
+            Some(s) => {
+                for frame in s.process(&samples) {
+                    let bytes = frame.iter().flat_map(|p| p.to_le_bytes()).collect();
+                    let bytes = encoding.compress(bytes)?;
+                    ws.send(Message::Binary(bytes)).map_err(ws_err)?;
+                }
+            }
+            None => {
+                let bytes = encoding.encode(&samples)?;
+                ws.send(Message::Binary(bytes)).map_err(ws_err)?;
+            }
+        }
+    }
+}
+
+/// Decimates by averaging consecutive samples.
+struct Decimator {
+    factor: usize,
+    sum: Complex32,
+    count: usize,
+}
+
+impl Decimator {
+    fn new(factor: usize) -> Self {
+        Self {
+            factor,
+            sum: Complex32::new(0.0, 0.0),
+            count: 0,
+        }
+    }
+
+    fn process(&mut self, input: &[Complex32], out: &mut Vec<Complex32>) {
+        if self.factor == 1 {
+            out.extend_from_slice(input);
+            return;
+        }
+        for s in input {
+            self.sum += s;
+            self.count += 1;
+            if self.count == self.factor {
+                out.push(self.sum / self.factor as f32);
+                self.sum = Complex32::new(0.0, 0.0);
+                self.count = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    #[test]
+    fn parse_requests() {
+        let iq = parse("/iq").unwrap();
+        assert_eq!(
+            (iq.mode, iq.decimation, iq.encoding),
+            (Mode::Iq, 1, Encoding::RAW)
+        );
+        let fft = parse("/fft?size=256&decimation=4").unwrap();
+        assert_eq!((fft.mode, fft.decimation), (Mode::Fft(256), 4));
+        let iq = parse("/iq?format=cs8").unwrap();
+        assert_eq!(iq.encoding.format, IqFormat::Cs8);
+        assert!(matches!(parse("/fft?format=cs8"), Err(Error::ValueError)));
+        assert!(matches!(
+            parse("/iq?compression=lz4"),
+            Err(Error::ValueError)
+        ));
+        assert!(matches!(parse("/fft?size=100"), Err(Error::ValueError)));
+        assert!(matches!(parse("/iq?decimation=0"), Err(Error::ValueError)));
+        assert!(matches!(parse("/waterfall"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn negotiate() {
+        let cs8 = Encoding {
+            format: IqFormat::Cs8,
+            zstd: false,
+        };
+        assert_eq!(Encoding::from_protocol("seify.cs8"), Some(cs8));
+        assert_eq!(cs8.protocol(), "seify.cs8");
+        let zstd = Encoding { zstd: true, ..cs8 };
+        assert_eq!(Encoding::from_protocol(" seify.cs8+zstd"), Some(zstd));
+        let offers = "chat, seify.cs8+zstd, seify.cs8";
+        let expected = if cfg!(feature = "zstd") { zstd } else { cs8 };
+        assert_eq!(Encoding::negotiate(offers, Mode::Iq), Some(expected));
+        assert_eq!(Encoding::negotiate("seify.cs8", Mode::Fft(64)), None);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn stream_to_client() {
+        use crate::Args;
+        use crate::DeviceTrait;
+        use tungstenite::client::IntoClientRequest;
+
+        let dev = crate::impls::Dummy::open(Args::new()).unwrap();
+        let rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let fanout = FanOut::start(rx, 512).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        IqWebSocketServer::new(fanout.clone()).serve(addr).unwrap();
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let url = format!("ws://{addr}/iq?decimation=4");
+        let (mut ws, _) = tungstenite::client(url, stream).unwrap();
+        loop {
+            match ws.read().unwrap() {
+                Message::Binary(b) => {
+                    assert_eq!(b.len(), 128 * 8);
+                    break;
+                }
+                Message::Text(t) => assert!(t.starts_with("{\"dropped\"")),
+                m => panic!("unexpected message {m:?}"),
+            }
+        }
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut request = format!("ws://{addr}/iq").into_client_request().unwrap();
+        request.headers_mut().insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("seify.cu8, seify.cs8"),
+        );
+        let (mut ws, response) = tungstenite::client(request, stream).unwrap();
+        assert_eq!(response.headers()[SEC_WEBSOCKET_PROTOCOL], "seify.cu8");
+        loop {
+            if let Message::Binary(b) = ws.read().unwrap() {
+                assert_eq!(b.len(), 512 * 2);
+                break;
+            }
+        }
+        fanout.stop().unwrap();
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn requires_token() {
+        use crate::Args;
+        use crate::DeviceTrait;
+        use crate::Permission;
+
+        let dev = crate::impls::Dummy::open(Args::new()).unwrap();
+        let rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let fanout = FanOut::start(rx, 512).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let access = Access::new().token("ro", Permission::ReadOnly);
+        IqWebSocketServer::new(fanout.clone())
+            .access(access)
+            .serve(addr)
+            .unwrap();
+
+        let stream = TcpStream::connect(addr).unwrap();
+        match tungstenite::client(format!("ws://{addr}/iq"), stream) {
+            Err(tungstenite::HandshakeError::Failure(tungstenite::Error::Http(r))) => {
+                assert_eq!(r.status(), tungstenite::http::StatusCode::UNAUTHORIZED)
+            }
+            r => panic!("unexpected handshake {:?}", r.map(|_| ())),
+        }
+        let stream = TcpStream::connect(addr).unwrap();
+        let url = format!("ws://{addr}/iq?access_token=ro");
+        assert!(tungstenite::client(url, stream).is_ok());
+        fanout.stop().unwrap();
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn stalled_handshake() {
+        use crate::Args;
+        use crate::DeviceTrait;
+        use std::io::Read;
+        use std::io::Write;
+        use std::time::Duration;
+
+        let dev = crate::impls::Dummy::open(Args::new()).unwrap();
+        let rx = dev.rx_streamer(&[0], Args::new()).unwrap();
+        let fanout = FanOut::start(rx, 512).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        IqWebSocketServer::new(fanout.clone()).serve(addr).unwrap();
+
+        // a client that stalls in the handshake does not block the others
+        let mut stalled = TcpStream::connect(addr).unwrap();
+        write!(stalled, "GET /iq HTTP/1.1\r\n").unwrap();
+        let stream = TcpStream::connect(addr).unwrap();
+        assert!(tungstenite::client(format!("ws://{addr}/iq"), stream).is_ok());
+
+        // and it is dropped after the handshake timeout
+        stalled
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        let mut r = Vec::new();
+        let _ = stalled.read_to_end(&mut r);
+        assert!(r.is_empty());
+        fanout.stop().unwrap();
+    }
+}